@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notification_bot::parsers::parse_duration;
+
+fuzz_target!(|text: &str| {
+    if let Some(duration) = parse_duration(text) {
+        assert!(!duration.is_zero());
+    }
+});
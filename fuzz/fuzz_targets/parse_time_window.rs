@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notification_bot::parsers::parse_time_window;
+
+fuzz_target!(|text: &str| {
+    if let Some((from, to)) = parse_time_window(text) {
+        assert!(from < to);
+    }
+});
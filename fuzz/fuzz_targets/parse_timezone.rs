@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notification_bot::parsers::parse_timezone;
+
+fuzz_target!(|text: &str| {
+    if let Some(offset) = parse_timezone(text) {
+        // Whatever we accept must survive a round trip through its rendering
+        assert_eq!(parse_timezone(&offset.to_string()), Some(offset));
+    }
+});
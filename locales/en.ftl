@@ -0,0 +1,592 @@
+error = Something went wrong 😫
+not-started = Send "/start" to turn on notifications first
+
+start-started =
+    Notifications started!
+    Current timezone: { $timezone }
+    Notifications will be sent from { $from } to { $to } every hour until the "/done" command is sent
+    Send "/language" to change the language of replies
+start-already-started = Already started!
+start-next = Next notification at { $next } your time
+start-next-now = The first notification comes right away
+onboarding-timezone =
+    Welcome! Three questions and notifications are on.
+    1/3. What's your timezone? Send an offset like +05:00 or a name like Europe/Moscow, or share your location
+onboarding-working-hours =
+    2/3. When do you work? Send whole hours like 09:00-18:00, { $from }–{ $to } when skipped
+onboarding-workdays =
+    3/3. Which days do you work? Send them like mon-fri or mon, wed, sat-sun, Monday to Friday when skipped
+onboarding-workdays-invalid = Invalid days, send them like mon-fri or mon, wed, sat-sun
+button-skip = Skip
+group-admins-only = Only admins of the group can start and stop notifications
+
+stop-stopped = Stopped!
+stop-nothing = Nothing to stop
+stop-confirm = Stop notifications and remove your settings?
+stop-undo-hint = Your settings are kept for a day, press "Undo" to bring them back
+stop-cancelled = Notifications go on as before
+stop-restored = Your settings are back and notifications are on again
+stop-undo-expired = It's too late to undo, send "/start" to start over
+stop-choose = Stop notifications and remove your settings, or stop only one of your reminders?
+stop-reminder-stopped = Stopped: { $reminder }
+stop-reminder-gone = This reminder has already come or was stopped
+stop-reminder-summary = Summary of the day
+stop-reminder-pomodoro = Pomodoro
+stop-reminder-preset = The { $preset } preset
+button-stop-everything = Stop everything
+button-stop-confirm = Yes, stop
+button-stop-cancel = No
+button-stop-undo = Undo
+button-pomodoro-pause = Pause
+button-pomodoro-resume = Resume
+button-pomodoro-skip = Skip
+button-pomodoro-stop = Stop
+
+done-delayed = Notifications delayed until tomorrow
+done-resuming = Notifications delayed. Resuming { $resume } your time
+done-nothing = Nothing to delay
+notification-cleared = ✅ Done
+
+timezone-prompt =
+    Current timezone: { $timezone }
+
+    Send new timezone.
+    Examples:
+    1. +05:00
+    2. -03:00
+    3. +03:30
+    4. Europe/Moscow, the offset then follows daylight saving time
+
+    Or share your location to find the timezone.
+timezone-disabled = Timezone cannot be changed while notifications are disabled
+timezone-invalid = Invalid timezone
+timezone-changed = Timezone is changed: { $timezone }
+button-location = Share location
+
+language-usage =
+    Current language: { $language }
+
+    Available languages:
+    { $languages }
+
+    Send "/language <code>" to change it, e.g. "/language en"
+language-unknown = Unknown language "{ $code }"
+language-disabled = Language cannot be changed while notifications are disabled
+language-changed = Language is changed: { $language }
+
+notification-footer = Send the "/done" command to turn off notifications until tomorrow
+
+button-done = Done for today
+button-snooze = Snooze 1h
+button-stop = Stop
+button-ack = Got it
+
+footer-usage = Send "/footer on" or "/footer off" to show or hide the hint under notifications
+footer-enabled = The hint under notifications is shown
+footer-disabled = The hint under notifications is hidden
+
+message-usage = Send "/addmessage" with a text of your own, e.g. "/addmessage Time to stretch!". Notifications then rotate through your texts instead of the usual one
+message-list =
+    Your notification texts:
+    { $messages }
+
+    Send "/addmessage" with a text to add one or "/removemessage" with its number to remove it
+message-added = { $count ->
+    [one] Added, notifications now use your text instead of the usual one
+   *[other] Added, notifications rotate through your { $count } texts
+}
+message-too-long = The text is too long, it can have at most { $limit } characters
+message-too-many = You have { $limit } texts already, remove one with "/removemessage" first
+message-remove-usage = Send "/removemessage" with the number of a text "/addmessage" lists, or "/removemessage all"
+message-removed = Text { $number } removed
+message-all-removed = Your texts are removed, notifications use the usual one again
+preset-list =
+    Pick a ready-made reminder, it comes during working hours along with your notifications:
+    /preset water: drink water every { $water }
+    /preset stretch: stretch every { $stretch }
+    /preset eyes: rest your eyes by the 20-20-20 rule every { $eyes }
+preset-applied = Done, the { $preset } reminder comes every { $interval } during working hours along with your notifications. "/stop" removes it
+preset-exists = The { $preset } reminder is already on, "/stop" removes it
+preset-water-1 = 💧 Time for a glass of water
+preset-water-2 = 💧 Stay hydrated, take a few sips
+preset-water-3 = 💧 Water break! Refill your glass
+preset-stretch-1 = 🤸 Stand up and stretch your back and shoulders
+preset-stretch-2 = 🤸 Time to move: roll your neck and stretch your legs
+preset-stretch-3 = 🤸 Stretch break! Walk around for a couple of minutes
+preset-eyes-1 = 👀 20-20-20: look at something 20 feet (6 m) away for 20 seconds
+preset-eyes-2 = 👀 Give your eyes a rest, look out of the window for 20 seconds
+preset-eyes-3 = 👀 Blink a few times and look into the distance for 20 seconds
+parse-mode-usage =
+    Your texts are read as { $mode }.
+    Send "/parsemode markdownv2" or "/parsemode html" to format them with Telegram's markup, "/parsemode plain" for plain text or "/parsemode default" to follow the bot's messages
+parse-mode-changed = Your texts are read as { $mode }, the values filled in are escaped for you
+
+as-usage =
+    Send "/as <chat id> <view>" to see another chat the way it sees the bot.
+    Views: settings, jobs, insights
+as-unknown-chat = Chat { $chat } has no settings
+
+settings-view =
+    Notifications: { $active ->
+        [yes] running
+       *[no] paused
+    }
+    Timezone: { $timezone }
+    Working hours: { $from }–{ $to }
+    Interval: { $interval }
+    Language: { $language }
+    Hint under notifications: { $footer ->
+        [yes] shown
+       *[no] hidden
+    }
+
+clock-skew-alert = The server clock is { $skew } { $direction ->
+        [ahead] ahead of
+       *[behind] behind
+    } Telegram's, notifications may come at the wrong time
+
+first-send-usage = Send "/firstsend on" to get a notification right after "/start" during working hours, or "/firstsend off" to wait for the next scheduled one
+first-send-enabled = "/start" will send a notification right away
+first-send-disabled = "/start" will wait for the next scheduled notification
+
+ack-mode-usage = Send "/ackmode on" to get each notification repeated until you press "Got it" or send "/ack", or "/ackmode off" to get it once
+ack-mode-enabled = Notifications will be repeated every { $interval } until you press "Got it"
+ack-mode-disabled = Notifications will be sent once
+ack-done = Got it, the next notification comes as scheduled
+ack-nothing = No notification is waiting for an acknowledgement
+
+single-message-usage = Send "/singlemessage on" to have each notification edit the day's previous one instead of coming as a new message, or "/singlemessage off" to get new messages
+single-message-enabled = Notifications will edit the day's previous one, they come without a sound after the first
+single-message-disabled = Notifications will come as new messages
+
+summary-usage = Send "/summary on" to get a summary of the day at the end of working hours, or "/summary off" to stop it
+summary-enabled = The summary of the day will come every working day at { $time }
+summary-disabled = The summary of the day is off
+summary =
+    Today you got { $sent } { $sent ->
+        [one] notification
+       *[other] notifications
+    }. { $done ->
+        [yes] You finished early with "/done"
+       *[no] "/done" wasn't used today
+    }
+
+start-groups-usage = Send "/startgroups <chat id> <chat id> ..." to start notifications in groups the bot is a member of
+start-groups-started = started
+start-groups-already-started = already started
+start-groups-invalid-id = not a chat id
+start-groups-unavailable = chat not found or the bot can't see it
+start-groups-not-group = not a group
+start-groups-not-member = the bot is not a member
+
+profile-usage =
+    Send "/profile set <name>; <timezone>; <working hours>; <holidays>" to define a team profile, e.g. "/profile set Berlin office; Europe/Berlin; 09:00-18:00; 2024-12-25, 2024-12-26". The holidays are optional.
+    Send "/profile remove <name>" to remove one, or "/profile" to list them
+profile-empty = No profiles yet
+profile-item = { $name }: { $timezone }, { $from }–{ $to }, holidays: { $holidays }, chats: { $chats }
+profile-saved = Profile "{ $name }" is saved, chats updated: { $chats }
+profile-removed = Profile "{ $name }" is removed, chats detached: { $chats }
+profile-unknown = Unknown profile "{ $name }"
+
+join-profile-usage =
+    Send "/joinprofile <name>" to take the timezone, working hours and holidays of a team profile and follow its changes, or "/joinprofile off" to leave it.
+    Profiles: { $profiles }
+    Current: { $current }
+join-profile-joined = You joined "{ $name }": { $timezone }, notifications from { $from } to { $to }
+join-profile-left = You left "{ $name }", your settings no longer follow it
+join-profile-none = You haven't joined a profile
+
+transfer-code = Send "/transfer { $code }" from the chat or account that should get your settings within { $ttl }. The code works once
+transfer-invalid = The code is wrong or expired, send "/transfer" in the old chat to get a new one
+transfer-same = The settings are in this chat already
+transfer-done = Settings moved here, notifications continue in this chat
+transfer-moved = Settings moved to chat { $chat }, this chat gets no more notifications
+
+whats-new = What's new in version { $version }
+whats-new-footer = Send "/whatsnew off" to stop getting these notes
+whats-new-usage = Send "/whatsnew" to see what's new, "/whatsnew off" to stop getting release notes or "/whatsnew on" to get them again
+whats-new-enabled = You'll get release notes after updates
+whats-new-disabled = You won't get release notes anymore
+whats-new-0-1-0 =
+    • "/timer", "/report" and "/summary" for countdowns, monthly timesheets and a summary of the day
+    • Team profiles: join one with "/joinprofile" to share a timezone, hours and holidays
+    • "/next" shows when the next notifications come
+    • "/transfer" moves your settings to a new chat or account
+    • In groups notifications go to the topic "/start" was sent from
+
+jobs-empty = No pending jobs
+jobs-summary =
+    Pending jobs: { $count }
+
+    { $jobs }
+
+maintenance-refused = Maintenance in progress, settings can't be changed right now. Notifications keep coming as scheduled
+maintenance-usage =
+    Maintenance mode is { $state }
+    Send "/maintenance on" to refuse changes to chats, e.g. during a database migration, and "/maintenance off" to allow them again
+maintenance-enabled = Maintenance mode is on, chats can't change their settings
+maintenance-disabled = Maintenance mode is off
+
+insights-empty = No "/done" presses recorded yet
+insights-view =
+    "/done" pressed { $total } { $total ->
+        [one] time
+       *[other] times
+    }, most often at { $peak }
+
+    { $chart }
+insights-all =
+    Chats that pressed "/done": { $chats }
+    { $insights }
+
+suggest-not-enough = Not enough history yet: "/done" was pressed { $total } { $total ->
+        [one] time
+       *[other] times
+    }, suggestions need { $needed }
+suggest-nothing = Your schedule already fits when you press "/done"
+suggest-window =
+    Notifications go out from { $from } to { $to }, but you never press "/done" before { $new_from }.
+    Send them from { $new_from } to { $new_to } instead?
+suggest-apply = Apply
+suggest-applied = Notifications are now sent from { $from } to { $to }
+
+token-show =
+    Send a POST request to /trigger/{ $token } on the bot's HTTP API to get the notification right away.
+    Keep the token secret, "/token new" replaces it and "/token off" turns it off.
+token-revoked = Token turned off
+token-usage = Send "/token" to see the token, "/token new" to replace it or "/token off" to turn it off
+
+template-usage =
+    Alerts of GitHub, Grafana, Alertmanager and UptimeRobot posted to /hook/<token> are formatted, any other JSON is relayed as is.
+    Send "/template" followed by a text to render it instead, e.g.
+    /template {"{{"}status{"}}"}: {"{{"}alerts.0.labels.alertname{"}}"}
+template-show =
+    Current template:
+    { $template }
+
+    Send "/template off" to relay JSON as is
+template-set = Template saved
+template-cleared = Template removed, JSON will be relayed as is
+
+webhook-show =
+    Point GitHub, Grafana, Alertmanager or UptimeRobot webhooks at
+    { $url }
+    and their alerts will come here. Keep the URL secret, "/webhook new" replaces it and "/webhook off" turns it off.
+webhook-no-url =
+    Point GitHub, Grafana, Alertmanager or UptimeRobot webhooks at /hook/{ $token } on the bot's HTTP API and their alerts will come here.
+    Keep the token secret, "/webhook new" replaces it and "/webhook off" turns it off.
+webhook-off = Webhook turned off, so is the "/token" it shares
+webhook-usage = Send "/webhook" to see the URL, "/webhook new" to replace it or "/webhook off" to turn it off
+
+set-time-prompt =
+    Current working hours: { $from }–{ $to }
+
+    Send new working hours in whole hours.
+    Example: 08:00-20:00
+set-time-invalid = Invalid working hours, send them like 08:00-20:00
+set-time-changed = Notifications are now sent from { $from } to { $to } on working days
+
+preview-timezone = New timezone: { $timezone }
+preview-working-hours = New working hours: { $from }–{ $to }
+preview-next =
+    Your next notifications would be at:
+    { $dates }
+preview-none = No notifications would be sent
+preview-cancelled = Nothing changed
+button-confirm = Confirm
+button-cancel = Cancel
+
+settings-menu =
+    Time zone: { $timezone }
+    Working hours: { $from }–{ $to }
+    Interval: { $interval }
+    Workdays: { $days }
+    Hint under notifications: { $footer ->
+        [yes] shown
+       *[no] hidden
+    }
+settings-choose-timezone = Choose your time zone, or send "/changetimezone" for a named one
+settings-choose-hours = Choose working hours, or send "/settime" for others
+settings-choose-interval = Choose how often notifications are sent, or send "/interval" with another interval
+settings-choose-workdays = Press a day to turn notifications on it on or off
+settings-choose-message = Choose what follows the message text
+settings-button-timezone = Time zone
+settings-button-hours = Working hours
+settings-button-interval = Interval
+settings-button-workdays = Workdays
+settings-button-message = Message
+settings-button-footer = Hint about "/done"
+settings-button-back = « Back
+settings-button-close = Close
+
+interval-usage =
+    Notifications are sent every { $interval }.
+    Send "/interval" with a new interval to change it, e.g. "/interval 30m" or "/interval 2h"
+interval-out-of-range = The interval must be from { $min } to { $max }
+interval-changed = Notifications are now sent every { $interval }
+
+cron-usage =
+    Current cron schedule: { $current }
+    Send "/cron" with seconds, minutes, hours, day of month, month and weekday to send notifications on that schedule instead of working hours, e.g. "/cron 0 0 9-18/2 * * MON-FRI". Send "/cron off" to go back to working hours
+cron-invalid = Invalid cron schedule: { $error }
+cron-too-frequent = Notifications can't be sent more often than every { $min }
+cron-set = Notifications follow the cron schedule now, working hours, workdays and the interval are ignored. Next one at { $next }
+cron-off = Notifications follow working hours again
+
+workdays-usage =
+    Notifications are sent on: { $days }.
+    Send "/workdays" with the days you want them on, e.g. "/workdays mon-fri" or "/workdays mon, wed, sat-sun"
+workdays-changed = Notifications are now sent on: { $days }
+quiet-usage = Send "/quiet" with breaks you don't want notifications in, e.g. "/quiet 13:00-14:00" or "/quiet 13:00-14:00, 16:00-16:30"
+quiet-show =
+    No notifications during: { $hours }.
+    Send "/quiet" with other breaks to replace them or "/quiet off" to remove them
+quiet-changed = No notifications during: { $hours }
+quiet-cleared = Quiet hours removed, notifications come throughout working hours
+soft-hours-usage = Send "/softhours" with how many hours at the start and the end of working hours notifications come without a sound in, up to { $max }, e.g. "/softhours 1"
+soft-hours-show =
+    Notifications come without a sound in the first and the last { $hours } of working hours.
+    Send "/softhours" with other hours to change it or "/softhours off" to turn it off
+soft-hours-changed = Notifications will come without a sound in the first and the last { $hours } of working hours
+soft-hours-cleared = Notifications will come with a sound throughout working hours
+
+away-set = Notifications are muted, send "/back" when you return
+away-already = You are already away, send "/back" to unmute notifications
+back-set = Welcome back, notifications are unmuted
+back-already = Notifications aren't muted
+
+alias-list =
+    Shortcuts:
+    { $aliases }
+
+    Send "/alias s stop" to add one or "/alias s" to remove it
+alias-usage = Send "/alias s stop" to add a shortcut or "/alias s" to remove it
+alias-invalid = "{ $alias }" can't be a shortcut, use up to 16 lowercase latin letters and digits that aren't a command
+alias-unknown-command = There is no "/{ $command }" command
+alias-set = "/{ $alias }" now runs "/{ $command }"
+alias-removed = Shortcut "/{ $alias }" removed
+
+snooze-usage = Send "/snooze" with a duration, e.g. "/snooze 45m" or "/snooze 3h"
+snooze-too-long = Notifications can be snoozed for at most { $max }
+snooze-set = Notifications are snoozed for { $duration }, until { $until }
+skip-usage = Send "/skip" to skip the next notification, "/skip" with a number up to { $max } to skip that many or "/skip off" to get them all again
+skip-set = { $count ->
+    [one] The next notification
+   *[other] The next { $count } notifications
+} will be skipped, the schedule stays the same
+skip-cleared = No notifications will be skipped
+
+remind-usage =
+    Send "/remind" with a time or a delay and the text, e.g. "/remind 15:30 call mom" or "/remind 45m stretch"
+
+    Start with "high" for a reminder that comes through "/snooze", "/done" and "/away", or with "low" for one that may be skipped when the bot is busy: "/remind high 15:30 call mom"
+remind-set = I'll remind you at { $until }
+
+timer-usage = Send "/timer" with a duration and the text, e.g. "/timer 25m tea is ready"
+timer-too-long = A timer can be at most { $max }
+timer-default-text = Time's up!
+timer-running = ⏳ { $text }: { $remaining } left
+timer-done = ⌛ { $text }: done
+
+pomodoro-usage = Send "/pomodoro" to work for 25 minutes and rest for 5 in turns, "/pomodoro 50 10" for other lengths in minutes or "/pomodoro off" to stop
+pomodoro-started = 🍅 Work for { $work }, then take a break for { $rest }
+pomodoro-break = ☕ Round { $round } is done, take a break for { $rest }
+pomodoro-work = 🍅 The break is over, round { $round }: work for { $work }
+pomodoro-paused = ⏸ Paused with { $remaining } left
+pomodoro-resumed = ▶️ Resumed, { $remaining } left
+pomodoro-stopped = ⏹ Pomodoro stopped
+pomodoro-none = No pomodoro is running
+pomodoro-expired = ⏹ Pomodoro stopped, it was paused for too long
+
+report-usage =
+    Send "/report" with a day of the month, a time and the text, e.g. "/report 25 10:00 send the timesheet".
+    Every month the reminder comes with a timesheet of your working days attached. Send "/report off" to stop it
+report-set = Every month on day { $day } at { $time } you'll get the reminder with a timesheet, the first one at { $until }
+report-off = Monthly report reminder is off
+
+weekly-usage =
+    Send "/weekly" with weekdays, a time and the text, e.g. "/weekly mon 10:00 plan the week" or "/weekly mon,thu 17:00 water the plants".
+    "/weekly" lists your weekly reminders, "/weekly off" drops them
+monthly-usage =
+    Send "/monthly" with a day of the month, a time and the text, e.g. "/monthly 1 10:00 pay the rent". Shorter months get it on their last day.
+    "/monthly" lists your monthly reminders, "/monthly off" drops them
+weekly-when = Every { $weekday } at { $time }
+monthly-when = Every month on day { $day } at { $time }
+recurring-set = { $when } I'll remind you, the first time at { $until }
+recurring-list = Your reminders:
+    { $reminders }
+recurring-off = { $count ->
+    [0] You have no such reminders
+    [one] The reminder is dropped
+   *[other] { $count } reminders are dropped
+}
+recurring-too-many = You can have at most { $max } of these, drop some with "/{ $command } off"
+
+deadline-usage =
+    Send "/deadline" with a date and what's due, e.g. "/deadline 2024-06-30 send the report", or add a time: "/deadline 2024-06-30 12:00 send the report". Without a time it's due when your working hours end.
+    Reminders come every week, then every day in the last week and every hour on the last day. "/deadline" lists your deadlines, "/deadline off" drops them
+deadline-past = The deadline must be in the future and at most a year ahead
+deadline-set = Deadline "{ $title }" is set for { $due }, the first reminder comes { $first }
+deadline-list = Your deadlines:
+    { $deadlines }
+deadline-off = { $count ->
+    [0] You have no deadlines
+    [one] The deadline is dropped
+   *[other] { $count } deadlines are dropped
+}
+deadline-days = 📅 { $title }: { $count ->
+    [one] 1 day
+   *[other] { $count } days
+} left, due { $due }
+deadline-hours = ⏰ { $title }: { $count ->
+    [one] 1 hour
+   *[other] { $count } hours
+} left, due at { $due }
+deadline-due = 🚩 { $title } is due now
+
+check-in-usage = Send "/checkin" with a period and the id of a chat to alert when you don't write to me for that long, e.g. "/checkin 24h -1001234567890". Any message counts as a check-in, "/checkin off" turns it off
+check-in-out-of-range = The period must be from { $min } to { $max }
+check-in-unreachable = I can't reach that chat, add me there first
+check-in-set = Check-in is on: write to me at least every { $period } or chat { $contact } gets an alert
+check-in-off = Check-in is off
+check-in-view = Write to me at least every { $period } or chat { $contact } gets an alert, next one at { $due }
+check-in-alert = ⚠️ { $name } hasn't written to the bot for { $period }, please check on them
+
+status-stopped = Notifications are off, send "/start" to turn them on
+status-view =
+    Notifications: { $state ->
+        [running] on
+        [away] muted until "/back"
+        [wake_up] done for today, back at { $until }
+        [snooze] snoozed until { $until }
+       *[paused] paused
+    }
+    Next notification: { $next }
+    Timezone: { $timezone }
+    Working hours: { $from }–{ $to }
+    Interval: { $interval }
+
+next-list =
+    Your next notifications are at:
+    { $dates }
+next-none = No notifications are coming
+next-blackout = No notifications from { $from } to { $to }: { $reason }
+next-blackout-reason = a break for everyone
+
+time-audit =
+    Your local time: { $local }
+    UTC: { $utc }
+    Server clock: { $server }
+    Timezone: { $timezone }
+    Stored offset: { $offset }
+    Next notification: { $next }
+
+    If your local time is wrong, fix the timezone with "/changetimezone"
+time-audit-next = { $date } ({ $utc }), in { $until }
+
+history-list =
+    Your last notifications:
+    { $sends }
+history-empty = No notifications were sent to you yet
+history-delivered = delivered
+history-failed = failed, { $error }
+mystats =
+    Notifications this week: { $week }, this month: { $month }
+    Average time until "/done": { $average }
+    Streak: { $streak ->
+        [one] one day
+       *[other] { $streak } days
+    } in a row with "/done"
+mystats-no-average = no "/done" after a notification yet
+
+budget-digest = { $count ->
+    [one] One earlier notification was held back
+   *[other] { $count } earlier notifications were held back
+} to stay within the bot's daily message budget
+budget-tight-alert = { $spent } of { $limit } messages of today's budget are sent. Until the day ends (UTC) only every other notification goes out and low priority messages are dropped
+budget-exhausted-alert = Today's budget of { $limit } messages is spent. Until the day ends (UTC) only high priority messages go out
+
+channels-list =
+    Notifications go through the first of these channels that works:
+    { $channels }
+
+    Available: { $available }. Send "/channels slack <webhook URL>" to add a channel, "/channels slack off" to remove it or "/channels slack telegram" to change the order
+channels-no-sends = nothing sent yet
+channels-delivered = delivered at { $time }
+channels-failed = failed at { $time }: { $reason }
+channels-unknown = There's no channel "{ $channel }"
+channels-no-address = Tell where "{ $channel }" delivers first, e.g. "/channels { $channel } <address>"
+channels-invalid-address = "{ $channel }" can't deliver to this address
+channels-added = Notifications go through "{ $channel }" when the channels before it fail, send "/channels" to change the order
+channels-removed = Notifications don't go through "{ $channel }" anymore
+
+connect-usage = Send "/connect <URL> [token]" to post every "/done" to a URL, e.g. to tick off a habit in Habitica or Beeminder. The token goes in the Authorization header
+connect-show = Every "/done" is posted to { $url } { $token ->
+    [yes] with a token
+   *[no] without a token
+}. Send "/connect off" to stop
+connect-set = Every "/done" will be posted to { $url }
+connect-removed = "/done" won't be posted anywhere anymore
+connect-invalid = That's not a public HTTPS URL, send "/connect <URL> [token]"
+
+help =
+    Commands:
+    { $commands }
+command-start = Start notifications sending
+command-help = List the commands and what they do
+command-stop = Stop notifications sending
+command-done = Stop notifications until tomorrow
+command-status = Show the current subscription state
+command-next = Show when the next notifications come
+command-whattimedoyouthinkitis = Show the time the bot thinks it is for you and in UTC
+command-history = Show your last notifications and whether they arrived
+command-mystats = Show how many notifications you got and your "/done" streak
+command-snooze = Pause notifications for a while, e.g. "/snooze 45m"
+command-skip = Skip the next notifications, e.g. "/skip 3"
+command-remind = Send a message once at a given time, e.g. "/remind 15:30 call mom"
+command-weekly = Remind every week, e.g. "/weekly mon 10:00 plan the week"
+command-monthly = Remind every month, e.g. "/monthly 1 10:00 pay the rent"
+command-checkin = Alert a contact when you don't write to the bot for a while, e.g. "/checkin 24h -1001234567890"
+command-timer = Count down and send a message when time is up, e.g. "/timer 25m tea"
+command-pomodoro = Work and take breaks in turns, 25 and 5 minutes or e.g. "/pomodoro 50 10"
+command-report = Remind every month with a timesheet attached, e.g. "/report 25 10:00 send the timesheet"
+command-deadline = Remind more often as a date comes closer, e.g. "/deadline 2024-06-30 send the report"
+command-settings = Change settings by pressing buttons
+command-changetimezone = Start time zone change dialog
+command-settime = Start working hours change dialog
+command-interval = Show or change how often notifications are sent
+command-workdays = Show or change the weekdays notifications are sent on
+command-quiet = Pause notifications for breaks, e.g. "/quiet 13:00-14:00"
+command-softhours = Get notifications without a sound at the ends of working hours, e.g. "/softhours 1"
+command-cron = Follow a cron schedule instead of working hours, e.g. "/cron 0 0 9-18 * * MON-FRI"
+command-alias = List, add or remove command shortcuts
+command-away = Mute notifications while you are away
+command-back = Unmute notifications muted with "/away"
+command-language = Show or change the language of replies
+command-footer = Turn the hint under notifications on or off
+command-addmessage = Add your own notification text to rotate through, e.g. "/addmessage Stretch!"
+command-removemessage = Remove one of your notification texts, e.g. "/removemessage 2"
+command-preset = Add a ready-made reminder, e.g. "/preset water"
+command-parsemode = Show or change how your own texts are formatted, e.g. "/parsemode html"
+command-firstsend = Choose whether "/start" sends a notification right away
+command-ackmode = Repeat notifications until you press "Got it", e.g. "/ackmode on"
+command-ack = Acknowledge the last notification in ack mode
+command-singlemessage = Edit the day's notification instead of sending new ones, e.g. "/singlemessage on"
+command-summary = Get a summary of the day at the end of working hours, e.g. "/summary on"
+command-insights = Show at which hours you usually press "/done"
+command-token = Show the token for triggering notifications over HTTP
+command-template = Set the template for JSON posted to the HTTP hook
+command-webhook = Get a URL services like GitHub or Grafana can post alerts to
+command-suggest = Suggest a schedule based on when you press "/done"
+command-allinsights = Admin: show at which hours all chats press "/done"
+command-as = Admin: show a read-only view of another chat
+command-startgroups = Admin: start notifications in several groups at once
+command-jobs = Admin: list pending delayed jobs
+command-journal = Admin: export every notification sent as CSV
+command-maintenance = Admin: turn read-only maintenance mode on or off
+command-profile = Admin: list, define or remove team profiles
+command-joinprofile = Join a team profile, e.g. "/joinprofile Berlin office"
+command-transfer = Move your settings to another chat with a one-time code
+command-whatsnew = Show what's new, or turn release notes on or off
+command-channels = List or change where notifications go, e.g. "/channels slack <webhook URL>"
+command-connect = Post to a URL on every "/done", e.g. "/connect https://example.com/hook token"
@@ -0,0 +1,597 @@
+error = Что-то пошло не так 😫
+not-started = Сначала включите уведомления командой "/start"
+
+start-started =
+    Уведомления включены!
+    Текущий часовой пояс: { $timezone }
+    Уведомления будут приходить с { $from } до { $to } каждый час, пока не будет отправлена команда "/done"
+    Отправьте "/language", чтобы сменить язык ответов
+start-already-started = Уже включено!
+start-next = Следующее уведомление в { $next } по вашему времени
+start-next-now = Первое уведомление придёт прямо сейчас
+onboarding-timezone =
+    Добро пожаловать! Три вопроса, и уведомления включатся.
+    1/3. Какой у вас часовой пояс? Отправьте смещение вроде +05:00 или название вроде Europe/Moscow либо поделитесь местоположением
+onboarding-working-hours =
+    2/3. Когда вы работаете? Отправьте целые часы вроде 09:00-18:00, если пропустить, будет { $from }–{ $to }
+onboarding-workdays =
+    3/3. В какие дни вы работаете? Отправьте их вроде пн-пт или пн, ср, сб-вс, если пропустить, будет с понедельника по пятницу
+onboarding-workdays-invalid = Неверные дни, отправьте их вроде пн-пт или пн, ср, сб-вс
+button-skip = Пропустить
+group-admins-only = Включать и выключать уведомления могут только администраторы группы
+
+stop-stopped = Остановлено!
+stop-nothing = Нечего останавливать
+stop-confirm = Остановить уведомления и удалить ваши настройки?
+stop-undo-hint = Настройки хранятся ещё сутки, нажмите "Отменить", чтобы вернуть их
+stop-cancelled = Уведомления приходят как прежде
+stop-restored = Настройки вернулись, уведомления снова включены
+stop-undo-expired = Отменить уже нельзя, отправьте "/start", чтобы начать заново
+stop-choose = Остановить уведомления и удалить ваши настройки или остановить только одно из напоминаний?
+stop-reminder-stopped = Остановлено: { $reminder }
+stop-reminder-gone = Это напоминание уже пришло или было остановлено
+stop-reminder-summary = Сводка дня
+stop-reminder-pomodoro = Помодоро
+stop-reminder-preset = Набор { $preset }
+button-stop-everything = Остановить всё
+button-stop-confirm = Да, остановить
+button-stop-cancel = Нет
+button-stop-undo = Отменить
+button-pomodoro-pause = Пауза
+button-pomodoro-resume = Продолжить
+button-pomodoro-skip = Пропустить
+button-pomodoro-stop = Остановить
+
+done-delayed = Уведомления отложены до завтра
+done-resuming = Уведомления отложены. Возобновятся { $resume } по вашему времени
+done-nothing = Нечего откладывать
+notification-cleared = ✅ Готово
+
+timezone-prompt =
+    Текущий часовой пояс: { $timezone }
+
+    Отправьте новый часовой пояс.
+    Примеры:
+    1. +05:00
+    2. -03:00
+    3. +03:30
+    4. Europe/Moscow, тогда смещение учитывает летнее время
+
+    Или отправьте геопозицию, чтобы определить часовой пояс.
+timezone-disabled = Часовой пояс нельзя изменить, пока уведомления выключены
+timezone-invalid = Неверный часовой пояс
+timezone-changed = Часовой пояс изменён: { $timezone }
+button-location = Отправить геопозицию
+
+language-usage =
+    Текущий язык: { $language }
+
+    Доступные языки:
+    { $languages }
+
+    Отправьте "/language <код>", чтобы сменить язык, например "/language ru"
+language-unknown = Неизвестный язык "{ $code }"
+language-disabled = Язык нельзя изменить, пока уведомления выключены
+language-changed = Язык изменён: { $language }
+
+notification-footer = Отправьте команду "/done", чтобы выключить уведомления до завтра
+
+button-done = На сегодня всё
+button-snooze = Отложить на час
+button-stop = Остановить
+button-ack = Понятно
+
+footer-usage = Отправьте "/footer on" или "/footer off", чтобы показать или скрыть подсказку под уведомлениями
+footer-enabled = Подсказка под уведомлениями показывается
+footer-disabled = Подсказка под уведомлениями скрыта
+
+message-usage = Отправьте "/addmessage" со своим текстом, например "/addmessage Пора размяться!". Тогда уведомления будут чередовать ваши тексты вместо обычного
+message-list =
+    Ваши тексты уведомлений:
+    { $messages }
+
+    Отправьте "/addmessage" с текстом, чтобы добавить ещё, или "/removemessage" с его номером, чтобы удалить
+message-added = { $count ->
+    [one] Добавлено, теперь уведомления приходят с вашим текстом вместо обычного
+    [few] Добавлено, уведомления чередуют ваши { $count } текста
+   *[other] Добавлено, уведомления чередуют ваши { $count } текстов
+}
+message-too-long = Текст слишком длинный, в нём может быть не больше { $limit } символов
+message-too-many = У вас уже { $limit } текстов, сначала удалите один с помощью "/removemessage"
+message-remove-usage = Отправьте "/removemessage" с номером текста из списка "/addmessage" или "/removemessage all"
+message-removed = Текст { $number } удалён
+message-all-removed = Ваши тексты удалены, уведомления снова приходят с обычным
+preset-list =
+    Выберите готовое напоминание, оно приходит в рабочие часы вместе с вашими уведомлениями:
+    /preset water: пить воду каждые { $water }
+    /preset stretch: разминаться каждые { $stretch }
+    /preset eyes: давать отдых глазам по правилу 20-20-20 каждые { $eyes }
+preset-applied = Готово, напоминание { $preset } приходит каждые { $interval } в рабочие часы вместе с вашими уведомлениями. "/stop" удалит его
+preset-exists = Напоминание { $preset } уже включено, "/stop" удалит его
+preset-water-1 = 💧 Время выпить стакан воды
+preset-water-2 = 💧 Не забывайте пить, сделайте пару глотков
+preset-water-3 = 💧 Перерыв на воду! Наполните стакан
+preset-stretch-1 = 🤸 Встаньте и потяните спину и плечи
+preset-stretch-2 = 🤸 Пора подвигаться: разомните шею и ноги
+preset-stretch-3 = 🤸 Перерыв на разминку! Пройдитесь пару минут
+preset-eyes-1 = 👀 20-20-20: посмотрите на что-нибудь в 6 метрах от вас в течение 20 секунд
+preset-eyes-2 = 👀 Дайте глазам отдохнуть, посмотрите в окно 20 секунд
+preset-eyes-3 = 👀 Поморгайте и посмотрите вдаль 20 секунд
+parse-mode-usage =
+    Ваши тексты читаются как { $mode }.
+    Отправьте "/parsemode markdownv2" или "/parsemode html", чтобы оформлять их разметкой Telegram, "/parsemode plain" для простого текста или "/parsemode default", чтобы читать их как сообщения бота
+parse-mode-changed = Ваши тексты читаются как { $mode }, подставляемые значения экранируются за вас
+
+as-usage =
+    Отправьте "/as <id чата> <раздел>", чтобы увидеть другой чат так, как его видит бот.
+    Разделы: settings, jobs, insights
+as-unknown-chat = У чата { $chat } нет настроек
+
+settings-view =
+    Уведомления: { $active ->
+        [yes] идут
+       *[no] на паузе
+    }
+    Часовой пояс: { $timezone }
+    Рабочее время: { $from }–{ $to }
+    Интервал: { $interval }
+    Язык: { $language }
+    Подсказка под уведомлениями: { $footer ->
+        [yes] показывается
+       *[no] скрыта
+    }
+
+clock-skew-alert = Часы сервера { $direction ->
+        [ahead] спешат
+       *[behind] отстают
+    } от Telegram на { $skew }, уведомления могут приходить не вовремя
+
+first-send-usage = Отправьте "/firstsend on", чтобы получать уведомление сразу после "/start" в рабочее время, или "/firstsend off", чтобы ждать следующего по расписанию
+first-send-enabled = "/start" сразу отправит уведомление
+first-send-disabled = "/start" дождётся следующего уведомления по расписанию
+
+ack-mode-usage = Отправьте "/ackmode on", чтобы уведомление повторялось, пока вы не нажмёте "Понятно" или не отправите "/ack", или "/ackmode off", чтобы получать его один раз
+ack-mode-enabled = Уведомления будут повторяться каждые { $interval }, пока вы не нажмёте "Понятно"
+ack-mode-disabled = Уведомления будут приходить один раз
+ack-done = Понятно, следующее уведомление придёт по расписанию
+ack-nothing = Нет уведомлений, ожидающих подтверждения
+
+single-message-usage = Отправьте "/singlemessage on", чтобы каждое уведомление изменяло предыдущее за день, а не приходило новым сообщением, или "/singlemessage off", чтобы получать новые сообщения
+single-message-enabled = Уведомления будут изменять предыдущее за день, после первого они приходят без звука
+single-message-disabled = Уведомления будут приходить новыми сообщениями
+
+summary-usage = Отправьте "/summary on", чтобы в конце рабочего дня получать его итоги, или "/summary off", чтобы отключить их
+summary-enabled = Итоги дня будут приходить каждый рабочий день в { $time }
+summary-disabled = Итоги дня отключены
+summary =
+    Уведомлений за сегодня: { $sent }. { $done ->
+        [yes] Вы закончили пораньше с "/done"
+       *[no] "/done" сегодня не использовалась
+    }
+
+start-groups-usage = Отправьте "/startgroups <id чата> <id чата> ...", чтобы включить уведомления в группах, где состоит бот
+start-groups-started = включено
+start-groups-already-started = уже включено
+start-groups-invalid-id = это не id чата
+start-groups-unavailable = чат не найден или недоступен боту
+start-groups-not-group = это не группа
+start-groups-not-member = бот не состоит в группе
+
+profile-usage =
+    Отправьте "/profile set <название>; <часовой пояс>; <рабочие часы>; <праздники>", чтобы задать профиль команды, например "/profile set Берлинский офис; Europe/Berlin; 09:00-18:00; 2024-12-25, 2024-12-26". Праздники можно не указывать.
+    Отправьте "/profile remove <название>", чтобы удалить профиль, или "/profile", чтобы увидеть список
+profile-empty = Профилей пока нет
+profile-item = { $name }: { $timezone }, { $from }–{ $to }, праздников: { $holidays }, чатов: { $chats }
+profile-saved = Профиль "{ $name }" сохранён, обновлено чатов: { $chats }
+profile-removed = Профиль "{ $name }" удалён, отсоединено чатов: { $chats }
+profile-unknown = Неизвестный профиль "{ $name }"
+
+join-profile-usage =
+    Отправьте "/joinprofile <название>", чтобы взять часовой пояс, рабочие часы и праздники профиля команды и следовать его изменениям, или "/joinprofile off", чтобы выйти из него.
+    Профили: { $profiles }
+    Текущий: { $current }
+join-profile-joined = Вы присоединились к "{ $name }": { $timezone }, уведомления с { $from } до { $to }
+join-profile-left = Вы вышли из "{ $name }", ваши настройки больше не следуют ему
+join-profile-none = Вы не состоите в профиле
+
+transfer-code = Отправьте "/transfer { $code }" из чата или аккаунта, куда нужно перенести настройки, в течение { $ttl }. Код действует один раз
+transfer-invalid = Код неверный или устарел, отправьте "/transfer" в старом чате, чтобы получить новый
+transfer-same = Настройки уже в этом чате
+transfer-done = Настройки перенесены, уведомления продолжатся в этом чате
+transfer-moved = Настройки перенесены в чат { $chat }, сюда уведомления больше не придут
+
+whats-new = Что нового в версии { $version }
+whats-new-footer = Отправьте "/whatsnew off", чтобы не получать такие заметки
+whats-new-usage = Отправьте "/whatsnew", чтобы узнать, что нового, "/whatsnew off", чтобы не получать заметки о выпусках, или "/whatsnew on", чтобы получать их снова
+whats-new-enabled = Заметки о выпусках будут приходить после обновлений
+whats-new-disabled = Заметки о выпусках больше не будут приходить
+whats-new-0-1-0 =
+    • "/timer", "/report" и "/summary" для обратного отсчёта, ежемесячного табеля и итогов дня
+    • Профили команд: вступите в профиль через "/joinprofile", чтобы разделить часовой пояс, рабочее время и праздники
+    • "/next" показывает, когда придут ближайшие уведомления
+    • "/transfer" переносит настройки в новый чат или аккаунт
+    • В группах уведомления приходят в тему, из которой отправили "/start"
+
+jobs-empty = Нет отложенных задач
+jobs-summary =
+    Отложенных задач: { $count }
+
+    { $jobs }
+
+maintenance-refused = Идут технические работы, настройки сейчас изменить нельзя. Уведомления приходят по расписанию
+maintenance-usage =
+    Режим обслуживания: { $state }
+    Отправьте "/maintenance on", чтобы запретить изменения в чатах, например на время миграции базы, и "/maintenance off", чтобы снова разрешить их
+maintenance-enabled = Режим обслуживания включён, чаты не могут менять настройки
+maintenance-disabled = Режим обслуживания выключен
+
+insights-empty = Нажатий "/done" пока не было
+insights-view =
+    "/done" нажата { $total } { $total ->
+        [few] раза
+       *[other] раз
+    }, чаще всего в { $peak }
+
+    { $chart }
+insights-all =
+    Чатов, нажимавших "/done": { $chats }
+    { $insights }
+
+suggest-not-enough = Пока мало истории: "/done" нажата { $total } { $total ->
+        [few] раза
+       *[other] раз
+    }, для подсказок нужно { $needed }
+suggest-nothing = Расписание уже подходит под то, когда вы нажимаете "/done"
+suggest-window =
+    Уведомления приходят с { $from } до { $to }, но вы ни разу не нажимали "/done" раньше { $new_from }.
+    Присылать их с { $new_from } до { $new_to }?
+suggest-apply = Применить
+suggest-applied = Теперь уведомления приходят с { $from } до { $to }
+
+token-show =
+    Отправьте POST-запрос на /trigger/{ $token } в HTTP API бота, чтобы сразу получить уведомление.
+    Храните токен в секрете, "/token new" заменит его, а "/token off" отключит.
+token-revoked = Токен отключён
+token-usage = Отправьте "/token", чтобы увидеть токен, "/token new", чтобы заменить его, или "/token off", чтобы отключить
+
+template-usage =
+    Оповещения GitHub, Grafana, Alertmanager и UptimeRobot, отправленные на /hook/<token>, оформляются, остальной JSON пересылается как есть.
+    Отправьте "/template" и текст, чтобы оформлять его по шаблону, например
+    /template {"{{"}status{"}}"}: {"{{"}alerts.0.labels.alertname{"}}"}
+template-show =
+    Текущий шаблон:
+    { $template }
+
+    Отправьте "/template off", чтобы пересылать JSON как есть
+template-set = Шаблон сохранён
+template-cleared = Шаблон удалён, JSON будет пересылаться как есть
+
+webhook-show =
+    Укажите в вебхуках GitHub, Grafana, Alertmanager или UptimeRobot адрес
+    { $url }
+    и их оповещения будут приходить сюда. Храните адрес в секрете, "/webhook new" заменит его, а "/webhook off" отключит.
+webhook-no-url =
+    Укажите в вебхуках GitHub, Grafana, Alertmanager или UptimeRobot адрес /hook/{ $token } в HTTP API бота, и их оповещения будут приходить сюда.
+    Храните токен в секрете, "/webhook new" заменит его, а "/webhook off" отключит.
+webhook-off = Вебхук отключён, как и общий с ним "/token"
+webhook-usage = Отправьте "/webhook", чтобы увидеть адрес, "/webhook new", чтобы заменить его, или "/webhook off", чтобы отключить
+
+set-time-prompt =
+    Текущее рабочее время: { $from }–{ $to }
+
+    Отправьте новое рабочее время в целых часах.
+    Пример: 08:00-20:00
+set-time-invalid = Неверное рабочее время, отправьте его в виде 08:00-20:00
+set-time-changed = Теперь уведомления приходят с { $from } до { $to } по рабочим дням
+
+preview-timezone = Новый часовой пояс: { $timezone }
+preview-working-hours = Новое рабочее время: { $from }–{ $to }
+preview-next =
+    Ближайшие уведомления придут:
+    { $dates }
+preview-none = Уведомления приходить не будут
+preview-cancelled = Ничего не изменилось
+button-confirm = Подтвердить
+button-cancel = Отмена
+
+settings-menu =
+    Часовой пояс: { $timezone }
+    Рабочее время: { $from }–{ $to }
+    Интервал: { $interval }
+    Рабочие дни: { $days }
+    Подсказка под уведомлениями: { $footer ->
+        [yes] показывается
+       *[no] скрыта
+    }
+settings-choose-timezone = Выберите часовой пояс или отправьте "/changetimezone", чтобы указать его по названию
+settings-choose-hours = Выберите рабочее время или отправьте "/settime", чтобы указать другое
+settings-choose-interval = Выберите, как часто присылать уведомления, или отправьте "/interval" с другим интервалом
+settings-choose-workdays = Нажмите на день, чтобы включить или выключить уведомления в этот день
+settings-choose-message = Выберите, что идёт после текста уведомления
+settings-button-timezone = Часовой пояс
+settings-button-hours = Рабочее время
+settings-button-interval = Интервал
+settings-button-workdays = Рабочие дни
+settings-button-message = Сообщение
+settings-button-footer = Подсказка о "/done"
+settings-button-back = « Назад
+settings-button-close = Закрыть
+
+interval-usage =
+    Уведомления приходят каждые { $interval }.
+    Отправьте "/interval" с новым интервалом, чтобы изменить его, например "/interval 30m" или "/interval 2h"
+interval-out-of-range = Интервал должен быть от { $min } до { $max }
+interval-changed = Теперь уведомления приходят каждые { $interval }
+
+cron-usage =
+    Текущее cron-расписание: { $current }
+    Отправьте "/cron" с секундами, минутами, часами, днём месяца, месяцем и днём недели, чтобы уведомления приходили по этому расписанию вместо рабочих часов, например "/cron 0 0 9-18/2 * * MON-FRI". Отправьте "/cron off", чтобы вернуться к рабочим часам
+cron-invalid = Неверное cron-расписание: { $error }
+cron-too-frequent = Уведомления нельзя отправлять чаще, чем раз в { $min }
+cron-set = Уведомления теперь приходят по cron-расписанию, рабочие часы, рабочие дни и интервал не учитываются. Следующее в { $next }
+cron-off = Уведомления снова приходят в рабочие часы
+
+workdays-usage =
+    Уведомления приходят по дням: { $days }.
+    Отправьте "/workdays" с нужными днями, например "/workdays пн-пт" или "/workdays пн, ср, сб-вс"
+workdays-changed = Теперь уведомления приходят по дням: { $days }
+quiet-usage = Отправьте "/quiet" с перерывами, в которые уведомления не нужны, например "/quiet 13:00-14:00" или "/quiet 13:00-14:00, 16:00-16:30"
+quiet-show =
+    Без уведомлений: { $hours }.
+    Отправьте "/quiet" с другими перерывами, чтобы заменить их, или "/quiet off", чтобы убрать
+quiet-changed = Без уведомлений: { $hours }
+quiet-cleared = Тихие часы убраны, уведомления приходят всё рабочее время
+soft-hours-usage = Отправьте "/softhours" с числом часов в начале и в конце рабочего времени, когда уведомления приходят без звука, до { $max }, например "/softhours 1"
+soft-hours-show =
+    Уведомления приходят без звука в первые и последние { $hours } рабочего времени.
+    Отправьте "/softhours" с другим числом часов, чтобы изменить, или "/softhours off", чтобы выключить
+soft-hours-changed = Уведомления будут приходить без звука в первые и последние { $hours } рабочего времени
+soft-hours-cleared = Уведомления будут приходить со звуком всё рабочее время
+
+away-set = Уведомления отключены, отправьте "/back", когда вернётесь
+away-already = Вы уже отошли, отправьте "/back", чтобы включить уведомления
+back-set = С возвращением, уведомления снова включены
+back-already = Уведомления не отключены
+
+alias-list =
+    Сокращения:
+    { $aliases }
+
+    Отправьте "/alias s stop", чтобы добавить сокращение, или "/alias s", чтобы удалить его
+alias-usage = Отправьте "/alias s stop", чтобы добавить сокращение, или "/alias s", чтобы удалить его
+alias-invalid = "{ $alias }" не может быть сокращением, используйте до 16 строчных латинских букв и цифр, не совпадающих с командой
+alias-unknown-command = Команды "/{ $command }" нет
+alias-set = "/{ $alias }" теперь выполняет "/{ $command }"
+alias-removed = Сокращение "/{ $alias }" удалено
+
+snooze-usage = Отправьте "/snooze" с длительностью, например "/snooze 45m" или "/snooze 3h"
+snooze-too-long = Уведомления можно отложить не больше чем на { $max }
+snooze-set = Уведомления отложены на { $duration }, до { $until }
+skip-usage = Отправьте "/skip", чтобы пропустить следующее уведомление, "/skip" с числом до { $max }, чтобы пропустить столько, или "/skip off", чтобы снова получать все
+skip-set = { $count ->
+    [one] Будет пропущено { $count } следующее уведомление
+    [few] Будут пропущены { $count } следующих уведомления
+   *[other] Будут пропущены { $count } следующих уведомлений
+}, расписание не меняется
+skip-cleared = Уведомления больше не пропускаются
+
+remind-usage =
+    Отправьте "/remind" со временем или задержкой и текстом, например "/remind 15:30 позвонить маме" или "/remind 45m размяться"
+
+    Начните с "high", чтобы напоминание пришло несмотря на "/snooze", "/done" и "/away", или с "low", если его можно пропустить, когда бот перегружен: "/remind high 15:30 позвонить маме"
+remind-set = Напомню в { $until }
+
+timer-usage = Отправьте "/timer" с длительностью и текстом, например "/timer 25m чай готов"
+timer-too-long = Таймер может быть не длиннее { $max }
+timer-default-text = Время вышло!
+timer-running = ⏳ { $text }: осталось { $remaining }
+timer-done = ⌛ { $text }: готово
+
+pomodoro-usage = Отправьте "/pomodoro", чтобы по очереди работать 25 минут и отдыхать 5, "/pomodoro 50 10" для другой длительности в минутах или "/pomodoro off", чтобы остановить
+pomodoro-started = 🍅 Работайте { $work }, затем отдохните { $rest }
+pomodoro-break = ☕ Раунд { $round } завершён, отдохните { $rest }
+pomodoro-work = 🍅 Перерыв окончен, раунд { $round }: работайте { $work }
+pomodoro-paused = ⏸ Пауза, осталось { $remaining }
+pomodoro-resumed = ▶️ Продолжаем, осталось { $remaining }
+pomodoro-stopped = ⏹ Помодоро остановлен
+pomodoro-none = Помодоро не запущен
+pomodoro-expired = ⏹ Помодоро остановлен, он слишком долго был на паузе
+
+report-usage =
+    Отправьте "/report" с днём месяца, временем и текстом, например "/report 25 10:00 отправить табель".
+    Каждый месяц напоминание придёт с табелем ваших рабочих дней. Отправьте "/report off", чтобы отключить его
+report-set = Каждый месяц { $day } числа в { $time } придёт напоминание с табелем, первое — { $until }
+report-off = Ежемесячное напоминание об отчёте отключено
+
+weekly-usage =
+    Отправьте "/weekly" с днями недели, временем и текстом, например "/weekly пн 10:00 спланировать неделю" или "/weekly пн,чт 17:00 полить цветы".
+    "/weekly" показывает еженедельные напоминания, "/weekly off" удаляет их
+monthly-usage =
+    Отправьте "/monthly" с днём месяца, временем и текстом, например "/monthly 1 10:00 оплатить аренду". В коротких месяцах напоминание придёт в последний день.
+    "/monthly" показывает ежемесячные напоминания, "/monthly off" удаляет их
+weekly-when = Каждую неделю ({ $weekday }) в { $time }
+monthly-when = Каждый месяц { $day } числа в { $time }
+recurring-set = { $when } придёт напоминание, первое — { $until }
+recurring-list = Ваши напоминания:
+    { $reminders }
+recurring-off = { $count ->
+    [0] У вас нет таких напоминаний
+    [one] Удалено { $count } напоминание
+    [few] Удалены { $count } напоминания
+   *[other] Удалено { $count } напоминаний
+}
+recurring-too-many = Таких напоминаний может быть не больше { $max }, удалите лишние через "/{ $command } off"
+
+deadline-usage =
+    Отправьте "/deadline" с датой и тем, что нужно сделать, например "/deadline 2024-06-30 отправить отчёт", или добавьте время: "/deadline 2024-06-30 12:00 отправить отчёт". Без времени срок наступает в конце рабочего дня.
+    Напоминания приходят раз в неделю, в последнюю неделю — каждый день, а в последний день — каждый час. "/deadline" показывает ваши сроки, "/deadline off" удаляет их
+deadline-past = Срок должен быть в будущем и не дальше чем через год
+deadline-set = Срок "{ $title }" назначен на { $due }, первое напоминание — { $first }
+deadline-list = Ваши сроки:
+    { $deadlines }
+deadline-off = { $count ->
+    [0] У вас нет сроков
+    [one] Удалён { $count } срок
+    [few] Удалены { $count } срока
+   *[other] Удалено { $count } сроков
+}
+deadline-days = 📅 { $title }: { $count ->
+    [one] остался { $count } день
+    [few] осталось { $count } дня
+   *[other] осталось { $count } дней
+}, срок { $due }
+deadline-hours = ⏰ { $title }: { $count ->
+    [one] остался { $count } час
+    [few] осталось { $count } часа
+   *[other] осталось { $count } часов
+}, срок в { $due }
+deadline-due = 🚩 { $title }: срок наступил
+
+check-in-usage = Отправьте "/checkin" с периодом и id чата, который предупредить, если вы не напишете мне за это время, например "/checkin 24h -1001234567890". Любое сообщение считается отметкой, "/checkin off" выключает проверку
+check-in-out-of-range = Период должен быть от { $min } до { $max }
+check-in-unreachable = Не могу написать в этот чат, сначала добавьте меня туда
+check-in-set = Проверка включена: пишите мне хотя бы раз в { $period }, иначе чат { $contact } получит предупреждение
+check-in-off = Проверка выключена
+check-in-view = Пишите мне хотя бы раз в { $period }, иначе чат { $contact } получит предупреждение, следующее в { $due }
+check-in-alert = ⚠️ { $name } не писал(а) боту уже { $period }, пожалуйста, проверьте, всё ли в порядке
+
+status-stopped = Уведомления выключены, отправьте "/start", чтобы включить их
+status-view =
+    Уведомления: { $state ->
+        [running] включены
+        [away] отключены до "/back"
+        [wake_up] на сегодня всё, вернутся в { $until }
+        [snooze] отложены до { $until }
+       *[paused] на паузе
+    }
+    Следующее уведомление: { $next }
+    Часовой пояс: { $timezone }
+    Рабочее время: { $from }–{ $to }
+    Интервал: { $interval }
+
+next-list =
+    Ближайшие уведомления придут:
+    { $dates }
+next-none = Уведомлений не ожидается
+next-blackout = Без уведомлений с { $from } по { $to }: { $reason }
+next-blackout-reason = перерыв для всех
+
+time-audit =
+    Ваше местное время: { $local }
+    UTC: { $utc }
+    Часы сервера: { $server }
+    Часовой пояс: { $timezone }
+    Сохранённое смещение: { $offset }
+    Следующее уведомление: { $next }
+
+    Если местное время неверное, исправьте часовой пояс через "/changetimezone"
+time-audit-next = { $date } ({ $utc }), через { $until }
+
+history-list =
+    Ваши последние уведомления:
+    { $sends }
+history-empty = Вам ещё не отправляли уведомлений
+history-delivered = доставлено
+history-failed = не доставлено, { $error }
+mystats =
+    Уведомлений на этой неделе: { $week }, в этом месяце: { $month }
+    Среднее время до "/done": { $average }
+    Серия: { $streak ->
+        [one] { $streak } день
+        [few] { $streak } дня
+       *[other] { $streak } дней
+    } подряд с "/done"
+mystats-no-average = пока нет "/done" после уведомления
+
+budget-digest = { $count ->
+    [one] { $count } предыдущее уведомление было пропущено
+    [few] { $count } предыдущих уведомления были пропущены
+   *[other] { $count } предыдущих уведомлений были пропущены
+}, чтобы не превысить дневной лимит сообщений бота
+budget-tight-alert = Отправлено { $spent } из { $limit } сообщений дневного лимита. До конца дня (UTC) уходит только каждое второе уведомление, а сообщения с низким приоритетом отбрасываются
+budget-exhausted-alert = Дневной лимит в { $limit } сообщений исчерпан. До конца дня (UTC) уходят только сообщения с высоким приоритетом
+
+channels-list =
+    Уведомления приходят через первый работающий из этих каналов:
+    { $channels }
+
+    Доступны: { $available }. Отправьте "/channels slack <адрес вебхука>", чтобы добавить канал, "/channels slack off", чтобы убрать его, или "/channels slack telegram", чтобы изменить порядок
+channels-no-sends = ещё ничего не отправлено
+channels-delivered = доставлено в { $time }
+channels-failed = не доставлено в { $time }: { $reason }
+channels-unknown = Канала "{ $channel }" нет
+channels-no-address = Сначала укажите, куда доставляет "{ $channel }", например "/channels { $channel } <адрес>"
+channels-invalid-address = "{ $channel }" не может доставлять по этому адресу
+channels-added = Уведомления приходят через "{ $channel }", когда каналы перед ним не сработали, отправьте "/channels", чтобы изменить порядок
+channels-removed = Уведомления больше не приходят через "{ $channel }"
+
+connect-usage = Отправьте "/connect <URL> [токен]", чтобы каждый "/done" отправлялся по адресу, например, чтобы отмечать привычку в Habitica или Beeminder. Токен передаётся в заголовке Authorization
+connect-show = Каждый "/done" отправляется на { $url } { $token ->
+    [yes] с токеном
+   *[no] без токена
+}. Отправьте "/connect off", чтобы прекратить
+connect-set = Каждый "/done" будет отправляться на { $url }
+connect-removed = "/done" больше никуда не отправляется
+connect-invalid = Это не публичный HTTPS-адрес, отправьте "/connect <URL> [токен]"
+
+help =
+    Команды:
+    { $commands }
+command-start = Начать присылать уведомления
+command-help = Показать команды и что они делают
+command-stop = Перестать присылать уведомления
+command-done = Остановить уведомления до завтра
+command-status = Показать состояние подписки
+command-next = Показать, когда придут ближайшие уведомления
+command-whattimedoyouthinkitis = Показать, который час у вас и в UTC по мнению бота
+command-history = Показать последние уведомления и дошли ли они
+command-mystats = Показать, сколько уведомлений пришло, и серию дней с "/done"
+command-snooze = Отложить уведомления на время, например "/snooze 45m"
+command-skip = Пропустить ближайшие уведомления, например "/skip 3"
+command-remind = Прислать сообщение один раз в заданное время, например "/remind 15:30 позвонить маме"
+command-weekly = Напоминать каждую неделю, например "/weekly пн 10:00 спланировать неделю"
+command-monthly = Напоминать каждый месяц, например "/monthly 1 10:00 оплатить аренду"
+command-checkin = Предупредить контакт, если вы долго не пишете боту, например "/checkin 24h -1001234567890"
+command-timer = Запустить обратный отсчёт и прислать сообщение, когда время выйдет, например "/timer 25m чай"
+command-pomodoro = Работать и отдыхать по очереди, 25 и 5 минут или например "/pomodoro 50 10"
+command-report = Напоминать каждый месяц с приложенным табелем, например "/report 25 10:00 отправить табель"
+command-deadline = Напоминать всё чаще по мере приближения даты, например "/deadline 2024-06-30 отправить отчёт"
+command-settings = Изменить настройки кнопками
+command-changetimezone = Изменить часовой пояс
+command-settime = Изменить рабочее время
+command-interval = Показать или изменить, как часто приходят уведомления
+command-workdays = Показать или изменить дни недели, в которые приходят уведомления
+command-quiet = Не присылать уведомления в перерывы, например "/quiet 13:00-14:00"
+command-softhours = Получать уведомления без звука в начале и в конце рабочего времени, например "/softhours 1"
+command-cron = Присылать уведомления по расписанию cron вместо рабочего времени, например "/cron 0 0 9-18 * * MON-FRI"
+command-alias = Показать, добавить или удалить сокращения команд
+command-away = Выключить уведомления, пока вас нет
+command-back = Включить уведомления, выключенные "/away"
+command-language = Показать или изменить язык ответов
+command-footer = Показать или скрыть подсказку под уведомлениями
+command-addmessage = Добавить свой текст уведомлений для чередования, например "/addmessage Разомнитесь!"
+command-removemessage = Удалить один из своих текстов уведомлений, например "/removemessage 2"
+command-preset = Добавить готовое напоминание, например "/preset water"
+command-parsemode = Показать или изменить оформление своих текстов, например "/parsemode html"
+command-firstsend = Выбрать, присылает ли "/start" уведомление сразу
+command-ackmode = Повторять уведомления, пока вы не нажмёте "Понятно", например "/ackmode on"
+command-ack = Подтвердить последнее уведомление в режиме подтверждений
+command-singlemessage = Изменять уведомление за день вместо отправки новых, например "/singlemessage on"
+command-summary = Получать сводку дня в конце рабочего времени, например "/summary on"
+command-insights = Показать, в какие часы вы обычно нажимаете "/done"
+command-token = Показать токен для отправки уведомлений по HTTP
+command-template = Задать шаблон JSON, который отправляется в HTTP-хук
+command-webhook = Получить адрес, на который GitHub или Grafana могут присылать оповещения
+command-suggest = Предложить расписание по тому, когда вы нажимаете "/done"
+command-allinsights = Админ: показать, в какие часы все чаты нажимают "/done"
+command-as = Админ: посмотреть настройки другого чата без права изменять
+command-startgroups = Админ: включить уведомления сразу в нескольких группах
+command-jobs = Админ: показать отложенные задачи
+command-journal = Админ: выгрузить все отправленные уведомления в CSV
+command-maintenance = Админ: включить или выключить режим обслуживания только для чтения
+command-profile = Админ: показать, задать или удалить профили команд
+command-joinprofile = Присоединиться к профилю команды, например "/joinprofile Berlin office"
+command-transfer = Перенести настройки в другой чат по одноразовому коду
+command-whatsnew = Показать, что нового, или включить и выключить заметки о выпусках
+command-channels = Показать или изменить, куда приходят уведомления, например "/channels slack <webhook URL>"
+command-connect = Отправлять запрос на URL при каждом "/done", например "/connect https://example.com/hook token"
@@ -0,0 +1,135 @@
+use std::{net::IpAddr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use teloxide::types::ChatId;
+
+/// Where a chat's "/done" is posted, e.g. a habit tracker's webhook.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AckHook {
+    pub url: String,
+    /// Sent as a bearer token when set
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// What a hook gets for every acknowledgement.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AckEvent {
+    pub event: &'static str,
+    pub chat_id: i64,
+    pub at: DateTime<Utc>,
+}
+
+impl AckEvent {
+    pub fn done(chat_id: ChatId, at: DateTime<Utc>) -> AckEvent {
+        AckEvent {
+            event: "done",
+            chat_id: chat_id.0,
+            at,
+        }
+    }
+}
+
+/// Parses "/connect" arguments, a URL and an optional token. Only public
+/// HTTPS URLs are accepted, the bot doesn't post into its own network.
+pub fn parse(args: &str) -> Option<AckHook> {
+    let mut words = args.split_whitespace();
+    let url = Url::parse(words.next()?).ok()?;
+    let token = words.next().map(str::to_string);
+    if words.next().is_some() || url.scheme() != "https" || !is_public(&url) {
+        return None;
+    }
+
+    Some(AckHook {
+        url: url.to_string(),
+        token,
+    })
+}
+
+fn is_public(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast())
+        }
+        Ok(IpAddr::V6(ip)) => !(ip.is_loopback() || ip.is_unspecified()),
+        Err(_) => host != "localhost" && !host.ends_with(".localhost") && host.contains('.'),
+    }
+}
+
+/// Posts the event to the hook, failures are only logged since the chat's
+/// "/done" counts either way.
+pub async fn post(hook: AckHook, event: AckEvent) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("a client without custom TLS settings always builds");
+    let mut request = client.post(&hook.url).json(&event);
+    if let Some(token) = &hook.token {
+        request = request.bearer_auth(token);
+    }
+
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {
+            log::debug!("Acknowledgement of {} posted", event.chat_id)
+        }
+        Ok(response) => log::warn!(
+            "Acknowledgement hook of {} rejected: {}",
+            event.chat_id,
+            response.status()
+        ),
+        Err(err) => log::warn!(
+            "Unable to post acknowledgement of {}: {}",
+            event.chat_id,
+            err.without_url()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use teloxide::types::ChatId;
+
+    use crate::ack_hooks::{parse, AckEvent, AckHook};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("https://habitica.com/api/v3/tasks/x/score/up secret"),
+            Some(AckHook {
+                url: "https://habitica.com/api/v3/tasks/x/score/up".to_string(),
+                token: Some("secret".to_string()),
+            })
+        );
+        assert_eq!(
+            parse(" https://example.com/hook ").map(|hook| hook.token),
+            Some(None)
+        );
+        assert_eq!(parse("http://example.com/hook"), None);
+        assert_eq!(parse("https://127.0.0.1/hook"), None);
+        assert_eq!(parse("https://192.168.1.10/hook"), None);
+        assert_eq!(parse("https://[::1]/hook"), None);
+        assert_eq!(parse("https://localhost/hook"), None);
+        assert_eq!(parse("https://example.com/hook a b"), None);
+        assert_eq!(parse(""), None);
+
+        let at = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+        assert_eq!(
+            serde_json::to_value(AckEvent::done(ChatId(42), at)).unwrap(),
+            serde_json::json!({
+                "event": "done",
+                "chat_id": 42,
+                "at": "2024-05-06T10:00:00Z",
+            })
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use serde_json::Value;
+
+/// Header GitHub names the event of a webhook delivery in.
+pub const GITHUB_EVENT_HEADER: &str = "x-github-event";
+
+/// Renders alerts of the services people point "/hook/{token}" at: GitHub,
+/// Grafana, Alertmanager and UptimeRobot. `None` for any other payload, it's
+/// relayed as is.
+pub fn format(github_event: Option<&str>, payload: &Value) -> Option<String> {
+    if let Some(event) = github_event {
+        return Some(github(event, payload));
+    }
+    if payload.get("monitorFriendlyName").is_some() {
+        return uptime_robot(payload);
+    }
+    if payload.get("alerts").is_some_and(Value::is_array) {
+        return alertmanager(payload);
+    }
+    if payload.get("ruleName").is_some() || payload.get("ruleUrl").is_some() {
+        return grafana(payload);
+    }
+    None
+}
+
+fn text<'a>(payload: &'a Value, pointer: &str) -> Option<&'a str> {
+    payload
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// Lines that are there, joined.
+fn lines<'a>(lines: impl IntoIterator<Item = Option<&'a str>>) -> String {
+    lines.into_iter().flatten().collect::<Vec<_>>().join("\n")
+}
+
+fn github(event: &str, payload: &Value) -> String {
+    let repository = text(payload, "/repository/full_name").unwrap_or("GitHub");
+    let sender = text(payload, "/sender/login").unwrap_or("someone");
+    let action = text(payload, "/action").unwrap_or("updated");
+
+    match event {
+        "ping" => format!("🔗 GitHub webhook of {} is connected", repository),
+        "push" => {
+            let branch = text(payload, "/ref")
+                .map(|name| name.trim_start_matches("refs/heads/"))
+                .unwrap_or("?");
+            let commits = payload
+                .get("commits")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            let heading = format!(
+                "⬆️ {} pushed {} commit{} to {}:{}",
+                text(payload, "/pusher/name").unwrap_or(sender),
+                commits,
+                if commits == 1 { "" } else { "s" },
+                repository,
+                branch
+            );
+            lines([Some(heading.as_str()), text(payload, "/compare")])
+        }
+        "issues" | "pull_request" => {
+            let (kind, item) = match event {
+                "issues" => ("issue", "/issue"),
+                _ => ("pull request", "/pull_request"),
+            };
+            let heading = format!(
+                "📌 {} {} {} #{} in {}: {}",
+                sender,
+                action,
+                kind,
+                payload
+                    .pointer(&format!("{}/number", item))
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default(),
+                repository,
+                text(payload, &format!("{}/title", item)).unwrap_or_default()
+            );
+            lines([
+                Some(heading.as_str()),
+                text(payload, &format!("{}/html_url", item)),
+            ])
+        }
+        "workflow_run" => {
+            let outcome = text(payload, "/workflow_run/conclusion")
+                .or_else(|| text(payload, "/workflow_run/status"))
+                .unwrap_or(action);
+            let icon = match outcome {
+                "success" => "✅",
+                "failure" | "timed_out" | "startup_failure" => "❌",
+                _ => "⚙️",
+            };
+            let heading = format!(
+                "{} {} {} in {}",
+                icon,
+                text(payload, "/workflow_run/name").unwrap_or("Workflow"),
+                outcome,
+                repository
+            );
+            lines([
+                Some(heading.as_str()),
+                text(payload, "/workflow_run/html_url"),
+            ])
+        }
+        "release" => {
+            let heading = format!(
+                "🏷 {} {} release {}",
+                repository,
+                action,
+                text(payload, "/release/tag_name").unwrap_or_default()
+            );
+            lines([Some(heading.as_str()), text(payload, "/release/html_url")])
+        }
+        event => format!("GitHub {} event in {} by {}", event, repository, sender),
+    }
+}
+
+fn firing_icon(firing: bool) -> &'static str {
+    match firing {
+        true => "🔥",
+        false => "✅",
+    }
+}
+
+/// Grafana's legacy alerting.
+fn grafana(payload: &Value) -> Option<String> {
+    let title = text(payload, "/title").or_else(|| text(payload, "/ruleName"))?;
+    let firing = text(payload, "/state") == Some("alerting");
+    let heading = format!("{} {}", firing_icon(firing), title);
+    Some(lines([
+        Some(heading.as_str()),
+        text(payload, "/message"),
+        text(payload, "/ruleUrl"),
+    ]))
+}
+
+/// Alertmanager and Grafana's unified alerting, which posts the same along
+/// with a title and a message.
+fn alertmanager(payload: &Value) -> Option<String> {
+    let firing = text(payload, "/status") == Some("firing");
+    if let Some(title) = text(payload, "/title") {
+        let heading = format!("{} {}", firing_icon(firing), title);
+        return Some(lines([Some(heading.as_str()), text(payload, "/message")]));
+    }
+
+    let alerts = payload.get("alerts")?.as_array()?;
+    let summary: Vec<String> = alerts
+        .iter()
+        .map(|alert| {
+            let name = text(alert, "/labels/alertname").unwrap_or("alert");
+            match text(alert, "/annotations/summary")
+                .or_else(|| text(alert, "/annotations/description"))
+            {
+                Some(summary) => format!("• {}: {}", name, summary),
+                None => format!("• {}", name),
+            }
+        })
+        .collect();
+    let heading = format!(
+        "{} {} alert{} {}",
+        firing_icon(firing),
+        alerts.len(),
+        if alerts.len() == 1 { "" } else { "s" },
+        if firing { "firing" } else { "resolved" }
+    );
+    Some(format!("{}\n{}", heading, summary.join("\n")))
+}
+
+fn uptime_robot(payload: &Value) -> Option<String> {
+    let monitor = text(payload, "/monitorFriendlyName")?;
+    let state = text(payload, "/alertTypeFriendlyName").unwrap_or("changed");
+    let icon = match state.to_lowercase().as_str() {
+        "down" => "🔴",
+        "up" => "🟢",
+        _ => "🟡",
+    };
+    let heading = format!("{} {} is {}", icon, monitor, state);
+    Some(lines([
+        Some(heading.as_str()),
+        text(payload, "/alertDetails"),
+        text(payload, "/monitorURL"),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::alerts::format;
+
+    #[test]
+    fn test_github() {
+        let repository = json!({ "full_name": "octo/bot" });
+        let push = json!({
+            "ref": "refs/heads/main",
+            "repository": repository,
+            "pusher": { "name": "octocat" },
+            "commits": [{}, {}],
+            "compare": "https://github.com/octo/bot/compare/a...b",
+        });
+        assert_eq!(
+            format(Some("push"), &push).unwrap(),
+            "⬆️ octocat pushed 2 commits to octo/bot:main\nhttps://github.com/octo/bot/compare/a...b"
+        );
+
+        let pull_request = json!({
+            "action": "opened",
+            "sender": { "login": "octocat" },
+            "repository": repository,
+            "pull_request": {
+                "number": 7,
+                "title": "Fix it",
+                "html_url": "https://github.com/octo/bot/pull/7",
+            },
+        });
+        assert_eq!(
+            format(Some("pull_request"), &pull_request).unwrap(),
+            "📌 octocat opened pull request #7 in octo/bot: Fix it\nhttps://github.com/octo/bot/pull/7"
+        );
+
+        let run = json!({
+            "action": "completed",
+            "repository": repository,
+            "workflow_run": { "name": "CI", "conclusion": "failure" },
+        });
+        assert_eq!(
+            format(Some("workflow_run"), &run).unwrap(),
+            "❌ CI failure in octo/bot"
+        );
+        assert_eq!(
+            format(Some("star"), &json!({})).unwrap(),
+            "GitHub star event in GitHub by someone"
+        );
+    }
+
+    #[test]
+    fn test_monitoring() {
+        let unified = json!({
+            "status": "firing",
+            "title": "[FIRING:1] High CPU",
+            "message": "CPU is at 95%",
+            "alerts": [],
+        });
+        assert_eq!(
+            format(None, &unified).unwrap(),
+            "🔥 [FIRING:1] High CPU\nCPU is at 95%"
+        );
+
+        let alertmanager = json!({
+            "status": "resolved",
+            "alerts": [
+                { "labels": { "alertname": "DiskFull" }, "annotations": { "summary": "/ is 99% full" } },
+                { "labels": { "alertname": "Down" } },
+            ],
+        });
+        assert_eq!(
+            format(None, &alertmanager).unwrap(),
+            "✅ 2 alerts resolved\n• DiskFull: / is 99% full\n• Down"
+        );
+
+        let legacy = json!({
+            "ruleName": "Latency",
+            "state": "alerting",
+            "ruleUrl": "https://grafana.example.com/d/1",
+        });
+        assert_eq!(
+            format(None, &legacy).unwrap(),
+            "🔥 Latency\nhttps://grafana.example.com/d/1"
+        );
+
+        let uptime = json!({
+            "monitorFriendlyName": "Website",
+            "alertTypeFriendlyName": "Down",
+            "alertDetails": "Connection timeout",
+        });
+        assert_eq!(
+            format(None, &uptime).unwrap(),
+            "🔴 Website is Down\nConnection timeout"
+        );
+
+        assert_eq!(format(None, &json!({ "text": "hello" })), None);
+    }
+}
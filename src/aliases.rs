@@ -0,0 +1,109 @@
+use crate::i18n::Locale;
+
+/// Words understood as commands when sent without the slash.
+const KEYWORDS: [(Locale, &str, &str); 10] = [
+    (Locale::En, "start", "start"),
+    (Locale::En, "stop", "stop"),
+    (Locale::En, "done", "done"),
+    (Locale::En, "status", "status"),
+    (Locale::En, "back", "back"),
+    (Locale::Ru, "старт", "start"),
+    (Locale::Ru, "стоп", "stop"),
+    (Locale::Ru, "готово", "done"),
+    (Locale::Ru, "статус", "status"),
+    (Locale::Ru, "вернулся", "back"),
+];
+
+/// Shortcuts available to everyone, user aliases can't redefine them.
+pub const BUILTIN: [(&str, &str); 2] = [("d", "done"), ("tz", "changetimezone")];
+
+/// Rewrites "/alias@bot args" into "/command@bot args".
+///
+/// `lookup` resolves user aliases, built-in ones take precedence. Returns
+/// `None` when `text` doesn't start with an alias.
+pub fn expand<F: Fn(&str) -> Option<String>>(text: &str, lookup: F) -> Option<String> {
+    let rest = text.strip_prefix('/')?;
+    let (head, args) = match rest.find(char::is_whitespace) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let (name, mention) = match head.split_once('@') {
+        Some((name, bot)) => (name, format!("@{}", bot)),
+        None => (head, String::new()),
+    };
+
+    let name = name.to_lowercase();
+    let command = match BUILTIN.iter().find(|(alias, _)| *alias == name) {
+        Some((_, command)) => command.to_string(),
+        None => lookup(&name)?,
+    };
+
+    Some(format!("/{}{}{}", command, mention, args))
+}
+
+/// The command a message consisting of a single `locale` keyword stands for.
+pub fn keyword(text: &str, locale: Locale) -> Option<&'static str> {
+    let text = text.trim().trim_end_matches(['.', '!']).to_lowercase();
+    KEYWORDS
+        .iter()
+        .find(|(keyword_locale, keyword, _)| *keyword_locale == locale && *keyword == text)
+        .map(|(_, _, command)| *command)
+}
+
+/// Alias names are short lowercase latin words and digits.
+pub fn is_valid_name(name: &str) -> bool {
+    (1..=16).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        aliases::{expand, is_valid_name, keyword},
+        i18n::Locale,
+    };
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "s" => Some("stop".to_string()),
+            "d" => Some("stop".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("/d", lookup), Some("/done".to_string()));
+        assert_eq!(
+            expand("/TZ@notify_bot", lookup),
+            Some("/changetimezone@notify_bot".to_string())
+        );
+        assert_eq!(
+            expand("/s now please", lookup),
+            Some("/stop now please".to_string())
+        );
+        assert_eq!(expand("/start", lookup), None);
+        assert_eq!(expand("d", lookup), None);
+        assert_eq!(expand("/", lookup), None);
+    }
+
+    #[test]
+    fn test_keyword() {
+        assert_eq!(keyword("Done", Locale::En), Some("done"));
+        assert_eq!(keyword(" стоп! ", Locale::Ru), Some("stop"));
+        assert_eq!(keyword("стоп", Locale::En), None);
+        assert_eq!(keyword("done for today", Locale::En), None);
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("s"));
+        assert!(is_valid_name("stop2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("Stop"));
+        assert!(!is_valid_name("/s"));
+        assert!(!is_valid_name("averyveryverylongalias"));
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::NaiveDate;
+
+/// Days the whole deployment sends no notifications on, e.g. a company
+/// shutdown, set by the operator with "BLACKOUT_DATES". They apply on top of
+/// every chat's own calendar, in its local time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Blackout {
+    pub from: NaiveDate,
+    /// The last day of the blackout, inclusive
+    pub to: NaiveDate,
+    /// Shown to users in "/next", may be empty
+    pub reason: String,
+}
+
+impl Blackout {
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.from <= day && day <= self.to
+    }
+}
+
+/// The blackout `day` falls in.
+pub fn find(blackouts: &[Blackout], day: NaiveDate) -> Option<&Blackout> {
+    blackouts.iter().find(|blackout| blackout.contains(day))
+}
+
+/// Parses blackouts separated with ";", each a date or a range of dates
+/// followed by an optional reason, e.g.
+/// "2024-12-23..2025-01-03 Winter shutdown; 2025-05-01".
+pub fn parse(value: &str) -> Option<Vec<Blackout>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (dates, reason) = entry.split_once(char::is_whitespace).unwrap_or((entry, ""));
+            let (from, to) = dates.split_once("..").unwrap_or((dates, dates));
+            let from = NaiveDate::parse_from_str(from, "%Y-%m-%d").ok()?;
+            let to = NaiveDate::parse_from_str(to, "%Y-%m-%d").ok()?;
+            (from <= to).then(|| Blackout {
+                from,
+                to,
+                reason: reason.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::blackouts::{find, parse, Blackout};
+
+    #[test]
+    fn test_parse_blackouts() {
+        let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+        let blackouts = parse("2024-12-23..2024-12-31 Winter shutdown; 2024-05-01;").unwrap();
+        assert_eq!(
+            blackouts,
+            vec![
+                Blackout {
+                    from: date(12, 23),
+                    to: date(12, 31),
+                    reason: "Winter shutdown".to_string(),
+                },
+                Blackout {
+                    from: date(5, 1),
+                    to: date(5, 1),
+                    reason: String::new(),
+                },
+            ]
+        );
+        assert_eq!(parse(""), Some(vec![]));
+        assert_eq!(parse("2024-12-31..2024-12-23"), None);
+        assert_eq!(parse("2024-05-01; christmas"), None);
+
+        assert_eq!(find(&blackouts, date(12, 25)), Some(&blackouts[0]));
+        assert_eq!(find(&blackouts, date(12, 31)), Some(&blackouts[0]));
+        assert_eq!(find(&blackouts, date(5, 1)), Some(&blackouts[1]));
+        assert_eq!(find(&blackouts, date(5, 2)), None);
+    }
+}
@@ -0,0 +1,794 @@
+use std::{sync::Arc, time::Instant};
+
+use async_mutex::Mutex;
+use chrono::Utc;
+use teloxide::{
+    adaptors::{throttle, CacheMe, Throttle},
+    dispatching::{
+        dialogue::{ErasedStorage, InMemStorage, Storage},
+        DpHandlerDescription,
+    },
+    dptree::di::DependencySupplier,
+    filter_command,
+    prelude::*,
+};
+use tokio::{
+    spawn,
+    sync::{mpsc, watch},
+};
+
+#[cfg(feature = "email")]
+use crate::email::Email;
+#[cfg(feature = "mqtt")]
+use crate::mqtt::{self, MqttConfig};
+use crate::{
+    aliases,
+    channels::{Channels, Slack},
+    chat_info::ChatInfoCache,
+    chat_locks::ChatLocks,
+    clock::SkewMonitor,
+    commands::{command_names, register_commands, Command},
+    config::Config,
+    delivery::Budget,
+    dialogue_storage::SqliteDialogues,
+    dialogues::{
+        handle_change_timezone_command, handle_message, handle_new_timezone,
+        handle_new_working_hours, handle_onboarding_timezone, handle_onboarding_workdays,
+        handle_onboarding_working_hours, handle_preview_button, handle_set_time_command, State,
+    },
+    formatting,
+    handlers::{
+        admin::{
+            handle_as_command, handle_jobs_command, handle_journal_command,
+            handle_maintenance_command, handle_profile_command, handle_start_groups_command,
+        },
+        detect_locale, handle_callback_query, handle_help_command, handle_maintenance,
+        handle_maintenance_callback,
+        reminders::{
+            handle_check_in_command, handle_deadline_command, handle_monthly_command,
+            handle_notification_button, handle_pomodoro_button, handle_pomodoro_command,
+            handle_remind_command, handle_report_command, handle_skip_command,
+            handle_snooze_command, handle_timer_command, handle_weekly_command,
+        },
+        settings::{
+            handle_add_message_command, handle_alias_command, handle_channels_command,
+            handle_connect_command, handle_cron_command, handle_footer_command,
+            handle_interval_command, handle_join_profile_command, handle_language_command,
+            handle_menu_button, handle_parse_mode_command, handle_preset_command,
+            handle_quiet_command, handle_remove_message_command, handle_settings_command,
+            handle_soft_hours_command, handle_summary_command, handle_transfer_command,
+            handle_whats_new_command, handle_workdays_command,
+        },
+        status::{
+            handle_all_insights_command, handle_history_command, handle_insights_command,
+            handle_mystats_command, handle_next_command, handle_status_command,
+            handle_suggest_command, handle_what_time_command,
+        },
+        subscription::{
+            handle_ack_command, handle_ack_mode_command, handle_away_command, handle_back_command,
+            handle_done_command, handle_first_send_command, handle_single_message_command,
+            handle_start_command, handle_stop_button, handle_stop_command,
+        },
+        with_text, HandlerResult,
+    },
+    health,
+    jobs::{JobKind, JobQueue},
+    journal::Journal,
+    keyboards::{NotificationButton, PomodoroButton, StopButton},
+    maintenance::Maintenance,
+    metrics,
+    notify_controller::Notification,
+    offsets_rep::OffsetsRepository,
+    previews::PreviewButton,
+    processed::ProcessedUpdates,
+    settings_menu::MenuButton,
+    store::{self, UserStore},
+    tasks::{
+        alert_budget, announce_release, evict_chats, record_deliveries, record_skips, run_jobs,
+    },
+    telemetry, tr,
+    transfers::Transfers,
+};
+#[cfg(feature = "http")]
+use crate::{
+    handlers::api::{handle_template_command, handle_token_command, handle_webhook_command},
+    http, webhook,
+};
+
+/// Sends of notifications with the SQLite store
+const JOURNAL_PATH: &str = "journal.sqlite3";
+/// The bot every request goes through, held back to stay within Telegram's
+/// rate limits, with "getMe" answered from memory.
+pub type Bot = Throttle<CacheMe<teloxide::Bot>>;
+
+/// Checks what the first bot needs and prints it as JSON, for Docker's
+/// HEALTHCHECK.
+pub async fn healthcheck(config: &Config) -> bool {
+    let health = health::self_test(&first_bot(config), config.user_store, &config.db_path).await;
+    println!("{}", serde_json::to_string(&health).unwrap());
+    health.healthy
+}
+
+/// Serves every bot of the config until Ctrl-C.
+pub async fn run(mut config: Config) {
+    // The first bot keeps the files and chats of a single bot, the others
+    // are told apart by their ids
+    let tokens = std::mem::take(&mut config.bot_tokens);
+    let bots: Vec<(i64, Bot)> = match tokens.is_empty() {
+        true => vec![(0, spawn_bot(single_bot(&config), &config))],
+        false => tokens
+            .into_iter()
+            .enumerate()
+            .map(|(index, (id, token))| {
+                let bot_id = if index == 0 { 0 } else { id };
+                (bot_id, spawn_bot(teloxide::Bot::new(token), &config))
+            })
+            .collect(),
+    };
+
+    let repository = match config.user_store {
+        store::Backend::Sqlite => {
+            Some(OffsetsRepository::open_or_import(&config.db_path, "users.db").unwrap())
+        }
+        store::Backend::Memory => None,
+    };
+    let channels = Channels::new().register(Slack::new());
+    #[cfg(feature = "email")]
+    let channels = match config.smtp.take().map(Email::new) {
+        Some(Ok(email)) => channels.register(email),
+        Some(Err(err)) => {
+            log::error!("Unable to set up email, it's off: {}", err);
+            channels
+        }
+        None => channels,
+    };
+    let channels = Arc::new(channels);
+    let mut primary = Some(Primary {
+        #[cfg(feature = "mqtt")]
+        mqtt: config.mqtt.take(),
+    });
+    let config = Arc::new(config);
+    if bots.len() > 1 {
+        log::info!(
+            "Running {} bots, the HTTP API, MQTT and telemetry serve the first one",
+            bots.len()
+        );
+    }
+
+    let runs: Vec<_> = bots
+        .into_iter()
+        .map(|(bot_id, bot)| {
+            let store: Arc<dyn UserStore> = match &repository {
+                Some(repository) => Arc::new(repository.for_bot(bot_id)),
+                None => Arc::new(store::MemoryStore::new()),
+            };
+            spawn(run_bot(
+                bot,
+                bot_id,
+                store,
+                Arc::clone(&channels),
+                Arc::clone(&config),
+                primary.take(),
+            ))
+        })
+        .collect();
+    for run in runs {
+        if let Err(err) = run.await {
+            log::error!("A bot failed: {}", err);
+        }
+    }
+}
+
+/// What only the first of several bots runs, the others would clash with it.
+struct Primary {
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<MqttConfig>,
+}
+
+/// The first of several bots, or the only one.
+pub fn first_bot(config: &Config) -> teloxide::Bot {
+    match config.bot_tokens.first() {
+        Some((_, token)) => teloxide::Bot::new(token),
+        None => single_bot(config),
+    }
+}
+
+/// The bot of "TELOXIDE_TOKEN", through the proxy teloxide reads from the
+/// environment.
+fn single_bot(config: &Config) -> teloxide::Bot {
+    let token = config
+        .token
+        .clone()
+        .expect("TELOXIDE_TOKEN or token in config.toml must be set");
+    teloxide::Bot::with_client(token, teloxide::net::client_from_env())
+}
+
+fn spawn_bot(bot: teloxide::Bot, config: &Config) -> Bot {
+    // Rejected sends aren't retried blindly, the delivery layer handles
+    // groups in slow mode by itself
+    let mut throttle = throttle::Settings::default().no_retry();
+    throttle.limits = config.throttle;
+    Throttle::spawn_with_settings(bot.cache_me(), throttle)
+}
+
+/// A file of the bot, the first one keeps the name of a single bot.
+fn bot_path(path: &str, bot_id: i64) -> String {
+    match (bot_id, path.rsplit_once('.')) {
+        (0, _) => path.to_string(),
+        (_, Some((name, extension))) => format!("{}-{}.{}", name, bot_id, extension),
+        (_, None) => format!("{}-{}", path, bot_id),
+    }
+}
+
+/// Serves the bot until Ctrl-C, then saves what it has to.
+async fn run_bot(
+    bot: Bot,
+    bot_id: i64,
+    store: Arc<dyn UserStore>,
+    channels: Arc<Channels>,
+    config: Arc<Config>,
+    primary: Option<Primary>,
+) {
+    register_commands(&bot).await;
+
+    let commands_handler = filter_command::<Command, _>()
+        .branch(
+            dptree::filter(|command: Command, maintenance: Arc<Maintenance>| {
+                maintenance.is_on() && !command.is_read_only()
+            })
+            .endpoint(handle_maintenance),
+        )
+        .branch(dptree::case![Command::Start].endpoint(handle_start_command))
+        .branch(dptree::case![Command::Help].endpoint(handle_help_command))
+        .branch(dptree::case![Command::Stop].endpoint(handle_stop_command))
+        .branch(dptree::case![Command::Done].endpoint(handle_done_command))
+        .branch(dptree::case![Command::Status].endpoint(handle_status_command))
+        .branch(dptree::case![Command::Next].endpoint(handle_next_command))
+        .branch(dptree::case![Command::History].endpoint(handle_history_command))
+        .branch(dptree::case![Command::MyStats].endpoint(handle_mystats_command))
+        .branch(dptree::case![Command::WhatTimeDoYouThinkItIs].endpoint(handle_what_time_command))
+        .branch(dptree::case![Command::Snooze(value)].endpoint(handle_snooze_command))
+        .branch(dptree::case![Command::Skip(value)].endpoint(handle_skip_command))
+        .branch(dptree::case![Command::Remind(args)].endpoint(handle_remind_command))
+        .branch(dptree::case![Command::Weekly(args)].endpoint(handle_weekly_command))
+        .branch(dptree::case![Command::Monthly(args)].endpoint(handle_monthly_command))
+        .branch(dptree::case![Command::CheckIn(args)].endpoint(handle_check_in_command))
+        .branch(dptree::case![Command::Timer(args)].endpoint(handle_timer_command))
+        .branch(dptree::case![Command::Pomodoro(args)].endpoint(handle_pomodoro_command))
+        .branch(dptree::case![Command::Report(args)].endpoint(handle_report_command))
+        .branch(dptree::case![Command::Deadline(args)].endpoint(handle_deadline_command))
+        .branch(dptree::case![Command::ChangeTimezone].endpoint(handle_change_timezone_command))
+        .branch(dptree::case![Command::SetTime].endpoint(handle_set_time_command))
+        .branch(dptree::case![Command::Interval(value)].endpoint(handle_interval_command))
+        .branch(dptree::case![Command::Settings].endpoint(handle_settings_command))
+        .branch(dptree::case![Command::Workdays(value)].endpoint(handle_workdays_command))
+        .branch(dptree::case![Command::Quiet(value)].endpoint(handle_quiet_command))
+        .branch(dptree::case![Command::SoftHours(value)].endpoint(handle_soft_hours_command))
+        .branch(dptree::case![Command::Cron(expression)].endpoint(handle_cron_command))
+        .branch(dptree::case![Command::Alias(args)].endpoint(handle_alias_command))
+        .branch(dptree::case![Command::Away].endpoint(handle_away_command))
+        .branch(dptree::case![Command::Back].endpoint(handle_back_command))
+        .branch(dptree::case![Command::Language(code)].endpoint(handle_language_command))
+        .branch(dptree::case![Command::Footer(value)].endpoint(handle_footer_command))
+        .branch(dptree::case![Command::AddMessage(message)].endpoint(handle_add_message_command))
+        .branch(
+            dptree::case![Command::RemoveMessage(value)].endpoint(handle_remove_message_command),
+        )
+        .branch(dptree::case![Command::Preset(name)].endpoint(handle_preset_command))
+        .branch(dptree::case![Command::ParseMode(value)].endpoint(handle_parse_mode_command))
+        .branch(dptree::case![Command::FirstSend(value)].endpoint(handle_first_send_command))
+        .branch(dptree::case![Command::AckMode(value)].endpoint(handle_ack_mode_command))
+        .branch(dptree::case![Command::Ack].endpoint(handle_ack_command))
+        .branch(
+            dptree::case![Command::SingleMessage(value)].endpoint(handle_single_message_command),
+        )
+        .branch(dptree::case![Command::Summary(value)].endpoint(handle_summary_command))
+        .branch(dptree::case![Command::JoinProfile(name)].endpoint(handle_join_profile_command))
+        .branch(dptree::case![Command::Transfer(code)].endpoint(handle_transfer_command))
+        .branch(dptree::case![Command::WhatsNew(value)].endpoint(handle_whats_new_command))
+        .branch(dptree::case![Command::Channels(args)].endpoint(handle_channels_command))
+        .branch(dptree::case![Command::Connect(args)].endpoint(handle_connect_command))
+        .branch(dptree::case![Command::Insights].endpoint(handle_insights_command))
+        .branch(dptree::case![Command::Suggest].endpoint(handle_suggest_command))
+        .branch(
+            dptree::case![Command::AllInsights]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_all_insights_command),
+        )
+        .branch(
+            dptree::case![Command::As(args)]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_as_command),
+        )
+        .branch(
+            dptree::case![Command::StartGroups(ids)]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_start_groups_command),
+        )
+        .branch(
+            dptree::case![Command::Jobs]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_jobs_command),
+        )
+        .branch(
+            dptree::case![Command::Journal]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_journal_command),
+        )
+        .branch(
+            dptree::case![Command::Maintenance(value)]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_maintenance_command),
+        )
+        .branch(
+            dptree::case![Command::Profile(args)]
+                .filter(|msg: Message, config: Arc<Config>| config.is_admin(&msg))
+                .endpoint(handle_profile_command),
+        );
+
+    #[cfg(feature = "http")]
+    let commands_handler = commands_handler
+        .branch(dptree::case![Command::Token(value)].endpoint(handle_token_command))
+        .branch(dptree::case![Command::Template(template)].endpoint(handle_template_command))
+        .branch(dptree::case![Command::Webhook(value)].endpoint(handle_webhook_command));
+
+    let messages_handler = Update::filter_message()
+        .inspect_async(check_clock_skew)
+        .map_async(expand_alias)
+        .enter_dialogue::<Message, ErasedStorage<State>, State>()
+        .chain(timed(message_handler_name))
+        .chain(serialized(|deps| {
+            let msg: Arc<Message> = deps.get();
+            Some(msg.chat.id)
+        }))
+        .inspect_async(record_contact)
+        .branch(commands_handler)
+        .branch(
+            dptree::case![State::RemoveMessages]
+                .filter(|config: Arc<Config>| config.delete_messages)
+                .endpoint(handle_message),
+        )
+        // Dialogues started before maintenance wait until it's over
+        .branch(
+            dptree::filter(|state: State, maintenance: Arc<Maintenance>| {
+                maintenance.is_on() && !matches!(state, State::RemoveMessages)
+            })
+            .endpoint(handle_maintenance),
+        )
+        .branch(dptree::case![State::RecieveNewTimezoneOffset].endpoint(handle_new_timezone))
+        .branch(dptree::case![State::RecieveWorkingHours].endpoint(handle_new_working_hours))
+        .branch(dptree::case![State::OnboardingTimezone].endpoint(handle_onboarding_timezone))
+        .branch(
+            dptree::case![State::OnboardingWorkingHours(onboarding)]
+                .endpoint(handle_onboarding_working_hours),
+        )
+        .branch(
+            dptree::case![State::OnboardingWorkdays(onboarding)]
+                .endpoint(handle_onboarding_workdays),
+        );
+
+    let callbacks_handler = Update::filter_callback_query()
+        .chain(timed(|_| "callback".to_string()))
+        .chain(serialized(|deps| {
+            let query: Arc<CallbackQuery> = deps.get();
+            query.message.as_ref().map(|msg| msg.chat.id)
+        }))
+        .branch(
+            dptree::filter(|maintenance: Arc<Maintenance>| maintenance.is_on())
+                .endpoint(handle_maintenance_callback),
+        )
+        .branch(
+            dptree::filter_map(|query: CallbackQuery| {
+                query.data.as_deref().and_then(NotificationButton::decode)
+            })
+            .endpoint(handle_notification_button),
+        )
+        .branch(
+            dptree::filter_map(|query: CallbackQuery| {
+                query.data.as_deref().and_then(StopButton::decode)
+            })
+            .endpoint(handle_stop_button),
+        )
+        .branch(
+            dptree::filter_map(|query: CallbackQuery| {
+                query.data.as_deref().and_then(PomodoroButton::decode)
+            })
+            .endpoint(handle_pomodoro_button),
+        )
+        .branch(
+            dptree::filter_map(|query: CallbackQuery| {
+                query.data.as_deref().and_then(PreviewButton::decode)
+            })
+            .endpoint(handle_preview_button),
+        )
+        .branch(
+            dptree::filter_map(|query: CallbackQuery| {
+                query.data.as_deref().and_then(MenuButton::decode)
+            })
+            .endpoint(handle_menu_button),
+        )
+        .endpoint(handle_callback_query);
+
+    let journal = Arc::new(
+        match config.user_store {
+            store::Backend::Sqlite => Journal::open(bot_path(JOURNAL_PATH, bot_id)),
+            store::Backend::Memory => Journal::open_in_memory(),
+        }
+        .unwrap(),
+    );
+    let dialogues: Arc<ErasedStorage<State>> = match config.dialogue_store {
+        store::Backend::Sqlite => SqliteDialogues::open(bot_path("dialogues.sqlite3", bot_id))
+            .unwrap()
+            .erase(),
+        store::Backend::Memory => InMemStorage::new().erase(),
+    };
+    let processed = ProcessedUpdates::open_or_create(&bot_path("updates.db", bot_id)).unwrap();
+    let job_queue = JobQueue::open_or_create(&bot_path("jobs.db", bot_id))
+        .unwrap()
+        .with_spread(config.job_spread);
+    let (evicted, evictions) = mpsc::unbounded_channel();
+    let (skipped, skips) = mpsc::unbounded_channel();
+    let (delivered, deliveries) = mpsc::unbounded_channel();
+    let (budget_alerts, budget_levels) = mpsc::unbounded_channel();
+    let mut notification_sender = Notification::build(
+        config.messages.clone(),
+        config.message_rotation,
+        config.parse_mode,
+    )
+    .sender(bot.clone(), evicted)
+    .with_blackouts(config.blackouts.clone())
+    .with_channels(Arc::clone(&channels))
+    .with_skips(skipped)
+    .with_deliveries(delivered)
+    .with_journal(Arc::clone(&journal));
+    if let Some(limit) = config.send_budget {
+        notification_sender = notification_sender.with_budget(Budget::new(limit, budget_alerts));
+        spawn(alert_budget(
+            budget_levels,
+            bot.clone(),
+            config.admins.clone(),
+            config.default_locale,
+            limit,
+        ));
+    }
+
+    store
+        .get_all()
+        .await
+        .iter()
+        // Chats with a pending wake up are paused until the job runs
+        .filter(|(user_id, _)| {
+            !job_queue
+                .for_chat(user_id)
+                .iter()
+                .any(|job| JobKind::RESUMING.contains(&job.kind))
+        })
+        .for_each(|(user_id, settings)| {
+            notification_sender.start(user_id, settings, false);
+        });
+    // Paused chats get reminders too
+    for (user_id, settings) in store.get_all().await {
+        notification_sender.set_topic(&user_id, settings.thread_id);
+    }
+
+    let notify_controller_mutex = Arc::new(Mutex::new(notification_sender));
+    let jobs_mutex = Arc::new(Mutex::new(job_queue));
+    let chat_locks = Arc::new(ChatLocks::new());
+    let maintenance = Arc::new(Maintenance::new(config.maintenance));
+    let (stop_jobs, jobs_stopped) = watch::channel(false);
+    let jobs_task = spawn(run_jobs(
+        bot.clone(),
+        Arc::clone(&jobs_mutex),
+        Arc::clone(&store),
+        Arc::clone(&notify_controller_mutex),
+        Arc::clone(&chat_locks),
+        jobs_stopped,
+    ));
+    spawn(evict_chats(
+        evictions,
+        Arc::clone(&store),
+        Arc::clone(&notify_controller_mutex),
+        Arc::clone(&jobs_mutex),
+        Arc::clone(&chat_locks),
+    ));
+    spawn(record_skips(
+        skips,
+        Arc::clone(&store),
+        Arc::clone(&chat_locks),
+    ));
+    spawn(record_deliveries(deliveries, Arc::clone(&store)));
+    spawn(announce_release(
+        Arc::clone(&store),
+        Arc::clone(&notify_controller_mutex),
+        Arc::clone(&chat_locks),
+    ));
+    if let Some(url) = config.telemetry_url.clone().filter(|_| primary.is_some()) {
+        spawn(telemetry::run(
+            url,
+            Arc::clone(&store),
+            config.user_store.name(),
+        ));
+    }
+    // Updates come from the webhook once Telegram accepted it, otherwise the
+    // dispatcher polls for them
+    #[cfg(feature = "http")]
+    let mut webhook_listener = None;
+    #[cfg(feature = "http")]
+    if let Some(addr) = config.http_addr.filter(|_| primary.is_some()) {
+        let webhook = config.webhook_url.clone().map(|url| {
+            let secret = config
+                .webhook_secret
+                .clone()
+                .unwrap_or_else(http::generate_token);
+            let (inbox, listener) = webhook::channel(secret.clone());
+            (url, secret, inbox, listener)
+        });
+        let inbox = webhook.as_ref().map(|(_, _, inbox, _)| inbox.clone());
+        let store = Arc::clone(&store);
+        let notify_controller_mutex = Arc::clone(&notify_controller_mutex);
+        let chat_locks = Arc::clone(&chat_locks);
+        let maintenance = Arc::clone(&maintenance);
+        let api_token = config.http_api_token.clone();
+        let plain_bot = bot.inner().inner().clone();
+        spawn(async move {
+            if let Err(err) = http::serve(
+                addr,
+                store,
+                notify_controller_mutex,
+                chat_locks,
+                maintenance,
+                inbox,
+                api_token,
+                plain_bot,
+            )
+            .await
+            {
+                log::error!("HTTP API stopped: {}", err);
+            }
+        });
+
+        if let Some((url, secret, _, listener)) = webhook {
+            match webhook::set(&bot, url.clone(), &secret).await {
+                Ok(()) => {
+                    log::info!("Receiving updates at {}", url);
+                    webhook_listener = Some(listener);
+                }
+                Err(err) => log::error!("Unable to set webhook {}, polling instead: {}", url, err),
+            }
+        }
+    } else if config.webhook_url.is_some() && primary.is_some() {
+        log::warn!("WEBHOOK_URL needs HTTP_ADDR, polling for updates");
+    }
+    #[cfg(feature = "mqtt")]
+    if let Some(mqtt_config) = primary.and_then(|primary| primary.mqtt) {
+        spawn(mqtt::run(
+            mqtt_config,
+            Arc::clone(&store),
+            Arc::clone(&notify_controller_mutex),
+        ));
+    }
+
+    let mut dispatcher = Dispatcher::builder(
+        bot,
+        dptree::entry()
+            .filter(|update: Update, processed: Arc<ProcessedUpdates>| {
+                let first_time = processed.first_time(update.id);
+                if !first_time {
+                    log::warn!("Update {} was delivered again, skipping it", update.id);
+                }
+                first_time
+            })
+            .branch(messages_handler)
+            .branch(callbacks_handler),
+    )
+    .enable_ctrlc_handler()
+    .dependencies(dptree::deps![
+        Arc::clone(&store),
+        Arc::clone(&notify_controller_mutex),
+        Arc::clone(&jobs_mutex),
+        chat_locks,
+        maintenance,
+        Arc::new(ChatInfoCache::new()),
+        Arc::new(Transfers::new()),
+        channels,
+        journal,
+        Arc::new(processed),
+        Arc::new(std::sync::Mutex::new(SkewMonitor::new(
+            config.clock_skew_threshold
+        ))),
+        config,
+        dialogues
+    ])
+    .build();
+    // Long polling removes a webhook left by a previous start and fetches
+    // the updates that waited for it
+    #[cfg(feature = "http")]
+    if let Some(listener) = webhook_listener {
+        dispatcher
+            .dispatch_with_listener(
+                listener,
+                LoggingErrorHandler::with_custom_text("An error from the webhook"),
+            )
+            .await;
+    } else {
+        dispatcher.dispatch().await;
+    }
+    #[cfg(not(feature = "http"))]
+    dispatcher.dispatch().await;
+
+    log::info!("Shutting down bot {}...", bot_id);
+    // Jobs finish the one running, the ones taken but not run go back to the
+    // queue, so snoozes and wake ups come back on the next start
+    let _ = stop_jobs.send(true);
+    if let Err(err) = jobs_task.await {
+        log::error!("Jobs runner failed: {}", err);
+    }
+    let stopped = metrics::lock(&notify_controller_mutex, "notify_controller")
+        .await
+        .stop_all();
+    log::info!("Stopped notifications of {} chats", stopped);
+
+    if let Err(err) = metrics::lock(&jobs_mutex, "jobs").await.flush() {
+        log::error!("Unable to flush jobs: {}", err);
+    }
+    if let Err(err) = store.flush().await {
+        log::error!("Unable to flush the user store: {}", err);
+    }
+}
+
+type UpdateHandler = dptree::Handler<'static, DependencyMap, HandlerResult, DpHandlerDescription>;
+
+/// Records how long the rest of the chain takes, under the name `name` gives
+/// the update.
+fn timed(name: fn(&DependencyMap) -> String) -> UpdateHandler {
+    dptree::from_fn(move |deps: DependencyMap, cont| async move {
+        let name = name(&deps);
+        let start = Instant::now();
+        let result = cont(deps).await;
+        metrics::REGISTRY.observe(&metrics::HANDLER_DURATION, &name, start.elapsed());
+        result
+    })
+}
+
+/// Holds the lock of the chat `chat_id` finds for the rest of the chain.
+fn serialized(chat_id: fn(&DependencyMap) -> Option<ChatId>) -> UpdateHandler {
+    dptree::from_fn(move |deps: DependencyMap, cont| async move {
+        let chat_locks: Arc<ChatLocks> = deps.get();
+        let _guard = match chat_id(&deps) {
+            Some(chat_id) => Some(chat_locks.lock(chat_id).await),
+            None => None,
+        };
+        cont(deps).await
+    })
+}
+
+/// The command a message runs, or the dialogue state handling it otherwise.
+fn message_handler_name(deps: &DependencyMap) -> String {
+    let msg: Arc<Message> = deps.get();
+    let command = msg
+        .text()
+        .and_then(|text| text.strip_prefix('/'))
+        .and_then(|text| text.split(|c: char| c.is_whitespace() || c == '@').next())
+        .map(str::to_lowercase)
+        .filter(|command| command_names().contains(command));
+    if let Some(command) = command {
+        return command;
+    }
+
+    let state: Arc<State> = deps.get();
+    match *state {
+        State::RemoveMessages => "message",
+        State::RecieveNewTimezoneOffset => "timezone",
+        State::RecieveWorkingHours => "working_hours",
+        State::OnboardingTimezone
+        | State::OnboardingWorkingHours(_)
+        | State::OnboardingWorkdays(_) => "onboarding",
+    }
+    .to_string()
+}
+
+/// Warns admins when message dates suggest the local clock is off, all the
+/// scheduling relies on it.
+async fn check_clock_skew(
+    bot: Bot,
+    msg: Message,
+    skew_monitor: Arc<std::sync::Mutex<SkewMonitor>>,
+    config: Arc<Config>,
+) {
+    let (alert, skew) = {
+        let mut monitor = skew_monitor.lock().unwrap();
+        (monitor.record(msg.date, Utc::now()), monitor.skew())
+    };
+    if let Some(skew) = skew {
+        metrics::REGISTRY.set(&metrics::CLOCK_SKEW, skew as f64);
+    }
+    let Some(skew) = alert else {
+        return;
+    };
+
+    log::warn!("The clock is {} s off from Telegram's", skew);
+    let text = tr!(
+        config.default_locale,
+        "clock-skew-alert",
+        skew = formatting::duration(
+            std::time::Duration::from_secs(skew.unsigned_abs()),
+            config.default_locale
+        ),
+        direction = if skew > 0 { "ahead" } else { "behind" }
+    );
+    for admin in &config.admins {
+        if let Err(err) = bot.send_message(*admin, text.clone()).await {
+            log::error!("Unable to alert admin {} about clock skew: {}", admin, err);
+        }
+    }
+}
+
+/// Replaces a leading command alias, or a command keyword sent without the
+/// slash in a private chat, with the command it stands for, so the rest of
+/// the chain only ever sees real commands.
+async fn expand_alias(msg: Message, store: Arc<dyn UserStore>, config: Arc<Config>) -> Message {
+    let Some(text) = msg.text() else {
+        return msg;
+    };
+
+    let settings = store.get(&msg.chat.id).await;
+    let expanded = match text.starts_with('/') {
+        true => {
+            let user_aliases = settings
+                .map(|settings| settings.aliases)
+                .unwrap_or_default();
+            aliases::expand(text, |name| user_aliases.get(name).cloned())
+        }
+        false if msg.chat.is_private() => {
+            let locale = settings
+                .map(|settings| settings.locale)
+                .unwrap_or_else(|| detect_locale(&msg, &config));
+            aliases::keyword(text, locale).map(|command| format!("/{}", command))
+        }
+        false => None,
+    };
+
+    match expanded {
+        Some(text) => with_text(msg, text),
+        None => msg,
+    }
+}
+
+/// Any message of a chat with "/checkin" on counts as a check-in.
+async fn record_contact(msg: Message, store: Arc<dyn UserStore>) {
+    let Some(check_in) = store
+        .get(&msg.chat.id)
+        .await
+        .and_then(|settings| settings.check_in)
+    else {
+        return;
+    };
+    // Periods are hours long, there's no need to write on every message
+    let now = Utc::now();
+    if now - check_in.last_contact < chrono::Duration::minutes(1) {
+        return;
+    }
+
+    let updated = store
+        .update(&msg.chat.id, |settings| {
+            if let Some(check_in) = settings.check_in.as_mut() {
+                check_in.last_contact = now;
+            }
+        })
+        .await;
+    if let Err(err) = updated {
+        log::error!("Unable to record check-in of {}: {}", msg.chat.id, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bot::bot_path;
+
+    #[test]
+    fn test_bot_path() {
+        assert_eq!(bot_path("jobs.db", 0), "jobs.db");
+        assert_eq!(bot_path("journal.sqlite3", 42), "journal-42.sqlite3");
+        assert_eq!(bot_path("updates", 42), "updates-42");
+    }
+}
@@ -0,0 +1,226 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use teloxide::types::ChatId;
+
+use crate::{delivery::Failure, offsets_rep::UserSettings};
+
+/// The chat itself, every chat has it.
+pub const TELEGRAM: &str = "telegram";
+
+/// A way to deliver notifications besides the chat, e.g. a Slack channel.
+#[async_trait]
+pub trait Channel: Send + Sync {
+    /// How users refer to the channel in "/channels"
+    fn name(&self) -> &'static str;
+
+    /// Checks where a user wants to be reached, e.g. a webhook URL, `None`
+    /// if the channel can't send there.
+    fn parse_address(&self, address: &str) -> Option<String>;
+
+    async fn send(&self, address: &str, text: &str) -> Result<(), String>;
+}
+
+/// How the last deliveries through a channel of a chat went.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Status {
+    pub delivered: Option<DateTime<Utc>>,
+    pub failed: Option<(DateTime<Utc>, String)>,
+}
+
+/// Channels notifications can go through, each chat lists the ones it uses
+/// in order of preference and the first that works delivers.
+#[derive(Default)]
+pub struct Channels {
+    channels: Vec<Box<dyn Channel>>,
+    statuses: Mutex<HashMap<(ChatId, String), Status>>,
+}
+
+impl Channels {
+    pub fn new() -> Channels {
+        Channels::default()
+    }
+
+    pub fn register<C: Channel + 'static>(mut self, channel: C) -> Channels {
+        self.channels.push(Box::new(channel));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Channel> {
+        self.channels
+            .iter()
+            .find(|channel| channel.name() == name)
+            .map(|channel| channel.as_ref())
+    }
+
+    /// Names of every channel, the chat first.
+    pub fn names(&self) -> Vec<&'static str> {
+        std::iter::once(TELEGRAM)
+            .chain(self.channels.iter().map(|channel| channel.name()))
+            .collect()
+    }
+
+    /// The chat's channels in order, the ones no longer available left out.
+    pub fn order(&self, settings: &UserSettings) -> Vec<String> {
+        let order: Vec<String> = settings
+            .channels
+            .iter()
+            .filter(|name| *name == TELEGRAM || self.get(name).is_some())
+            .cloned()
+            .collect();
+        match order.is_empty() {
+            true => vec![TELEGRAM.to_string()],
+            false => order,
+        }
+    }
+
+    /// Sends through a channel other than the chat to the address the chat
+    /// gave it.
+    pub async fn send(
+        &self,
+        name: &str,
+        settings: &UserSettings,
+        text: &str,
+    ) -> Result<(), String> {
+        let channel = self
+            .get(name)
+            .ok_or_else(|| format!("{} isn't available", name))?;
+        let address = settings
+            .channel_addresses
+            .get(name)
+            .ok_or_else(|| format!("no address for {}", name))?;
+        channel.send(address, text).await
+    }
+
+    pub fn record(&self, chat_id: ChatId, name: &str, result: Result<(), String>) {
+        self.record_at(chat_id, name, result, Utc::now())
+    }
+
+    pub fn status(&self, chat_id: ChatId, name: &str) -> Status {
+        self.statuses
+            .lock()
+            .unwrap()
+            .get(&(chat_id, name.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn record_at(
+        &self,
+        chat_id: ChatId,
+        name: &str,
+        result: Result<(), String>,
+        now: DateTime<Utc>,
+    ) {
+        let mut statuses = self.statuses.lock().unwrap();
+        let status = statuses.entry((chat_id, name.to_string())).or_default();
+        match result {
+            Ok(()) => status.delivered = Some(now),
+            Err(err) => status.failed = Some((now, err)),
+        }
+    }
+}
+
+/// Why a message didn't reach the chat, for the status of its channel.
+pub fn describe(failure: Failure) -> String {
+    match failure {
+        Failure::Gone => "the chat is gone".to_string(),
+        Failure::RetryAfter(retry_after) => {
+            format!("rate limited for {} s", retry_after.as_secs())
+        }
+        Failure::Transient => "Telegram is unreachable".to_string(),
+        Failure::Dropped => "left out to save the send budget".to_string(),
+    }
+}
+
+/// Posts to a Slack channel through an incoming webhook.
+pub struct Slack {
+    client: reqwest::Client,
+}
+
+impl Slack {
+    pub fn new() -> Slack {
+        Slack {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .expect("a client without custom TLS settings always builds"),
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for Slack {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    /// Only Slack's own webhooks, the bot doesn't post to arbitrary URLs.
+    fn parse_address(&self, address: &str) -> Option<String> {
+        let url = Url::parse(address.trim()).ok()?;
+        (url.scheme() == "https" && url.host_str() == Some("hooks.slack.com"))
+            .then(|| url.to_string())
+    }
+
+    async fn send(&self, address: &str, text: &str) -> Result<(), String> {
+        self.client
+            .post(address)
+            .json(&serde_json::json!({ "text": text }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| err.without_url().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use teloxide::types::ChatId;
+
+    use crate::{
+        channels::{Channels, Slack, Status, TELEGRAM},
+        offsets_rep::UserSettings,
+    };
+
+    #[test]
+    fn test_channels() {
+        let channels = Channels::new().register(Slack::new());
+        assert_eq!(channels.names(), vec![TELEGRAM, "slack"]);
+
+        let mut settings = UserSettings::default();
+        assert_eq!(channels.order(&settings), vec![TELEGRAM]);
+        settings.channels = vec![
+            "email".to_string(),
+            "slack".to_string(),
+            TELEGRAM.to_string(),
+        ];
+        assert_eq!(channels.order(&settings), vec!["slack", TELEGRAM]);
+
+        let slack = channels.get("slack").unwrap();
+        assert_eq!(
+            slack.parse_address(" https://hooks.slack.com/services/T0/B0/x "),
+            Some("https://hooks.slack.com/services/T0/B0/x".to_string())
+        );
+        assert_eq!(
+            slack.parse_address("http://hooks.slack.com/services/T0"),
+            None
+        );
+        assert_eq!(slack.parse_address("https://example.com/hook"), None);
+
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+        channels.record_at(ChatId(1), "slack", Ok(()), now);
+        channels.record_at(ChatId(1), "slack", Err("timeout".to_string()), now);
+        assert_eq!(
+            channels.status(ChatId(1), "slack"),
+            Status {
+                delivered: Some(now),
+                failed: Some((now, "timeout".to_string())),
+            }
+        );
+        assert_eq!(channels.status(ChatId(2), "slack"), Status::default());
+    }
+}
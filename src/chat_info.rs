@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use teloxide::{
+    requests::Requester,
+    types::{Chat, ChatId, ChatPermissions},
+};
+
+/// How long chat metadata is trusted before asking Telegram again.
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+/// What group features need to know about a chat.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatInfo {
+    pub title: Option<String>,
+    /// A group or a supergroup
+    pub is_group: bool,
+    /// Default permissions of members, groups only
+    pub permissions: Option<ChatPermissions>,
+}
+
+impl From<&Chat> for ChatInfo {
+    fn from(chat: &Chat) -> ChatInfo {
+        ChatInfo {
+            title: chat.title().map(str::to_string),
+            is_group: chat.is_group() || chat.is_supergroup(),
+            permissions: chat.permissions(),
+        }
+    }
+}
+
+/// Chat metadata fetched with "getChat", kept for a while so handlers
+/// looking at the same chats don't repeat the call.
+#[derive(Default)]
+pub struct ChatInfoCache {
+    chats: Mutex<HashMap<ChatId, (Instant, ChatInfo)>>,
+}
+
+impl ChatInfoCache {
+    pub fn new() -> ChatInfoCache {
+        ChatInfoCache::default()
+    }
+
+    pub async fn get<B: Requester>(&self, bot: &B, chat_id: ChatId) -> Result<ChatInfo, B::Err> {
+        if let Some(info) = self.cached(chat_id, Instant::now()) {
+            return Ok(info);
+        }
+
+        let info = ChatInfo::from(&bot.get_chat(chat_id).await?);
+        self.insert(chat_id, info.clone(), Instant::now());
+        Ok(info)
+    }
+
+    fn cached(&self, chat_id: ChatId, now: Instant) -> Option<ChatInfo> {
+        let chats = self.chats.lock().unwrap();
+        match chats.get(&chat_id) {
+            Some((fetched, info)) if now.duration_since(*fetched) < TTL => Some(info.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, chat_id: ChatId, info: ChatInfo, now: Instant) {
+        let mut chats = self.chats.lock().unwrap();
+        chats.retain(|_, (fetched, _)| now.duration_since(*fetched) < TTL);
+        chats.insert(chat_id, (now, info));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use teloxide::types::ChatId;
+
+    use crate::chat_info::{ChatInfo, ChatInfoCache, TTL};
+
+    #[test]
+    fn test_chat_info_cache() {
+        let cache = ChatInfoCache::new();
+        let now = Instant::now();
+        let info = ChatInfo {
+            title: Some("Team".to_string()),
+            is_group: true,
+            permissions: None,
+        };
+
+        assert_eq!(cache.cached(ChatId(-1), now), None);
+        cache.insert(ChatId(-1), info.clone(), now);
+        assert_eq!(
+            cache.cached(ChatId(-1), now + Duration::from_secs(60)),
+            Some(info)
+        );
+        assert_eq!(cache.cached(ChatId(-1), now + TTL), None);
+        assert_eq!(cache.cached(ChatId(-2), now), None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as StdMutex},
+    time::Instant,
+};
+
+use async_mutex::{Mutex, MutexGuardArc};
+use teloxide::types::ChatId;
+
+use crate::metrics;
+
+/// One lock per chat, held while an update, a delayed job or an HTTP call
+/// changes the chat's state.
+///
+/// Telegram updates of a chat are already handled one at a time, the lock
+/// keeps jobs and the HTTP API from interleaving with them, e.g. a due wake
+/// up restarting notifications paused again a moment before.
+#[derive(Default)]
+pub struct ChatLocks {
+    locks: StdMutex<HashMap<ChatId, Arc<Mutex<()>>>>,
+}
+
+impl ChatLocks {
+    pub fn new() -> ChatLocks {
+        ChatLocks::default()
+    }
+
+    /// Waits until nothing else changes the chat, the chat is free again once
+    /// the guard is dropped.
+    pub async fn lock(&self, chat_id: ChatId) -> MutexGuardArc<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            // Locks nobody holds or waits for are dropped as the map grows
+            if locks.len() >= locks.capacity() {
+                locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            }
+            Arc::clone(locks.entry(chat_id).or_default())
+        };
+
+        let start = Instant::now();
+        let guard = lock.lock_arc().await;
+        metrics::REGISTRY.observe(&metrics::LOCK_WAIT, "chat", start.elapsed());
+        guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use teloxide::types::ChatId;
+    use tokio::time::sleep;
+
+    use crate::chat_locks::ChatLocks;
+
+    #[tokio::test]
+    async fn test_chat_locks() {
+        let locks = Arc::new(ChatLocks::new());
+        let events = Arc::new(std::sync::Mutex::new(vec![]));
+
+        let guard = locks.lock(ChatId(1)).await;
+        let waiting = {
+            let (locks, events) = (Arc::clone(&locks), Arc::clone(&events));
+            tokio::spawn(async move {
+                let _guard = locks.lock(ChatId(1)).await;
+                events.lock().unwrap().push("second");
+            })
+        };
+        // Other chats aren't held up
+        drop(locks.lock(ChatId(2)).await);
+
+        sleep(Duration::from_millis(20)).await;
+        events.lock().unwrap().push("first");
+        drop(guard);
+        waiting.await.unwrap();
+
+        assert_eq!(*events.lock().unwrap(), vec!["first", "second"]);
+    }
+}
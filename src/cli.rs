@@ -0,0 +1,199 @@
+use clap::{Parser, Subcommand};
+use teloxide::{prelude::*, types::ChatId};
+
+use crate::{
+    bot,
+    config::Config,
+    formatting,
+    offsets_rep::{OffsetsRepository, SCHEMA_VERSION},
+    store::{self, UserStore},
+};
+
+/// Notifications during working hours. Maintenance commands work on the
+/// store without starting the dispatcher.
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Check what the bot needs, for Docker's HEALTHCHECK
+    #[arg(long)]
+    pub healthcheck: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// What the binary is asked to do, serving the bots unless told otherwise.
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum Command {
+    /// Serve the bots, the default
+    Run,
+    /// Subscribed chats of the first bot
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Send a message to a chat from the first bot
+    #[command(allow_negative_numbers = true)]
+    Send {
+        #[arg(value_parser = parse_chat_id)]
+        chat_id: ChatId,
+        /// Words of the message, joined with spaces
+        #[arg(required = true, trailing_var_arg = true)]
+        text: Vec<String>,
+    },
+    /// Bring the database up to the current schema
+    MigrateDb,
+}
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum UsersCommand {
+    /// List subscribed chats
+    List,
+    /// Unsubscribe a chat
+    #[command(allow_negative_numbers = true)]
+    Remove {
+        #[arg(value_parser = parse_chat_id)]
+        chat_id: ChatId,
+    },
+}
+
+fn parse_chat_id(id: &str) -> Result<ChatId, String> {
+    id.parse()
+        .map(ChatId)
+        .map_err(|_| format!("Invalid chat id {}", id))
+}
+
+/// Runs the command, `false` if it failed.
+pub async fn run(cli: Cli, config: Config) -> bool {
+    if cli.healthcheck {
+        return bot::healthcheck(&config).await;
+    }
+    let result = match cli.command.unwrap_or(Command::Run) {
+        Command::Run => {
+            log::info!("Starting bot...");
+            bot::run(config).await;
+            Ok(())
+        }
+        Command::Users(UsersCommand::List) => list_users(&config).await,
+        Command::Users(UsersCommand::Remove { chat_id }) => remove_user(&config, chat_id).await,
+        Command::Send { chat_id, text } => send(&config, chat_id, text.join(" ")).await,
+        Command::MigrateDb => open_repository(&config).map(|_| {
+            println!(
+                "{} is at schema version {}",
+                config.db_path.display(),
+                SCHEMA_VERSION
+            )
+        }),
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("{}", err);
+            false
+        }
+    }
+}
+
+fn open_repository(config: &Config) -> Result<OffsetsRepository, String> {
+    match config.user_store {
+        store::Backend::Sqlite => OffsetsRepository::open_or_import(&config.db_path, "users.db")
+            .map_err(|err| format!("Unable to open {}: {}", config.db_path.display(), err)),
+        store::Backend::Memory => Err("The memory store keeps no chats between runs".to_string()),
+    }
+}
+
+/// One chat per line: its id, language, timezone and working hours.
+async fn list_users(config: &Config) -> Result<(), String> {
+    for (chat_id, settings) in open_repository(config)?.get_all().await {
+        let timezone = settings
+            .timezone
+            .clone()
+            .unwrap_or_else(|| formatting::offset(&settings.fixed_offset()));
+        println!(
+            "{}\t{}\t{}\t{:02}-{:02}",
+            chat_id,
+            settings.locale.code(),
+            timezone,
+            settings.working_hours.from,
+            settings.working_hours.to
+        );
+    }
+    Ok(())
+}
+
+/// A running bot keeps notifying the chat until it restarts.
+async fn remove_user(config: &Config, chat_id: ChatId) -> Result<(), String> {
+    match open_repository(config)?.rem(&chat_id).await {
+        Ok(true) => {
+            println!("Removed {}", chat_id);
+            Ok(())
+        }
+        Ok(false) => Err(format!("No chat {}", chat_id)),
+        Err(err) => Err(format!("Unable to remove {}: {}", chat_id, err)),
+    }
+}
+
+async fn send(config: &Config, chat_id: ChatId, text: String) -> Result<(), String> {
+    // Into the chat's topic when the store knows it
+    let thread_id = match open_repository(config) {
+        Ok(repository) => repository
+            .get(&chat_id)
+            .await
+            .and_then(|settings| settings.thread_id),
+        Err(_) => None,
+    };
+
+    let request = bot::first_bot(config).send_message(chat_id, text);
+    let request = match thread_id {
+        Some(thread_id) => request.message_thread_id(thread_id),
+        None => request,
+    };
+    match request.await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("Unable to send to {}: {}", chat_id, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use teloxide::types::ChatId;
+
+    use crate::cli::{Cli, Command, UsersCommand};
+
+    #[test]
+    fn test_parse() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(std::iter::once("notification_bot").chain(args.iter().copied()))
+        };
+        let command = |args: &[&str]| parse(args).unwrap().command;
+
+        assert_eq!(command(&[]), None);
+        assert_eq!(command(&["run"]), Some(Command::Run));
+        assert!(parse(&["--healthcheck"]).unwrap().healthcheck);
+        assert_eq!(
+            command(&["users", "list"]),
+            Some(Command::Users(UsersCommand::List))
+        );
+        assert_eq!(
+            command(&["users", "remove", "-100123"]),
+            Some(Command::Users(UsersCommand::Remove {
+                chat_id: ChatId(-100123)
+            }))
+        );
+        assert_eq!(
+            command(&["send", "42", "Back", "in 5 minutes"]),
+            Some(Command::Send {
+                chat_id: ChatId(42),
+                text: vec!["Back".to_string(), "in 5 minutes".to_string()]
+            })
+        );
+        assert_eq!(command(&["migrate-db"]), Some(Command::MigrateDb));
+
+        assert!(parse(&["users", "remove", "someone"])
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid chat id someone"));
+        assert!(parse(&["send", "42"]).is_err());
+        assert!(parse(&["users"]).is_err());
+        assert!(parse(&["--healthcheck", "run"]).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::{collections::VecDeque, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+/// Skew tolerated before admins are alerted, unless configured otherwise.
+pub const DEFAULT_SKEW_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Incoming messages the skew is estimated over.
+const WINDOW: usize = 10;
+
+/// How often admins are reminded while the clock stays off.
+const ALERT_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Estimates how far the local clock is off from Telegram's by the dates of
+/// incoming messages.
+///
+/// Delivery only ever delays a message, so the smallest difference between
+/// receiving and sending over the recent messages is the best estimate.
+pub struct SkewMonitor {
+    threshold: Duration,
+    delays: VecDeque<i64>,
+    last_alert: Option<DateTime<Utc>>,
+}
+
+impl SkewMonitor {
+    pub fn new(threshold: Duration) -> SkewMonitor {
+        SkewMonitor {
+            threshold,
+            delays: VecDeque::with_capacity(WINDOW),
+            last_alert: None,
+        }
+    }
+
+    /// Seconds the local clock is ahead of Telegram's, negative when it's
+    /// behind, `None` until there are enough messages to tell.
+    ///
+    /// A message dated in the future proves the clock is behind right away.
+    pub fn skew(&self) -> Option<i64> {
+        let min = self.delays.iter().copied().min()?;
+        match self.delays.len() == WINDOW || min < 0 {
+            true => Some(min),
+            false => None,
+        }
+    }
+
+    /// Records a message sent at `sent` and received at `now`, returns the
+    /// skew when it's beyond the threshold and admins are due an alert.
+    pub fn record(&mut self, sent: DateTime<Utc>, now: DateTime<Utc>) -> Option<i64> {
+        if self.delays.len() == WINDOW {
+            self.delays.pop_front();
+        }
+        self.delays.push_back((now - sent).num_seconds());
+
+        let skew = self.skew()?;
+        if skew.unsigned_abs() <= self.threshold.as_secs() {
+            return None;
+        }
+
+        let alert_interval = chrono::Duration::seconds(ALERT_INTERVAL.as_secs() as i64);
+        match self.last_alert {
+            Some(last_alert) if now - last_alert < alert_interval => None,
+            _ => {
+                self.last_alert = Some(now);
+                Some(skew)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::clock::{SkewMonitor, WINDOW};
+
+    #[test]
+    fn test_skew_monitor() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let seconds = chrono::Duration::seconds;
+
+        let mut monitor = SkewMonitor::new(Duration::from_secs(30));
+        for delay in 0..WINDOW as i64 - 1 {
+            assert_eq!(monitor.record(now - seconds(120 + delay), now), None);
+        }
+        assert_eq!(monitor.skew(), None);
+        assert_eq!(monitor.record(now - seconds(125), now), Some(120));
+        // Already alerted within the hour
+        assert_eq!(monitor.record(now - seconds(200), now + seconds(60)), None);
+        assert_eq!(
+            monitor.record(now + seconds(3600 - 130), now + seconds(3600)),
+            Some(122)
+        );
+
+        // A prompt message brings the estimate back
+        assert_eq!(monitor.record(now - seconds(1), now), None);
+        assert_eq!(monitor.skew(), Some(1));
+
+        let mut monitor = SkewMonitor::new(Duration::from_secs(30));
+        assert_eq!(monitor.record(now + seconds(45), now), Some(-45));
+    }
+}
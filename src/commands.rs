@@ -0,0 +1,250 @@
+use teloxide::{prelude::*, types::BotCommand, utils::command::BotCommands};
+
+use crate::{bot::Bot, i18n::Locale, tr};
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum Command {
+    #[command(description = "Start notifications sending")]
+    Start,
+    #[command(description = "List the commands and what they do")]
+    Help,
+    #[command(description = "Stop notifications sending")]
+    Stop,
+    #[command(description = "Stop notifications until tomorrow")]
+    Done,
+    #[command(description = "Show the current subscription state")]
+    Status,
+    #[command(description = "Show when the next notifications come")]
+    Next,
+    #[command(description = "Show the time the bot thinks it is for you and in UTC")]
+    WhatTimeDoYouThinkItIs,
+    #[command(description = "Show your last notifications and whether they arrived")]
+    History,
+    #[command(description = "Show how many notifications you got and your \"/done\" streak")]
+    MyStats,
+    #[command(description = "Pause notifications for a while, e.g. \"/snooze 45m\"")]
+    Snooze(String),
+    #[command(description = "Skip the next notifications, e.g. \"/skip 3\"")]
+    Skip(String),
+    #[command(
+        description = "Send a message once at a given time, e.g. \"/remind 15:30 call mom\""
+    )]
+    Remind(String),
+    #[command(description = "Remind every week, e.g. \"/weekly mon 10:00 plan the week\"")]
+    Weekly(String),
+    #[command(description = "Remind every month, e.g. \"/monthly 1 10:00 pay the rent\"")]
+    Monthly(String),
+    #[command(
+        description = "Alert a contact when you don't write to the bot for a while, e.g. \"/checkin 24h -1001234567890\""
+    )]
+    CheckIn(String),
+    #[command(
+        description = "Count down and send a message when time is up, e.g. \"/timer 25m tea\""
+    )]
+    Timer(String),
+    #[command(
+        description = "Work and take breaks in turns, 25 and 5 minutes or e.g. \"/pomodoro 50 10\""
+    )]
+    Pomodoro(String),
+    #[command(
+        description = "Remind every month with a timesheet attached, e.g. \"/report 25 10:00 send the timesheet\""
+    )]
+    Report(String),
+    #[command(
+        description = "Remind more often as a date comes closer, e.g. \"/deadline 2024-06-30 send the report\""
+    )]
+    Deadline(String),
+    #[command(description = "Change settings by pressing buttons")]
+    Settings,
+    #[command(description = "Start time zone change dialog")]
+    ChangeTimezone,
+    #[command(description = "Start working hours change dialog")]
+    SetTime,
+    #[command(description = "Show or change how often notifications are sent")]
+    Interval(String),
+    #[command(description = "Show or change the weekdays notifications are sent on")]
+    Workdays(String),
+    #[command(description = "Pause notifications for breaks, e.g. \"/quiet 13:00-14:00\"")]
+    Quiet(String),
+    #[command(
+        description = "Get notifications without a sound at the ends of working hours, e.g. \"/softhours 1\""
+    )]
+    SoftHours(String),
+    #[command(
+        description = "Follow a cron schedule instead of working hours, e.g. \"/cron 0 0 9-18 * * MON-FRI\""
+    )]
+    Cron(String),
+    #[command(description = "List, add or remove command shortcuts")]
+    Alias(String),
+    #[command(description = "Mute notifications while you are away")]
+    Away,
+    #[command(description = "Unmute notifications muted with \"/away\"")]
+    Back,
+    #[command(description = "Show or change the language of replies")]
+    Language(String),
+    #[command(description = "Turn the hint under notifications on or off")]
+    Footer(String),
+    #[command(
+        description = "Add your own notification text to rotate through, e.g. \"/addmessage Stretch!\""
+    )]
+    AddMessage(String),
+    #[command(description = "Remove one of your notification texts, e.g. \"/removemessage 2\"")]
+    RemoveMessage(String),
+    #[command(description = "Add a ready-made reminder, e.g. \"/preset water\"")]
+    Preset(String),
+    #[command(
+        description = "Show or change how your own texts are formatted, e.g. \"/parsemode html\""
+    )]
+    ParseMode(String),
+    #[command(description = "Choose whether \"/start\" sends a notification right away")]
+    FirstSend(String),
+    #[command(
+        description = "Repeat notifications until you press \"Got it\", e.g. \"/ackmode on\""
+    )]
+    AckMode(String),
+    #[command(description = "Acknowledge the last notification in ack mode")]
+    Ack,
+    #[command(
+        description = "Edit the day's notification instead of sending new ones, e.g. \"/singlemessage on\""
+    )]
+    SingleMessage(String),
+    #[command(
+        description = "Get a summary of the day at the end of working hours, e.g. \"/summary on\""
+    )]
+    Summary(String),
+    #[command(description = "Show at which hours you usually press \"/done\"")]
+    Insights,
+    #[cfg(feature = "http")]
+    #[command(description = "Show the token for triggering notifications over HTTP")]
+    Token(String),
+    #[cfg(feature = "http")]
+    #[command(description = "Set the template for JSON posted to the HTTP hook")]
+    Template(String),
+    #[cfg(feature = "http")]
+    #[command(description = "Get a URL services like GitHub or Grafana can post alerts to")]
+    Webhook(String),
+    #[command(description = "Suggest a schedule based on when you press \"/done\"")]
+    Suggest,
+    #[command(description = "Admin: show at which hours all chats press \"/done\"")]
+    AllInsights,
+    #[command(description = "Admin: show a read-only view of another chat")]
+    As(String),
+    #[command(description = "Admin: start notifications in several groups at once")]
+    StartGroups(String),
+    #[command(description = "Admin: list pending delayed jobs")]
+    Jobs,
+    #[command(description = "Admin: export every notification sent as CSV")]
+    Journal,
+    #[command(description = "Admin: turn read-only maintenance mode on or off")]
+    Maintenance(String),
+    #[command(description = "Admin: list, define or remove team profiles")]
+    Profile(String),
+    #[command(description = "Join a team profile, e.g. \"/joinprofile Berlin office\"")]
+    JoinProfile(String),
+    #[command(description = "Move your settings to another chat with a one-time code")]
+    Transfer(String),
+    #[command(description = "Show what's new, or turn release notes on or off")]
+    WhatsNew(String),
+    #[command(
+        description = "List or change where notifications go, e.g. \"/channels slack <webhook URL>\""
+    )]
+    Channels(String),
+    #[command(
+        description = "Post to a URL on every \"/done\", e.g. \"/connect https://example.com/hook token\""
+    )]
+    Connect(String),
+}
+
+impl Command {
+    /// Commands still answered in maintenance mode, they don't change chats.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Command::Help
+                | Command::Status
+                | Command::Next
+                | Command::WhatTimeDoYouThinkItIs
+                | Command::History
+                | Command::MyStats
+                | Command::Insights
+                | Command::Suggest
+                | Command::AllInsights
+                | Command::As(_)
+                | Command::Jobs
+                | Command::Journal
+                | Command::Maintenance(_)
+        )
+    }
+}
+
+pub fn command_names() -> Vec<String> {
+    Command::bot_commands()
+        .into_iter()
+        .map(|command| command.command.trim_start_matches('/').to_string())
+        .collect()
+}
+
+/// Commands described in `locale`, the admins' ones only `with_admin`.
+pub fn commands(locale: Locale, with_admin: bool) -> Vec<BotCommand> {
+    Command::bot_commands()
+        .into_iter()
+        .filter(|command| with_admin || !command.description.starts_with("Admin:"))
+        .map(|command| {
+            let name = command.command.trim_start_matches('/');
+            BotCommand::new(name, tr!(locale, &format!("command-{}", name)))
+        })
+        .collect()
+}
+
+/// Fills the menu Telegram shows by the input field, English for languages
+/// the bot doesn't speak.
+pub async fn register_commands(bot: &Bot) {
+    for locale in Locale::ALL {
+        let request = bot.set_my_commands(commands(locale, false));
+        let request = match locale {
+            Locale::En => request,
+            _ => request.language_code(locale.code()),
+        };
+        if let Err(err) = request.await {
+            log::warn!("Unable to register {} commands: {}", locale.code(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::utils::command::BotCommands;
+
+    use crate::{
+        commands::{commands, Command},
+        i18n::Locale,
+        tr,
+    };
+
+    #[test]
+    fn test_commands() {
+        // The English catalog mirrors the descriptions, other catalogs
+        // translate every command
+        for command in Command::bot_commands() {
+            let name = command.command.trim_start_matches('/');
+            assert_eq!(
+                tr!(Locale::En, &format!("command-{}", name)),
+                command.description,
+                "{}",
+                name
+            );
+            for locale in Locale::ALL {
+                let description = tr!(locale, &format!("command-{}", name));
+                assert!((3..=256).contains(&description.chars().count()), "{}", name);
+            }
+        }
+
+        let menu = commands(Locale::Ru, false);
+        assert!(menu.iter().any(|command| command.command == "help"));
+        assert!(menu.iter().all(|command| command.command != "journal"));
+        assert!(commands(Locale::Ru, true)
+            .iter()
+            .any(|command| command.command == "journal"));
+    }
+}
@@ -0,0 +1,576 @@
+#[cfg(feature = "http")]
+use std::net::SocketAddr;
+use std::{collections::HashMap, env::VarError, io::ErrorKind, path::PathBuf, time::Duration};
+
+use teloxide::{
+    adaptors::throttle::Limits,
+    types::{Message, UserId},
+};
+use toml::{Table, Value};
+#[cfg(feature = "http")]
+use url::Url;
+
+#[cfg(feature = "email")]
+use crate::email::SmtpConfig;
+#[cfg(feature = "mqtt")]
+use crate::mqtt::MqttConfig;
+#[cfg(feature = "http")]
+use crate::webhook;
+use crate::{
+    blackouts::{self, Blackout},
+    clock,
+    dialogues::parse_working_hours,
+    i18n::Locale,
+    jobs,
+    message_text::Markup,
+    offsets_rep::WorkingHours,
+    parsers, store,
+    templates::{self, Rotation},
+};
+
+/// Where settings are read from unless "CONFIG_FILE" points elsewhere.
+const CONFIG_PATH: &str = "config.toml";
+/// Settings of subscribed chats with the SQLite store
+const DB_PATH: &str = "users.sqlite3";
+
+/// Deployment-wide settings read once at startup.
+pub struct Config {
+    /// Token of the bot, from "TELOXIDE_TOKEN".
+    pub token: Option<String>,
+    /// Where the SQLite store keeps settings of subscribed chats.
+    pub db_path: PathBuf,
+    /// Working hours of chats that start the bot, 9 to 18 by default.
+    pub working_hours: WorkingHours,
+    /// Language for chats whose Telegram client doesn't report a supported one.
+    pub default_locale: Locale,
+    /// Telegram users allowed to run admin commands.
+    pub admins: Vec<UserId>,
+    /// Send the first notification right after "/start" unless the user chose otherwise.
+    pub first_send_on_start: bool,
+    /// Delete messages that aren't commands, off for group deployments where
+    /// the bot shares chats with people.
+    pub delete_messages: bool,
+    /// How far the clock may be off from Telegram's before admins are alerted.
+    pub clock_skew_threshold: Duration,
+    /// Where settings of subscribed chats are kept.
+    pub user_store: store::Backend,
+    /// Where dialogues in progress are kept, SQLite ones survive restarts.
+    pub dialogue_store: store::Backend,
+    /// Start in read-only maintenance mode, admins turn it off with "/maintenance off".
+    pub maintenance: bool,
+    /// How many messages the bot sends, requests beyond the limits wait.
+    /// Every send goes through these, notifications and broadcasts alike, by
+    /// default 30 per second overall and one per second per chat.
+    pub throttle: Limits,
+    /// Window over which delayed jobs due at the same moment are spread.
+    pub job_spread: Duration,
+    /// Days nobody gets notifications on, e.g. a company shutdown.
+    pub blackouts: Vec<Blackout>,
+    /// Pool of notification messages, from "NOTIFICATION_MESSAGES_FILE",
+    /// "NOTIFICATION_MESSAGES" or "NOTIFICATION_MESSAGE", the first one set.
+    pub messages: Vec<String>,
+    /// How a message is picked out of the pool for every send.
+    pub message_rotation: Rotation,
+    /// How the messages are written, Telegram's MarkdownV2 or HTML or plain
+    /// text.
+    pub parse_mode: Markup,
+    /// Where anonymous usage pings go, none are sent unless the operator
+    /// opts in by setting it.
+    pub telemetry_url: Option<reqwest::Url>,
+    /// Messages the deployment may send per UTC day, sends are cut down
+    /// when it comes close. Unlimited when unset.
+    pub send_budget: Option<u32>,
+    /// Address of the HTTP API, it's off when unset.
+    #[cfg(feature = "http")]
+    pub http_addr: Option<SocketAddr>,
+    /// Bearer token of "/notify/{chat_id}" and "/broadcast", they are off
+    /// when unset.
+    #[cfg(feature = "http")]
+    pub http_api_token: Option<String>,
+    /// Public URL of the HTTP API's "/telegram" route, updates come there
+    /// instead of long polling when set.
+    #[cfg(feature = "http")]
+    pub webhook_url: Option<Url>,
+    /// Secret Telegram sends along with webhook updates, a random one is
+    /// made on every start when unset.
+    #[cfg(feature = "http")]
+    pub webhook_secret: Option<String>,
+    /// Public URL of the HTTP API "/webhook" hands out hook URLs under,
+    /// where "WEBHOOK_URL" points when unset.
+    #[cfg(feature = "http")]
+    pub public_url: Option<Url>,
+    /// MQTT broker to relay messages from, it's off when unset.
+    #[cfg(feature = "mqtt")]
+    pub mqtt: Option<MqttConfig>,
+    /// Bots run side by side from one process, with the ids their tokens
+    /// start with, empty for the only bot of "TELOXIDE_TOKEN".
+    pub bot_tokens: Vec<(i64, String)>,
+    /// SMTP relay for the email channel, it's off when unset.
+    #[cfg(feature = "email")]
+    pub smtp: Option<SmtpConfig>,
+}
+
+impl Config {
+    pub fn load(source: &Source) -> Config {
+        let user_store = match source.var("USER_STORE") {
+            Ok(name) => store::Backend::from_name(&name).unwrap_or_else(|| {
+                log::warn!("Unsupported USER_STORE {}, using SQLite", name);
+                store::Backend::default()
+            }),
+            Err(_) => store::Backend::default(),
+        };
+        #[cfg(feature = "http")]
+        let webhook_url: Option<Url> = source.var("WEBHOOK_URL").ok().and_then(|url| {
+            url.parse()
+                .map_err(|_| log::warn!("Invalid WEBHOOK_URL {}, polling for updates", url))
+                .ok()
+        });
+
+        Config {
+            token: source.var("TELOXIDE_TOKEN").ok(),
+            db_path: source
+                .var("DB_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from(DB_PATH)),
+            working_hours: match source.var("WORKING_HOURS") {
+                Ok(value) => parse_working_hours(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid WORKING_HOURS {}, using the default", value);
+                    WorkingHours::default()
+                }),
+                Err(_) => WorkingHours::default(),
+            },
+            default_locale: match source.var("DEFAULT_LANGUAGE") {
+                Ok(code) => Locale::from_code(&code).unwrap_or_else(|| {
+                    log::warn!("Unsupported DEFAULT_LANGUAGE {}, using English", code);
+                    Locale::default()
+                }),
+                Err(_) => Locale::default(),
+            },
+            admins: source
+                .var("ADMIN_IDS")
+                .map(|ids| parse_user_ids(&ids))
+                .unwrap_or_default(),
+            first_send_on_start: source
+                .var("FIRST_SEND_ON_START")
+                .map(|value| parse_bool(&value))
+                .unwrap_or(false),
+            delete_messages: source
+                .var("DELETE_MESSAGES")
+                .map(|value| parse_bool(&value))
+                .unwrap_or(true),
+            clock_skew_threshold: match source.var("CLOCK_SKEW_THRESHOLD") {
+                Ok(value) => parsers::parse_duration(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid CLOCK_SKEW_THRESHOLD {}, using the default", value);
+                    clock::DEFAULT_SKEW_THRESHOLD
+                }),
+                Err(_) => clock::DEFAULT_SKEW_THRESHOLD,
+            },
+            user_store,
+            // Follows the chats' store unless set
+            dialogue_store: match source.var("DIALOGUE_STORE") {
+                Ok(name) => store::Backend::from_name(&name).unwrap_or_else(|| {
+                    log::warn!(
+                        "Unsupported DIALOGUE_STORE {}, using {}",
+                        name,
+                        user_store.name()
+                    );
+                    user_store
+                }),
+                Err(_) => user_store,
+            },
+            maintenance: source
+                .var("MAINTENANCE")
+                .map(|value| parse_bool(&value))
+                .unwrap_or(false),
+            throttle: {
+                let defaults = Limits::default();
+                Limits {
+                    messages_per_sec_overall: parse_limit(
+                        source,
+                        "THROTTLE_PER_SECOND",
+                        defaults.messages_per_sec_overall,
+                    ),
+                    messages_per_min_chat: parse_limit(
+                        source,
+                        "THROTTLE_PER_MINUTE_CHAT",
+                        defaults.messages_per_min_chat,
+                    ),
+                    ..defaults
+                }
+            },
+            job_spread: match source.var("JOB_SPREAD") {
+                Ok(value) => parse_spread(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid JOB_SPREAD {}, using the default", value);
+                    jobs::DEFAULT_SPREAD
+                }),
+                Err(_) => jobs::DEFAULT_SPREAD,
+            },
+            blackouts: match source.var("BLACKOUT_DATES") {
+                Ok(value) => blackouts::parse(&value).unwrap_or_else(|| {
+                    log::warn!("Invalid BLACKOUT_DATES {}, ignoring them", value);
+                    vec![]
+                }),
+                Err(_) => vec![],
+            },
+            messages: messages(source),
+            message_rotation: match source.var("MESSAGE_ROTATION") {
+                Ok(name) => Rotation::from_name(&name).unwrap_or_else(|| {
+                    log::warn!("Unsupported MESSAGE_ROTATION {}, picking randomly", name);
+                    Rotation::default()
+                }),
+                Err(_) => Rotation::default(),
+            },
+            parse_mode: match source.var("PARSE_MODE") {
+                Ok(name) => Markup::from_name(&name).unwrap_or_else(|| {
+                    log::warn!("Unsupported PARSE_MODE {}, using plain text", name);
+                    Markup::default()
+                }),
+                Err(_) => Markup::default(),
+            },
+            telemetry_url: source
+                .var("TELEMETRY_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+                .and_then(|url| {
+                    url.trim()
+                        .parse()
+                        .map_err(|_| log::warn!("Invalid TELEMETRY_URL {}, no usage pings", url))
+                        .ok()
+                }),
+            send_budget: source.var("DAILY_SEND_BUDGET").ok().and_then(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|budget| *budget > 0)
+                    .or_else(|| {
+                        log::warn!("Invalid DAILY_SEND_BUDGET {}, sends are unlimited", value);
+                        None
+                    })
+            }),
+            #[cfg(feature = "http")]
+            http_addr: source.var("HTTP_ADDR").ok().and_then(|addr| {
+                addr.parse()
+                    .map_err(|_| log::warn!("Invalid HTTP_ADDR {}, HTTP API is off", addr))
+                    .ok()
+            }),
+            #[cfg(feature = "http")]
+            http_api_token: source
+                .var("HTTP_API_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty()),
+            #[cfg(feature = "http")]
+            public_url: match source.var("PUBLIC_URL") {
+                Ok(url) => url
+                    .parse()
+                    .map_err(|_| log::warn!("Invalid PUBLIC_URL {}", url))
+                    .ok(),
+                Err(_) => webhook_url.as_ref().and_then(|url| url.join("./").ok()),
+            },
+            #[cfg(feature = "http")]
+            webhook_url,
+            #[cfg(feature = "http")]
+            webhook_secret: source.var("WEBHOOK_SECRET").ok().filter(|secret| {
+                let valid = webhook::is_valid_secret(secret);
+                if !valid {
+                    log::warn!("Invalid WEBHOOK_SECRET, using a random one");
+                }
+                valid
+            }),
+            #[cfg(feature = "mqtt")]
+            mqtt: MqttConfig::from_source(source),
+            #[cfg(feature = "email")]
+            smtp: SmtpConfig::from_source(source),
+            bot_tokens: source
+                .var("BOT_TOKENS")
+                .map(|tokens| parse_bot_tokens(&tokens))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_admin(&self, msg: &Message) -> bool {
+        msg.from()
+            .map(|user| self.admins.contains(&user.id))
+            .unwrap_or(false)
+    }
+}
+
+/// Environment variables, with config.toml for the ones that aren't set.
+///
+/// A key of the file is the variable's name in lower case and a table is the
+/// name's prefix, `[webhook] url` stands for "WEBHOOK_URL". `token` stands
+/// for "TELOXIDE_TOKEN".
+#[derive(Default)]
+pub struct Source {
+    file: HashMap<String, String>,
+}
+
+impl Source {
+    /// Reads the file unless there is none, it's an error when it can't be
+    /// read or isn't valid TOML.
+    pub fn load() -> Result<Source, String> {
+        let path = std::env::var("CONFIG_FILE").unwrap_or_else(|_| CONFIG_PATH.to_string());
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Source::default()),
+            Err(err) => return Err(format!("Unable to read {}: {}", path, err)),
+        };
+        let file = parse_file(&contents).map_err(|err| format!("Invalid {}: {}", path, err))?;
+        Ok(Source { file })
+    }
+
+    /// The variable, or the file's setting when it isn't set.
+    pub fn var(&self, name: &str) -> Result<String, VarError> {
+        std::env::var(name).or_else(|err| self.file.get(name).cloned().ok_or(err))
+    }
+}
+
+/// Settings of the file by the variables they stand for.
+fn parse_file(contents: &str) -> Result<HashMap<String, String>, String> {
+    let table: Table = toml::from_str(contents).map_err(|err| err.message().to_string())?;
+    let mut values = HashMap::new();
+    flatten("", table, &mut values)?;
+    Ok(values)
+}
+
+/// Puts the settings of the table into `values`, a nested table adds its
+/// name to the `prefix` of the variables.
+fn flatten(prefix: &str, table: Table, values: &mut HashMap<String, String>) -> Result<(), String> {
+    for (key, value) in table {
+        let name = match (prefix, key.as_str()) {
+            ("", "token") => "TELOXIDE_TOKEN".to_string(),
+            ("", key) => key.to_uppercase(),
+            (prefix, key) => format!("{}_{}", prefix, key.to_uppercase()),
+        }
+        .replace(['.', '-'], "_");
+        match value {
+            Value::Table(table) => flatten(&name, table, values)?,
+            value => {
+                let value = to_var(value).ok_or_else(|| format!("unsupported value of {}", key))?;
+                values.insert(name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The value as a variable would hold it, arrays are joined with commas as
+/// lists in variables are.
+fn to_var(value: Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(value.to_string()),
+        Value::Boolean(value) => Some(value.to_string()),
+        Value::Datetime(value) => Some(value.to_string()),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Array(_) | Value::Table(_) => None,
+                item => to_var(item),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(",")),
+        Value::Table(_) => None,
+    }
+}
+
+/// Messages separated with `---` lines, read from the file when one is set.
+fn messages(source: &Source) -> Vec<String> {
+    let pool = match source.var("NOTIFICATION_MESSAGES_FILE") {
+        Ok(path) => match std::fs::read_to_string(&path) {
+            Ok(contents) => templates::pool(&contents),
+            Err(err) => {
+                log::error!(
+                    "Unable to read NOTIFICATION_MESSAGES_FILE {}: {}",
+                    path,
+                    err
+                );
+                vec![]
+            }
+        },
+        Err(_) => source
+            .var("NOTIFICATION_MESSAGES")
+            .map(|value| templates::pool(&value))
+            .unwrap_or_default(),
+    };
+    if !pool.is_empty() {
+        return pool;
+    }
+
+    match source.var("NOTIFICATION_MESSAGE") {
+        Ok(message) => vec![message],
+        Err(_) => {
+            log::warn!("NOTIFICATION_MESSAGE environment variable not set");
+            vec!["Notify!".to_string()]
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// A positive message count from the `name` variable.
+fn parse_limit(source: &Source, name: &str, default: u32) -> u32 {
+    match source.var(name) {
+        Ok(value) => match value.trim().parse::<u32>() {
+            Ok(limit) if limit > 0 => limit,
+            _ => {
+                log::warn!("Invalid {} {}, using {}", name, value, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// A duration such as "10m", or "0" to run jobs exactly when due.
+fn parse_spread(value: &str) -> Option<Duration> {
+    match value.trim() {
+        "0" => Some(Duration::ZERO),
+        value => parsers::parse_duration(value),
+    }
+}
+
+fn parse_user_ids(ids: &str) -> Vec<UserId> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| match id.parse::<u64>() {
+            Ok(id) => Some(UserId(id)),
+            Err(_) => {
+                log::warn!("Ignoring invalid admin id {}", id);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Tokens separated with commas or whitespace, each with the bot's id
+/// before the colon.
+fn parse_bot_tokens(tokens: &str) -> Vec<(i64, String)> {
+    let mut parsed: Vec<(i64, String)> = Vec::new();
+    for token in tokens.split(|c: char| c == ',' || c.is_whitespace()) {
+        if token.is_empty() {
+            continue;
+        }
+        let id = token
+            .split_once(':')
+            .and_then(|(id, _)| id.parse::<i64>().ok())
+            .filter(|id| *id > 0);
+        match id {
+            Some(id) if parsed.iter().any(|(other, _)| *other == id) => {
+                log::warn!("Ignoring a second token of bot {}", id)
+            }
+            Some(id) => parsed.push((id, token.to_string())),
+            None => log::warn!("Ignoring a token in BOT_TOKENS without a bot id"),
+        }
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use teloxide::types::UserId;
+
+    use crate::config::{parse_bool, parse_bot_tokens, parse_file, parse_spread, parse_user_ids};
+
+    #[test]
+    fn test_parse_bool() {
+        assert!(parse_bool("true"));
+        assert!(parse_bool(" ON "));
+        assert!(parse_bool("1"));
+        assert!(!parse_bool("false"));
+        assert!(!parse_bool(""));
+    }
+
+    #[test]
+    fn test_parse_user_ids() {
+        assert_eq!(
+            parse_user_ids("1, 22,,333"),
+            vec![UserId(1), UserId(22), UserId(333)]
+        );
+        assert_eq!(parse_user_ids("1,admin"), vec![UserId(1)]);
+        assert_eq!(parse_user_ids(""), vec![]);
+    }
+
+    #[test]
+    fn test_parse_spread() {
+        assert_eq!(parse_spread("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_spread(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_spread("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let file = parse_file(
+            r#"
+# Settings of the production bot
+token = "123:abc"
+admin_ids = [1, 22]  # Ops
+first_send_on_start = true
+working_hours = '08:00-17:00'
+
+[notification]
+message = "Time to \"stretch\"\n\u2615"
+messages = """
+Stand up
+---
+Drink water"""
+
+[webhook]
+url = "https://bot.example.com/telegram"
+"#,
+        )
+        .unwrap();
+        let value = |name: &str| file.get(name).map(String::as_str);
+        assert_eq!(value("TELOXIDE_TOKEN"), Some("123:abc"));
+        assert_eq!(value("ADMIN_IDS"), Some("1,22"));
+        assert_eq!(value("FIRST_SEND_ON_START"), Some("true"));
+        assert_eq!(value("WORKING_HOURS"), Some("08:00-17:00"));
+        assert_eq!(
+            value("NOTIFICATION_MESSAGE"),
+            Some("Time to \"stretch\"\n☕")
+        );
+        assert_eq!(
+            value("NOTIFICATION_MESSAGES"),
+            Some("Stand up\n---\nDrink water")
+        );
+        assert_eq!(
+            value("WEBHOOK_URL"),
+            Some("https://bot.example.com/telegram")
+        );
+        assert_eq!(file.len(), 7);
+
+        let multiline = parse_file("admin_ids = [\n  1, # Ops\n  22,\n]").unwrap();
+        assert_eq!(multiline.get("ADMIN_IDS").unwrap(), "1,22");
+        let nested = parse_file("[smtp.tls]\nmode = 'off'").unwrap();
+        assert_eq!(nested.get("SMTP_TLS_MODE").unwrap(), "off");
+
+        assert!(parse_file("token").is_err());
+        assert!(parse_file("\n[webhook\nurl = 1").is_err());
+        assert!(parse_file("url = https://").is_err());
+        assert!(parse_file("token = \"a\" \"b\"").is_err());
+        assert!(parse_file("admin_ids = [1, 2").is_err());
+        assert!(parse_file("admin_ids = [[1], [2]]").is_err());
+    }
+
+    #[test]
+    fn test_parse_bot_tokens() {
+        assert_eq!(
+            parse_bot_tokens("123:abc, 456:def\n789:ghi,123:again,token"),
+            vec![
+                (123, "123:abc".to_string()),
+                (456, "456:def".to_string()),
+                (789, "789:ghi".to_string()),
+            ]
+        );
+        assert_eq!(parse_bot_tokens(" "), vec![]);
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
+
+use crate::parsers;
+
+/// How far ahead "/deadline" accepts dates.
+pub const MAX_AHEAD: Duration = Duration::days(366);
+
+/// Parses "/deadline" arguments like "2024-06-30 submit the report" or
+/// "2024-06-30 12:00 submit the report" into the date, the optional local
+/// time and the title.
+pub fn parse(args: &str) -> Option<(NaiveDate, Option<NaiveTime>, String)> {
+    let (date, rest) = args.trim().split_once(char::is_whitespace)?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let rest = rest.trim();
+    let (time, title) = match rest.split_once(char::is_whitespace) {
+        Some((time, title)) => match parsers::parse_time(time) {
+            Some(time) => (Some(time), title.trim()),
+            None => (None, rest),
+        },
+        None => (None, rest),
+    };
+
+    match title.is_empty() {
+        true => None,
+        false => Some((date, time, title.to_string())),
+    }
+}
+
+/// When the chat is reminded of the `deadline` next after `now`, reminders
+/// come more often as it gets closer: every week until its last week, every
+/// day in the last week, every hour on its last day from `day_start` on and
+/// at the deadline itself. Unset once it's passed.
+pub fn next_reminder(
+    deadline: DateTime<FixedOffset>,
+    day_start: NaiveTime,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let last_day = deadline
+        .date_naive()
+        .and_time(day_start)
+        .and_local_timezone(*deadline.offset())
+        .single()
+        .map(|start| start.with_timezone(&Utc));
+    let deadline = deadline.with_timezone(&Utc);
+    let left = deadline - now;
+    if left <= Duration::zero() {
+        return None;
+    }
+
+    // The last weekly one strictly after now, the deadline itself when none
+    let weeks = (left - Duration::seconds(1)).num_weeks();
+    let weekly = deadline - Duration::weeks(weeks);
+    let daily = (1..=7).map(|days| deadline - Duration::days(days));
+    let hourly = (1..24)
+        .map(|hours| deadline - Duration::hours(hours))
+        .filter(|at| last_day.is_some_and(|start| *at >= start));
+
+    std::iter::once(weekly)
+        .chain(daily)
+        .chain(hourly)
+        .filter(|at| *at > now)
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc};
+
+    use crate::deadlines::{next_reminder, parse};
+
+    #[test]
+    fn test_parse() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        assert_eq!(
+            parse("2024-06-30 submit the report"),
+            Some((date, None, "submit the report".to_string()))
+        );
+        assert_eq!(
+            parse(" 2024-06-30  12:00 submit "),
+            Some((
+                date,
+                NaiveTime::from_hms_opt(12, 0, 0),
+                "submit".to_string()
+            ))
+        );
+        // A time alone is the title
+        assert_eq!(
+            parse("2024-06-30 12:00"),
+            Some((date, None, "12:00".to_string()))
+        );
+        assert_eq!(parse("2024-06-30"), None);
+        assert_eq!(parse("30.06.2024 submit"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_next_reminder() {
+        let offset = FixedOffset::east_opt(3 * 3600).unwrap();
+        // Sunday, June 30th, 18:00 at +03:00
+        let deadline = offset.with_ymd_and_hms(2024, 6, 30, 18, 0, 0).unwrap();
+        let day_start = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let utc = |month, day, hour| Utc.with_ymd_and_hms(2024, month, day, hour, 0, 0).unwrap();
+        let next = |now: DateTime<Utc>| next_reminder(deadline, day_start, now);
+
+        // Weekly three weeks ahead
+        assert_eq!(next(utc(6, 1, 12)), Some(utc(6, 2, 15)));
+        assert_eq!(next(utc(6, 2, 15)), Some(utc(6, 9, 15)));
+        // Daily in the last week
+        assert_eq!(next(utc(6, 23, 15)), Some(utc(6, 24, 15)));
+        assert_eq!(next(utc(6, 28, 16)), Some(utc(6, 29, 15)));
+        // Hourly on the last day from its start
+        assert_eq!(next(utc(6, 29, 15)), Some(utc(6, 30, 6)));
+        assert_eq!(next(utc(6, 30, 6)), Some(utc(6, 30, 7)));
+        assert_eq!(next(utc(6, 30, 14)), Some(utc(6, 30, 15)));
+        assert_eq!(next(utc(6, 30, 15)), None);
+
+        // A deadline before the day starts gets no hourly ones
+        let early = offset.with_ymd_and_hms(2024, 6, 30, 8, 0, 0).unwrap();
+        assert_eq!(
+            next_reminder(early, day_start, utc(6, 29, 5)),
+            Some(utc(6, 30, 5))
+        );
+    }
+}
@@ -0,0 +1,638 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use teloxide::{types::ChatId, ApiError, RequestError};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{offsets_rep::UserSettings, templates::Media};
+
+/// How long a delivered text blocks identical ones to the same chat.
+/// Shorter than any notification interval, so it only merges sends of one slot.
+pub const DEDUP_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// How many sends may be waiting for the rate limiter or a slow mode delay
+/// before low priority ones are dropped.
+pub const CONGESTION_LIMIT: usize = 50;
+
+/// Share of the daily send budget after which notifications are cut down.
+pub const BUDGET_TIGHT: f64 = 0.8;
+
+/// First delay before a failed notification is sent again, it doubles with
+/// every failure in a row up to [`RETRY_MAX`].
+pub const RETRY_FIRST: Duration = Duration::from_secs(60);
+pub const RETRY_MAX: Duration = Duration::from_secs(3600);
+
+/// Why a message didn't reach the chat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Failure {
+    /// The bot was blocked or kicked, or the account is gone, sends to the
+    /// chat will never succeed
+    Gone,
+    /// Telegram asked to wait this long before the next request
+    RetryAfter(Duration),
+    /// Anything else, network trouble mostly
+    Transient,
+    /// Left out before it was sent, the send queue was congested or the
+    /// daily budget ran out. It's not tried again
+    Dropped,
+}
+
+impl Failure {
+    pub fn of(err: &RequestError) -> Failure {
+        match err {
+            // All of these come with 403 Forbidden
+            RequestError::Api(
+                ApiError::BotBlocked
+                | ApiError::BotKicked
+                | ApiError::BotKickedFromSupergroup
+                | ApiError::UserDeactivated
+                | ApiError::CantInitiateConversation
+                | ApiError::CantTalkWithBots,
+            ) => Failure::Gone,
+            RequestError::RetryAfter(retry_after) => Failure::RetryAfter(*retry_after),
+            _ => Failure::Transient,
+        }
+    }
+}
+
+/// Whether the message to edit is gone or can't be edited anymore.
+pub fn is_uneditable(err: &RequestError) -> bool {
+    matches!(
+        err,
+        RequestError::Api(
+            ApiError::MessageToEditNotFound
+                | ApiError::MessageCantBeEdited
+                | ApiError::MessageIdInvalid
+        )
+    )
+}
+
+/// Whether Telegram rejected a message for its markup.
+pub fn is_markup_error(err: &RequestError) -> bool {
+    match err {
+        RequestError::Api(ApiError::CantParseEntities) => true,
+        // Telegram adds where the markup broke, teloxide doesn't know that
+        RequestError::Api(ApiError::Unknown(message)) => {
+            message.starts_with("Bad Request: can't parse entities")
+        }
+        _ => false,
+    }
+}
+
+/// Delay before the next attempt after `failures` transient failures in a row.
+pub fn backoff(failures: u32) -> Duration {
+    RETRY_FIRST
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(RETRY_MAX)
+}
+
+/// A file sent along with a message, the message becomes its caption.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+impl Document {
+    /// CSV with a row per working day of the month `date` falls in, filled
+    /// from the chat's working hours and weekdays.
+    pub fn timesheet(settings: &UserSettings, date: NaiveDate) -> Document {
+        let hours = settings.working_hours;
+        let mut csv = String::from("date,weekday,from,to,hours\n");
+        let first = date.with_day(1).unwrap();
+        for day in first
+            .iter_days()
+            .take_while(|day| day.month() == first.month())
+            .filter(|day| settings.workdays.contains(day.weekday()))
+        {
+            csv.push_str(&format!(
+                "{},{},{:02}:00,{:02}:00,{}\n",
+                day.format("%Y-%m-%d"),
+                day.weekday(),
+                hours.from,
+                hours.to,
+                hours.to - hours.from
+            ));
+        }
+
+        Document {
+            name: format!("timesheet-{}.csv", first.format("%Y-%m")),
+            contents: csv.into_bytes(),
+        }
+    }
+}
+
+/// What a message is sent along with, the message becomes its caption when
+/// it fits.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Attachment {
+    /// A file made by the bot
+    Document(Document),
+    /// A photo, sticker or document configured by a file id or URL
+    Media(Media),
+}
+
+/// Importance of a message set by the user.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Dropped when the send queue is congested or the daily budget runs low
+    Low,
+    #[default]
+    Normal,
+    /// Sent through snoozes and "/away" as usual
+    High,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::Low, Priority::Normal, Priority::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Priority> {
+        Self::ALL
+            .into_iter()
+            .find(|priority| priority.name().eq_ignore_ascii_case(text))
+    }
+}
+
+/// What every send to chats shares: merging of identical texts, spacing for
+/// groups in slow mode, forum topics to post in, the daily send budget and
+/// the count of sends in flight.
+pub struct Pipeline {
+    pub deduplicator: Deduplicator,
+    pub slow_mode: SlowMode,
+    pub topics: Topics,
+    pub budget: Budget,
+    in_flight: AtomicUsize,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline {
+            deduplicator: Deduplicator::new(DEDUP_WINDOW),
+            slow_mode: SlowMode::new(),
+            topics: Topics::new(),
+            budget: Budget::default(),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_budget(budget: Budget) -> Pipeline {
+        Pipeline {
+            budget,
+            ..Pipeline::new()
+        }
+    }
+
+    /// Counts a send as in flight until the guard is dropped.
+    pub fn start_send(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(&self.in_flight)
+    }
+
+    /// Whether so many sends are in flight that low priority ones should go.
+    pub fn is_congested(&self) -> bool {
+        self.in_flight.load(Ordering::Relaxed) >= CONGESTION_LIMIT
+    }
+}
+
+/// A send counted by [`Pipeline::start_send`].
+pub struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Remembers recently delivered notifications so that overlapping reminders
+/// producing the same text for the same slot reach the chat only once.
+pub struct Deduplicator {
+    window: Duration,
+    sent: Mutex<HashMap<(ChatId, u64), Instant>>,
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration) -> Deduplicator {
+        Deduplicator {
+            window,
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the same text was delivered to the chat within the window.
+    pub fn is_duplicate(&self, chat_id: &ChatId, text: &str) -> bool {
+        self.is_duplicate_at(chat_id, text, Instant::now())
+    }
+
+    /// Records a successful delivery.
+    pub fn record(&self, chat_id: &ChatId, text: &str) {
+        self.record_at(chat_id, text, Instant::now())
+    }
+
+    fn is_duplicate_at(&self, chat_id: &ChatId, text: &str, now: Instant) -> bool {
+        let mut sent = self.sent.lock().unwrap();
+        sent.retain(|_, sent_at| now.duration_since(*sent_at) < self.window);
+
+        sent.contains_key(&(*chat_id, hash(text)))
+    }
+
+    fn record_at(&self, chat_id: &ChatId, text: &str, now: Instant) {
+        self.sent
+            .lock()
+            .unwrap()
+            .insert((*chat_id, hash(text)), now);
+    }
+}
+
+/// Spacing of sends to groups that reject messages with "Too Many Requests"
+/// while their slow mode is on.
+///
+/// A chat is tracked after its first rejection: further sends wait for the
+/// delay Telegram asked for, the longest seen so far, after the previous one.
+#[derive(Default)]
+pub struct SlowMode {
+    chats: Mutex<HashMap<ChatId, SlowChat>>,
+}
+
+struct SlowChat {
+    delay: Duration,
+    next: Instant,
+}
+
+impl SlowMode {
+    pub fn new() -> SlowMode {
+        SlowMode::default()
+    }
+
+    /// How long to wait before sending to the chat.
+    pub fn wait(&self, chat_id: &ChatId) -> Option<Duration> {
+        self.wait_at(chat_id, Instant::now())
+    }
+
+    /// Records a successful send, the next one waits for the chat's delay.
+    pub fn sent(&self, chat_id: &ChatId) {
+        self.sent_at(chat_id, Instant::now())
+    }
+
+    /// Records a send rejected until `retry_after` passes.
+    pub fn rejected(&self, chat_id: &ChatId, retry_after: Duration) {
+        self.rejected_at(chat_id, retry_after, Instant::now())
+    }
+
+    fn wait_at(&self, chat_id: &ChatId, now: Instant) -> Option<Duration> {
+        let chats = self.chats.lock().unwrap();
+        chats
+            .get(chat_id)
+            .map(|chat| chat.next.saturating_duration_since(now))
+            .filter(|wait| !wait.is_zero())
+    }
+
+    fn sent_at(&self, chat_id: &ChatId, now: Instant) {
+        if let Some(chat) = self.chats.lock().unwrap().get_mut(chat_id) {
+            chat.next = now + chat.delay;
+        }
+    }
+
+    fn rejected_at(&self, chat_id: &ChatId, retry_after: Duration, now: Instant) {
+        let mut chats = self.chats.lock().unwrap();
+        let chat = chats.entry(*chat_id).or_insert(SlowChat {
+            delay: retry_after,
+            next: now,
+        });
+        chat.delay = chat.delay.max(retry_after);
+        chat.next = now + retry_after;
+    }
+}
+
+/// Forum topics of groups, messages to a group go to the topic "/start" was
+/// sent from instead of the general one.
+#[derive(Default)]
+pub struct Topics {
+    chats: Mutex<HashMap<ChatId, i32>>,
+}
+
+impl Topics {
+    pub fn new() -> Topics {
+        Topics::default()
+    }
+
+    pub fn get(&self, chat_id: &ChatId) -> Option<i32> {
+        self.chats.lock().unwrap().get(chat_id).copied()
+    }
+
+    /// Remembers the chat's topic, `None` posts outside of topics.
+    pub fn set(&self, chat_id: &ChatId, thread_id: Option<i32>) {
+        let mut chats = self.chats.lock().unwrap();
+        match thread_id {
+            Some(thread_id) => chats.insert(*chat_id, thread_id),
+            None => chats.remove(chat_id),
+        };
+    }
+}
+
+/// How much of the daily send budget is spent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BudgetLevel {
+    Normal,
+    /// Past [`BUDGET_TIGHT`]: every other scheduled notification is held
+    /// back and low priority messages are dropped
+    Tight,
+    /// Only high priority messages are sent until the day ends
+    Exhausted,
+}
+
+/// Messages the whole deployment may send per UTC day, set by the operator
+/// with "DAILY_SEND_BUDGET" to protect API quotas shared with other bots on
+/// the host.
+///
+/// It's a soft limit: sends are cut down as it comes closer rather than
+/// refused outright, and `alerts` hears when the level goes up.
+#[derive(Default)]
+pub struct Budget {
+    limit: Option<u32>,
+    spent: Mutex<(NaiveDate, u32)>,
+    alerts: Option<UnboundedSender<(BudgetLevel, u32)>>,
+}
+
+impl Budget {
+    pub fn new(limit: u32, alerts: UnboundedSender<(BudgetLevel, u32)>) -> Budget {
+        Budget {
+            limit: Some(limit),
+            spent: Mutex::new((NaiveDate::MIN, 0)),
+            alerts: Some(alerts),
+        }
+    }
+
+    pub fn level(&self) -> BudgetLevel {
+        self.level_on(Utc::now().date_naive())
+    }
+
+    /// Counts a message sent, alerting when it moves the budget to the next
+    /// level.
+    pub fn record(&self) {
+        self.record_on(Utc::now().date_naive())
+    }
+
+    fn level_on(&self, day: NaiveDate) -> BudgetLevel {
+        let spent = match *self.spent.lock().unwrap() {
+            (counted, spent) if counted == day => spent,
+            _ => 0,
+        };
+        self.level_of(spent)
+    }
+
+    fn level_of(&self, spent: u32) -> BudgetLevel {
+        match self.limit {
+            Some(limit) if spent >= limit => BudgetLevel::Exhausted,
+            Some(limit) if f64::from(spent) >= f64::from(limit) * BUDGET_TIGHT => {
+                BudgetLevel::Tight
+            }
+            _ => BudgetLevel::Normal,
+        }
+    }
+
+    fn record_on(&self, day: NaiveDate) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let spent = {
+            let mut spent = self.spent.lock().unwrap();
+            if spent.0 != day {
+                *spent = (day, 0);
+            }
+            spent.1 += 1;
+            spent.1
+        };
+
+        let level = self.level_of(spent);
+        if level != self.level_of(spent - 1) {
+            log::warn!(
+                "{} of {} messages of the daily budget sent, it's {:?} now",
+                spent,
+                limit,
+                level
+            );
+            if let Some(alerts) = &self.alerts {
+                // Nobody listens only while shutting down
+                let _ = alerts.send((level, spent));
+            }
+        }
+    }
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::{NaiveDate, Weekday};
+    use teloxide::{types::ChatId, ApiError, RequestError};
+
+    use crate::{
+        delivery::{
+            backoff, is_markup_error, Budget, BudgetLevel, Deduplicator, Document, Failure,
+            Pipeline, Priority, SlowMode, Topics, CONGESTION_LIMIT, RETRY_FIRST, RETRY_MAX,
+        },
+        offsets_rep::{UserSettings, Workdays, WorkingHours},
+    };
+
+    #[test]
+    fn test_timesheet() {
+        let settings = UserSettings {
+            working_hours: WorkingHours { from: 10, to: 19 },
+            workdays: Workdays::from_days([Weekday::Mon, Weekday::Fri]),
+            ..Default::default()
+        };
+        let timesheet =
+            Document::timesheet(&settings, NaiveDate::from_ymd_opt(2024, 5, 20).unwrap());
+
+        assert_eq!(timesheet.name, "timesheet-2024-05.csv");
+        let csv = String::from_utf8(timesheet.contents).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "date,weekday,from,to,hours");
+        assert_eq!(rows[1], "2024-05-03,Fri,10:00,19:00,9");
+        assert_eq!(rows[2], "2024-05-06,Mon,10:00,19:00,9");
+        assert_eq!(rows.last(), Some(&"2024-05-31,Fri,10:00,19:00,9"));
+        assert_eq!(rows.len(), 1 + 9);
+    }
+
+    #[test]
+    fn test_failure() {
+        assert_eq!(
+            Failure::of(&RequestError::Api(ApiError::BotBlocked)),
+            Failure::Gone
+        );
+        assert_eq!(
+            Failure::of(&RequestError::Api(ApiError::UserDeactivated)),
+            Failure::Gone
+        );
+        assert_eq!(
+            Failure::of(&RequestError::RetryAfter(Duration::from_secs(7))),
+            Failure::RetryAfter(Duration::from_secs(7))
+        );
+        assert_eq!(
+            Failure::of(&RequestError::Io(std::io::ErrorKind::TimedOut.into())),
+            Failure::Transient
+        );
+        assert_eq!(
+            Failure::of(&RequestError::Api(ApiError::MessageIsTooLong)),
+            Failure::Transient
+        );
+
+        assert!(is_markup_error(&RequestError::Api(ApiError::Unknown(
+            "Bad Request: can't parse entities: Character '.' is reserved".to_string()
+        ))));
+        assert!(!is_markup_error(&RequestError::Api(
+            ApiError::MessageIsTooLong
+        )));
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), RETRY_FIRST);
+        assert_eq!(backoff(2), RETRY_FIRST * 2);
+        assert_eq!(backoff(3), RETRY_FIRST * 4);
+        assert_eq!(backoff(10), RETRY_MAX);
+        assert_eq!(backoff(u32::MAX), RETRY_MAX);
+    }
+
+    #[test]
+    fn test_deduplicator() {
+        let deduplicator = Deduplicator::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(!deduplicator.is_duplicate_at(&ChatId(1), "Drink water", now));
+        deduplicator.record_at(&ChatId(1), "Drink water", now);
+
+        let later = now + Duration::from_secs(30);
+        assert!(deduplicator.is_duplicate_at(&ChatId(1), "Drink water", later));
+        assert!(!deduplicator.is_duplicate_at(&ChatId(2), "Drink water", later));
+        assert!(!deduplicator.is_duplicate_at(&ChatId(1), "Stretch", later));
+
+        let next_slot = now + Duration::from_secs(60);
+        assert!(!deduplicator.is_duplicate_at(&ChatId(1), "Drink water", next_slot));
+    }
+
+    #[test]
+    fn test_congestion() {
+        let pipeline = Pipeline::new();
+
+        let sends: Vec<_> = (0..CONGESTION_LIMIT - 1)
+            .map(|_| pipeline.start_send())
+            .collect();
+        assert!(!pipeline.is_congested());
+        let last = pipeline.start_send();
+        assert!(pipeline.is_congested());
+
+        drop(last);
+        assert!(!pipeline.is_congested());
+        drop(sends);
+        assert!(!pipeline.is_congested());
+    }
+
+    #[test]
+    fn test_budget() {
+        let (alerts, mut alerted) = tokio::sync::mpsc::unbounded_channel();
+        let budget = Budget::new(10, alerts);
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+
+        for _ in 0..7 {
+            budget.record_on(monday);
+        }
+        assert_eq!(budget.level_on(monday), BudgetLevel::Normal);
+        budget.record_on(monday);
+        assert_eq!(budget.level_on(monday), BudgetLevel::Tight);
+        assert_eq!(alerted.try_recv(), Ok((BudgetLevel::Tight, 8)));
+        budget.record_on(monday);
+        assert!(alerted.try_recv().is_err());
+        budget.record_on(monday);
+        assert_eq!(budget.level_on(monday), BudgetLevel::Exhausted);
+        assert_eq!(alerted.try_recv(), Ok((BudgetLevel::Exhausted, 10)));
+
+        // A new day starts from scratch
+        assert_eq!(budget.level_on(tuesday), BudgetLevel::Normal);
+        budget.record_on(tuesday);
+        assert_eq!(budget.level_on(tuesday), BudgetLevel::Normal);
+
+        let unlimited = Budget::default();
+        unlimited.record_on(monday);
+        assert_eq!(unlimited.level_on(monday), BudgetLevel::Normal);
+    }
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(Priority::parse("HIGH"), Some(Priority::High));
+        assert_eq!(Priority::parse("low"), Some(Priority::Low));
+        assert_eq!(Priority::parse("15:30"), None);
+        assert_eq!(Priority::default(), Priority::Normal);
+    }
+
+    #[test]
+    fn test_slow_mode() {
+        let slow_mode = SlowMode::new();
+        let now = Instant::now();
+        let group = ChatId(-100);
+
+        slow_mode.sent_at(&group, now);
+        assert_eq!(slow_mode.wait_at(&group, now), None);
+
+        slow_mode.rejected_at(&group, Duration::from_secs(30), now);
+        assert_eq!(
+            slow_mode.wait_at(&group, now + Duration::from_secs(10)),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(slow_mode.wait_at(&ChatId(-200), now), None);
+
+        let later = now + Duration::from_secs(30);
+        assert_eq!(slow_mode.wait_at(&group, later), None);
+        slow_mode.sent_at(&group, later);
+        assert_eq!(
+            slow_mode.wait_at(&group, later),
+            Some(Duration::from_secs(30))
+        );
+
+        slow_mode.rejected_at(&group, Duration::from_secs(5), later);
+        slow_mode.sent_at(&group, later + Duration::from_secs(5));
+        assert_eq!(
+            slow_mode.wait_at(&group, later + Duration::from_secs(5)),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn test_topics() {
+        let topics = Topics::new();
+        let group = ChatId(-100);
+        assert_eq!(topics.get(&group), None);
+
+        topics.set(&group, Some(4));
+        assert_eq!(topics.get(&group), Some(4));
+        assert_eq!(topics.get(&ChatId(-200)), None);
+
+        topics.set(&group, None);
+        assert_eq!(topics.get(&group), None);
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::{params, types::Type, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use teloxide::{dispatching::dialogue::Storage, types::ChatId};
+
+type StorageFuture<T> = Pin<Box<dyn Future<Output = rusqlite::Result<T>> + Send>>;
+
+/// Dialogue states kept in SQLite as JSON, so that a chat asked for its new
+/// timezone can still answer after a restart.
+pub struct SqliteDialogues<D> {
+    conn: Mutex<Connection>,
+    state: PhantomData<fn() -> D>,
+}
+
+impl<D> SqliteDialogues<D> {
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Arc<SqliteDialogues<D>>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS dialogues (
+                chat_id INTEGER PRIMARY KEY,
+                state TEXT NOT NULL
+            );",
+        )?;
+
+        Ok(Arc::new(SqliteDialogues {
+            conn: Mutex::new(conn),
+            state: PhantomData,
+        }))
+    }
+}
+
+impl<D> Storage<D> for SqliteDialogues<D>
+where
+    D: Serialize + DeserializeOwned + Send + 'static,
+{
+    type Error = rusqlite::Error;
+
+    /// Fails with `QueryReturnedNoRows` when there's no dialogue, as
+    /// `InMemStorage` does.
+    fn remove_dialogue(self: Arc<Self>, chat_id: ChatId) -> StorageFuture<()> {
+        Box::pin(async move {
+            let conn = self.conn.lock().unwrap();
+            match conn.execute("DELETE FROM dialogues WHERE chat_id = ?1", [chat_id.0])? {
+                0 => Err(rusqlite::Error::QueryReturnedNoRows),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn update_dialogue(self: Arc<Self>, chat_id: ChatId, dialogue: D) -> StorageFuture<()> {
+        Box::pin(async move {
+            let state = serde_json::to_string(&dialogue)
+                .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "INSERT OR REPLACE INTO dialogues (chat_id, state) VALUES (?1, ?2)",
+                params![chat_id.0, state],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn get_dialogue(self: Arc<Self>, chat_id: ChatId) -> StorageFuture<Option<D>> {
+        Box::pin(async move {
+            let state: Option<String> = {
+                let conn = self.conn.lock().unwrap();
+                conn.query_row(
+                    "SELECT state FROM dialogues WHERE chat_id = ?1",
+                    [chat_id.0],
+                    |row| row.get(0),
+                )
+                .optional()?
+            };
+            state
+                .map(|state| serde_json::from_str(&state))
+                .transpose()
+                .map_err(|err| {
+                    rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err))
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use teloxide::{dispatching::dialogue::Storage, types::ChatId};
+
+    use crate::dialogue_storage::SqliteDialogues;
+
+    #[tokio::test]
+    async fn test_sqlite_dialogues() {
+        let path = std::env::temp_dir().join("notification_bot_test_dialogues.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        let chat_id = ChatId(42);
+
+        {
+            let dialogues = SqliteDialogues::<Vec<u32>>::open(&path).unwrap();
+            assert_eq!(
+                Arc::clone(&dialogues).get_dialogue(chat_id).await.unwrap(),
+                None
+            );
+            Arc::clone(&dialogues)
+                .update_dialogue(chat_id, vec![1])
+                .await
+                .unwrap();
+            Arc::clone(&dialogues)
+                .update_dialogue(chat_id, vec![2, 3])
+                .await
+                .unwrap();
+        }
+
+        // Survives restarts
+        let dialogues = SqliteDialogues::<Vec<u32>>::open(&path).unwrap();
+        assert_eq!(
+            Arc::clone(&dialogues).get_dialogue(chat_id).await.unwrap(),
+            Some(vec![2, 3])
+        );
+        Arc::clone(&dialogues)
+            .remove_dialogue(chat_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            Arc::clone(&dialogues).get_dialogue(chat_id).await.unwrap(),
+            None
+        );
+        assert!(Arc::clone(&dialogues)
+            .remove_dialogue(chat_id)
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,545 @@
+use std::sync::Arc;
+
+use async_mutex::Mutex;
+use chrono::{Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    dispatching::dialogue::ErasedStorage,
+    prelude::*,
+    types::{KeyboardButton, KeyboardMarkup},
+};
+
+use crate::{
+    bot::Bot,
+    config::Config,
+    handlers::{
+        answer, detect_locale, format_hour, format_local, reply_locale,
+        subscription::start_notifications, HandlerResult,
+    },
+    i18n::Locale,
+    jobs::JobQueue,
+    keyboards, metrics,
+    notify_controller::{upcoming_notifications, NotificationSender},
+    offsets_rep::{UserSettings, Workdays, WorkingHours},
+    parsers,
+    previews::{self, PreviewButton, SettingsChange},
+    store::UserStore,
+    tr,
+};
+
+/// Kept in memory or in SQLite as "DIALOGUE_STORE" says.
+pub type MyDialogue = Dialogue<State, ErasedStorage<State>>;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum State {
+    #[default]
+    RemoveMessages,
+    RecieveNewTimezoneOffset,
+    RecieveWorkingHours,
+    /// "/start" of a new chat asks for its timezone, working hours and
+    /// workdays in turn before notifications start
+    OnboardingTimezone,
+    OnboardingWorkingHours(Onboarding),
+    OnboardingWorkdays(Onboarding),
+}
+
+/// What a new chat answered so far, the defaults stay for skipped questions.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Onboarding {
+    /// Seconds east of UTC
+    pub offset: Option<i32>,
+    /// IANA timezone name, the offset follows it
+    pub timezone: Option<String>,
+    pub working_hours: Option<WorkingHours>,
+    pub workdays: Option<Workdays>,
+}
+
+impl Onboarding {
+    pub fn apply(&self, settings: &mut UserSettings) {
+        match (&self.timezone, self.offset) {
+            (Some(timezone), _) => {
+                if let Ok(tz) = timezone.parse() {
+                    SettingsChange::Timezone(tz).apply(settings);
+                }
+            }
+            (None, Some(offset)) => SettingsChange::Offset(offset).apply(settings),
+            (None, None) => {}
+        }
+        if let Some(hours) = self.working_hours {
+            settings.working_hours = hours;
+        }
+        if let Some(workdays) = self.workdays {
+            settings.workdays = workdays;
+        }
+    }
+}
+
+/// Whether `msg` is a press of the button skipping an onboarding question.
+fn is_skip(msg: &Message, locale: Locale) -> bool {
+    msg.text().is_some_and(|text| {
+        text.trim()
+            .eq_ignore_ascii_case(&tr!(locale, "button-skip"))
+    })
+}
+
+/// Common answers to an onboarding question along with the skip button.
+fn onboarding_choices(choices: &[&str], locale: Locale) -> KeyboardMarkup {
+    keyboards::choices(choices, choices.len())
+        .append_row(vec![KeyboardButton::new(tr!(locale, "button-skip"))])
+}
+
+/// Starts onboarding a chat that sent "/start" for the first time.
+pub async fn start_onboarding(
+    bot: &Bot,
+    msg: &Message,
+    dialogue: &MyDialogue,
+    locale: Locale,
+) -> HandlerResult {
+    dialogue.update(State::OnboardingTimezone).await?;
+    answer(bot, msg, tr!(locale, "onboarding-timezone"))
+        .reply_markup(
+            keyboards::timezones(locale, msg.chat.is_private())
+                .append_row(vec![KeyboardButton::new(tr!(locale, "button-skip"))]),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_onboarding_timezone(
+    bot: Bot,
+    msg: Message,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = detect_locale(&msg, &config);
+
+    let onboarding = match is_skip(&msg, locale) {
+        true => Onboarding::default(),
+        false => match timezone_change(&msg) {
+            Some(SettingsChange::Timezone(tz)) => Onboarding {
+                timezone: Some(tz.name().to_string()),
+                ..Onboarding::default()
+            },
+            Some(SettingsChange::Offset(offset)) => Onboarding {
+                offset: Some(offset),
+                ..Onboarding::default()
+            },
+            _ => {
+                answer(&bot, &msg, tr!(locale, "timezone-invalid")).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    dialogue
+        .update(State::OnboardingWorkingHours(onboarding))
+        .await?;
+    answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "onboarding-working-hours",
+            from = format_hour(config.working_hours.from),
+            to = format_hour(config.working_hours.to)
+        ),
+    )
+    .reply_markup(onboarding_choices(
+        &["08:00-17:00", "09:00-18:00", "10:00-19:00"],
+        locale,
+    ))
+    .await?;
+
+    Ok(())
+}
+
+pub async fn handle_onboarding_working_hours(
+    bot: Bot,
+    msg: Message,
+    dialogue: MyDialogue,
+    onboarding: Onboarding,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = detect_locale(&msg, &config);
+
+    let working_hours = match is_skip(&msg, locale) {
+        true => None,
+        false => match msg.text().and_then(parse_working_hours) {
+            Some(hours) => Some(hours),
+            None => {
+                answer(&bot, &msg, tr!(locale, "set-time-invalid")).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    dialogue
+        .update(State::OnboardingWorkdays(Onboarding {
+            working_hours,
+            ..onboarding
+        }))
+        .await?;
+    answer(&bot, &msg, tr!(locale, "onboarding-workdays"))
+        .reply_markup(onboarding_choices(
+            &["mon-fri", "mon-sat", "mon-sun"],
+            locale,
+        ))
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_onboarding_workdays(
+    bot: Bot,
+    msg: Message,
+    dialogue: MyDialogue,
+    onboarding: Onboarding,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = detect_locale(&msg, &config);
+
+    let workdays = match is_skip(&msg, locale) {
+        true => None,
+        false => match msg.text().and_then(parsers::parse_weekdays) {
+            Some(days) => Some(Workdays::from_days(days)),
+            None => {
+                answer(&bot, &msg, tr!(locale, "onboarding-workdays-invalid")).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    dialogue.exit().await?;
+    let onboarding = Onboarding {
+        workdays,
+        ..onboarding
+    };
+    start_notifications(
+        &bot,
+        &msg,
+        &*store,
+        &notify_controller_mutex,
+        &jobs_mutex,
+        &config,
+        Some(&onboarding),
+    )
+    .await
+}
+
+pub async fn handle_change_timezone_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    match store.get(&msg.chat.id).await {
+        Some(settings) => {
+            dialogue.update(State::RecieveNewTimezoneOffset).await?;
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    settings.locale,
+                    "timezone-prompt",
+                    timezone = settings.timezone_label()
+                ),
+            )
+            .reply_markup(keyboards::timezones(settings.locale, msg.chat.is_private()))
+            .await?;
+        }
+        None => {
+            answer(
+                &bot,
+                &msg,
+                tr!(detect_locale(&msg, &config), "timezone-disabled"),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn handle_message(bot: Bot, msg: Message) -> HandlerResult {
+    bot.delete_message(msg.chat.id, msg.id).await?;
+    Ok(())
+}
+
+/// A fixed offset, or a timezone name whose offset follows daylight saving
+/// time, typed or found at a shared location.
+fn timezone_change(msg: &Message) -> Option<SettingsChange> {
+    let text = msg.text().unwrap_or_default();
+    match (parsers::parse_timezone(text), msg.location()) {
+        (Some(offset), _) => Some(SettingsChange::Offset(offset.local_minus_utc())),
+        (None, Some(location)) => parsers::timezone_at(location.latitude, location.longitude)
+            .map(SettingsChange::Timezone),
+        (None, None) => parsers::parse_timezone_name(text).map(SettingsChange::Timezone),
+    }
+}
+
+pub async fn handle_new_timezone(
+    bot: Bot,
+    msg: Message,
+    dialogue: MyDialogue,
+    store: Arc<dyn UserStore>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let Some(change) = timezone_change(&msg) else {
+        answer(&bot, &msg, tr!(locale, "timezone-invalid")).await?;
+        return Ok(());
+    };
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        return Ok(());
+    };
+    send_preview(&bot, msg.chat.id, &settings, change, &config).await?;
+    dialogue.exit().await?;
+
+    Ok(())
+}
+
+pub async fn handle_set_time_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    match store.get(&msg.chat.id).await {
+        Some(settings) => {
+            dialogue.update(State::RecieveWorkingHours).await?;
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    settings.locale,
+                    "set-time-prompt",
+                    from = format_hour(settings.working_hours.from),
+                    to = format_hour(settings.working_hours.to)
+                ),
+            )
+            .await?;
+        }
+        None => {
+            answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Whole-hour window like "08:00-20:00".
+pub fn parse_working_hours(text: &str) -> Option<WorkingHours> {
+    let (from, to) = parsers::parse_time_window(text)?;
+    if from.minute() != 0 || to.minute() != 0 {
+        return None;
+    }
+
+    Some(WorkingHours {
+        from: from.hour(),
+        to: to.hour(),
+    })
+}
+
+pub async fn handle_new_working_hours(
+    bot: Bot,
+    msg: Message,
+    dialogue: MyDialogue,
+    store: Arc<dyn UserStore>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let Some(hours) = msg.text().and_then(parse_working_hours) else {
+        answer(&bot, &msg, tr!(locale, "set-time-invalid")).await?;
+        return Ok(());
+    };
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        return Ok(());
+    };
+    send_preview(
+        &bot,
+        msg.chat.id,
+        &settings,
+        SettingsChange::WorkingHours(hours),
+        &config,
+    )
+    .await?;
+    dialogue.exit().await?;
+
+    Ok(())
+}
+
+/// How many upcoming notifications a preview lists.
+pub const PREVIEW_COUNT: usize = 3;
+
+/// Shows when the next notifications would come after `change`, under
+/// buttons confirming or dropping it. Nothing is stored until confirmed.
+async fn send_preview(
+    bot: &Bot,
+    chat_id: ChatId,
+    settings: &UserSettings,
+    change: SettingsChange,
+    config: &Config,
+) -> HandlerResult {
+    let locale = settings.locale;
+    let preview = change.preview(settings);
+
+    let summary = match &change {
+        SettingsChange::WorkingHours(hours) => tr!(
+            locale,
+            "preview-working-hours",
+            from = format_hour(hours.from),
+            to = format_hour(hours.to)
+        ),
+        _ => tr!(
+            locale,
+            "preview-timezone",
+            timezone = preview.timezone_label()
+        ),
+    };
+    let dates: Vec<String> =
+        upcoming_notifications(&preview, &config.blackouts, Utc::now(), PREVIEW_COUNT)
+            .into_iter()
+            .map(|date| format_local(date, &preview))
+            .collect();
+    let upcoming = match dates.is_empty() {
+        true => tr!(locale, "preview-none"),
+        false => tr!(locale, "preview-next", dates = dates.join("\n")),
+    };
+
+    bot.send_message(chat_id, format!("{}\n\n{}", summary, upcoming))
+        .reply_markup(previews::keyboard(locale, &change))
+        .await?;
+    Ok(())
+}
+
+/// Applies a confirmed timezone or working hours change, returns the reply.
+pub async fn apply_change(
+    chat_id: &ChatId,
+    change: &SettingsChange,
+    locale: Locale,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+) -> String {
+    let mut controller = metrics::lock(notify_controller_mutex, "notify_controller").await;
+
+    if let Err(err) = store
+        .update(chat_id, |settings| change.apply(settings))
+        .await
+    {
+        log::error!("Failed settings update {}: {}", chat_id, err);
+        return tr!(locale, "error");
+    }
+    let Some(settings) = store.get(chat_id).await else {
+        return tr!(locale, "error");
+    };
+
+    match change {
+        SettingsChange::WorkingHours(hours) => {
+            controller.restart(chat_id, &settings);
+            tr!(
+                locale,
+                "set-time-changed",
+                from = format_hour(hours.from),
+                to = format_hour(hours.to)
+            )
+        }
+        _ => {
+            controller.stop(chat_id);
+            controller.start(chat_id, &settings, false);
+            tr!(
+                locale,
+                "timezone-changed",
+                timezone = settings.timezone_label()
+            )
+        }
+    }
+}
+
+pub async fn handle_preview_button(
+    bot: Bot,
+    query: CallbackQuery,
+    button: PreviewButton,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+) -> HandlerResult {
+    bot.answer_callback_query(query.id).await?;
+
+    let Some(msg) = query.message else {
+        return Ok(());
+    };
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        return Ok(());
+    };
+
+    let reply = match button {
+        PreviewButton::Confirm(change) => {
+            log::info!("{} confirmed {:?}", msg.chat.id, change);
+            apply_change(
+                &msg.chat.id,
+                &change,
+                settings.locale,
+                &*store,
+                &notify_controller_mutex,
+            )
+            .await
+        }
+        PreviewButton::Cancel => tr!(settings.locale, "preview-cancelled"),
+    };
+    bot.edit_message_text(msg.chat.id, msg.id, reply).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use crate::{
+        dialogues::{parse_working_hours, Onboarding},
+        offsets_rep::{UserSettings, Workdays, WorkingHours},
+    };
+
+    #[test]
+    fn test_onboarding_apply() {
+        let mut settings = UserSettings::default();
+        Onboarding::default().apply(&mut settings);
+        assert_eq!(settings.offset, UserSettings::default().offset);
+        assert_eq!(settings.timezone, None);
+
+        let workdays = Workdays::from_days([Weekday::Mon, Weekday::Tue]);
+        Onboarding {
+            offset: Some(3600),
+            timezone: Some("Asia/Kolkata".to_string()),
+            working_hours: Some(WorkingHours { from: 8, to: 17 }),
+            workdays: Some(workdays),
+        }
+        .apply(&mut settings);
+        assert_eq!(settings.timezone.as_deref(), Some("Asia/Kolkata"));
+        assert_eq!(settings.offset, 19800);
+        assert_eq!(settings.working_hours, WorkingHours { from: 8, to: 17 });
+        assert_eq!(settings.workdays, workdays);
+    }
+
+    #[test]
+    fn test_parse_working_hours() {
+        assert_eq!(
+            parse_working_hours("08:00-20:00"),
+            Some(WorkingHours { from: 8, to: 20 })
+        );
+        assert_eq!(
+            parse_working_hours(" 9:00 – 18:00 "),
+            Some(WorkingHours { from: 9, to: 18 })
+        );
+        assert_eq!(parse_working_hours("08:30-20:00"), None);
+        assert_eq!(parse_working_hours("20:00-08:00"), None);
+        assert_eq!(parse_working_hours("eight"), None);
+    }
+}
@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{channels::Channel, config::Source};
+
+/// SMTP relay notifications are mailed through.
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub credentials: Option<Credentials>,
+    pub from: Mailbox,
+}
+
+impl SmtpConfig {
+    /// Reads `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD` and
+    /// `SMTP_FROM`, email is off without a host and a sender.
+    pub fn from_source(source: &Source) -> Option<SmtpConfig> {
+        let host = source.var("SMTP_HOST").ok()?;
+        let from = match source.var("SMTP_FROM").map(|from| from.parse()) {
+            Ok(Ok(from)) => from,
+            Ok(Err(err)) => {
+                log::warn!("Invalid SMTP_FROM, email is off: {}", err);
+                return None;
+            }
+            Err(_) => {
+                log::warn!("SMTP_HOST is set without SMTP_FROM, email is off");
+                return None;
+            }
+        };
+
+        Some(SmtpConfig {
+            host,
+            port: source.var("SMTP_PORT").ok().and_then(|port| {
+                port.parse()
+                    .map_err(|_| log::warn!("Invalid SMTP_PORT {}, using 465", port))
+                    .ok()
+            }),
+            credentials: match (source.var("SMTP_USERNAME"), source.var("SMTP_PASSWORD")) {
+                (Ok(username), Ok(password)) => Some(Credentials::new(username, password)),
+                _ => None,
+            },
+            from,
+        })
+    }
+}
+
+/// Mails notifications over implicit TLS.
+pub struct Email {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Email {
+    pub fn new(config: SmtpConfig) -> Result<Email, lettre::transport::smtp::Error> {
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?;
+        if let Some(port) = config.port {
+            transport = transport.port(port);
+        }
+        if let Some(credentials) = config.credentials {
+            transport = transport.credentials(credentials);
+        }
+        Ok(Email {
+            transport: transport.build(),
+            from: config.from,
+        })
+    }
+}
+
+#[async_trait]
+impl Channel for Email {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn parse_address(&self, address: &str) -> Option<String> {
+        address
+            .trim()
+            .parse::<Mailbox>()
+            .ok()
+            .map(|mailbox| mailbox.email.to_string())
+    }
+
+    async fn send(&self, address: &str, text: &str) -> Result<(), String> {
+        let to = address.parse::<Mailbox>().map_err(|err| err.to_string())?;
+        let subject = text.lines().next().unwrap_or_default();
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .body(text.to_string())
+            .map_err(|err| err.to_string())?;
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use chrono::{FixedOffset, NaiveTime, Weekday};
+
+use crate::{i18n::Locale, insights::HourHistogram};
+
+/// Length of the longest bar in "hour_chart".
+const CHART_WIDTH: u32 = 10;
+
+/// Renders an offset as "UTC+05:00" ("UTC" for a zero offset).
+pub fn offset(offset: &FixedOffset) -> String {
+    let secs = offset.local_minus_utc();
+    if secs == 0 {
+        return "UTC".to_string();
+    }
+
+    let sign = if secs < 0 { '-' } else { '+' };
+    let secs = secs.abs();
+    format!("UTC{}{:0>2}:{:0>2}", sign, secs / 3600, secs % 3600 / 60)
+}
+
+/// Renders a duration with minute precision, e.g. "5 h 30 min" or "5 ч 30 мин".
+///
+/// Durations shorter than a minute are rendered in seconds.
+pub fn duration(duration: Duration, locale: Locale) -> String {
+    let (hours_unit, minutes_unit, seconds_unit) = match locale {
+        Locale::En => ("h", "min", "s"),
+        Locale::Ru => ("ч", "мин", "с"),
+    };
+
+    let secs = duration.as_secs();
+    if secs < 60 {
+        return format!("{} {}", secs, seconds_unit);
+    }
+
+    let (hours, minutes) = (secs / 3600, secs % 3600 / 60);
+    let mut parts = vec![];
+    if hours > 0 {
+        parts.push(format!("{} {}", hours, hours_unit));
+    }
+    if minutes > 0 {
+        parts.push(format!("{} {}", minutes, minutes_unit));
+    }
+    parts.join(" ")
+}
+
+/// Renders a time of day as "09:00".
+pub fn time(time: &NaiveTime) -> String {
+    time.format("%H:%M").to_string()
+}
+
+/// Renders one "09:00 ▇▇▇▇ 4" line per hour having acknowledgements.
+pub fn hour_chart(histogram: &HourHistogram) -> String {
+    let max = histogram.hours().map(|(_, count)| count).max().unwrap_or(0);
+
+    histogram
+        .hours()
+        .map(|(hour, count)| {
+            let width = (count * CHART_WIDTH / max).max(1) as usize;
+            format!("{:0>2}:00 {} {}", hour, "▇".repeat(width), count)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn weekday(weekday: Weekday, locale: Locale) -> &'static str {
+    match (locale, weekday) {
+        (Locale::En, Weekday::Mon) => "Monday",
+        (Locale::En, Weekday::Tue) => "Tuesday",
+        (Locale::En, Weekday::Wed) => "Wednesday",
+        (Locale::En, Weekday::Thu) => "Thursday",
+        (Locale::En, Weekday::Fri) => "Friday",
+        (Locale::En, Weekday::Sat) => "Saturday",
+        (Locale::En, Weekday::Sun) => "Sunday",
+        (Locale::Ru, Weekday::Mon) => "понедельник",
+        (Locale::Ru, Weekday::Tue) => "вторник",
+        (Locale::Ru, Weekday::Wed) => "среда",
+        (Locale::Ru, Weekday::Thu) => "четверг",
+        (Locale::Ru, Weekday::Fri) => "пятница",
+        (Locale::Ru, Weekday::Sat) => "суббота",
+        (Locale::Ru, Weekday::Sun) => "воскресенье",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{FixedOffset, NaiveTime, Weekday};
+
+    use crate::{formatting, i18n::Locale, insights::HourHistogram};
+
+    #[test]
+    fn test_offset() {
+        assert_eq!(
+            formatting::offset(&FixedOffset::east_opt(0).unwrap()),
+            "UTC"
+        );
+        assert_eq!(
+            formatting::offset(&FixedOffset::east_opt(5 * 3600).unwrap()),
+            "UTC+05:00"
+        );
+        assert_eq!(
+            formatting::offset(&FixedOffset::west_opt(3 * 3600 + 30 * 60).unwrap()),
+            "UTC-03:30"
+        );
+    }
+
+    #[test]
+    fn test_duration() {
+        let duration = |secs| Duration::from_secs(secs);
+
+        assert_eq!(formatting::duration(duration(0), Locale::En), "0 s");
+        assert_eq!(formatting::duration(duration(59), Locale::Ru), "59 с");
+        assert_eq!(formatting::duration(duration(60), Locale::En), "1 min");
+        assert_eq!(formatting::duration(duration(3600), Locale::En), "1 h");
+        assert_eq!(
+            formatting::duration(duration(5 * 3600 + 30 * 60 + 15), Locale::En),
+            "5 h 30 min"
+        );
+        assert_eq!(
+            formatting::duration(duration(5 * 3600 + 30 * 60), Locale::Ru),
+            "5 ч 30 мин"
+        );
+    }
+
+    #[test]
+    fn test_time_and_weekday() {
+        assert_eq!(
+            formatting::time(&NaiveTime::from_hms_opt(9, 5, 0).unwrap()),
+            "09:05"
+        );
+        assert_eq!(formatting::weekday(Weekday::Mon, Locale::En), "Monday");
+        assert_eq!(formatting::weekday(Weekday::Mon, Locale::Ru), "понедельник");
+    }
+
+    #[test]
+    fn test_hour_chart() {
+        let mut histogram = HourHistogram::default();
+        assert_eq!(formatting::hour_chart(&histogram), "");
+
+        for _ in 0..20 {
+            histogram.record(10);
+        }
+        histogram.record(9);
+        assert_eq!(
+            formatting::hour_chart(&histogram),
+            "09:00 ▇ 1\n10:00 ▇▇▇▇▇▇▇▇▇▇ 20"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+pub mod admin;
+#[cfg(feature = "http")]
+pub mod api;
+pub mod reminders;
+pub mod settings;
+pub mod status;
+pub mod subscription;
+
+use std::sync::Arc;
+
+use async_mutex::Mutex;
+use chrono::{DateTime, Datelike, Days, NaiveTime, Timelike, Utc};
+use teloxide::{
+    prelude::*,
+    types::{MediaKind, MessageCommon, MessageKind},
+};
+
+use crate::{
+    blackouts::Blackout,
+    bot::Bot,
+    commands::commands,
+    config::Config,
+    dialogues::MyDialogue,
+    formatting,
+    i18n::Locale,
+    insights::Suggestion,
+    jobs::{JobKind, JobQueue},
+    metrics,
+    notify_controller::{upcoming_notifications, NotificationSender},
+    offsets_rep::{UserSettings, WorkingHours},
+    store::UserStore,
+    tr,
+};
+
+pub type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// Language of the sender's Telegram client, or the configured default.
+pub fn detect_locale(msg: &Message, config: &Config) -> Locale {
+    msg.from()
+        .and_then(|user| user.language_code.as_deref())
+        .and_then(Locale::from_code)
+        .unwrap_or(config.default_locale)
+}
+
+/// Language of replies: the chosen one for known chats, detected otherwise.
+pub async fn reply_locale(msg: &Message, store: &dyn UserStore, config: &Config) -> Locale {
+    store
+        .get(&msg.chat.id)
+        .await
+        .map(|settings| settings.locale)
+        .unwrap_or_else(|| detect_locale(msg, config))
+}
+
+pub fn with_text(mut msg: Message, text: String) -> Message {
+    if let MessageKind::Common(MessageCommon {
+        media_kind: MediaKind::Text(media),
+        ..
+    }) = &mut msg.kind
+    {
+        media.text = text;
+    }
+    msg
+}
+
+/// Renders a whole local hour as "09:00", 24 stands for the end of the day.
+pub fn format_hour(hour: u32) -> String {
+    match NaiveTime::from_hms_opt(hour, 0, 0) {
+        Some(time) => formatting::time(&time),
+        None => "24:00".to_string(),
+    }
+}
+
+/// Forum topic the message was sent in, replies to other messages in groups
+/// without topics carry a thread id too.
+pub fn topic(msg: &Message) -> Option<i32> {
+    match &msg.kind {
+        MessageKind::Common(common) if common.is_topic_message => msg.thread_id,
+        _ => None,
+    }
+}
+
+/// Answers `msg` in its chat, within its forum topic if it has one.
+pub fn answer<T: Into<String>>(
+    bot: &Bot,
+    msg: &Message,
+    text: T,
+) -> <Bot as Requester>::SendMessage {
+    let request = bot.send_message(msg.chat.id, text);
+    match topic(msg) {
+        Some(thread_id) => request.message_thread_id(thread_id),
+        None => request,
+    }
+}
+
+/// Whether the sender may start and stop notifications of the chat: anyone
+/// in private chats, admins only in groups.
+pub async fn is_chat_admin(bot: &Bot, msg: &Message) -> bool {
+    if msg.chat.is_private() {
+        return true;
+    }
+    // Anonymous admins write on behalf of the group itself
+    if msg.sender_chat().map(|chat| chat.id) == Some(msg.chat.id) {
+        return true;
+    }
+    let Some(user) = msg.from() else {
+        return false;
+    };
+    is_privileged(bot, msg.chat.id, user.id).await
+}
+
+/// Whether the user is an admin or the owner of the group.
+pub async fn is_privileged(bot: &Bot, chat_id: ChatId, user_id: UserId) -> bool {
+    match bot.get_chat_member(chat_id, user_id).await {
+        Ok(member) => member.is_privileged(),
+        Err(err) => {
+            log::warn!("Unable to get member {} of {}: {}", user_id, chat_id, err);
+            false
+        }
+    }
+}
+
+pub fn format_local(moment: DateTime<Utc>, settings: &UserSettings) -> String {
+    let zone = settings.zone();
+    let (moment, now) = (zone.local(moment), zone.local(Utc::now()));
+    let time = formatting::time(&moment.time());
+    match moment.date_naive() == now.date_naive() {
+        true => time,
+        false => format!(
+            "{} {}",
+            formatting::weekday(moment.weekday(), settings.locale),
+            time
+        ),
+    }
+}
+
+/// When notifications paused with "/done" at `now` resume: the chat's first
+/// notification from its local midnight on, leaving out its holidays and the
+/// `blackouts`. Done before the day's working hours, e.g. just after
+/// midnight, it's the same day's. A cron schedule ignores working hours, it
+/// resumes at the next midnight.
+pub fn wake_up_tomorrow(
+    settings: &UserSettings,
+    blackouts: &[Blackout],
+    now: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let zone = settings.zone();
+    let local = zone.local(now);
+    let day = match settings.schedule().is_none() && local.hour() < settings.working_hours.from {
+        true => local.date_naive(),
+        false => local.date_naive() + Days::new(1),
+    };
+    let midnight = zone.from_local(day.and_time(NaiveTime::MIN));
+    if settings.schedule().is_some() {
+        return midnight;
+    }
+
+    upcoming_notifications(
+        settings,
+        blackouts,
+        midnight - chrono::Duration::seconds(1),
+        1,
+    )
+    .first()
+    .copied()
+    .unwrap_or(midnight)
+}
+
+/// Drops reminders, timers, reports, summaries, deadlines and pomodoros the
+/// chat set with "/remind", "/weekly", "/monthly", "/timer", "/report",
+/// "/summary", "/deadline" and "/pomodoro".
+pub async fn cancel_reminders(jobs_mutex: &Mutex<JobQueue>, chat_id: &ChatId) {
+    let mut jobs = metrics::lock(jobs_mutex, "jobs").await;
+    for job in jobs.for_chat(chat_id) {
+        if !job.kind.is_reminder() {
+            continue;
+        }
+        if let Err(err) = jobs.remove(job.id) {
+            log::error!(
+                "Unable to cancel reminder {} of {}: {}",
+                job.id,
+                chat_id,
+                err
+            );
+        }
+    }
+}
+
+/// Drops pending wake ups and snoozes of the chat.
+pub async fn cancel_wake_up(jobs_mutex: &Mutex<JobQueue>, chat_id: &ChatId) {
+    let mut jobs = metrics::lock(jobs_mutex, "jobs").await;
+    for kind in JobKind::RESUMING {
+        if let Err(err) = jobs.cancel(chat_id, kind) {
+            log::error!("Unable to cancel wake up of {}: {}", chat_id, err);
+        }
+    }
+}
+
+pub async fn handle_help_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let locale = match store.get(&msg.chat.id).await {
+        Some(settings) => settings.locale,
+        None => detect_locale(&msg, &config),
+    };
+    let commands = commands(locale, config.is_admin(&msg))
+        .into_iter()
+        .map(|command| format!("/{} — {}", command.command, command.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+    answer(&bot, &msg, tr!(locale, "help", commands = commands)).await?;
+
+    Ok(())
+}
+
+/// Restarts the chat's notifications with its stored settings.
+pub async fn restart_with_stored(
+    chat_id: &ChatId,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+) {
+    if let Some(settings) = store.get(chat_id).await {
+        metrics::lock(notify_controller_mutex, "notify_controller")
+            .await
+            .restart(chat_id, &settings);
+    }
+}
+
+pub async fn handle_maintenance(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+    answer(&bot, &msg, tr!(locale, "maintenance-refused")).await?;
+    Ok(())
+}
+
+pub async fn handle_maintenance_callback(
+    bot: Bot,
+    query: CallbackQuery,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = query
+        .from
+        .language_code
+        .as_deref()
+        .and_then(Locale::from_code)
+        .unwrap_or(config.default_locale);
+    bot.answer_callback_query(query.id)
+        .text(tr!(locale, "maintenance-refused"))
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_callback_query(
+    bot: Bot,
+    q: CallbackQuery,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+) -> HandlerResult {
+    bot.answer_callback_query(q.id).await?;
+
+    let (Some(msg), Some(suggestion)) = (q.message, q.data.as_deref().and_then(Suggestion::decode))
+    else {
+        log::debug!("Ignored callback query with data {:?}", q.data);
+        return Ok(());
+    };
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        return Ok(());
+    };
+
+    let Suggestion::Window { from, to } = suggestion;
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.working_hours = WorkingHours { from, to }
+        })
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            bot.edit_message_text(
+                msg.chat.id,
+                msg.id,
+                tr!(
+                    settings.locale,
+                    "suggest-applied",
+                    from = format_hour(from),
+                    to = format_hour(to)
+                ),
+            )
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Failed working hours update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+
+    use crate::{
+        blackouts::Blackout,
+        handlers::wake_up_tomorrow,
+        offsets_rep::{QuietHours, UserSettings, WorkingHours},
+    };
+
+    #[test]
+    fn test_wake_up_tomorrow() {
+        // +05:00, working 09-18 on weekdays
+        let settings = UserSettings::default();
+        // Wednesday, 15:00 local
+        let wednesday = Utc.with_ymd_and_hms(2024, 5, 8, 10, 0, 0).unwrap();
+        assert_eq!(
+            wake_up_tomorrow(&settings, &[], wednesday),
+            Utc.with_ymd_and_hms(2024, 5, 9, 4, 0, 0).unwrap()
+        );
+        // Friday, 23:30 local, over the weekend
+        let friday = Utc.with_ymd_and_hms(2024, 5, 10, 18, 30, 0).unwrap();
+        assert_eq!(
+            wake_up_tomorrow(&settings, &[], friday),
+            Utc.with_ymd_and_hms(2024, 5, 13, 4, 0, 0).unwrap()
+        );
+        // Thursday, 00:30 local, right after midnight, the same day
+        let thursday = Utc.with_ymd_and_hms(2024, 5, 8, 19, 30, 0).unwrap();
+        assert_eq!(
+            wake_up_tomorrow(&settings, &[], thursday),
+            Utc.with_ymd_and_hms(2024, 5, 9, 4, 0, 0).unwrap()
+        );
+        // Saturday, 00:30 local, over the rest of the weekend
+        let saturday = Utc.with_ymd_and_hms(2024, 5, 10, 19, 30, 0).unwrap();
+        assert_eq!(
+            wake_up_tomorrow(&settings, &[], saturday),
+            Utc.with_ymd_and_hms(2024, 5, 13, 4, 0, 0).unwrap()
+        );
+
+        let late = UserSettings {
+            offset: -3 * 3600,
+            working_hours: WorkingHours { from: 11, to: 20 },
+            quiet_hours: vec![QuietHours {
+                from: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+                to: NaiveTime::from_hms_opt(12, 30, 0).unwrap(),
+            }],
+            ..Default::default()
+        };
+        // Wednesday, 07:00 local, before the day's working time past the quiet hours
+        assert_eq!(
+            wake_up_tomorrow(&late, &[], wednesday),
+            Utc.with_ymd_and_hms(2024, 5, 8, 15, 30, 0).unwrap()
+        );
+        // Wednesday, 17:00 local, the next day's
+        assert_eq!(
+            wake_up_tomorrow(&late, &[], wednesday + chrono::Duration::hours(10)),
+            Utc.with_ymd_and_hms(2024, 5, 9, 15, 30, 0).unwrap()
+        );
+
+        // Done on a Friday afternoon, nothing comes until Monday
+        let afternoon = Utc.with_ymd_and_hms(2024, 5, 10, 10, 0, 0).unwrap();
+        assert_eq!(
+            wake_up_tomorrow(&settings, &[], afternoon),
+            Utc.with_ymd_and_hms(2024, 5, 13, 4, 0, 0).unwrap()
+        );
+        // Or Tuesday when Monday is a blackout
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 13).unwrap();
+        let blackouts = [Blackout {
+            from: monday,
+            to: monday,
+            reason: String::new(),
+        }];
+        assert_eq!(
+            wake_up_tomorrow(&settings, &blackouts, afternoon),
+            Utc.with_ymd_and_hms(2024, 5, 14, 4, 0, 0).unwrap()
+        );
+        // Berlin moves to +02:00 over the weekend, it's 09:00 by the new offset
+        let berlin = UserSettings {
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            wake_up_tomorrow(
+                &berlin,
+                &[],
+                Utc.with_ymd_and_hms(2024, 3, 29, 14, 0, 0).unwrap()
+            ),
+            Utc.with_ymd_and_hms(2024, 4, 1, 7, 0, 0).unwrap()
+        );
+
+        // A cron schedule resumes at the midnight
+        let cron = UserSettings {
+            cron: Some("0 0 7 * * *".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            wake_up_tomorrow(&cron, &[], wednesday),
+            Utc.with_ymd_and_hms(2024, 5, 8, 19, 0, 0).unwrap()
+        );
+    }
+}
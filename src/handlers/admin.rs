@@ -0,0 +1,507 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use async_mutex::Mutex;
+use chrono::NaiveDate;
+use teloxide::{prelude::*, types::InputFile};
+
+use crate::{
+    bot::Bot,
+    chat_info::ChatInfoCache,
+    chat_locks::ChatLocks,
+    config::Config,
+    dialogues::parse_working_hours,
+    formatting,
+    handlers::{
+        answer, format_hour, reply_locale, status::format_insights, subscription::subscribe, topic,
+        HandlerResult,
+    },
+    i18n::Locale,
+    jobs::{Job, JobQueue},
+    journal::Journal,
+    maintenance::Maintenance,
+    metrics,
+    notify_controller::{NotificationSender, StartEnum},
+    offsets_rep::{Footer, UserSettings},
+    parsers,
+    profiles::{self, Profile},
+    store::UserStore,
+    tr,
+};
+
+/// How many jobs "/jobs" lists, the rest are only counted
+const JOBS_LISTED: usize = 20;
+
+/// Profile definition like "Berlin office; Europe/Berlin; 09:00-18:00;
+/// 2024-12-25, 2024-12-26", the holidays are optional.
+fn parse_profile(text: &str) -> Option<(String, Profile)> {
+    let mut fields = text.split(';').map(str::trim);
+    let name = fields.next().filter(|name| !name.is_empty())?;
+    let tz = parsers::parse_timezone_name(fields.next()?)?;
+    let working_hours = parse_working_hours(fields.next()?)?;
+    let holidays = match fields.next() {
+        Some(dates) => dates
+            .split(',')
+            .map(str::trim)
+            .filter(|date| !date.is_empty())
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .collect::<Option<BTreeSet<_>>>()?,
+        None => BTreeSet::new(),
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some((
+        name.to_string(),
+        Profile {
+            timezone: tz.name().to_string(),
+            working_hours,
+            holidays,
+        },
+    ))
+}
+
+/// Read-only views of a chat available to admins through "/as"
+enum AdminView {
+    Settings,
+    Jobs,
+    Insights,
+}
+
+impl AdminView {
+    const ALL: [AdminView; 3] = [AdminView::Settings, AdminView::Jobs, AdminView::Insights];
+
+    fn parse(name: &str) -> Option<AdminView> {
+        AdminView::ALL.into_iter().find(|view| view.name() == name)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            AdminView::Settings => "settings",
+            AdminView::Jobs => "jobs",
+            AdminView::Insights => "insights",
+        }
+    }
+}
+
+pub async fn handle_as_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let mut args = args.split_whitespace();
+    let (Some(chat_id), Some(view)) = (
+        args.next()
+            .and_then(|id| id.parse::<i64>().ok())
+            .map(ChatId),
+        args.next().and_then(AdminView::parse),
+    ) else {
+        answer(&bot, &msg, tr!(locale, "as-usage")).await?;
+        return Ok(());
+    };
+
+    log::info!(
+        target: "audit",
+        "Admin {} viewed {} of chat {}",
+        msg.from().map(|user| user.id.to_string()).unwrap_or_default(),
+        view.name(),
+        chat_id
+    );
+
+    let reply = match view {
+        AdminView::Settings => match store.get(&chat_id).await {
+            Some(settings) => tr!(
+                locale,
+                "settings-view",
+                active = if metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .is_running(&chat_id)
+                {
+                    "yes"
+                } else {
+                    "no"
+                },
+                timezone = settings.timezone_label(),
+                from = format_hour(settings.working_hours.from),
+                to = format_hour(settings.working_hours.to),
+                interval = formatting::duration(settings.interval(), locale),
+                language = settings.locale.name(),
+                footer = match settings.footer {
+                    Footer::Text => "yes",
+                    Footer::Hidden => "no",
+                }
+            ),
+            None => tr!(locale, "as-unknown-chat", chat = chat_id.to_string()),
+        },
+        AdminView::Jobs => format_jobs(
+            &metrics::lock(&jobs_mutex, "jobs").await.for_chat(&chat_id),
+            locale,
+        ),
+        AdminView::Insights => match store.get(&chat_id).await {
+            Some(settings) => format_insights(&settings.done_hours, locale),
+            None => tr!(locale, "as-unknown-chat", chat = chat_id.to_string()),
+        },
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_start_groups_command(
+    bot: Bot,
+    msg: Message,
+    ids: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    chat_info: Arc<ChatInfoCache>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let ids: Vec<&str> = ids
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|id| !id.is_empty())
+        .collect();
+    if ids.is_empty() {
+        answer(&bot, &msg, tr!(locale, "start-groups-usage")).await?;
+        return Ok(());
+    }
+
+    let me = bot.get_me().await?;
+    let mut report = vec![];
+    for id in ids {
+        let outcome = match id.parse::<i64>().map(ChatId) {
+            Err(_) => Err("start-groups-invalid-id"),
+            Ok(chat_id) => match chat_info.get(&bot, chat_id).await {
+                Err(err) => {
+                    log::warn!("Unable to get group {}: {}", chat_id, err);
+                    Err("start-groups-unavailable")
+                }
+                Ok(info) if !info.is_group => Err("start-groups-not-group"),
+                Ok(_) => match bot.get_chat_member(chat_id, me.id).await {
+                    Ok(member) if member.kind.is_present() => {
+                        let mut notify_controller =
+                            metrics::lock(&notify_controller_mutex, "notify_controller").await;
+                        match subscribe(
+                            &*store,
+                            &mut notify_controller,
+                            &chat_id,
+                            true,
+                            None,
+                            config.default_locale,
+                            &config,
+                            None,
+                        )
+                        .await
+                        {
+                            Ok((_, StartEnum::Added)) => Ok("start-groups-started"),
+                            Ok((_, StartEnum::AlreadyExist)) => Ok("start-groups-already-started"),
+                            Err(err) => {
+                                log::error!("Failed to add {} group {}", err, chat_id);
+                                Err("error")
+                            }
+                        }
+                    }
+                    _ => Err("start-groups-not-member"),
+                },
+            },
+        };
+
+        report.push(match outcome {
+            Ok(message) => format!("✅ {}: {}", id, tr!(locale, message)),
+            Err(message) => format!("❌ {}: {}", id, tr!(locale, message)),
+        });
+    }
+
+    log::info!(
+        target: "audit",
+        "Admin {} started groups:\n{}",
+        msg.from().map(|user| user.id.to_string()).unwrap_or_default(),
+        report.join("\n")
+    );
+    answer(&bot, &msg, report.join("\n")).await?;
+
+    Ok(())
+}
+
+pub async fn handle_profile_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    chat_locks: Arc<ChatLocks>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let args = args.trim();
+    let (action, args) = match args.split_once(char::is_whitespace) {
+        Some((action, args)) => (action, args.trim()),
+        None => (args, ""),
+    };
+    let admin = msg
+        .from()
+        .map(|user| user.id.to_string())
+        .unwrap_or_default();
+    let reply = match action.to_lowercase().as_str() {
+        "" => {
+            let members = store.get_all().await;
+            let profiles: Vec<String> = store
+                .get_profiles()
+                .await
+                .into_iter()
+                .map(|(name, profile)| {
+                    tr!(
+                        locale,
+                        "profile-item",
+                        name = name.clone(),
+                        timezone = profile.timezone,
+                        from = format_hour(profile.working_hours.from),
+                        to = format_hour(profile.working_hours.to),
+                        holidays = profile.holidays.len(),
+                        chats = members
+                            .iter()
+                            .filter(|(_, settings)| settings.profile.as_ref() == Some(&name))
+                            .count()
+                    )
+                })
+                .collect();
+            match profiles.is_empty() {
+                true => tr!(locale, "profile-empty"),
+                false => profiles.join("\n"),
+            }
+        }
+        "set" => match parse_profile(args) {
+            Some((name, profile)) => match store.set_profile(&name, &profile).await {
+                Ok(()) => {
+                    let chats = update_members(
+                        &name,
+                        &msg.chat.id,
+                        &*store,
+                        &notify_controller_mutex,
+                        &chat_locks,
+                        |settings| profile.apply(&name, settings),
+                    )
+                    .await;
+                    log::info!(target: "audit", "Admin {} set profile {:?}: {:?}", admin, name, profile);
+                    tr!(locale, "profile-saved", name = name, chats = chats)
+                }
+                Err(err) => {
+                    log::error!("Unable to save profile {}: {}", name, err);
+                    tr!(locale, "error")
+                }
+            },
+            None => tr!(locale, "profile-usage"),
+        },
+        "remove" if !args.is_empty() => match store.rem_profile(args).await {
+            Ok(true) => {
+                let chats = update_members(
+                    args,
+                    &msg.chat.id,
+                    &*store,
+                    &notify_controller_mutex,
+                    &chat_locks,
+                    profiles::leave,
+                )
+                .await;
+                log::info!(target: "audit", "Admin {} removed profile {:?}", admin, args);
+                tr!(locale, "profile-removed", name = args, chats = chats)
+            }
+            Ok(false) => tr!(locale, "profile-unknown", name = args),
+            Err(err) => {
+                log::error!("Unable to remove profile {}: {}", args, err);
+                tr!(locale, "error")
+            }
+        },
+        _ => tr!(locale, "profile-usage"),
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Applies `f` to the settings of every chat that joined the profile `name`
+/// and restarts their notifications, returns how many chats there were.
+async fn update_members<F: Fn(&mut UserSettings)>(
+    name: &str,
+    current: &ChatId,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    chat_locks: &ChatLocks,
+    f: F,
+) -> usize {
+    let members: Vec<ChatId> = store
+        .get_all()
+        .await
+        .into_iter()
+        .filter(|(_, settings)| settings.profile.as_deref() == Some(name))
+        .map(|(chat_id, _)| chat_id)
+        .collect();
+    for chat_id in &members {
+        // The chat the command came from holds its lock already
+        let _guard = match chat_id == current {
+            true => None,
+            false => Some(chat_locks.lock(*chat_id).await),
+        };
+        if let Err(err) = store.update(chat_id, &f).await {
+            log::error!(
+                "Unable to update {} from profile {}: {}",
+                chat_id,
+                name,
+                err
+            );
+            continue;
+        }
+        if let Some(settings) = store.get(chat_id).await {
+            metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .restart(chat_id, &settings);
+        }
+    }
+
+    members.len()
+}
+
+fn format_jobs(jobs: &[Job], locale: Locale) -> String {
+    if jobs.is_empty() {
+        return tr!(locale, "jobs-empty");
+    }
+
+    jobs.iter()
+        .map(|job| {
+            format!(
+                "#{} {} {} {}",
+                job.id,
+                job.chat_id,
+                job.kind.name(),
+                job.due.format("%Y-%m-%d %H:%M UTC")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub async fn handle_maintenance_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    maintenance: Arc<Maintenance>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let on = match value.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            let state = match maintenance.is_on() {
+                true => "on",
+                false => "off",
+            };
+            answer(&bot, &msg, tr!(locale, "maintenance-usage", state = state)).await?;
+            return Ok(());
+        }
+    };
+
+    maintenance.set(on);
+    log::info!(
+        target: "audit",
+        "Admin {} turned maintenance mode {}",
+        msg.from().map(|user| user.id.to_string()).unwrap_or_default(),
+        if on { "on" } else { "off" }
+    );
+    let reply = match on {
+        true => "maintenance-enabled",
+        false => "maintenance-disabled",
+    };
+    answer(&bot, &msg, tr!(locale, reply)).await?;
+
+    Ok(())
+}
+
+pub async fn handle_jobs_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let jobs = metrics::lock(&jobs_mutex, "jobs").await.all();
+    answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "jobs-summary",
+            count = jobs.len(),
+            jobs = format_jobs(&jobs[..jobs.len().min(JOBS_LISTED)], locale)
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn handle_journal_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    journal: Arc<Journal>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let csv = match journal.export() {
+        Ok(csv) => csv,
+        Err(err) => {
+            log::error!("Unable to export the journal: {}", err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+            return Ok(());
+        }
+    };
+    let file = InputFile::memory(csv.into_bytes()).file_name("journal.csv");
+    let request = bot.send_document(msg.chat.id, file);
+    match topic(&msg) {
+        Some(thread_id) => request.message_thread_id(thread_id).await?,
+        None => request.await?,
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{handlers::admin::parse_profile, offsets_rep::WorkingHours};
+
+    #[test]
+    fn test_parse_profile() {
+        let (name, profile) =
+            parse_profile("Berlin office; europe/berlin; 09:00-18:00; 2024-12-25, 2024-12-26")
+                .unwrap();
+        assert_eq!(name, "Berlin office");
+        assert_eq!(profile.timezone, "Europe/Berlin");
+        assert_eq!(profile.working_hours, WorkingHours { from: 9, to: 18 });
+        assert_eq!(profile.holidays.len(), 2);
+
+        let (_, profile) = parse_profile("Remote; Asia/Tokyo; 10:00-19:00").unwrap();
+        assert!(profile.holidays.is_empty());
+
+        assert_eq!(parse_profile("Remote; Mars/Olympus; 10:00-19:00"), None);
+        assert_eq!(
+            parse_profile("Remote; Asia/Tokyo; 10:00-19:00; 25.12.2024"),
+            None
+        );
+        assert_eq!(parse_profile("; Asia/Tokyo; 10:00-19:00"), None);
+        assert_eq!(parse_profile("Remote; Asia/Tokyo"), None);
+    }
+}
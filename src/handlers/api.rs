@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use teloxide::prelude::*;
+
+use crate::{
+    bot::Bot,
+    config::Config,
+    dialogues::MyDialogue,
+    handlers::{answer, detect_locale, HandlerResult},
+    http,
+    store::UserStore,
+    tr,
+};
+
+pub async fn handle_token_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let Some(token) = next_token(&value, settings.api_token) else {
+        answer(&bot, &msg, tr!(settings.locale, "token-usage")).await?;
+        return Ok(());
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.api_token = token.clone())
+        .await
+    {
+        Ok(_) => {
+            let reply = match token {
+                Some(token) => tr!(settings.locale, "token-show", token = token),
+                None => tr!(settings.locale, "token-revoked"),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed token update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The token "/token" and "/webhook" leave the chat with: the current one or a
+/// new one when there's none, a new one for "new" and none for "off". `None`
+/// for anything else.
+fn next_token(value: &str, current: Option<String>) -> Option<Option<String>> {
+    match (value.trim().to_lowercase().as_str(), current) {
+        ("", Some(token)) => Some(Some(token)),
+        ("" | "new", _) => Some(Some(http::generate_token())),
+        ("off", _) => Some(None),
+        _ => None,
+    }
+}
+
+/// Shows the URL of the chat's hook, it shares the token with "/token".
+pub async fn handle_webhook_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let Some(token) = next_token(&value, settings.api_token) else {
+        answer(&bot, &msg, tr!(settings.locale, "webhook-usage")).await?;
+        return Ok(());
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.api_token = token.clone())
+        .await
+    {
+        Ok(_) => {
+            let url = config
+                .public_url
+                .as_ref()
+                .zip(token.as_ref())
+                .and_then(|(public_url, token)| http::hook_url(public_url, token));
+            let reply = match (url, token) {
+                (Some(url), _) => tr!(settings.locale, "webhook-show", url = url.to_string()),
+                (None, Some(token)) => tr!(settings.locale, "webhook-no-url", token = token),
+                (None, None) => tr!(settings.locale, "webhook-off"),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed webhook update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_template_command(
+    bot: Bot,
+    msg: Message,
+    template: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let template = match template.trim() {
+        "" => {
+            let reply = match settings.hook_template {
+                Some(template) => tr!(settings.locale, "template-show", template = template),
+                None => tr!(settings.locale, "template-usage"),
+            };
+            answer(&bot, &msg, reply).await?;
+            return Ok(());
+        }
+        "off" => None,
+        template => Some(template.to_string()),
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.hook_template = template.clone()
+        })
+        .await
+    {
+        Ok(_) => {
+            let reply = match template {
+                Some(_) => "template-set",
+                None => "template-cleared",
+            };
+            answer(&bot, &msg, tr!(settings.locale, reply)).await?;
+        }
+        Err(err) => {
+            log::error!("Failed template update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1358 @@
+use std::sync::Arc;
+
+use async_mutex::Mutex;
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, Utc, Weekday};
+use teloxide::prelude::*;
+
+use crate::{
+    bot::Bot,
+    chat_info::ChatInfoCache,
+    config::Config,
+    deadlines,
+    delivery::Priority,
+    dialogues::MyDialogue,
+    formatting,
+    handlers::{
+        answer, cancel_wake_up, detect_locale, format_local, restart_with_stored,
+        subscription::{acknowledge, done, stop},
+        HandlerResult,
+    },
+    i18n::Locale,
+    jobs::{Job, JobKind, JobQueue},
+    keyboards::{self, NotificationButton, PomodoroButton},
+    metrics,
+    notify_controller::NotificationSender,
+    offsets_rep::{CheckIn, UserSettings},
+    parsers,
+    pomodoro::{self, Pomodoro},
+    scheduling::Zone,
+    store::UserStore,
+    tr,
+};
+
+/// Longest pause "/snooze" accepts.
+const MAX_SNOOZE: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 3600);
+
+/// Pause of the snooze button under notifications.
+const BUTTON_SNOOZE: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Most notifications "/skip" leaves out at once.
+const MAX_SKIP: u32 = 100;
+
+pub async fn handle_skip_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let skip = match value.trim() {
+        "" => 1,
+        "off" => 0,
+        value => match value.parse() {
+            Ok(skip) if skip <= MAX_SKIP => skip,
+            _ => {
+                answer(
+                    &bot,
+                    &msg,
+                    tr!(settings.locale, "skip-usage", max = MAX_SKIP),
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.skip = skip)
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = match skip {
+                0 => tr!(settings.locale, "skip-cleared"),
+                skip => tr!(settings.locale, "skip-set", count = skip),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed skip update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_snooze_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let Some(duration) = parsers::parse_duration(&value) else {
+        answer(&bot, &msg, tr!(locale, "snooze-usage")).await?;
+        return Ok(());
+    };
+    if duration > MAX_SNOOZE {
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "snooze-too-long",
+                max = formatting::duration(MAX_SNOOZE, locale)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let reply = snooze(
+        &msg.chat.id,
+        &settings,
+        duration,
+        &notify_controller_mutex,
+        &jobs_mutex,
+    )
+    .await;
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Pauses notifications of the chat for `duration`, returns the reply.
+pub async fn snooze(
+    chat_id: &ChatId,
+    settings: &UserSettings,
+    duration: std::time::Duration,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    jobs_mutex: &Mutex<JobQueue>,
+) -> String {
+    let locale = settings.locale;
+    let mut notify_controller = metrics::lock(notify_controller_mutex, "notify_controller").await;
+    notify_controller.stop(chat_id);
+    cancel_wake_up(jobs_mutex, chat_id).await;
+
+    let due = Utc::now() + chrono::Duration::seconds(duration.as_secs() as i64);
+    match metrics::lock(jobs_mutex, "jobs")
+        .await
+        .push(*chat_id, due, JobKind::Snooze)
+    {
+        Ok(job) => {
+            log::info!("Snoozed {} until {}", chat_id, job.due);
+            tr!(
+                locale,
+                "snooze-set",
+                duration = formatting::duration(duration, locale),
+                until = format_local(job.due, settings)
+            )
+        }
+        Err(err) => {
+            log::error!("Unable to snooze {}: {}", chat_id, err);
+            // Don't leave the chat paused without a way back
+            notify_controller.start(chat_id, settings, false);
+            tr!(locale, "error")
+        }
+    }
+}
+
+/// The next moment the clock in `zone` shows `time`, today or tomorrow.
+pub fn next_local_time(time: NaiveTime, zone: &Zone, now: DateTime<Utc>) -> DateTime<Utc> {
+    let local_now = zone.local(now).naive_local();
+    let today = local_now.date().and_time(time);
+    let due = match today > local_now {
+        true => today,
+        false => today + Days::new(1),
+    };
+    zone.from_local(due)
+}
+
+pub async fn handle_remind_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    // An optional priority, then a time of day or a delay like "/snooze" takes
+    let args = args.trim();
+    let (priority, args) = match args.split_once(char::is_whitespace) {
+        Some((word, rest)) => match Priority::parse(word) {
+            Some(priority) => (priority, rest.trim()),
+            None => (Priority::Normal, args),
+        },
+        None => (Priority::Normal, args),
+    };
+    let due = args
+        .split_once(char::is_whitespace)
+        .and_then(|(when, text)| {
+            let due = match parsers::parse_time(when) {
+                Some(time) => next_local_time(time, &settings.zone(), Utc::now()),
+                None => {
+                    let delay =
+                        parsers::parse_duration(when).filter(|delay| *delay <= MAX_SNOOZE)?;
+                    Utc::now() + chrono::Duration::seconds(delay.as_secs() as i64)
+                }
+            };
+            Some((due, text.trim().to_string()))
+        });
+    let Some((due, text)) = due else {
+        answer(&bot, &msg, tr!(locale, "remind-usage")).await?;
+        return Ok(());
+    };
+
+    match metrics::lock(&jobs_mutex, "jobs").await.push(
+        msg.chat.id,
+        due,
+        JobKind::Reminder { text, priority },
+    ) {
+        Ok(job) => {
+            log::info!(
+                "Reminder {} for {} set at {} with {} priority",
+                job.id,
+                msg.chat.id,
+                job.due,
+                priority.name()
+            );
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    locale,
+                    "remind-set",
+                    until = format_local(job.due, &settings)
+                ),
+            )
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Unable to set reminder for {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The next moment the clock in `zone` shows `time` on `day` of a month,
+/// on the last day of months shorter than that.
+pub fn next_monthly(day: u32, time: NaiveTime, zone: &Zone, now: DateTime<Utc>) -> DateTime<Utc> {
+    let local_now = zone.local(now).naive_local();
+    let (mut year, mut month) = (local_now.year(), local_now.month());
+    loop {
+        let due = (1..=day)
+            .rev()
+            .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+            .unwrap()
+            .and_time(time);
+        if due > local_now {
+            return zone.from_local(due);
+        }
+        (year, month) = match month {
+            12 => (year + 1, 1),
+            _ => (year, month + 1),
+        };
+    }
+}
+
+pub async fn handle_report_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let args = args.trim();
+    let report = match args.eq_ignore_ascii_case("off") {
+        true => None,
+        false => {
+            // A day of the month, a local time, then the text
+            let mut words = args.splitn(3, char::is_whitespace);
+            let day = words
+                .next()
+                .and_then(|day| day.parse::<u32>().ok())
+                .filter(|day| (1..=31).contains(day));
+            let time = words.next().and_then(parsers::parse_time);
+            let text = words.next().map(str::trim).unwrap_or_default();
+            match (day, time) {
+                (Some(day), Some(time)) if !text.is_empty() => Some((day, time, text.to_string())),
+                _ => {
+                    answer(&bot, &msg, tr!(locale, "report-usage")).await?;
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+    // A chat has a single report, a new one replaces it
+    for job in jobs.for_chat(&msg.chat.id) {
+        if let JobKind::Report { .. } = job.kind {
+            if let Err(err) = jobs.remove(job.id) {
+                log::error!(
+                    "Unable to cancel report {} of {}: {}",
+                    job.id,
+                    msg.chat.id,
+                    err
+                );
+            }
+        }
+    }
+
+    let reply = match report {
+        Some((day, time, text)) => {
+            let due = next_monthly(day, time, &settings.zone(), Utc::now());
+            match jobs.push(msg.chat.id, due, JobKind::Report { text, day, time }) {
+                Ok(job) => {
+                    log::info!("Report {} for {} set at {}", job.id, msg.chat.id, job.due);
+                    tr!(
+                        locale,
+                        "report-set",
+                        day = day,
+                        time = formatting::time(&time),
+                        until = format_local(job.due, &settings)
+                    )
+                }
+                Err(err) => {
+                    log::error!("Unable to set report for {}: {}", msg.chat.id, err);
+                    tr!(locale, "error")
+                }
+            }
+        }
+        None => tr!(locale, "report-off"),
+    };
+    drop(jobs);
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_deadline_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+    let args = args.trim();
+
+    let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+    let deadlines: Vec<(u64, String, DateTime<Utc>)> = jobs
+        .for_chat(&msg.chat.id)
+        .into_iter()
+        .filter_map(|job| match job.kind {
+            JobKind::Deadline { title, deadline } => Some((job.id, title, deadline)),
+            _ => None,
+        })
+        .collect();
+
+    let reply = if args.is_empty() {
+        match deadlines.is_empty() {
+            true => tr!(locale, "deadline-usage"),
+            false => {
+                let lines: Vec<String> = deadlines
+                    .iter()
+                    .map(|(_, title, deadline)| {
+                        let local = deadline.with_timezone(&settings.offset_at(*deadline));
+                        format!("{} — {}", local.format("%Y-%m-%d %H:%M"), title)
+                    })
+                    .collect();
+                tr!(locale, "deadline-list", deadlines = lines.join("\n"))
+            }
+        }
+    } else if args.eq_ignore_ascii_case("off") {
+        let mut dropped = 0;
+        for (id, _, _) in &deadlines {
+            match jobs.remove(*id) {
+                Ok(_) => dropped += 1,
+                Err(err) => {
+                    log::error!("Unable to drop deadline {} of {}: {}", id, msg.chat.id, err)
+                }
+            }
+        }
+        tr!(locale, "deadline-off", count = dropped)
+    } else {
+        let Some((date, time, title)) = deadlines::parse(args) else {
+            drop(jobs);
+            answer(&bot, &msg, tr!(locale, "deadline-usage")).await?;
+            return Ok(());
+        };
+
+        // Due when the working day ends unless the time is given
+        let time = time
+            .or_else(|| NaiveTime::from_hms_opt(settings.working_hours.to, 0, 0))
+            .or_else(|| NaiveTime::from_hms_opt(23, 59, 0))
+            .unwrap_or_default();
+        let now = Utc::now();
+        let deadline = Some(settings.zone().from_local(date.and_time(time)))
+            .filter(|deadline| *deadline > now && *deadline - now <= deadlines::MAX_AHEAD);
+        let due = deadline.and_then(|deadline| {
+            let day_start = NaiveTime::from_hms_opt(settings.working_hours.from, 0, 0)?;
+            let local = deadline.with_timezone(&settings.offset_at(deadline));
+            deadlines::next_reminder(local, day_start, now).map(|due| (deadline, due))
+        });
+        match due {
+            Some((deadline, due)) => {
+                match jobs.push(
+                    msg.chat.id,
+                    due,
+                    JobKind::Deadline {
+                        title: title.clone(),
+                        deadline,
+                    },
+                ) {
+                    Ok(job) => {
+                        log::info!(
+                            "Deadline {} for {} set at {}, first reminder at {}",
+                            job.id,
+                            msg.chat.id,
+                            deadline,
+                            job.due
+                        );
+                        let local = deadline.with_timezone(&settings.offset_at(deadline));
+                        tr!(
+                            locale,
+                            "deadline-set",
+                            title = title,
+                            due = local.format("%Y-%m-%d %H:%M").to_string(),
+                            first = format_local(job.due, &settings)
+                        )
+                    }
+                    Err(err) => {
+                        log::error!("Unable to set deadline for {}: {}", msg.chat.id, err);
+                        tr!(locale, "error")
+                    }
+                }
+            }
+            None => tr!(locale, "deadline-past"),
+        }
+    };
+    drop(jobs);
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Most "/weekly" or "/monthly" reminders a chat may have of each.
+const MAX_RECURRING: usize = 20;
+
+/// How often a reminder set with "/weekly" or "/monthly" repeats.
+#[derive(Clone, Copy)]
+enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    fn command(self) -> &'static str {
+        match self {
+            Period::Week => "weekly",
+            Period::Month => "monthly",
+        }
+    }
+
+    /// Whether the job is a reminder repeating with this period.
+    fn matches(self, kind: &JobKind) -> bool {
+        matches!(
+            (self, kind),
+            (Period::Week, JobKind::Weekly { .. }) | (Period::Month, JobKind::Monthly { .. })
+        )
+    }
+
+    /// Parses when the reminder comes, e.g. "mon,thu" or "25", into a job
+    /// for each of the days.
+    fn kinds(self, when: &str, time: NaiveTime, text: &str) -> Option<Vec<JobKind>> {
+        match self {
+            Period::Week => parsers::parse_weekdays(when).map(|weekdays| {
+                weekdays
+                    .into_iter()
+                    .map(|weekday| JobKind::Weekly {
+                        text: text.to_string(),
+                        weekday,
+                        time,
+                    })
+                    .collect()
+            }),
+            Period::Month => when
+                .parse::<u32>()
+                .ok()
+                .filter(|day| (1..=31).contains(day))
+                .map(|day| {
+                    vec![JobKind::Monthly {
+                        text: text.to_string(),
+                        day,
+                        time,
+                    }]
+                }),
+        }
+    }
+}
+
+/// When a "/weekly" or "/monthly" reminder comes, e.g. "Every Monday at 10:00".
+pub fn describe_recurring(kind: &JobKind, locale: Locale) -> Option<String> {
+    match kind {
+        JobKind::Weekly { weekday, time, .. } => Some(tr!(
+            locale,
+            "weekly-when",
+            weekday = formatting::weekday(*weekday, locale),
+            time = formatting::time(time)
+        )),
+        JobKind::Monthly { day, time, .. } => Some(tr!(
+            locale,
+            "monthly-when",
+            day = *day,
+            time = formatting::time(time)
+        )),
+        _ => None,
+    }
+}
+
+/// The first time a "/weekly" or "/monthly" reminder comes after `now`.
+pub fn next_recurring(kind: &JobKind, zone: &Zone, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match kind {
+        JobKind::Weekly { weekday, time, .. } => Some(next_weekly(*weekday, *time, zone, now)),
+        JobKind::Monthly { day, time, .. } => Some(next_monthly(*day, *time, zone, now)),
+        _ => None,
+    }
+}
+
+/// The next moment the clock in `zone` shows `time` on `weekday`.
+pub fn next_weekly(
+    weekday: Weekday,
+    time: NaiveTime,
+    zone: &Zone,
+    now: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let local_now = zone.local(now).naive_local();
+    let days =
+        (weekday.num_days_from_monday() + 7 - local_now.weekday().num_days_from_monday()) % 7;
+    let due = (local_now.date() + Days::new(u64::from(days))).and_time(time);
+    let due = match due > local_now {
+        true => due,
+        false => due + Days::new(7),
+    };
+    zone.from_local(due)
+}
+
+pub async fn handle_weekly_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+    set_recurring(
+        Period::Week,
+        &bot,
+        &msg,
+        &args,
+        &*store,
+        &jobs_mutex,
+        &config,
+    )
+    .await
+}
+
+pub async fn handle_monthly_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+    set_recurring(
+        Period::Month,
+        &bot,
+        &msg,
+        &args,
+        &*store,
+        &jobs_mutex,
+        &config,
+    )
+    .await
+}
+
+/// Lists the chat's reminders repeating with the `period`, drops them with
+/// "off" or adds one from the days, a local time and the text.
+async fn set_recurring(
+    period: Period,
+    bot: &Bot,
+    msg: &Message,
+    args: &str,
+    store: &dyn UserStore,
+    jobs_mutex: &Mutex<JobQueue>,
+    config: &Config,
+) -> HandlerResult {
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(bot, msg, tr!(detect_locale(msg, config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+    let usage = format!("{}-usage", period.command());
+    let args = args.trim();
+
+    let mut jobs = metrics::lock(jobs_mutex, "jobs").await;
+    let existing: Vec<Job> = jobs
+        .for_chat(&msg.chat.id)
+        .into_iter()
+        .filter(|job| period.matches(&job.kind))
+        .collect();
+
+    let reply = if args.is_empty() {
+        let lines: Vec<String> = existing
+            .iter()
+            .filter_map(|job| match &job.kind {
+                JobKind::Weekly { text, .. } | JobKind::Monthly { text, .. } => {
+                    let when = describe_recurring(&job.kind, locale)?;
+                    Some(format!("{} — {}", when, text))
+                }
+                _ => None,
+            })
+            .collect();
+        match lines.is_empty() {
+            true => tr!(locale, &usage),
+            false => tr!(locale, "recurring-list", reminders = lines.join("\n")),
+        }
+    } else if args.eq_ignore_ascii_case("off") {
+        let mut dropped = 0;
+        for job in &existing {
+            match jobs.remove(job.id) {
+                Ok(_) => dropped += 1,
+                Err(err) => log::error!(
+                    "Unable to drop reminder {} of {}: {}",
+                    job.id,
+                    msg.chat.id,
+                    err
+                ),
+            }
+        }
+        tr!(locale, "recurring-off", count = dropped)
+    } else {
+        // The days, a local time, then the text
+        let mut words = args.splitn(3, char::is_whitespace);
+        let when = words.next().unwrap_or_default();
+        let time = words.next().and_then(parsers::parse_time);
+        let text = words.next().map(str::trim).unwrap_or_default();
+        let kinds = time
+            .filter(|_| !text.is_empty())
+            .and_then(|time| period.kinds(when, time, text));
+        let Some(kinds) = kinds else {
+            drop(jobs);
+            answer(bot, msg, tr!(locale, &usage)).await?;
+            return Ok(());
+        };
+        if existing.len() + kinds.len() > MAX_RECURRING {
+            drop(jobs);
+            answer(
+                bot,
+                msg,
+                tr!(
+                    locale,
+                    "recurring-too-many",
+                    max = MAX_RECURRING,
+                    command = period.command()
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let mut set = vec![];
+        for kind in kinds {
+            let Some(due) = next_recurring(&kind, &settings.zone(), now) else {
+                continue;
+            };
+            let when = describe_recurring(&kind, locale).unwrap_or_default();
+            match jobs.push(msg.chat.id, due, kind) {
+                Ok(job) => {
+                    log::info!(
+                        "Reminder {} for {} repeats from {}",
+                        job.id,
+                        msg.chat.id,
+                        job.due
+                    );
+                    set.push(tr!(
+                        locale,
+                        "recurring-set",
+                        when = when,
+                        until = format_local(job.due, &settings)
+                    ));
+                }
+                Err(err) => {
+                    log::error!("Unable to set reminder for {}: {}", msg.chat.id, err);
+                    set.push(tr!(locale, "error"));
+                }
+            }
+        }
+        set.join("\n")
+    };
+    drop(jobs);
+    answer(bot, msg, reply).await?;
+
+    Ok(())
+}
+
+/// Longest "/timer" accepted.
+const MAX_TIMER: std::time::Duration = std::time::Duration::from_secs(24 * 3600);
+
+pub async fn handle_timer_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let args = args.trim();
+    let (duration, text) = match args.split_once(char::is_whitespace) {
+        Some((duration, text)) => (duration, text.trim()),
+        None => (args, ""),
+    };
+    let Some(duration) = parsers::parse_duration(duration) else {
+        answer(&bot, &msg, tr!(locale, "timer-usage")).await?;
+        return Ok(());
+    };
+    if duration > MAX_TIMER {
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "timer-too-long",
+                max = formatting::duration(MAX_TIMER, locale)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    let text = match text.is_empty() {
+        true => tr!(locale, "timer-default-text"),
+        false => text.to_string(),
+    };
+
+    let now = Utc::now();
+    let ends = now + chrono::Duration::seconds(duration.as_secs() as i64);
+    let countdown = answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "timer-running",
+            text = text.clone(),
+            remaining = timer_remaining(now, ends, locale)
+        ),
+    )
+    .await?;
+
+    let kind = JobKind::Timer {
+        text,
+        message_id: countdown.id.0,
+        ends,
+    };
+    match metrics::lock(&jobs_mutex, "jobs").await.push(
+        msg.chat.id,
+        next_timer_update(now, ends),
+        kind,
+    ) {
+        Ok(job) => log::info!("Timer {} for {} ends at {}", job.id, msg.chat.id, ends),
+        Err(err) => {
+            log::error!("Unable to set timer for {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// When the countdown message of a timer ending at `ends` is next updated,
+/// less often the further away the end is.
+pub fn next_timer_update(now: DateTime<Utc>, ends: DateTime<Utc>) -> DateTime<Utc> {
+    let remaining = ends - now;
+    let step = match remaining {
+        _ if remaining > chrono::Duration::hours(1) => chrono::Duration::minutes(15),
+        _ if remaining > chrono::Duration::minutes(10) => chrono::Duration::minutes(5),
+        _ => chrono::Duration::minutes(1),
+    };
+    (now + step).min(ends)
+}
+
+/// Time left until `ends`, rounded up to a minute.
+pub fn timer_remaining(now: DateTime<Utc>, ends: DateTime<Utc>, locale: Locale) -> String {
+    let secs = (ends - now).num_seconds().max(0) as u64;
+    formatting::duration(
+        std::time::Duration::from_secs(secs.div_ceil(60) * 60),
+        locale,
+    )
+}
+
+/// Takes the chat's "/pomodoro" cycle out of the queue along with when its
+/// job is due.
+fn take_pomodoro(jobs: &mut JobQueue, chat_id: &ChatId) -> Option<(DateTime<Utc>, Pomodoro)> {
+    let mut taken = None;
+    for job in jobs.for_chat(chat_id) {
+        if let JobKind::Pomodoro(cycle) = job.kind {
+            if let Err(err) = jobs.remove(job.id) {
+                log::error!("Unable to take pomodoro {} of {}: {}", job.id, chat_id, err);
+                continue;
+            }
+            taken = Some((job.due, cycle));
+        }
+    }
+    taken
+}
+
+pub async fn handle_pomodoro_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    if args.trim().eq_ignore_ascii_case("off") {
+        let stopped = take_pomodoro(&mut *metrics::lock(&jobs_mutex, "jobs").await, &msg.chat.id);
+        let reply = match stopped {
+            Some(_) => "pomodoro-stopped",
+            None => "pomodoro-none",
+        };
+        answer(&bot, &msg, tr!(locale, reply)).await?;
+        return Ok(());
+    }
+    let Some((work, rest)) = pomodoro::parse(&args) else {
+        answer(&bot, &msg, tr!(locale, "pomodoro-usage")).await?;
+        return Ok(());
+    };
+
+    // A new cycle replaces the running one
+    let cycle = Pomodoro::new(work, rest);
+    let pushed = {
+        let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+        take_pomodoro(&mut jobs, &msg.chat.id);
+        jobs.push(
+            msg.chat.id,
+            Utc::now() + cycle.length(),
+            JobKind::Pomodoro(cycle),
+        )
+    };
+    match pushed {
+        Ok(job) => {
+            log::info!("Pomodoro {} for {} started", job.id, msg.chat.id);
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    locale,
+                    "pomodoro-started",
+                    work = pomodoro::format_minutes(work, locale),
+                    rest = pomodoro::format_minutes(rest, locale)
+                ),
+            )
+            .reply_markup(keyboards::pomodoro(locale, false))
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Unable to start pomodoro for {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Shortest and longest periods "/checkin" accepts.
+const MIN_CHECK_IN: std::time::Duration = std::time::Duration::from_secs(3600);
+const MAX_CHECK_IN: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 3600);
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_check_in_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    chat_info: Arc<ChatInfoCache>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let check_in = match args.trim() {
+        "" => {
+            let reply = match &settings.check_in {
+                Some(check_in) => tr!(
+                    locale,
+                    "check-in-view",
+                    period = formatting::duration(check_in.period(), locale),
+                    contact = check_in.contact.to_string(),
+                    due = format_local(check_in.due(), &settings)
+                ),
+                None => tr!(locale, "check-in-usage"),
+            };
+            answer(&bot, &msg, reply).await?;
+            return Ok(());
+        }
+        "off" => None,
+        args => {
+            let parsed = args
+                .split_once(char::is_whitespace)
+                .and_then(|(period, contact)| {
+                    Some((
+                        parsers::parse_duration(period)?,
+                        contact.trim().parse::<i64>().ok().map(ChatId)?,
+                    ))
+                });
+            let Some((period, contact)) = parsed else {
+                answer(&bot, &msg, tr!(locale, "check-in-usage")).await?;
+                return Ok(());
+            };
+            if !(MIN_CHECK_IN..=MAX_CHECK_IN).contains(&period) {
+                answer(
+                    &bot,
+                    &msg,
+                    tr!(
+                        locale,
+                        "check-in-out-of-range",
+                        min = formatting::duration(MIN_CHECK_IN, locale),
+                        max = formatting::duration(MAX_CHECK_IN, locale)
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+            if let Err(err) = chat_info.get(&bot, contact).await {
+                log::warn!("Unable to get check-in contact {}: {}", contact, err);
+                answer(&bot, &msg, tr!(locale, "check-in-unreachable")).await?;
+                return Ok(());
+            }
+
+            let name = match msg.from() {
+                Some(user) => user.full_name(),
+                None => msg.chat.title().unwrap_or_default().to_string(),
+            };
+            Some(CheckIn {
+                period_secs: period.as_secs(),
+                contact,
+                name,
+                last_contact: Utc::now(),
+            })
+        }
+    };
+
+    if let Err(err) = store
+        .update(&msg.chat.id, |settings| {
+            settings.check_in = check_in.clone()
+        })
+        .await
+    {
+        log::error!("Failed check-in update {}: {}", msg.chat.id, err);
+        answer(&bot, &msg, tr!(locale, "error")).await?;
+        return Ok(());
+    }
+
+    let scheduled = {
+        let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+        jobs.cancel(&msg.chat.id, JobKind::CheckIn)
+            .and_then(|_| match &check_in {
+                Some(check_in) => jobs
+                    .push(msg.chat.id, check_in.due(), JobKind::CheckIn)
+                    .map(|_| ()),
+                None => Ok(()),
+            })
+    };
+    if let Err(err) = scheduled {
+        log::error!("Unable to schedule check-in of {}: {}", msg.chat.id, err);
+    }
+
+    log::info!(target: "audit", "{} set check-in {:?}", msg.chat.id, check_in);
+    let reply = match check_in {
+        Some(check_in) => tr!(
+            locale,
+            "check-in-set",
+            period = formatting::duration(check_in.period(), locale),
+            contact = check_in.contact.to_string()
+        ),
+        None => tr!(locale, "check-in-off"),
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Runs the command of a button pressed under a notification, the reply
+/// shows up as a popup so the chat isn't cluttered.
+pub async fn handle_notification_button(
+    bot: Bot,
+    query: CallbackQuery,
+    button: NotificationButton,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let Some(chat_id) = query.message.as_ref().map(|msg| msg.chat.id) else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    let settings = store.get(&chat_id).await;
+    let locale = match &settings {
+        Some(settings) => settings.locale,
+        None => query
+            .from
+            .language_code
+            .as_deref()
+            .and_then(Locale::from_code)
+            .unwrap_or(config.default_locale),
+    };
+
+    let reply = match (button, settings) {
+        (NotificationButton::Ack, _) => {
+            acknowledge(&chat_id, locale, &*store, &notify_controller_mutex).await
+        }
+        (NotificationButton::Done, _) => {
+            done(
+                &chat_id,
+                locale,
+                &*store,
+                &notify_controller_mutex,
+                &jobs_mutex,
+            )
+            .await
+        }
+        (NotificationButton::Snooze, Some(settings)) => {
+            snooze(
+                &chat_id,
+                &settings,
+                BUTTON_SNOOZE,
+                &notify_controller_mutex,
+                &jobs_mutex,
+            )
+            .await
+        }
+        (NotificationButton::Snooze, None) => tr!(locale, "not-started"),
+        (NotificationButton::Stop, _) => {
+            stop(
+                &chat_id,
+                locale,
+                &*store,
+                &notify_controller_mutex,
+                &jobs_mutex,
+            )
+            .await
+            .0
+        }
+    };
+    log::info!("{} pressed {:?} under a notification", chat_id, button);
+    bot.answer_callback_query(query.id).text(reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_pomodoro_button(
+    bot: Bot,
+    query: CallbackQuery,
+    button: PomodoroButton,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let Some(msg) = query.message else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+    let locale = match store.get(&msg.chat.id).await {
+        Some(settings) => settings.locale,
+        None => query
+            .from
+            .language_code
+            .as_deref()
+            .and_then(Locale::from_code)
+            .unwrap_or(config.default_locale),
+    };
+    log::info!("{} pressed {:?} under a pomodoro", msg.chat.id, button);
+
+    let now = Utc::now();
+    let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+    let Some((due, mut cycle)) = take_pomodoro(&mut jobs, &msg.chat.id) else {
+        drop(jobs);
+        bot.answer_callback_query(query.id)
+            .text(tr!(locale, "pomodoro-none"))
+            .await?;
+        bot.edit_message_reply_markup(msg.chat.id, msg.id).await?;
+        return Ok(());
+    };
+
+    // Where the cycle goes back to the queue and what the message turns into,
+    // a press the message is stale for changes nothing
+    let (due, edit) = match (button, cycle.paused) {
+        (PomodoroButton::Pause, None) => {
+            let left = (due - now).max(chrono::Duration::zero());
+            cycle.paused = Some(left.num_seconds());
+            let remaining = timer_remaining(now, due.max(now), locale);
+            let text = tr!(locale, "pomodoro-paused", remaining = remaining);
+            (Some(now + pomodoro::MAX_PAUSE), Some((text, true)))
+        }
+        (PomodoroButton::Resume, Some(left)) => {
+            cycle.paused = None;
+            let ends = now + chrono::Duration::seconds(left);
+            let remaining = timer_remaining(now, ends, locale);
+            let text = tr!(locale, "pomodoro-resumed", remaining = remaining);
+            (Some(ends), Some((text, false)))
+        }
+        // The ticker ends the phase and sends the next one's message
+        (PomodoroButton::Skip, _) => {
+            cycle.paused = None;
+            (Some(now), None)
+        }
+        (PomodoroButton::Stop, _) => (None, None),
+        _ => (Some(due), None),
+    };
+    if let Some(due) = due {
+        if let Err(err) = jobs.push(msg.chat.id, due, JobKind::Pomodoro(cycle)) {
+            log::error!("Unable to put back pomodoro of {}: {}", msg.chat.id, err);
+        }
+    }
+    drop(jobs);
+    bot.answer_callback_query(query.id).await?;
+
+    match (button, edit) {
+        (_, Some((text, paused))) => {
+            bot.edit_message_text(msg.chat.id, msg.id, text)
+                .reply_markup(keyboards::pomodoro(locale, paused))
+                .await?;
+        }
+        (PomodoroButton::Skip, None) => {
+            bot.edit_message_reply_markup(msg.chat.id, msg.id).await?;
+        }
+        (PomodoroButton::Stop, None) => {
+            bot.edit_message_text(msg.chat.id, msg.id, tr!(locale, "pomodoro-stopped"))
+                .await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveTime, TimeZone, Utc, Weekday};
+
+    use crate::{
+        handlers::reminders::{next_local_time, next_monthly, next_timer_update, next_weekly},
+        scheduling::Zone,
+    };
+
+    #[test]
+    fn test_next_local_time() {
+        let zone = Zone::Fixed(FixedOffset::east_opt(3 * 3600).unwrap());
+        // 14:00 at +03:00
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 11, 0, 0).unwrap();
+        let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+
+        assert_eq!(
+            next_local_time(time(15, 30), &zone, now),
+            Utc.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap()
+        );
+        assert_eq!(
+            next_local_time(time(14, 0), &zone, now),
+            Utc.with_ymd_and_hms(2023, 5, 2, 11, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_local_time(time(1, 0), &zone, now),
+            Utc.with_ymd_and_hms(2023, 5, 1, 22, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_weekly_across_dst() {
+        // Berlin moves from +01:00 to +02:00 on 2024-03-31 at 02:00 and back
+        // on 2024-10-27 at 03:00
+        let zone = Zone::Named(chrono_tz::Europe::Berlin);
+        let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+        // Friday, 12:00 at +01:00
+        let now = Utc.with_ymd_and_hms(2024, 3, 29, 11, 0, 0).unwrap();
+
+        assert_eq!(
+            next_weekly(Weekday::Mon, time(10, 0), &zone, now),
+            Utc.with_ymd_and_hms(2024, 4, 1, 8, 0, 0).unwrap()
+        );
+        // 02:30 doesn't exist that night, 03:00 comes instead
+        assert_eq!(
+            next_weekly(Weekday::Sun, time(2, 30), &zone, now),
+            Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_local_time(time(2, 30), &zone, now + chrono::Duration::days(1)),
+            Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap()
+        );
+        // 02:30 comes twice that night, the first one counts
+        assert_eq!(
+            next_monthly(
+                27,
+                time(2, 30),
+                &zone,
+                Utc.with_ymd_and_hms(2024, 10, 1, 0, 0, 0).unwrap()
+            ),
+            Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_monthly() {
+        let zone = Zone::Fixed(FixedOffset::east_opt(3 * 3600).unwrap());
+        let time = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 25, 6, 0, 0).unwrap();
+
+        assert_eq!(
+            next_monthly(25, time, &zone, now),
+            Utc.with_ymd_and_hms(2024, 1, 25, 7, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_monthly(25, time, &zone, now + chrono::Duration::hours(1)),
+            Utc.with_ymd_and_hms(2024, 2, 25, 7, 0, 0).unwrap()
+        );
+        // Short months get it on their last day
+        assert_eq!(
+            next_monthly(31, time, &zone, now + chrono::Duration::days(7)),
+            Utc.with_ymd_and_hms(2024, 2, 29, 7, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_monthly(
+                5,
+                time,
+                &zone,
+                Utc.with_ymd_and_hms(2024, 12, 6, 0, 0, 0).unwrap()
+            ),
+            Utc.with_ymd_and_hms(2025, 1, 5, 7, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_weekly() {
+        let zone = Zone::Fixed(FixedOffset::east_opt(3 * 3600).unwrap());
+        let time = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+        // Wednesday, 09:00 at +03:00
+        let now = Utc.with_ymd_and_hms(2024, 5, 8, 6, 0, 0).unwrap();
+
+        assert_eq!(
+            next_weekly(Weekday::Wed, time, &zone, now),
+            Utc.with_ymd_and_hms(2024, 5, 8, 7, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_weekly(Weekday::Wed, time, &zone, now + chrono::Duration::hours(1)),
+            Utc.with_ymd_and_hms(2024, 5, 15, 7, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_weekly(Weekday::Mon, time, &zone, now),
+            Utc.with_ymd_and_hms(2024, 5, 13, 7, 0, 0).unwrap()
+        );
+        // Already Thursday at +03:00
+        assert_eq!(
+            next_weekly(
+                Weekday::Thu,
+                time,
+                &zone,
+                Utc.with_ymd_and_hms(2024, 5, 8, 22, 0, 0).unwrap()
+            ),
+            Utc.with_ymd_and_hms(2024, 5, 9, 7, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_timer_update() {
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 11, 0, 0).unwrap();
+        let after = |minutes| now + chrono::Duration::minutes(minutes);
+
+        assert_eq!(next_timer_update(now, after(120)), after(15));
+        assert_eq!(next_timer_update(now, after(25)), after(5));
+        assert_eq!(next_timer_update(now, after(10)), after(1));
+        assert_eq!(
+            next_timer_update(now, now + chrono::Duration::seconds(20)),
+            now + chrono::Duration::seconds(20)
+        );
+    }
+}
@@ -0,0 +1,1695 @@
+use std::{sync::Arc, time::Duration};
+
+use async_mutex::Mutex;
+use chrono::{NaiveTime, Utc};
+use teloxide::prelude::*;
+
+use crate::{
+    ack_hooks, aliases,
+    bot::Bot,
+    channels::{self, Channels},
+    chat_locks::ChatLocks,
+    commands::command_names,
+    config::Config,
+    dialogues::{apply_change, MyDialogue},
+    formatting,
+    handlers::{
+        answer, detect_locale, format_hour, format_local, reminders::next_local_time, reply_locale,
+        restart_with_stored, topic, HandlerResult,
+    },
+    i18n::Locale,
+    jobs::{JobKind, JobQueue},
+    keyboards,
+    message_text::Markup,
+    metrics,
+    notify_controller::{
+        next_notification, too_frequent, NotificationSender, MAX_INTERVAL, MIN_INTERVAL,
+    },
+    offsets_rep::{Footer, QuietHours, UserSettings, Workdays, WorkingHours},
+    parsers,
+    presets::Preset,
+    profiles, release_notes,
+    settings_menu::{self, MenuButton, Section},
+    store::UserStore,
+    templates, tr,
+    transfers::{Transfers, TRANSFER_TTL},
+};
+
+/// Current settings above the "/settings" menu, with a prompt for the
+/// choices of `section`.
+fn menu_text(section: Option<Section>, settings: &UserSettings) -> String {
+    let locale = settings.locale;
+    let summary = tr!(
+        locale,
+        "settings-menu",
+        timezone = settings.timezone_label(),
+        from = format_hour(settings.working_hours.from),
+        to = format_hour(settings.working_hours.to),
+        interval = formatting::duration(settings.interval(), locale),
+        days = format_workdays(settings.workdays, locale),
+        footer = match settings.footer {
+            Footer::Text => "yes",
+            Footer::Hidden => "no",
+        }
+    );
+    match section {
+        Some(section) => format!(
+            "{}\n\n{}",
+            summary,
+            tr!(locale, &format!("settings-choose-{}", section.name()))
+        ),
+        None => summary,
+    }
+}
+
+pub async fn handle_settings_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    answer(&bot, &msg, menu_text(None, &settings))
+        .reply_markup(settings_menu::keyboard(None, &settings))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_menu_button(
+    bot: Bot,
+    query: CallbackQuery,
+    button: MenuButton,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+) -> HandlerResult {
+    bot.answer_callback_query(query.id).await?;
+
+    let Some(msg) = query.message else {
+        return Ok(());
+    };
+    let Some(mut settings) = store.get(&msg.chat.id).await else {
+        return Ok(());
+    };
+
+    let navigation = matches!(
+        button,
+        MenuButton::Open(_) | MenuButton::Back | MenuButton::Close
+    );
+    if button.apply(&mut settings.clone()) {
+        log::info!("{} changed {:?} in the menu", msg.chat.id, button);
+        match &button {
+            MenuButton::Change(change) => {
+                apply_change(
+                    &msg.chat.id,
+                    change,
+                    settings.locale,
+                    &*store,
+                    &notify_controller_mutex,
+                )
+                .await;
+            }
+            _ => {
+                if let Err(err) = store
+                    .update(&msg.chat.id, |settings| {
+                        button.apply(settings);
+                    })
+                    .await
+                {
+                    log::error!("Failed settings update {}: {}", msg.chat.id, err);
+                    bot.edit_message_text(msg.chat.id, msg.id, tr!(settings.locale, "error"))
+                        .await?;
+                    return Ok(());
+                }
+                restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            }
+        }
+        match store.get(&msg.chat.id).await {
+            Some(stored) => settings = stored,
+            None => return Ok(()),
+        }
+    } else if !navigation {
+        // The menu already shows it, Telegram refuses an edit without changes
+        return Ok(());
+    }
+
+    let section = button.section();
+    let text = menu_text(section, &settings);
+    match button {
+        MenuButton::Close => bot.edit_message_text(msg.chat.id, msg.id, text).await?,
+        _ => {
+            bot.edit_message_text(msg.chat.id, msg.id, text)
+                .reply_markup(settings_menu::keyboard(section, &settings))
+                .await?
+        }
+    };
+
+    Ok(())
+}
+
+pub async fn handle_cron_command(
+    bot: Bot,
+    msg: Message,
+    expression: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let cron = match expression.trim() {
+        "" => {
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    locale,
+                    "cron-usage",
+                    current = settings.cron.unwrap_or_else(|| "—".to_string())
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+        "off" => None,
+        expression => match expression.parse::<cron::Schedule>() {
+            Ok(schedule) if too_frequent(&schedule) => {
+                answer(
+                    &bot,
+                    &msg,
+                    tr!(
+                        locale,
+                        "cron-too-frequent",
+                        min = formatting::duration(MIN_INTERVAL, locale)
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+            Ok(_) => Some(expression.to_string()),
+            Err(err) => {
+                answer(
+                    &bot,
+                    &msg,
+                    tr!(locale, "cron-invalid", error = err.to_string()),
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.cron = cron)
+        .await
+    {
+        Ok(_) => {
+            let Some(settings) = store.get(&msg.chat.id).await else {
+                return Ok(());
+            };
+            metrics::lock(&notify_controller_mutex, "notify_controller")
+                .await
+                .restart(&msg.chat.id, &settings);
+
+            let reply = match next_notification(&settings, Utc::now()) {
+                _ if settings.cron.is_none() => tr!(locale, "cron-off"),
+                Some(next) => tr!(locale, "cron-set", next = format_local(next, &settings)),
+                None => tr!(locale, "cron-set", next = "—"),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed cron update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_interval_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    let Some(interval) = parsers::parse_duration(&value) else {
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "interval-usage",
+                interval = formatting::duration(settings.interval(), locale)
+            ),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    if !(MIN_INTERVAL..=MAX_INTERVAL).contains(&interval) {
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "interval-out-of-range",
+                min = formatting::duration(MIN_INTERVAL, locale),
+                max = formatting::duration(MAX_INTERVAL, locale)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.interval_secs = interval.as_secs()
+        })
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    locale,
+                    "interval-changed",
+                    interval = formatting::duration(interval, locale)
+                ),
+            )
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Failed interval update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn format_workdays(workdays: Workdays, locale: Locale) -> String {
+    workdays
+        .days()
+        .map(|day| formatting::weekday(day, locale))
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+pub async fn handle_workdays_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    let Some(days) = parsers::parse_weekdays(&value) else {
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "workdays-usage",
+                days = format_workdays(settings.workdays, locale)
+            ),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let workdays = Workdays::from_days(days);
+    match store
+        .update(&msg.chat.id, |settings| settings.workdays = workdays)
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    locale,
+                    "workdays-changed",
+                    days = format_workdays(workdays, locale)
+                ),
+            )
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Failed workdays update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Quiet hours a chat can have, a few breaks a day is plenty.
+const MAX_QUIET_HOURS: usize = 5;
+
+/// Parses breaks like "13:00-14:00, 16:00-16:30", sorted by their start.
+fn parse_quiet_hours(text: &str) -> Option<Vec<QuietHours>> {
+    let mut quiet = text
+        .split(',')
+        .map(|window| {
+            let (from, to) = parsers::parse_time_window(window)?;
+            Some(QuietHours { from, to })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if quiet.len() > MAX_QUIET_HOURS {
+        return None;
+    }
+    quiet.sort_by_key(|window| window.from);
+    Some(quiet)
+}
+
+fn format_quiet_hours(quiet: &[QuietHours]) -> String {
+    quiet
+        .iter()
+        .map(|window| {
+            format!(
+                "{}–{}",
+                formatting::time(&window.from),
+                formatting::time(&window.to)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub async fn handle_quiet_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    let quiet = match value.trim() {
+        "off" => vec![],
+        value => match parse_quiet_hours(value) {
+            Some(quiet) => quiet,
+            None => {
+                let reply = match settings.quiet_hours.is_empty() {
+                    true => tr!(locale, "quiet-usage"),
+                    false => tr!(
+                        locale,
+                        "quiet-show",
+                        hours = format_quiet_hours(&settings.quiet_hours)
+                    ),
+                };
+                answer(&bot, &msg, reply).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.quiet_hours = quiet.clone()
+        })
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = match quiet.is_empty() {
+                true => tr!(locale, "quiet-cleared"),
+                false => tr!(locale, "quiet-changed", hours = format_quiet_hours(&quiet)),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed quiet hours update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Hours at each end of working hours notifications may come without a
+/// sound in.
+const MAX_SOFT_HOURS: u32 = 3;
+
+pub async fn handle_soft_hours_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    let hours = |hours: u32| formatting::duration(Duration::from_secs(hours as u64 * 3600), locale);
+    let soft_hours = match value.trim() {
+        "off" => 0,
+        value => match value.parse() {
+            Ok(soft_hours) if (1..=MAX_SOFT_HOURS).contains(&soft_hours) => soft_hours,
+            _ => {
+                let reply = match settings.soft_hours {
+                    0 => tr!(locale, "soft-hours-usage", max = MAX_SOFT_HOURS),
+                    soft_hours => tr!(locale, "soft-hours-show", hours = hours(soft_hours)),
+                };
+                answer(&bot, &msg, reply).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.soft_hours = soft_hours)
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = match soft_hours {
+                0 => tr!(locale, "soft-hours-cleared"),
+                soft_hours => tr!(locale, "soft-hours-changed", hours = hours(soft_hours)),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed soft hours update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Texts a chat can have of its own.
+const MAX_MESSAGES: usize = 20;
+
+/// The chat's texts numbered, each by its first line past the `[morning]`
+/// like headers and media.
+fn format_messages(messages: &[String]) -> String {
+    messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let is_header = |line: &str| line.starts_with('[') && line.ends_with(']');
+            let line = message
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !is_header(line))
+                .unwrap_or_default();
+            match line.chars().count() > 50 {
+                true => format!(
+                    "{}. {}…",
+                    index + 1,
+                    line.chars().take(49).collect::<String>()
+                ),
+                false => format!("{}. {}", index + 1, line),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub async fn handle_add_message_command(
+    bot: Bot,
+    msg: Message,
+    message: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    let message = message.trim().to_string();
+    if message.is_empty() {
+        let reply = match settings.messages.is_empty() {
+            true => tr!(locale, "message-usage"),
+            false => tr!(
+                locale,
+                "message-list",
+                messages = format_messages(&settings.messages)
+            ),
+        };
+        answer(&bot, &msg, reply).await?;
+        return Ok(());
+    }
+    if message.chars().count() > templates::MESSAGE_LIMIT {
+        let reply = tr!(locale, "message-too-long", limit = templates::MESSAGE_LIMIT);
+        answer(&bot, &msg, reply).await?;
+        return Ok(());
+    }
+    if settings.messages.len() >= MAX_MESSAGES {
+        answer(
+            &bot,
+            &msg,
+            tr!(locale, "message-too-many", limit = MAX_MESSAGES),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.messages.push(message.clone())
+        })
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = tr!(locale, "message-added", count = settings.messages.len() + 1);
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed messages update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_remove_message_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    // Numbers as "/addmessage" lists them, `None` for all of them
+    let number = match value.trim() {
+        "all" => None,
+        value => match value
+            .parse::<usize>()
+            .ok()
+            .filter(|number| (1..=settings.messages.len()).contains(number))
+        {
+            Some(number) => Some(number),
+            None => {
+                answer(&bot, &msg, tr!(locale, "message-remove-usage")).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| match number {
+            Some(number) if number <= settings.messages.len() => {
+                settings.messages.remove(number - 1);
+            }
+            Some(_) => {}
+            None => settings.messages.clear(),
+        })
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = match number.filter(|_| settings.messages.len() > 1) {
+                Some(number) => tr!(locale, "message-removed", number = number),
+                None => tr!(locale, "message-all-removed"),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed messages update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_preset_command(
+    bot: Bot,
+    msg: Message,
+    name: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    let Some(preset) = Preset::from_name(&name) else {
+        let interval = |preset: Preset| formatting::duration(preset.interval(), locale);
+        let choices: Vec<String> = Preset::ALL
+            .iter()
+            .map(|preset| format!("/preset {}", preset.name()))
+            .collect();
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "preset-list",
+                water = interval(Preset::Water),
+                stretch = interval(Preset::Stretch),
+                eyes = interval(Preset::Eyes)
+            ),
+        )
+        .reply_markup(keyboards::choices(&choices, Preset::ALL.len()))
+        .await?;
+        return Ok(());
+    };
+
+    // Presets come along with the chat's notifications, one job for each
+    let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+    let exists = jobs.for_chat(&msg.chat.id).iter().any(
+        |job| matches!(job.kind, JobKind::Preset { preset: existing, .. } if existing == preset),
+    );
+    let reply = match (exists, preset.next_after(&settings, Utc::now())) {
+        (true, _) => tr!(locale, "preset-exists", preset = preset.name()),
+        (false, None) => tr!(locale, "error"),
+        (false, Some(due)) => {
+            match jobs.push(msg.chat.id, due, JobKind::Preset { preset, turn: 0 }) {
+                Ok(job) => {
+                    log::info!(
+                        "{} set up the {} preset, job {} at {}",
+                        msg.chat.id,
+                        preset.name(),
+                        job.id,
+                        job.due
+                    );
+                    tr!(
+                        locale,
+                        "preset-applied",
+                        preset = preset.name(),
+                        interval = formatting::duration(preset.interval(), locale)
+                    )
+                }
+                Err(err) => {
+                    log::error!("Unable to set preset for {}: {}", msg.chat.id, err);
+                    tr!(locale, "error")
+                }
+            }
+        }
+    };
+    drop(jobs);
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_parse_mode_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    // `None` follows the deployment's messages
+    let markup = match value.trim() {
+        "default" => None,
+        value => match Markup::from_name(value) {
+            Some(markup) => Some(markup),
+            None => {
+                let current = settings.markup.unwrap_or(config.parse_mode);
+                let reply = tr!(locale, "parse-mode-usage", mode = current.name());
+                answer(&bot, &msg, reply).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.markup = markup)
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let mode = markup.unwrap_or(config.parse_mode).name();
+            answer(&bot, &msg, tr!(locale, "parse-mode-changed", mode = mode)).await?;
+        }
+        Err(err) => {
+            log::error!("Failed parse mode update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_alias_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let args: Vec<String> = args
+        .split_whitespace()
+        .map(|arg| arg.trim_start_matches('/').to_lowercase())
+        .collect();
+    let (name, command) = match args.as_slice() {
+        [] => {
+            let list = aliases::BUILTIN
+                .iter()
+                .map(|(name, command)| (name.to_string(), command.to_string()))
+                .chain(settings.aliases.clone())
+                .map(|(name, command)| format!("/{} → /{}", name, command))
+                .collect::<Vec<String>>()
+                .join("\n");
+            answer(&bot, &msg, tr!(locale, "alias-list", aliases = list)).await?;
+            return Ok(());
+        }
+        [name] => (name.clone(), None),
+        [name, command] => (name.clone(), Some(command.clone())),
+        _ => {
+            answer(&bot, &msg, tr!(locale, "alias-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    let names = command_names();
+    let reserved =
+        names.contains(&name) || aliases::BUILTIN.iter().any(|(alias, _)| *alias == name);
+    if !aliases::is_valid_name(&name) || reserved {
+        answer(&bot, &msg, tr!(locale, "alias-invalid", alias = name)).await?;
+        return Ok(());
+    }
+    if let Some(command) = command.as_ref().filter(|command| !names.contains(command)) {
+        answer(
+            &bot,
+            &msg,
+            tr!(locale, "alias-unknown-command", command = command.as_str()),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    match store
+        .update(&msg.chat.id, |settings| match &command {
+            Some(command) => {
+                settings.aliases.insert(name.clone(), command.clone());
+            }
+            None => {
+                settings.aliases.remove(&name);
+            }
+        })
+        .await
+    {
+        Ok(_) => {
+            let reply = match &command {
+                Some(command) => tr!(
+                    locale,
+                    "alias-set",
+                    alias = name.as_str(),
+                    command = command.as_str()
+                ),
+                None => tr!(locale, "alias-removed", alias = name.as_str()),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!("Failed alias update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_language_command(
+    bot: Bot,
+    msg: Message,
+    code: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(
+            &bot,
+            &msg,
+            tr!(detect_locale(&msg, &config), "language-disabled"),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let code = code.trim();
+    if code.is_empty() {
+        let languages = Locale::ALL
+            .iter()
+            .map(|locale| format!("{} - {}", locale.code(), locale.name()))
+            .collect::<Vec<String>>()
+            .join("\n");
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                settings.locale,
+                "language-usage",
+                language = settings.locale.name(),
+                languages = languages
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let Some(locale) = Locale::from_code(code) else {
+        answer(
+            &bot,
+            &msg,
+            tr!(settings.locale, "language-unknown", code = code),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.locale = locale)
+        .await
+    {
+        Ok(_) => {
+            // Notification footers are rendered by the task, restart it to pick up the language
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            answer(
+                &bot,
+                &msg,
+                tr!(locale, "language-changed", language = locale.name()),
+            )
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Failed language update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_footer_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let footer = match value.trim().to_lowercase().as_str() {
+        "on" => Footer::Text,
+        "off" => Footer::Hidden,
+        _ => {
+            answer(&bot, &msg, tr!(settings.locale, "footer-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.footer = footer)
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            let reply = match footer {
+                Footer::Text => "footer-enabled",
+                Footer::Hidden => "footer-disabled",
+            };
+            answer(&bot, &msg, tr!(settings.locale, reply)).await?;
+        }
+        Err(err) => {
+            log::error!("Failed footer update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// When the daily summary comes: the end of working hours, or a minute before
+/// midnight when they last until then.
+pub fn summary_time(hours: WorkingHours) -> NaiveTime {
+    NaiveTime::from_hms_opt(hours.to, 0, 0).unwrap_or(NaiveTime::from_hms_opt(23, 59, 0).unwrap())
+}
+
+pub async fn handle_summary_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let summary = match value.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            answer(&bot, &msg, tr!(settings.locale, "summary-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    if let Err(err) = store
+        .update(&msg.chat.id, |settings| settings.summary = summary)
+        .await
+    {
+        log::error!("Failed summary update {}: {}", msg.chat.id, err);
+        answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        return Ok(());
+    }
+
+    let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+    for job in jobs.for_chat(&msg.chat.id) {
+        if job.kind == JobKind::Summary {
+            if let Err(err) = jobs.remove(job.id) {
+                log::error!(
+                    "Unable to cancel summary {} of {}: {}",
+                    job.id,
+                    msg.chat.id,
+                    err
+                );
+            }
+        }
+    }
+    let reply = match summary {
+        true => {
+            let time = summary_time(settings.working_hours);
+            let due = next_local_time(time, &settings.zone(), Utc::now());
+            match jobs.push(msg.chat.id, due, JobKind::Summary) {
+                Ok(_) => tr!(
+                    settings.locale,
+                    "summary-enabled",
+                    time = formatting::time(&time)
+                ),
+                Err(err) => {
+                    log::error!("Unable to schedule summary of {}: {}", msg.chat.id, err);
+                    tr!(settings.locale, "error")
+                }
+            }
+        }
+        false => tr!(settings.locale, "summary-disabled"),
+    };
+    drop(jobs);
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_join_profile_command(
+    bot: Bot,
+    msg: Message,
+    name: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let name = name.trim();
+    let reply = if name.is_empty() {
+        let profiles: Vec<String> = store
+            .get_profiles()
+            .await
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        tr!(
+            locale,
+            "join-profile-usage",
+            profiles = match profiles.is_empty() {
+                true => "—".to_string(),
+                false => profiles.join(", "),
+            },
+            current = settings.profile.clone().unwrap_or("—".to_string())
+        )
+    } else if name.eq_ignore_ascii_case("off") {
+        match settings.profile {
+            Some(profile) => match store.update(&msg.chat.id, profiles::leave).await {
+                Ok(_) => {
+                    restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+                    tr!(locale, "join-profile-left", name = profile)
+                }
+                Err(err) => {
+                    log::error!("Unable to leave profile {}: {}", msg.chat.id, err);
+                    tr!(locale, "error")
+                }
+            },
+            None => tr!(locale, "join-profile-none"),
+        }
+    } else {
+        match store.get_profile(name).await {
+            Some(profile) => {
+                match store
+                    .update(&msg.chat.id, |settings| profile.apply(name, settings))
+                    .await
+                {
+                    Ok(_) => {
+                        restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+                        tr!(
+                            locale,
+                            "join-profile-joined",
+                            name = name,
+                            timezone = profile.timezone,
+                            from = format_hour(profile.working_hours.from),
+                            to = format_hour(profile.working_hours.to)
+                        )
+                    }
+                    Err(err) => {
+                        log::error!("Unable to join profile {}: {}", msg.chat.id, err);
+                        tr!(locale, "error")
+                    }
+                }
+            }
+            None => tr!(locale, "profile-unknown", name = name),
+        }
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_transfer_command(
+    bot: Bot,
+    msg: Message,
+    code: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    chat_locks: Arc<ChatLocks>,
+    transfers: Arc<Transfers>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let target = msg.chat.id;
+    let locale = reply_locale(&msg, &*store, &config).await;
+    let code = code.trim();
+    if code.is_empty() {
+        let reply = match store.exists(&target).await {
+            true => {
+                let code = transfers.issue(target);
+                log::info!(target: "audit", "Chat {} issued a transfer code", target);
+                tr!(
+                    locale,
+                    "transfer-code",
+                    code = code,
+                    ttl = formatting::duration(TRANSFER_TTL, locale)
+                )
+            }
+            false => tr!(locale, "not-started"),
+        };
+        answer(&bot, &msg, reply).await?;
+        return Ok(());
+    }
+
+    let Some(source) = transfers.redeem(code) else {
+        log::info!(target: "audit", "Chat {} tried an invalid transfer code", target);
+        answer(&bot, &msg, tr!(locale, "transfer-invalid")).await?;
+        return Ok(());
+    };
+    if source == target {
+        answer(&bot, &msg, tr!(locale, "transfer-same")).await?;
+        return Ok(());
+    }
+
+    // Two chats redeeming each other's codes at once would wait for each other
+    let Ok(_guard) =
+        tokio::time::timeout(std::time::Duration::from_secs(10), chat_locks.lock(source)).await
+    else {
+        answer(&bot, &msg, tr!(locale, "error")).await?;
+        return Ok(());
+    };
+    let Some(mut settings) = store.get(&source).await else {
+        answer(&bot, &msg, tr!(locale, "transfer-invalid")).await?;
+        return Ok(());
+    };
+
+    settings.is_group = !msg.chat.is_private();
+    settings.thread_id = topic(&msg);
+
+    let mut controller = metrics::lock(&notify_controller_mutex, "notify_controller").await;
+    if let Err(err) = store.set(&target, &settings).await {
+        log::error!("Unable to transfer {} to {}: {}", source, target, err);
+        answer(&bot, &msg, tr!(locale, "error")).await?;
+        return Ok(());
+    }
+    controller.stop(&source);
+    controller.stop(&target);
+
+    // Jobs follow the settings, except timers editing messages of the old chat
+    let mut jobs = metrics::lock(&jobs_mutex, "jobs").await;
+    let mut paused = false;
+    for job in jobs.for_chat(&target) {
+        if let Err(err) = jobs.remove(job.id) {
+            log::error!("Unable to drop job {} of {}: {}", job.id, target, err);
+        }
+    }
+    for job in jobs.for_chat(&source) {
+        if let Err(err) = jobs.remove(job.id) {
+            log::error!("Unable to move job {} of {}: {}", job.id, source, err);
+            continue;
+        }
+        if let JobKind::Timer { .. } = job.kind {
+            continue;
+        }
+        paused |= JobKind::RESUMING.contains(&job.kind);
+        if let Err(err) = jobs.push(target, job.due, job.kind) {
+            log::error!("Unable to move job {} to {}: {}", job.id, target, err);
+        }
+    }
+    drop(jobs);
+
+    if let Err(err) = store.rem(&source).await {
+        log::error!("Unable to remove transferred chat {}: {}", source, err);
+    }
+    controller.set_topic(&target, settings.thread_id);
+    if !paused {
+        controller.start(&target, &settings, false);
+    }
+    drop(controller);
+
+    log::info!(target: "audit", "Moved settings of {} to {}", source, target);
+    answer(&bot, &msg, tr!(settings.locale, "transfer-done")).await?;
+    // The old chat may be gone already, e.g. a deleted account
+    if let Err(err) = bot
+        .send_message(
+            source,
+            tr!(settings.locale, "transfer-moved", chat = target.to_string()),
+        )
+        .await
+    {
+        log::debug!("Unable to tell {} about the transfer: {}", source, err);
+    }
+
+    Ok(())
+}
+
+pub async fn handle_whats_new_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let release_notes = match value.trim().to_lowercase().as_str() {
+        "" => {
+            let versions = release_notes::unseen(None);
+            answer(
+                &bot,
+                &msg,
+                release_notes::message(&versions, settings.locale),
+            )
+            .await?;
+            return Ok(());
+        }
+        "on" => true,
+        "off" => false,
+        _ => {
+            answer(&bot, &msg, tr!(settings.locale, "whats-new-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    let reply = match store
+        .update(&msg.chat.id, |settings| {
+            settings.release_notes = release_notes
+        })
+        .await
+    {
+        Ok(_) if release_notes => tr!(settings.locale, "whats-new-enabled"),
+        Ok(_) => tr!(settings.locale, "whats-new-disabled"),
+        Err(err) => {
+            log::error!("Failed release notes update {}: {}", msg.chat.id, err);
+            tr!(settings.locale, "error")
+        }
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// What "/channels" was asked to do.
+#[derive(Debug, PartialEq)]
+enum ChannelsChange {
+    Show,
+    /// Where a channel delivers, unset to stop using it
+    Address(String, Option<String>),
+    Order(Vec<String>),
+}
+
+/// Parses the arguments of "/channels", an error is the id of the reply and
+/// the channel it's about.
+fn parse_channels(
+    args: &str,
+    channels: &Channels,
+    settings: &UserSettings,
+) -> Result<ChannelsChange, (&'static str, String)> {
+    let words: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+    let Some(first) = words.first().map(|word| word.to_lowercase()) else {
+        return Ok(ChannelsChange::Show);
+    };
+
+    // A channel followed by anything but another channel sets its address
+    if let (Some(channel), [_, address]) = (channels.get(&first), words.as_slice()) {
+        if !channels.names().contains(&address.to_lowercase().as_str()) {
+            if address.eq_ignore_ascii_case("off") {
+                return Ok(ChannelsChange::Address(first, None));
+            }
+            return match channel.parse_address(address) {
+                Some(address) => Ok(ChannelsChange::Address(first, Some(address))),
+                None => Err(("channels-invalid-address", first)),
+            };
+        }
+    }
+
+    let mut order = vec![];
+    for name in words.iter().map(|word| word.to_lowercase()) {
+        if name != channels::TELEGRAM {
+            if channels.get(&name).is_none() {
+                return Err(("channels-unknown", name));
+            }
+            if !settings.channel_addresses.contains_key(&name) {
+                return Err(("channels-no-address", name));
+            }
+        }
+        if !order.contains(&name) {
+            order.push(name);
+        }
+    }
+    Ok(ChannelsChange::Order(order))
+}
+
+fn format_channels(channels: &Channels, chat_id: ChatId, settings: &UserSettings) -> String {
+    let locale = settings.locale;
+    let lines: Vec<String> = channels
+        .order(settings)
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let status = channels.status(chat_id, name);
+            let status = match (status.delivered, status.failed) {
+                (delivered, Some((failed, reason)))
+                    if delivered.is_none_or(|delivered| delivered < failed) =>
+                {
+                    tr!(
+                        locale,
+                        "channels-failed",
+                        time = format_local(failed, settings),
+                        reason = reason
+                    )
+                }
+                (Some(delivered), _) => tr!(
+                    locale,
+                    "channels-delivered",
+                    time = format_local(delivered, settings)
+                ),
+                _ => tr!(locale, "channels-no-sends"),
+            };
+            format!("{}. {} — {}", index + 1, name, status)
+        })
+        .collect();
+
+    tr!(
+        locale,
+        "channels-list",
+        channels = lines.join("\n"),
+        available = channels.names().join(", ")
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_channels_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    channels: Arc<Channels>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let change = match parse_channels(&args, &channels, &settings) {
+        Ok(ChannelsChange::Show) => {
+            let reply = format_channels(&channels, msg.chat.id, &settings);
+            answer(&bot, &msg, reply).await?;
+            return Ok(());
+        }
+        Ok(change) => change,
+        Err((reply, channel)) => {
+            answer(&bot, &msg, tr!(settings.locale, reply, channel = channel)).await?;
+            return Ok(());
+        }
+    };
+
+    let updated = store
+        .update(&msg.chat.id, |settings| match &change {
+            ChannelsChange::Address(name, Some(address)) => {
+                settings
+                    .channel_addresses
+                    .insert(name.clone(), address.clone());
+                // New channels are tried after the ones the chat has
+                if settings.channels.is_empty() {
+                    settings.channels.push(channels::TELEGRAM.to_string());
+                }
+                if !settings.channels.contains(name) {
+                    settings.channels.push(name.clone());
+                }
+            }
+            ChannelsChange::Address(name, None) => {
+                settings.channel_addresses.remove(name);
+                settings.channels.retain(|channel| channel != name);
+            }
+            ChannelsChange::Order(order) => settings.channels = order.clone(),
+            ChannelsChange::Show => {}
+        })
+        .await;
+    let reply = match (updated, &change) {
+        (Err(err), _) => {
+            log::error!("Failed channels update {}: {}", msg.chat.id, err);
+            tr!(settings.locale, "error")
+        }
+        (Ok(_), ChannelsChange::Address(name, address)) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = match address {
+                Some(_) => "channels-added",
+                None => "channels-removed",
+            };
+            tr!(settings.locale, reply, channel = name.clone())
+        }
+        (Ok(_), _) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            match store.get(&msg.chat.id).await {
+                Some(settings) => format_channels(&channels, msg.chat.id, &settings),
+                None => tr!(settings.locale, "not-started"),
+            }
+        }
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_connect_command(
+    bot: Bot,
+    msg: Message,
+    args: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let hook = match args.trim() {
+        "" => {
+            let reply = match settings.ack_hook {
+                Some(hook) => tr!(
+                    settings.locale,
+                    "connect-show",
+                    url = hook.url,
+                    token = if hook.token.is_some() { "yes" } else { "no" }
+                ),
+                None => tr!(settings.locale, "connect-usage"),
+            };
+            answer(&bot, &msg, reply).await?;
+            return Ok(());
+        }
+        "off" => None,
+        args => match ack_hooks::parse(args) {
+            Some(hook) => Some(hook),
+            None => {
+                answer(&bot, &msg, tr!(settings.locale, "connect-invalid")).await?;
+                return Ok(());
+            }
+        },
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.ack_hook = hook.clone())
+        .await
+    {
+        Ok(_) => {
+            log::info!(
+                target: "audit",
+                "{} {} the acknowledgement hook",
+                msg.chat.id,
+                if hook.is_some() { "set" } else { "removed" }
+            );
+            let reply = match hook {
+                Some(hook) => tr!(settings.locale, "connect-set", url = hook.url),
+                None => tr!(settings.locale, "connect-removed"),
+            };
+            answer(&bot, &msg, reply).await?;
+        }
+        Err(err) => {
+            log::error!(
+                "Failed acknowledgement hook update {}: {}",
+                msg.chat.id,
+                err
+            );
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use crate::{
+        channels::{Channels, Slack},
+        handlers::settings::{format_messages, parse_channels, parse_quiet_hours, ChannelsChange},
+        offsets_rep::{QuietHours, UserSettings},
+    };
+
+    #[test]
+    fn test_format_messages() {
+        let messages = [
+            "\n[morning]\nGood morning, stretch\n[evening]\nGood evening".to_string(),
+            "a".repeat(60),
+        ];
+        assert_eq!(
+            format_messages(&messages),
+            format!("1. Good morning, stretch\n2. {}…", "a".repeat(49))
+        );
+        assert_eq!(format_messages(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_quiet_hours() {
+        let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+        assert_eq!(
+            parse_quiet_hours("16:00-16:30, 13:00–14:00"),
+            Some(vec![
+                QuietHours {
+                    from: time(13, 0),
+                    to: time(14, 0),
+                },
+                QuietHours {
+                    from: time(16, 0),
+                    to: time(16, 30),
+                },
+            ])
+        );
+        assert_eq!(parse_quiet_hours("14:00-13:00"), None);
+        assert_eq!(parse_quiet_hours("13:00-14:00,"), None);
+        assert_eq!(parse_quiet_hours(""), None);
+        assert_eq!(parse_quiet_hours(&["10:00-10:15"; 6].join(",")), None);
+    }
+
+    #[test]
+    fn test_parse_channels() {
+        let channels = Channels::new().register(Slack::new());
+        let mut settings = UserSettings::default();
+        let hook = "https://hooks.slack.com/services/T0/B0/x";
+
+        assert_eq!(
+            parse_channels("", &channels, &settings),
+            Ok(ChannelsChange::Show)
+        );
+        assert_eq!(
+            parse_channels(&format!("Slack {}", hook), &channels, &settings),
+            Ok(ChannelsChange::Address(
+                "slack".to_string(),
+                Some(hook.to_string())
+            ))
+        );
+        assert_eq!(
+            parse_channels("slack https://example.com", &channels, &settings),
+            Err(("channels-invalid-address", "slack".to_string()))
+        );
+        assert_eq!(
+            parse_channels("slack off", &channels, &settings),
+            Ok(ChannelsChange::Address("slack".to_string(), None))
+        );
+        assert_eq!(
+            parse_channels("slack telegram", &channels, &settings),
+            Err(("channels-no-address", "slack".to_string()))
+        );
+        assert_eq!(
+            parse_channels("email telegram", &channels, &settings),
+            Err(("channels-unknown", "email".to_string()))
+        );
+
+        settings
+            .channel_addresses
+            .insert("slack".to_string(), hook.to_string());
+        assert_eq!(
+            parse_channels("slack telegram slack", &channels, &settings),
+            Ok(ChannelsChange::Order(vec![
+                "slack".to_string(),
+                "telegram".to_string()
+            ]))
+        );
+    }
+}
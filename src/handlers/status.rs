@@ -0,0 +1,413 @@
+use std::sync::Arc;
+
+use async_mutex::Mutex;
+use chrono::{Datelike, Local, Utc};
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::{
+    bot::Bot,
+    config::Config,
+    dialogues::{MyDialogue, PREVIEW_COUNT},
+    formatting,
+    handlers::{answer, detect_locale, format_hour, format_local, reply_locale, HandlerResult},
+    i18n::Locale,
+    insights::{self, HourHistogram, Suggestion},
+    jobs::{JobKind, JobQueue},
+    journal::{Entry, Journal},
+    metrics,
+    notify_controller::{upcoming_notifications, NotificationSender},
+    offsets_rep::UserSettings,
+    stats,
+    store::UserStore,
+    tr,
+};
+
+/// How many of the latest sends "/history" lists
+const HISTORY_LISTED: usize = 10;
+
+pub async fn handle_status_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(
+            &bot,
+            &msg,
+            tr!(detect_locale(&msg, &config), "status-stopped"),
+        )
+        .await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let running = metrics::lock(&notify_controller_mutex, "notify_controller")
+        .await
+        .is_running(&msg.chat.id);
+    let resume = metrics::lock(&jobs_mutex, "jobs")
+        .await
+        .for_chat(&msg.chat.id)
+        .into_iter()
+        .find(|job| JobKind::RESUMING.contains(&job.kind));
+
+    let (state, until) = match (running, resume) {
+        (true, _) if settings.away => ("away", None),
+        (true, _) => ("running", None),
+        (false, Some(job)) => (job.kind.name(), Some(format_local(job.due, &settings))),
+        (false, None) => ("paused", None),
+    };
+    let next = match running && !settings.away {
+        true => upcoming_notifications(&settings, &config.blackouts, Utc::now(), 1)
+            .first()
+            .map(|next| format_local(*next, &settings))
+            .unwrap_or_else(|| "—".to_string()),
+        false => "—".to_string(),
+    };
+
+    answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "status-view",
+            state = state,
+            until = until.unwrap_or_default(),
+            next = next,
+            timezone = settings.timezone_label(),
+            from = format_hour(settings.working_hours.from),
+            to = format_hour(settings.working_hours.to),
+            interval = formatting::duration(settings.interval(), locale)
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Renders a moment in the user's timezone, with the weekday unless it's today.
+pub async fn handle_next_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let now = Utc::now();
+    let dates: Vec<String> =
+        upcoming_notifications(&settings, &config.blackouts, now, PREVIEW_COUNT)
+            .into_iter()
+            .map(|date| format_local(date, &settings))
+            .collect();
+    let mut reply = match dates.is_empty() {
+        true => tr!(locale, "next-none"),
+        false => tr!(locale, "next-list", dates = dates.join("\n")),
+    };
+    // Blackouts ahead explain gaps in the schedule
+    let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+    for blackout in config
+        .blackouts
+        .iter()
+        .filter(|blackout| blackout.to >= today)
+    {
+        let reason = match blackout.reason.is_empty() {
+            true => tr!(locale, "next-blackout-reason"),
+            false => blackout.reason.clone(),
+        };
+        reply.push_str("\n\n");
+        reply.push_str(&tr!(
+            locale,
+            "next-blackout",
+            from = blackout.from.to_string(),
+            to = blackout.to.to_string(),
+            reason = reason
+        ));
+    }
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Shows how the bot sees the chat's time, for telling a wrong timezone
+/// from a bug.
+pub async fn handle_what_time_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let now = Utc::now();
+    let local = now.with_timezone(&settings.offset_at(now));
+    let next = match upcoming_notifications(&settings, &config.blackouts, now, 1).first() {
+        Some(next) => tr!(
+            locale,
+            "time-audit-next",
+            date = format_local(*next, &settings),
+            utc = next.format("%Y-%m-%d %H:%M UTC").to_string(),
+            until = formatting::duration((*next - now).to_std().unwrap_or_default(), locale)
+        ),
+        None => "—".to_string(),
+    };
+
+    answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "time-audit",
+            local = format!(
+                "{} {}, {}",
+                local.format("%Y-%m-%d"),
+                formatting::time(&local.time()),
+                formatting::weekday(local.weekday(), locale)
+            ),
+            utc = now.format("%Y-%m-%d %H:%M UTC").to_string(),
+            server = Local::now().format("%Y-%m-%d %H:%M %:z").to_string(),
+            timezone = settings.timezone_label(),
+            offset = formatting::offset(&settings.stored_offset()),
+            next = next
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn format_history(entries: &[Entry], settings: &UserSettings) -> String {
+    let locale = settings.locale;
+    if entries.is_empty() {
+        return tr!(locale, "history-empty");
+    }
+
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let status = match &entry.error {
+                None => tr!(locale, "history-delivered"),
+                Some(error) => tr!(locale, "history-failed", error = error.clone()),
+            };
+            format!(
+                "{} — {}: {}",
+                format_local(entry.at, settings),
+                entry.channel,
+                status
+            )
+        })
+        .collect();
+    tr!(locale, "history-list", sends = lines.join("\n"))
+}
+
+pub async fn handle_history_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    journal: Arc<Journal>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let reply = match journal.recent(msg.chat.id, HISTORY_LISTED) {
+        Ok(entries) => format_history(&entries, &settings),
+        Err(err) => {
+            log::error!("Unable to read the history of {}: {}", msg.chat.id, err);
+            tr!(settings.locale, "error")
+        }
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_mystats_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+    let locale = settings.locale;
+
+    let now = Utc::now();
+    let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+    let days = store
+        .daily_stats(&msg.chat.id, today - stats::LOOKBACK)
+        .await;
+    let summary = stats::summarize(&days, today);
+    let average = match summary.average_done_after {
+        Some(average) => formatting::duration(average, locale),
+        None => tr!(locale, "mystats-no-average"),
+    };
+    answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "mystats",
+            week = summary.week_sent,
+            month = summary.month_sent,
+            average = average,
+            streak = summary.streak
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub fn format_insights(histogram: &HourHistogram, locale: Locale) -> String {
+    match histogram.peak() {
+        Some(peak) => tr!(
+            locale,
+            "insights-view",
+            total = histogram.total(),
+            peak = format_hour(peak),
+            chart = formatting::hour_chart(histogram)
+        ),
+        None => tr!(locale, "insights-empty"),
+    }
+}
+
+pub async fn handle_insights_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let reply = match store.get(&msg.chat.id).await {
+        Some(settings) => format_insights(&settings.done_hours, settings.locale),
+        None => tr!(detect_locale(&msg, &config), "not-started"),
+    };
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+pub async fn handle_all_insights_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let locale = reply_locale(&msg, &*store, &config).await;
+
+    let mut histogram = HourHistogram::default();
+    let mut chats = 0;
+    for (_, settings) in store.get_all().await {
+        if !settings.done_hours.is_empty() {
+            histogram.merge(&settings.done_hours);
+            chats += 1;
+        }
+    }
+
+    answer(
+        &bot,
+        &msg,
+        tr!(
+            locale,
+            "insights-all",
+            chats = chats,
+            insights = format_insights(&histogram, locale)
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn handle_suggest_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let locale = settings.locale;
+    if settings.done_hours.total() < insights::MIN_SAMPLES {
+        answer(
+            &bot,
+            &msg,
+            tr!(
+                locale,
+                "suggest-not-enough",
+                total = settings.done_hours.total(),
+                needed = insights::MIN_SAMPLES
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let hours = settings.working_hours;
+    match insights::suggest(&settings.done_hours, hours.from, hours.to) {
+        Some(suggestion @ Suggestion::Window { from, to }) => {
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    locale,
+                    "suggest-window",
+                    from = format_hour(hours.from),
+                    to = format_hour(hours.to),
+                    new_from = format_hour(from),
+                    new_to = format_hour(to)
+                ),
+            )
+            .reply_markup(InlineKeyboardMarkup::new([[
+                InlineKeyboardButton::callback(tr!(locale, "suggest-apply"), suggestion.encode()),
+            ]]))
+            .await?;
+        }
+        None => {
+            answer(&bot, &msg, tr!(locale, "suggest-nothing")).await?;
+        }
+    }
+
+    Ok(())
+}
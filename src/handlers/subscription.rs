@@ -0,0 +1,798 @@
+use std::sync::Arc;
+
+use async_mutex::Mutex;
+use chrono::{TimeZone, Timelike, Utc};
+use teloxide::{prelude::*, types::KeyboardRemove};
+use tokio::spawn;
+
+use crate::{
+    ack_hooks::{self, AckEvent},
+    bot::Bot,
+    config::Config,
+    dialogues::{start_onboarding, MyDialogue, Onboarding},
+    formatting,
+    handlers::{
+        answer, cancel_reminders, cancel_wake_up, detect_locale, format_hour, format_local,
+        is_chat_admin, is_privileged, reply_locale, restart_with_stored, topic, wake_up_tomorrow,
+        HandlerResult,
+    },
+    i18n::Locale,
+    jobs::{Job, JobKind, JobQueue},
+    keyboards::{self, StopButton},
+    metrics,
+    notify_controller::{NotificationSender, StartEnum, ACK_INTERVAL},
+    offsets_rep::UserSettings,
+    release_notes,
+    store::{self, UserStore},
+    tr,
+};
+
+/// Adds the chat to the repository if it's new and schedules its
+/// notifications, in the forum topic `thread_id` of groups. A new chat takes
+/// the `onboarding` answers.
+#[allow(clippy::too_many_arguments)]
+pub async fn subscribe(
+    store: &dyn UserStore,
+    notify_controller: &mut NotificationSender<Bot>,
+    chat_id: &ChatId,
+    is_group: bool,
+    thread_id: Option<i32>,
+    locale: Locale,
+    config: &Config,
+    onboarding: Option<&Onboarding>,
+) -> store::Result<(UserSettings, StartEnum)> {
+    let added = !store.exists(chat_id).await;
+    if added {
+        log::debug!("Adding user {}", chat_id);
+        store.add(chat_id, locale).await?;
+        log::info!("Added user in repo: {}", chat_id);
+    } else {
+        log::debug!("User already exist {}", chat_id);
+    }
+    store
+        .update(chat_id, |settings| {
+            settings.is_group = is_group;
+            settings.thread_id = thread_id;
+            // New chats have nothing to catch up on
+            if added {
+                settings.seen_version = Some(release_notes::VERSION.to_string());
+                settings.working_hours = config.working_hours;
+                if let Some(onboarding) = onboarding {
+                    onboarding.apply(settings);
+                }
+            }
+        })
+        .await?;
+
+    let settings = store
+        .get(chat_id)
+        .await
+        .ok_or("settings are missing right after adding")?;
+    let send_immediately = settings.first_send.unwrap_or(config.first_send_on_start);
+    let started = notify_controller.start(chat_id, &settings, send_immediately);
+
+    Ok((settings, started))
+}
+
+pub async fn handle_start_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    if !is_chat_admin(&bot, &msg).await {
+        let locale = reply_locale(&msg, &*store, &config).await;
+        answer(&bot, &msg, tr!(locale, "group-admins-only")).await?;
+        return Ok(());
+    }
+
+    // New chats answer a few questions first
+    if !store.exists(&msg.chat.id).await {
+        log::info!("Onboarding {}", msg.chat.id);
+        return start_onboarding(&bot, &msg, &dialogue, detect_locale(&msg, &config)).await;
+    }
+
+    start_notifications(
+        &bot,
+        &msg,
+        &*store,
+        &notify_controller_mutex,
+        &jobs_mutex,
+        &config,
+        None,
+    )
+    .await
+}
+
+/// Subscribes the chat that sent `msg` and tells when notifications come,
+/// with the answers of a new chat's `onboarding`.
+pub async fn start_notifications(
+    bot: &Bot,
+    msg: &Message,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    jobs_mutex: &Mutex<JobQueue>,
+    config: &Config,
+    onboarding: Option<&Onboarding>,
+) -> HandlerResult {
+    let mut notify_controller = metrics::lock(notify_controller_mutex, "notify_controller").await;
+    cancel_wake_up(jobs_mutex, &msg.chat.id).await;
+
+    let (settings, started) = match subscribe(
+        store,
+        &mut notify_controller,
+        &msg.chat.id,
+        !msg.chat.is_private(),
+        topic(msg),
+        detect_locale(msg, config),
+        config,
+        onboarding,
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(err) => {
+            log::error!("Failed to add {} user {}", err, msg.chat.id);
+            answer(bot, msg, tr!(detect_locale(msg, config), "error")).await?;
+            return Ok(());
+        }
+    };
+
+    let mut reply = match started {
+        StartEnum::Added => tr!(
+            settings.locale,
+            "start-started",
+            timezone = settings.timezone_label(),
+            from = format_hour(settings.working_hours.from),
+            to = format_hour(settings.working_hours.to)
+        ),
+        StartEnum::AlreadyExist => tr!(settings.locale, "start-already-started"),
+    };
+    let next = notify_controller.next_send(&msg.chat.id, &settings);
+    drop(notify_controller);
+    match next {
+        Some(next) if next - Utc::now() < chrono::Duration::minutes(1) => {
+            reply.push_str(&format!("\n\n{}", tr!(settings.locale, "start-next-now")));
+        }
+        Some(next) => reply.push_str(&format!(
+            "\n\n{}",
+            tr!(
+                settings.locale,
+                "start-next",
+                next = format_local(next, &settings)
+            )
+        )),
+        None => {}
+    }
+    let request = answer(bot, msg, reply);
+    match onboarding {
+        // Hides the answers of the last question
+        Some(_) => request.reply_markup(KeyboardRemove::new()).await?,
+        None => request.await?,
+    };
+    Ok(())
+}
+
+/// Reminders "/stop" offers to stop one by one, the earliest ones.
+const MAX_STOP_CHOICES: usize = 10;
+/// Characters of a reminder's text shown on its "/stop" button.
+const LABEL_LIMIT: usize = 32;
+
+/// How a reminder shows up on its "/stop" button, what it says and when it
+/// comes next.
+fn reminder_label(job: &Job, settings: &UserSettings) -> String {
+    let locale = settings.locale;
+    let (text, due) = match &job.kind {
+        JobKind::Timer { text, ends, .. } => (text.clone(), *ends),
+        JobKind::Reminder { text, .. }
+        | JobKind::Report { text, .. }
+        | JobKind::Weekly { text, .. }
+        | JobKind::Monthly { text, .. } => (text.clone(), job.due),
+        JobKind::Deadline { title, .. } => (title.clone(), job.due),
+        JobKind::Summary => (tr!(locale, "stop-reminder-summary"), job.due),
+        JobKind::Pomodoro(_) => (tr!(locale, "stop-reminder-pomodoro"), job.due),
+        JobKind::Preset { preset, .. } => (
+            tr!(locale, "stop-reminder-preset", preset = preset.name()),
+            job.due,
+        ),
+        kind => (kind.name().to_string(), job.due),
+    };
+    let text = match text.chars().count() > LABEL_LIMIT {
+        true => format!(
+            "{}…",
+            text.chars().take(LABEL_LIMIT - 1).collect::<String>()
+        ),
+        false => text,
+    };
+    format!("{} · {}", text, format_local(due, settings))
+}
+
+pub async fn handle_stop_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let locale = reply_locale(&msg, &*store, &config).await;
+    if !is_chat_admin(&bot, &msg).await {
+        answer(&bot, &msg, tr!(locale, "group-admins-only")).await?;
+        return Ok(());
+    }
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(locale, "stop-nothing")).await?;
+        return Ok(());
+    };
+
+    // With reminders set, one of them can be stopped instead of everything
+    let reminders: Vec<(u64, String)> = metrics::lock(&jobs_mutex, "jobs")
+        .await
+        .for_chat(&msg.chat.id)
+        .into_iter()
+        .filter(|job| job.kind.is_reminder())
+        .take(MAX_STOP_CHOICES)
+        .map(|job| (job.id, reminder_label(&job, &settings)))
+        .collect();
+    match reminders.is_empty() {
+        true => {
+            answer(&bot, &msg, tr!(locale, "stop-confirm"))
+                .reply_markup(keyboards::stop_confirmation(locale))
+                .await?
+        }
+        false => {
+            answer(&bot, &msg, tr!(locale, "stop-choose"))
+                .reply_markup(keyboards::stop_choice(locale, &reminders))
+                .await?
+        }
+    };
+
+    Ok(())
+}
+
+/// How long settings removed with "/stop" can be restored.
+const STOP_UNDO_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+
+/// Removes the chat and its notifications, keeping its settings for
+/// `STOP_UNDO_WINDOW`. Returns the reply and whether the chat was removed.
+pub async fn stop(
+    chat_id: &ChatId,
+    locale: Locale,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    jobs_mutex: &Mutex<JobQueue>,
+) -> (String, bool) {
+    match store.trash(chat_id).await {
+        Ok(true) => {
+            let mut notify_controller =
+                metrics::lock(notify_controller_mutex, "notify_controller").await;
+            notify_controller.stop(chat_id);
+            cancel_wake_up(jobs_mutex, chat_id).await;
+            cancel_reminders(jobs_mutex, chat_id).await;
+
+            let mut jobs = metrics::lock(jobs_mutex, "jobs").await;
+            let purge = jobs
+                .cancel(chat_id, JobKind::Purge)
+                .and_then(|_| jobs.push(*chat_id, Utc::now() + STOP_UNDO_WINDOW, JobKind::Purge));
+            if let Err(err) = purge {
+                log::error!("Unable to schedule purge of {}: {}", chat_id, err);
+            }
+
+            (tr!(locale, "stop-stopped"), true)
+        }
+        Ok(false) => (tr!(locale, "stop-nothing"), false),
+        Err(err) => {
+            log::error!("Unable to remove user {}: {}", chat_id, err);
+            (tr!(locale, "error"), false)
+        }
+    }
+}
+
+/// Brings back settings removed with "/stop" and their notifications,
+/// returns the reply.
+async fn undo_stop(
+    chat_id: &ChatId,
+    locale: Locale,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    jobs_mutex: &Mutex<JobQueue>,
+) -> String {
+    match store.restore(chat_id).await {
+        Ok(true) => {
+            if let Err(err) = metrics::lock(jobs_mutex, "jobs")
+                .await
+                .cancel(chat_id, JobKind::Purge)
+            {
+                log::error!("Unable to cancel purge of {}: {}", chat_id, err);
+            }
+            let Some(settings) = store.get(chat_id).await else {
+                return tr!(locale, "error");
+            };
+            let mut notify_controller =
+                metrics::lock(notify_controller_mutex, "notify_controller").await;
+            notify_controller.stop(chat_id);
+            notify_controller.start(chat_id, &settings, false);
+
+            tr!(settings.locale, "stop-restored")
+        }
+        Ok(false) => tr!(locale, "stop-undo-expired"),
+        Err(err) => {
+            log::error!("Unable to restore user {}: {}", chat_id, err);
+            tr!(locale, "error")
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_stop_button(
+    bot: Bot,
+    query: CallbackQuery,
+    button: StopButton,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let Some(msg) = query.message else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+    let locale = match store.get(&msg.chat.id).await {
+        Some(settings) => settings.locale,
+        None => query
+            .from
+            .language_code
+            .as_deref()
+            .and_then(Locale::from_code)
+            .unwrap_or(config.default_locale),
+    };
+
+    if !msg.chat.is_private() && !is_privileged(&bot, msg.chat.id, query.from.id).await {
+        bot.answer_callback_query(query.id)
+            .text(tr!(locale, "group-admins-only"))
+            .await?;
+        return Ok(());
+    }
+    bot.answer_callback_query(query.id).await?;
+    log::info!("{} pressed {:?} after \"/stop\"", msg.chat.id, button);
+
+    match button {
+        StopButton::Confirm => {
+            let (reply, stopped) = stop(
+                &msg.chat.id,
+                locale,
+                &*store,
+                &notify_controller_mutex,
+                &jobs_mutex,
+            )
+            .await;
+            match stopped {
+                true => {
+                    let text = format!("{}\n{}", reply, tr!(locale, "stop-undo-hint"));
+                    bot.edit_message_text(msg.chat.id, msg.id, text)
+                        .reply_markup(keyboards::stop_undo(locale))
+                        .await?
+                }
+                false => bot.edit_message_text(msg.chat.id, msg.id, reply).await?,
+            }
+        }
+        StopButton::Cancel => {
+            bot.edit_message_text(msg.chat.id, msg.id, tr!(locale, "stop-cancelled"))
+                .await?
+        }
+        StopButton::Reminder(id) => {
+            let settings = store.get(&msg.chat.id).await;
+            let removed = metrics::lock(&jobs_mutex, "jobs")
+                .await
+                .remove_for(&msg.chat.id, id);
+            let reply = match (removed, settings) {
+                (Ok(Some(job)), Some(settings)) => {
+                    log::info!(target: "audit", "{} stopped reminder {}", msg.chat.id, job.id);
+                    tr!(
+                        locale,
+                        "stop-reminder-stopped",
+                        reminder = reminder_label(&job, &settings)
+                    )
+                }
+                (Err(err), _) => {
+                    log::error!("Unable to stop reminder {} of {}: {}", id, msg.chat.id, err);
+                    tr!(locale, "error")
+                }
+                _ => tr!(locale, "stop-reminder-gone"),
+            };
+            bot.edit_message_text(msg.chat.id, msg.id, reply).await?
+        }
+        StopButton::Undo => {
+            let reply = undo_stop(
+                &msg.chat.id,
+                locale,
+                &*store,
+                &notify_controller_mutex,
+                &jobs_mutex,
+            )
+            .await;
+            bot.edit_message_text(msg.chat.id, msg.id, reply).await?
+        }
+    };
+
+    Ok(())
+}
+
+pub async fn handle_done_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let locale = reply_locale(&msg, &*store, &config).await;
+    let reply = done(
+        &msg.chat.id,
+        locale,
+        &*store,
+        &notify_controller_mutex,
+        &jobs_mutex,
+    )
+    .await;
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Pauses notifications of the chat until tomorrow, returns the reply.
+pub async fn done(
+    chat_id: &ChatId,
+    locale: Locale,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    jobs_mutex: &Mutex<JobQueue>,
+) -> String {
+    let mut notify_controller = metrics::lock(notify_controller_mutex, "notify_controller").await;
+    if !notify_controller.stop(chat_id) {
+        return tr!(locale, "done-nothing");
+    }
+
+    if let Err(err) = store
+        .update(chat_id, |settings| {
+            let date = settings
+                .fixed_offset()
+                .from_utc_datetime(&Utc::now().naive_utc());
+            settings.done_hours.record(date.hour());
+        })
+        .await
+    {
+        log::error!("Unable to record done hour of {}: {}", chat_id, err);
+    }
+    let Some(settings) = store.get(chat_id).await else {
+        return tr!(locale, "done-delayed");
+    };
+    let now = Utc::now();
+    let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+    if let Err(err) = store.record_done(chat_id, today, now).await {
+        log::error!("Unable to count \"/done\" of {}: {}", chat_id, err);
+    }
+    spawn(notify_controller.clear_sent(chat_id, today, locale));
+    if let Some(hook) = settings.ack_hook.clone() {
+        spawn(ack_hooks::post(hook, AckEvent::done(*chat_id, now)));
+    }
+
+    let due = wake_up_tomorrow(&settings, notify_controller.blackouts(), now);
+    match metrics::lock(jobs_mutex, "jobs")
+        .await
+        .push(*chat_id, due, JobKind::WakeUp)
+    {
+        Ok(job) => log::info!("Scheduled wake up of {} at {}", chat_id, job.due),
+        Err(err) => log::error!("Unable to schedule wake up of {}: {}", chat_id, err),
+    }
+
+    // The chat restarted at the wake up sends at the schedule's next slot,
+    // the working time starts with one
+    let resume = notify_controller
+        .next_after(&settings, due - chrono::Duration::seconds(1))
+        .map(|next| format_local(next, &settings));
+    match resume {
+        Some(resume) => tr!(locale, "done-resuming", resume = resume),
+        None => tr!(locale, "done-delayed"),
+    }
+}
+
+pub async fn handle_away_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    set_away(
+        bot,
+        msg,
+        true,
+        store,
+        notify_controller_mutex,
+        dialogue,
+        config,
+    )
+    .await
+}
+
+pub async fn handle_back_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    set_away(
+        bot,
+        msg,
+        false,
+        store,
+        notify_controller_mutex,
+        dialogue,
+        config,
+    )
+    .await
+}
+
+/// Mutes or unmutes scheduled notifications, unlike "/done" and "/stop" the
+/// schedule itself keeps going.
+async fn set_away(
+    bot: Bot,
+    msg: Message,
+    away: bool,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    if settings.away == away {
+        let reply = match away {
+            true => "away-already",
+            false => "back-already",
+        };
+        answer(&bot, &msg, tr!(settings.locale, reply)).await?;
+        return Ok(());
+    }
+
+    match store
+        .update(&msg.chat.id, |settings| settings.away = away)
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            let reply = match away {
+                true => "away-set",
+                false => "back-set",
+            };
+            answer(&bot, &msg, tr!(settings.locale, reply)).await?;
+        }
+        Err(err) => {
+            log::error!("Failed presence update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_first_send_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let first_send = match value.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            answer(&bot, &msg, tr!(settings.locale, "first-send-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.first_send = Some(first_send)
+        })
+        .await
+    {
+        Ok(_) => {
+            let reply = match first_send {
+                true => "first-send-enabled",
+                false => "first-send-disabled",
+            };
+            answer(&bot, &msg, tr!(settings.locale, reply)).await?;
+        }
+        Err(err) => {
+            log::error!("Failed first send update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_ack_mode_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let ack = match value.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            answer(&bot, &msg, tr!(settings.locale, "ack-mode-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| settings.ack = ack)
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = store.get(&msg.chat.id).await {
+                metrics::lock(&notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&msg.chat.id, &settings);
+            }
+
+            let reply = match ack {
+                true => "ack-mode-enabled",
+                false => "ack-mode-disabled",
+            };
+            answer(
+                &bot,
+                &msg,
+                tr!(
+                    settings.locale,
+                    reply,
+                    interval = formatting::duration(ACK_INTERVAL, settings.locale)
+                ),
+            )
+            .await?;
+        }
+        Err(err) => {
+            log::error!("Failed ack mode update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_single_message_command(
+    bot: Bot,
+    msg: Message,
+    value: String,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let Some(settings) = store.get(&msg.chat.id).await else {
+        answer(&bot, &msg, tr!(detect_locale(&msg, &config), "not-started")).await?;
+        return Ok(());
+    };
+
+    let single_message = match value.trim().to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            answer(&bot, &msg, tr!(settings.locale, "single-message-usage")).await?;
+            return Ok(());
+        }
+    };
+
+    match store
+        .update(&msg.chat.id, |settings| {
+            settings.single_message = single_message
+        })
+        .await
+    {
+        Ok(_) => {
+            restart_with_stored(&msg.chat.id, &*store, &notify_controller_mutex).await;
+            let reply = match single_message {
+                true => "single-message-enabled",
+                false => "single-message-disabled",
+            };
+            answer(&bot, &msg, tr!(settings.locale, reply)).await?;
+        }
+        Err(err) => {
+            log::error!("Failed single message update {}: {}", msg.chat.id, err);
+            answer(&bot, &msg, tr!(settings.locale, "error")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_ack_command(
+    bot: Bot,
+    msg: Message,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    dialogue: MyDialogue,
+    config: Arc<Config>,
+) -> HandlerResult {
+    dialogue.exit().await?;
+
+    let locale = reply_locale(&msg, &*store, &config).await;
+    let reply = acknowledge(&msg.chat.id, locale, &*store, &notify_controller_mutex).await;
+    answer(&bot, &msg, reply).await?;
+
+    Ok(())
+}
+
+/// Stops repeating the last notification in ack mode and removes the ones
+/// sent today, returns the reply.
+pub async fn acknowledge(
+    chat_id: &ChatId,
+    locale: Locale,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+) -> String {
+    // Read before locking, the controller isn't held while the store answers
+    let now = Utc::now();
+    let today = store
+        .get(chat_id)
+        .await
+        .map(|settings| now.with_timezone(&settings.offset_at(now)).date_naive());
+
+    let notify_controller = metrics::lock(notify_controller_mutex, "notify_controller").await;
+    if !notify_controller.ack(chat_id) {
+        return tr!(locale, "ack-nothing");
+    }
+
+    if let Some(today) = today {
+        spawn(notify_controller.clear_sent(chat_id, today, locale));
+    }
+    tr!(locale, "ack-done")
+}
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use serde::Serialize;
+use teloxide::requests::Requester;
+
+use crate::{
+    offsets_rep::OffsetsRepository,
+    store::{self, UserStore},
+};
+
+/// What "/healthz" and "--healthcheck" report, each check is "ok" or why
+/// it failed.
+#[derive(Serialize)]
+pub struct Health {
+    pub healthy: bool,
+    /// Settings of subscribed chats can be read
+    pub store: String,
+    /// Telegram accepts the bot token
+    pub telegram: String,
+}
+
+/// Checks the running bot's store and token.
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+pub async fn check<B: Requester>(bot: &B, store: &dyn UserStore) -> Health {
+    report(store.check().await.map_err(|err| err.to_string()), bot).await
+}
+
+/// Checks the store file and the token for another process, e.g. Docker's
+/// HEALTHCHECK, without creating anything.
+pub async fn self_test<B: Requester>(bot: &B, backend: store::Backend, path: &Path) -> Health {
+    let store = match backend {
+        store::Backend::Sqlite => check_file(path).await,
+        // Lives in the bot's process only
+        store::Backend::Memory => Ok(()),
+    };
+    report(store, bot).await
+}
+
+async fn check_file(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("{} not found", path.display()));
+    }
+    let rep = OffsetsRepository::open(path).map_err(|err| err.to_string())?;
+    rep.check().await.map_err(|err| err.to_string())
+}
+
+async fn report<B: Requester>(store: Result<(), String>, bot: &B) -> Health {
+    let telegram = bot
+        .get_me()
+        .await
+        .map(|_| ())
+        .map_err(|err| err.to_string());
+    if let Err(err) = &store {
+        log::error!("Health check of the store failed: {}", err);
+    }
+    if let Err(err) = &telegram {
+        log::error!("Health check of the bot token failed: {}", err);
+    }
+
+    Health {
+        healthy: store.is_ok() && telegram.is_ok(),
+        store: status(store),
+        telegram: status(telegram),
+    }
+}
+
+fn status(result: Result<(), String>) -> String {
+    result.err().unwrap_or_else(|| "ok".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{health::check_file, offsets_rep::OffsetsRepository};
+
+    #[tokio::test]
+    async fn test_check_file() {
+        let path = std::env::temp_dir().join("notification_bot_test_health.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        assert!(check_file(&path).await.is_err());
+
+        std::fs::write(&path, "not a database").unwrap();
+        assert!(check_file(&path).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        OffsetsRepository::open(&path).unwrap();
+        assert_eq!(check_file(&path).await, Ok(()));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,395 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use async_mutex::Mutex;
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use teloxide::types::{ChatId, Update};
+use url::Url;
+
+use crate::{
+    alerts,
+    bot::Bot,
+    chat_locks::ChatLocks,
+    delivery::Priority,
+    health,
+    maintenance::Maintenance,
+    metrics,
+    notify_controller::NotificationSender,
+    store::UserStore,
+    templates,
+    webhook::{self, Inbox},
+};
+
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Clone)]
+struct AppState {
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    chat_locks: Arc<ChatLocks>,
+    maintenance: Arc<Maintenance>,
+    webhook: Option<Inbox>,
+    api_token: Option<String>,
+    /// Asks Telegram every time, unlike the bot's cached "getMe"
+    bot: teloxide::Bot,
+}
+
+/// A message pushed by "/notify/{chat_id}" or "/broadcast".
+#[derive(Deserialize)]
+struct Push {
+    /// The chat's usual notification when unset
+    text: Option<String>,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default)]
+    silent: bool,
+}
+
+/// A new secret for "POST /trigger/{token}".
+pub fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Serves the HTTP API until the process exits, with Telegram's updates
+/// going to `webhook` when the bot doesn't poll for them.
+#[allow(clippy::too_many_arguments)]
+pub async fn serve(
+    addr: SocketAddr,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    chat_locks: Arc<ChatLocks>,
+    maintenance: Arc<Maintenance>,
+    webhook: Option<Inbox>,
+    api_token: Option<String>,
+    bot: teloxide::Bot,
+) -> std::io::Result<()> {
+    let app = Router::new()
+        .route("/trigger/:token", post(trigger))
+        .route("/hook/:token", post(hook))
+        .route("/presence/:token/:presence", post(presence))
+        .route("/metrics", get(export_metrics))
+        .route("/healthz", get(healthz))
+        .route("/telegram", post(telegram))
+        .route("/notify/:chat_id", post(notify))
+        .route("/broadcast", post(broadcast))
+        .with_state(AppState {
+            store,
+            notify_controller_mutex,
+            chat_locks,
+            maintenance,
+            webhook,
+            api_token,
+            bot,
+        });
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("HTTP API listening on {}", addr);
+    axum::serve(listener, app).await
+}
+
+/// Sends the token owner's notification right away.
+async fn trigger(State(state): State<AppState>, Path(token): Path<String>) -> StatusCode {
+    let Some((chat_id, settings)) = state.store.find_by_token(&token).await else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    log::info!("Notification for {} triggered over HTTP", chat_id);
+    let notification = metrics::lock(&state.notify_controller_mutex, "notify_controller")
+        .await
+        .notify(&chat_id, &settings);
+    respond(notification.await)
+}
+
+/// Whether the request carries the operator's token, the routes needing it
+/// are off without one.
+fn is_operator(api_token: Option<&str>, headers: &HeaderMap) -> Option<bool> {
+    let token = api_token?;
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    Some(bearer == Some(token))
+}
+
+/// Sends a message, or the usual notification, to a chat for an external
+/// system such as CI or monitoring.
+async fn notify(
+    State(state): State<AppState>,
+    Path(chat_id): Path<i64>,
+    headers: HeaderMap,
+    push: Option<Json<Push>>,
+) -> StatusCode {
+    match is_operator(state.api_token.as_deref(), &headers) {
+        None => return StatusCode::NOT_FOUND,
+        Some(false) => return StatusCode::UNAUTHORIZED,
+        Some(true) => {}
+    }
+
+    let chat_id = ChatId(chat_id);
+    let Some(settings) = state.store.get(&chat_id).await else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    log::info!(target: "audit", "Notification for {} pushed over HTTP", chat_id);
+    let delivered = match push {
+        Some(Json(Push {
+            text: Some(text),
+            priority,
+            silent,
+        })) => {
+            let relayed = metrics::lock(&state.notify_controller_mutex, "notify_controller")
+                .await
+                .relay(&chat_id, templates::truncate(&text), priority, silent);
+            relayed.await
+        }
+        _ => {
+            let notification = metrics::lock(&state.notify_controller_mutex, "notify_controller")
+                .await
+                .notify(&chat_id, &settings);
+            notification.await
+        }
+    };
+    respond(delivered)
+}
+
+/// Sends a message to every chat, only high priority ones reach chats that
+/// are away.
+async fn broadcast(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(push): Json<Push>,
+) -> (StatusCode, Json<Value>) {
+    match is_operator(state.api_token.as_deref(), &headers) {
+        None => return (StatusCode::NOT_FOUND, Json(Value::Null)),
+        Some(false) => return (StatusCode::UNAUTHORIZED, Json(Value::Null)),
+        Some(true) => {}
+    }
+    let Some(text) = push.text.filter(|text| !text.trim().is_empty()) else {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(Value::Null));
+    };
+
+    let text = templates::truncate(&text);
+    let (mut sent, mut failed) = (0, 0);
+    for (chat_id, settings) in state.store.get_all().await {
+        if settings.away && push.priority != Priority::High {
+            continue;
+        }
+        // The lock is only held to queue the message, not while it's sent
+        let delivered = metrics::lock(&state.notify_controller_mutex, "notify_controller")
+            .await
+            .relay(&chat_id, text.clone(), push.priority, push.silent);
+        match delivered.await {
+            true => sent += 1,
+            false => failed += 1,
+        }
+    }
+
+    log::info!(
+        target: "audit",
+        "Broadcast over HTTP sent to {} chats, {} failed",
+        sent,
+        failed
+    );
+    (
+        StatusCode::OK,
+        Json(json!({ "sent": sent, "failed": failed })),
+    )
+}
+
+fn respond(delivered: bool) -> StatusCode {
+    match delivered {
+        true => StatusCode::NO_CONTENT,
+        false => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// Checks the store and the bot token for monitoring, 503 when either fails.
+async fn healthz(State(state): State<AppState>) -> (StatusCode, Json<health::Health>) {
+    let health = health::check(&state.bot, state.store.as_ref()).await;
+    let status = match health.healthy {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(health))
+}
+
+/// Takes an update Telegram posts to the webhook.
+async fn telegram(State(state): State<AppState>, headers: HeaderMap, body: String) -> StatusCode {
+    let Some(inbox) = state.webhook else {
+        return StatusCode::NOT_FOUND;
+    };
+    let secret = headers
+        .get(webhook::SECRET_HEADER)
+        .and_then(|secret| secret.to_str().ok());
+    if !inbox.is_authorized(secret) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let update = match serde_json::from_str::<Update>(&body) {
+        Ok(update) => update,
+        Err(err) => {
+            // Sending it again wouldn't help
+            log::error!("Unable to parse a webhook update: {}\n{}", err, body);
+            return StatusCode::OK;
+        }
+    };
+    match inbox.push(update) {
+        true => StatusCode::OK,
+        // Telegram tries again later, by then another instance may listen
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// Lock waits and handler durations in the Prometheus text format.
+async fn export_metrics() -> String {
+    metrics::REGISTRY.render()
+}
+
+/// Mutes scheduled notifications of the token owner with "away" and unmutes
+/// them with "back".
+async fn presence(
+    State(state): State<AppState>,
+    Path((token, presence)): Path<(String, String)>,
+) -> StatusCode {
+    let away = match presence.as_str() {
+        "away" => true,
+        "back" => false,
+        _ => return StatusCode::NOT_FOUND,
+    };
+    if state.maintenance.is_on() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    let Some((chat_id, _)) = state.store.find_by_token(&token).await else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    let _guard = state.chat_locks.lock(chat_id).await;
+    match state
+        .store
+        .update(&chat_id, |settings| settings.away = away)
+        .await
+    {
+        Ok(_) => {
+            if let Some(settings) = state.store.get(&chat_id).await {
+                metrics::lock(&state.notify_controller_mutex, "notify_controller")
+                    .await
+                    .restart(&chat_id, &settings);
+            }
+            log::info!("{} is {} according to HTTP presence", chat_id, presence);
+            StatusCode::NO_CONTENT
+        }
+        Err(err) => {
+            log::error!("Failed presence update {}: {}", chat_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Where services post alerts for the owner of `token` to.
+pub fn hook_url(public_url: &Url, token: &str) -> Option<Url> {
+    let mut base = public_url.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(&format!("hook/{}", token)).ok()
+}
+
+/// Relays a JSON payload rendered through the token owner's template, alerts
+/// of known services are formatted when there's none.
+async fn hook(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> StatusCode {
+    let Some((chat_id, settings)) = state.store.find_by_token(&token).await else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    let text = match settings.hook_template {
+        Some(template) => templates::render(&template, &payload),
+        None => {
+            let github_event = headers
+                .get(alerts::GITHUB_EVENT_HEADER)
+                .and_then(|event| event.to_str().ok());
+            alerts::format(github_event, &payload)
+                .unwrap_or_else(|| serde_json::to_string_pretty(&payload).unwrap_or_default())
+        }
+    };
+    if text.trim().is_empty() {
+        log::info!("Hook for {} rendered to an empty message", chat_id);
+        return StatusCode::UNPROCESSABLE_ENTITY;
+    }
+
+    log::info!("Hook relayed to {}", chat_id);
+    let notification = metrics::lock(&state.notify_controller_mutex, "notify_controller")
+        .await
+        .relay(
+            &chat_id,
+            templates::truncate(&text),
+            Priority::Normal,
+            false,
+        );
+    respond(notification.await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::AUTHORIZATION, HeaderMap};
+
+    use crate::http::{generate_token, hook_url, is_operator, TOKEN_LENGTH};
+
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), TOKEN_LENGTH);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn test_hook_url() {
+        let hook_url = |public_url: &str| {
+            hook_url(&public_url.parse().unwrap(), "abc")
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            hook_url("https://bot.example.com"),
+            "https://bot.example.com/hook/abc"
+        );
+        assert_eq!(
+            hook_url("https://example.com/bot"),
+            "https://example.com/bot/hook/abc"
+        );
+        assert_eq!(
+            hook_url("https://example.com/bot/"),
+            "https://example.com/bot/hook/abc"
+        );
+    }
+
+    #[test]
+    fn test_is_operator() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(is_operator(None, &headers), None);
+        assert_eq!(is_operator(Some("secret"), &headers), Some(false));
+
+        headers.insert(AUTHORIZATION, "Bearer guess".parse().unwrap());
+        assert_eq!(is_operator(Some("secret"), &headers), Some(false));
+        headers.insert(AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert_eq!(is_operator(Some("secret"), &headers), Some(true));
+        assert_eq!(is_operator(None, &headers), None);
+    }
+}
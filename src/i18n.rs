@@ -0,0 +1,209 @@
+//! Message catalogs for user-facing replies.
+//!
+//! Catalogs are Fluent (`.ftl`) files in the `locales` directory, embedded at
+//! build time. To add a language, create `locales/<code>.ftl` with the same
+//! message ids as `locales/en.ftl` and register it in [`Locale`].
+
+use std::sync::OnceLock;
+
+use fluent_bundle::{concurrent::FluentBundle, FluentResource};
+use serde::{Deserialize, Serialize};
+
+pub use fluent_bundle::FluentArgs;
+
+/// Language of user-facing replies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Ru];
+
+    /// Resolves IETF language tags like "ru" or "en-US".
+    pub fn from_code(code: &str) -> Option<Locale> {
+        let language = code.split(['-', '_']).next()?.to_lowercase();
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.code() == language)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ru => "ru",
+        }
+    }
+
+    /// Language name as its speakers write it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Ru => "Русский",
+        }
+    }
+
+    fn catalog(&self) -> &'static str {
+        match self {
+            Locale::En => include_str!("../locales/en.ftl"),
+            Locale::Ru => include_str!("../locales/ru.ftl"),
+        }
+    }
+}
+
+struct Catalogs(Vec<(Locale, FluentBundle<FluentResource>)>);
+
+impl Catalogs {
+    fn load() -> Catalogs {
+        Catalogs(
+            Locale::ALL
+                .into_iter()
+                .map(|locale| {
+                    let resource = FluentResource::try_new(locale.catalog().to_string())
+                        .unwrap_or_else(|(_, errors)| {
+                            panic!("Invalid {} catalog: {:?}", locale.code(), errors)
+                        });
+
+                    let mut bundle = FluentBundle::new_concurrent(vec![locale
+                        .code()
+                        .parse()
+                        .expect("Locale code must be a valid language identifier")]);
+                    // Telegram renders isolation marks as garbage in some clients
+                    bundle.set_use_isolating(false);
+                    bundle.add_resource(resource).unwrap_or_else(|errors| {
+                        panic!("Invalid {} catalog: {:?}", locale.code(), errors)
+                    });
+
+                    (locale, bundle)
+                })
+                .collect(),
+        )
+    }
+
+    fn bundle(&self, locale: Locale) -> &FluentBundle<FluentResource> {
+        &self
+            .0
+            .iter()
+            .find(|(bundle_locale, _)| *bundle_locale == locale)
+            .expect("Every locale must have a catalog")
+            .1
+    }
+}
+
+fn catalogs() -> &'static Catalogs {
+    static CATALOGS: OnceLock<Catalogs> = OnceLock::new();
+    CATALOGS.get_or_init(Catalogs::load)
+}
+
+/// Formats message `id` for `locale`, falling back to English when the
+/// catalog lacks the message. Prefer the [`tr!`](crate::tr) macro.
+pub fn translate(locale: Locale, id: &str, args: Option<&FluentArgs>) -> String {
+    for locale in [locale, Locale::En] {
+        let bundle = catalogs().bundle(locale);
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = vec![];
+            let value = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                log::error!("Failed to format {} for {:?}: {:?}", id, locale, errors);
+            }
+            return value.into_owned();
+        }
+        log::warn!("Message {} is missing in the {} catalog", id, locale.code());
+    }
+    id.to_string()
+}
+
+/// Translates a catalog message: `tr!(locale, "timezone-changed", timezone = value)`.
+#[macro_export]
+macro_rules! tr {
+    ($locale:expr, $id:expr) => {
+        $crate::i18n::translate($locale, $id, None)
+    };
+    ($locale:expr, $id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::translate($locale, $id, Some(&args))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use fluent_bundle::FluentResource;
+
+    use crate::i18n::{catalogs, Locale};
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(Locale::from_code("ru"), Some(Locale::Ru));
+        assert_eq!(Locale::from_code("en-US"), Some(Locale::En));
+        assert_eq!(Locale::from_code("RU_ru"), Some(Locale::Ru));
+        assert_eq!(Locale::from_code("de"), None);
+        assert_eq!(Locale::from_code(""), None);
+    }
+
+    #[test]
+    fn test_catalogs_have_same_messages() {
+        let ids = |locale: Locale| {
+            let resource = FluentResource::try_new(locale.catalog().to_string()).unwrap();
+            let mut ids: Vec<String> = resource
+                .entries()
+                .filter_map(|entry| match entry {
+                    fluent_syntax::ast::Entry::Message(message) => {
+                        Some(message.id.name.to_string())
+                    }
+                    _ => None,
+                })
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        for locale in Locale::ALL {
+            assert_eq!(ids(locale), ids(Locale::En), "{:?} catalog", locale);
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        catalogs();
+        assert_eq!(tr!(Locale::En, "stop-stopped"), "Stopped!");
+        assert_eq!(
+            tr!(Locale::Ru, "timezone-changed", timezone = "UTC+05:00"),
+            "Часовой пояс изменён: UTC+05:00"
+        );
+        assert_eq!(
+            tr!(Locale::En, "timezone-prompt", timezone = "UTC"),
+            "Current timezone: UTC\n\nSend new timezone.\nExamples:\n1. +05:00\n2. -03:00\n3. +03:30\n4. Europe/Moscow, the offset then follows daylight saving time\n\nOr share your location to find the timezone."
+        );
+        assert_eq!(
+            tr!(
+                Locale::En,
+                "settings-view",
+                active = "no",
+                timezone = "UTC",
+                from = "09:00",
+                to = "18:00",
+                interval = "1 h",
+                language = "English",
+                footer = "yes"
+            ),
+            "Notifications: paused\nTimezone: UTC\nWorking hours: 09:00–18:00\nInterval: 1 h\nLanguage: English\nHint under notifications: shown"
+        );
+        assert_eq!(
+            tr!(
+                Locale::Ru,
+                "insights-view",
+                total = 3,
+                peak = "10:00",
+                chart = "10:00 ▇ 3"
+            ),
+            "\"/done\" нажата 3 раза, чаще всего в 10:00\n\n10:00 ▇ 3"
+        );
+        assert!(tr!(Locale::En, "template-usage")
+            .ends_with("/template {{status}}: {{alerts.0.labels.alertname}}"));
+        assert_eq!(tr!(Locale::Ru, "no-such-message"), "no-such-message");
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+/// Acknowledgements needed before "suggest" proposes anything.
+pub const MIN_SAMPLES: u32 = 10;
+
+/// Number of acknowledgements per local hour of the day.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HourHistogram([u32; 24]);
+
+impl HourHistogram {
+    /// Counts one acknowledgement at `hour`, hours past 23 are ignored.
+    pub fn record(&mut self, hour: u32) {
+        if let Some(count) = self.0.get_mut(hour as usize) {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    pub fn count(&self, hour: u32) -> u32 {
+        self.0.get(hour as usize).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u32 {
+        self.0.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// The hour with the most acknowledgements, the earliest one on ties.
+    pub fn peak(&self) -> Option<u32> {
+        (0..24u32)
+            .filter(|hour| self.count(*hour) > 0)
+            .max_by_key(|hour| (self.count(*hour), std::cmp::Reverse(*hour)))
+    }
+
+    /// Hours having at least one acknowledgement, in order.
+    pub fn hours(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (0..24u32)
+            .map(|hour| (hour, self.count(hour)))
+            .filter(|(_, count)| *count > 0)
+    }
+
+    pub fn merge(&mut self, other: &HourHistogram) {
+        for (count, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *count = count.saturating_add(*other);
+        }
+    }
+}
+
+/// A schedule change proposed from the acknowledgement history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Suggestion {
+    /// Send notifications between these local hours, `to` is exclusive
+    Window { from: u32, to: u32 },
+}
+
+impl Suggestion {
+    const PREFIX: &'static str = "suggest:";
+
+    /// Callback data of the button applying the suggestion.
+    pub fn encode(&self) -> String {
+        match self {
+            Suggestion::Window { from, to } => format!("{}window:{}-{}", Self::PREFIX, from, to),
+        }
+    }
+
+    pub fn decode(data: &str) -> Option<Suggestion> {
+        let window = data.strip_prefix(Self::PREFIX)?.strip_prefix("window:")?;
+        let (from, to) = window.split_once('-')?;
+        let (from, to) = (from.parse::<u32>().ok()?, to.parse::<u32>().ok()?);
+        if from >= to || to > 24 {
+            return None;
+        }
+
+        Some(Suggestion::Window { from, to })
+    }
+}
+
+/// Proposes starting the window at the earliest hour "/done" was ever pressed
+/// inside the current `from..to` window, if that is later than `from`.
+pub fn suggest(histogram: &HourHistogram, from: u32, to: u32) -> Option<Suggestion> {
+    if histogram.total() < MIN_SAMPLES {
+        return None;
+    }
+
+    let (earliest, _) = histogram
+        .hours()
+        .find(|(hour, _)| (from..to).contains(hour))?;
+    if earliest <= from {
+        return None;
+    }
+
+    Some(Suggestion::Window { from: earliest, to })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::insights::{suggest, HourHistogram, Suggestion, MIN_SAMPLES};
+
+    #[test]
+    fn test_hour_histogram() {
+        let mut histogram = HourHistogram::default();
+        assert!(histogram.is_empty());
+        assert_eq!(histogram.peak(), None);
+
+        histogram.record(17);
+        histogram.record(10);
+        histogram.record(10);
+        histogram.record(24);
+        assert_eq!(histogram.total(), 3);
+        assert_eq!(histogram.peak(), Some(10));
+        assert_eq!(
+            histogram.hours().collect::<Vec<_>>(),
+            vec![(10, 2), (17, 1)]
+        );
+
+        let mut other = HourHistogram::default();
+        other.record(17);
+        histogram.merge(&other);
+        assert_eq!(histogram.count(17), 2);
+        assert_eq!(histogram.peak(), Some(10));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let mut histogram = HourHistogram::default();
+        for hour in [10, 11, 15, 19] {
+            histogram.record(hour);
+        }
+        assert_eq!(suggest(&histogram, 9, 18), None);
+
+        for _ in 0..MIN_SAMPLES {
+            histogram.record(12);
+        }
+        assert_eq!(
+            suggest(&histogram, 9, 18),
+            Some(Suggestion::Window { from: 10, to: 18 })
+        );
+        assert_eq!(suggest(&histogram, 10, 18), None);
+        assert_eq!(suggest(&histogram, 20, 23), None);
+    }
+
+    #[test]
+    fn test_suggestion_encoding() {
+        let suggestion = Suggestion::Window { from: 10, to: 18 };
+        assert_eq!(suggestion.encode(), "suggest:window:10-18");
+        assert_eq!(Suggestion::decode(&suggestion.encode()), Some(suggestion));
+
+        assert_eq!(Suggestion::decode("suggest:window:18-10"), None);
+        assert_eq!(Suggestion::decode("suggest:window:10-25"), None);
+        assert_eq!(Suggestion::decode("suggest:window:10"), None);
+        assert_eq!(Suggestion::decode("window:10-18"), None);
+    }
+}
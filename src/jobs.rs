@@ -0,0 +1,425 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    ffi::OsStr,
+    hash::{Hash, Hasher},
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveTime, Utc, Weekday};
+use pickledb::error::Result;
+use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
+use serde::{Deserialize, Serialize};
+use teloxide::types::ChatId;
+
+use crate::{delivery::Priority, pomodoro::Pomodoro, presets::Preset};
+
+/// How often the ticker looks for due jobs.
+pub const TICK: Duration = Duration::from_secs(10);
+
+/// Default width of the window jobs due at the same moment are spread over.
+pub const DEFAULT_SPREAD: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobKind {
+    /// Resume notifications paused with "/done"
+    WakeUp,
+    /// Resume notifications paused with "/snooze"
+    Snooze,
+    /// Send the text set with "/remind"
+    Reminder {
+        text: String,
+        #[serde(default)]
+        priority: Priority,
+    },
+    /// Alert the "/checkin" contact unless the chat wrote in time
+    CheckIn,
+    /// Update the "/timer" countdown message, or send the text once it ends
+    Timer {
+        text: String,
+        message_id: i32,
+        ends: DateTime<Utc>,
+    },
+    /// Send the text set with "/report" along with a timesheet, then schedule
+    /// the next month's
+    Report {
+        text: String,
+        day: u32,
+        time: NaiveTime,
+    },
+    /// Send the "/summary" of the day, then schedule the next day's
+    Summary,
+    /// Send the text set with "/weekly", then schedule the next week's
+    Weekly {
+        text: String,
+        weekday: Weekday,
+        time: NaiveTime,
+    },
+    /// Send the text set with "/monthly", then schedule the next month's
+    Monthly {
+        text: String,
+        day: u32,
+        time: NaiveTime,
+    },
+    /// Remind of the "/deadline", then schedule the next reminder, more often
+    /// as it gets closer
+    Deadline {
+        title: String,
+        deadline: DateTime<Utc>,
+    },
+    /// End the phase of the "/pomodoro" cycle and start the next one, or drop
+    /// the cycle once it's been paused for too long
+    Pomodoro(Pomodoro),
+    /// Send the `turn`'s text of the "/preset", then schedule the next one
+    Preset { preset: Preset, turn: u32 },
+    /// Forget settings removed with "/stop" once they can't be restored
+    Purge,
+}
+
+impl JobKind {
+    pub const RESUMING: [JobKind; 2] = [JobKind::WakeUp, JobKind::Snooze];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::WakeUp => "wake_up",
+            JobKind::Snooze => "snooze",
+            JobKind::Reminder { .. } => "reminder",
+            JobKind::CheckIn => "check_in",
+            JobKind::Timer { .. } => "timer",
+            JobKind::Report { .. } => "report",
+            JobKind::Summary => "summary",
+            JobKind::Weekly { .. } => "weekly",
+            JobKind::Monthly { .. } => "monthly",
+            JobKind::Deadline { .. } => "deadline",
+            JobKind::Pomodoro(_) => "pomodoro",
+            JobKind::Preset { .. } => "preset",
+            JobKind::Purge => "purge",
+        }
+    }
+
+    /// Whether the chat set the job up itself, e.g. with "/remind", "/stop"
+    /// offers to stop these one by one.
+    pub fn is_reminder(&self) -> bool {
+        matches!(
+            self,
+            JobKind::Reminder { .. }
+                | JobKind::Timer { .. }
+                | JobKind::Report { .. }
+                | JobKind::Summary
+                | JobKind::Weekly { .. }
+                | JobKind::Monthly { .. }
+                | JobKind::Deadline { .. }
+                | JobKind::Pomodoro(_)
+                | JobKind::Preset { .. }
+        )
+    }
+
+    /// Snoozes are due at the moment the user asked for, only wake ups pile
+    /// up at the same time.
+    fn spread(&self) -> bool {
+        match self {
+            JobKind::WakeUp => true,
+            JobKind::Snooze
+            | JobKind::Reminder { .. }
+            | JobKind::CheckIn
+            | JobKind::Timer { .. }
+            | JobKind::Report { .. }
+            | JobKind::Summary
+            | JobKind::Weekly { .. }
+            | JobKind::Monthly { .. }
+            | JobKind::Deadline { .. }
+            | JobKind::Pomodoro(_)
+            | JobKind::Preset { .. }
+            | JobKind::Purge => false,
+        }
+    }
+}
+
+/// Something to do for a chat at a given time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub chat_id: ChatId,
+    pub due: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: JobKind,
+}
+
+/// Delayed jobs persisted on disk so they survive restarts.
+pub struct JobQueue {
+    db: PickleDb,
+    next_id: u64,
+    spread: Duration,
+}
+
+impl JobQueue {
+    pub fn new<P: AsRef<Path>>(path: P) -> JobQueue {
+        let db = PickleDb::new(
+            path,
+            PickleDbDumpPolicy::AutoDump,
+            SerializationMethod::Json,
+        );
+
+        JobQueue {
+            db,
+            next_id: 1,
+            spread: Duration::ZERO,
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<JobQueue> {
+        let db = PickleDb::load(
+            path,
+            PickleDbDumpPolicy::AutoDump,
+            SerializationMethod::Json,
+        )?;
+        let next_id = db
+            .get_all()
+            .iter()
+            .filter_map(|id| id.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        Ok(JobQueue {
+            db,
+            next_id,
+            spread: Duration::ZERO,
+        })
+    }
+
+    pub fn open_or_create<S: AsRef<OsStr> + ?Sized>(s: &S) -> Result<JobQueue> {
+        let path = Path::new(s);
+
+        if path.exists() {
+            return JobQueue::open(path);
+        }
+        Ok(JobQueue::new(path))
+    }
+
+    /// Delays pushed wake ups by up to `spread`, so that jobs due at the same
+    /// moment (e.g. midnight of a popular timezone) don't all run at once.
+    pub fn with_spread(mut self, spread: Duration) -> JobQueue {
+        self.spread = spread;
+        self
+    }
+
+    pub fn push(&mut self, chat_id: ChatId, due: DateTime<Utc>, kind: JobKind) -> Result<Job> {
+        let delay = match kind.spread() {
+            true => spread_delay(&chat_id, self.spread),
+            false => chrono::Duration::zero(),
+        };
+        let job = Job {
+            id: self.next_id,
+            chat_id,
+            due: due + delay,
+            kind,
+        };
+        self.db.set(&job.id.to_string(), &job)?;
+        self.next_id += 1;
+
+        Ok(job)
+    }
+
+    /// All pending jobs, the earliest first.
+    pub fn all(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .db
+            .get_all()
+            .iter()
+            .filter_map(|id| self.db.get::<Job>(id))
+            .collect();
+        jobs.sort_by_key(|job| (job.due, job.id));
+        jobs
+    }
+
+    pub fn for_chat(&self, chat_id: &ChatId) -> Vec<Job> {
+        self.all()
+            .into_iter()
+            .filter(|job| job.chat_id == *chat_id)
+            .collect()
+    }
+
+    /// Drops pending jobs of `kind` for the chat, returns how many were dropped.
+    pub fn cancel(&mut self, chat_id: &ChatId, kind: JobKind) -> Result<usize> {
+        let jobs: Vec<Job> = self
+            .for_chat(chat_id)
+            .into_iter()
+            .filter(|job| job.kind == kind)
+            .collect();
+        for job in &jobs {
+            self.db.rem(&job.id.to_string())?;
+        }
+
+        Ok(jobs.len())
+    }
+
+    /// Drops the job, returns `false` if it wasn't pending.
+    pub fn remove(&mut self, id: u64) -> Result<bool> {
+        self.db.rem(&id.to_string())
+    }
+
+    /// Drops the chat's reminder `id`, returns it unless it wasn't pending,
+    /// belongs to another chat or isn't a reminder.
+    pub fn remove_for(&mut self, chat_id: &ChatId, id: u64) -> Result<Option<Job>> {
+        match self.db.get::<Job>(&id.to_string()) {
+            Some(job) if job.chat_id == *chat_id && job.kind.is_reminder() => {
+                self.db.rem(&id.to_string())?;
+                Ok(Some(job))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Puts back jobs taken with `take_due` that didn't run, keeping their ids.
+    pub fn restore(&mut self, jobs: &[Job]) -> Result<()> {
+        for job in jobs {
+            self.db.set(&job.id.to_string(), job)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the queue to disk, called on shutdown.
+    pub fn flush(&mut self) -> Result<()> {
+        self.db.dump()
+    }
+
+    /// Removes and returns the jobs due at `now`.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Result<Vec<Job>> {
+        let due: Vec<Job> = self
+            .all()
+            .into_iter()
+            .take_while(|job| job.due <= now)
+            .collect();
+        for job in &due {
+            self.db.rem(&job.id.to_string())?;
+        }
+
+        Ok(due)
+    }
+}
+
+/// Stable per-chat delay below `spread`, with second precision.
+fn spread_delay(chat_id: &ChatId, spread: Duration) -> chrono::Duration {
+    if spread.as_secs() == 0 {
+        return chrono::Duration::zero();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    chat_id.0.hash(&mut hasher);
+    chrono::Duration::seconds((hasher.finish() % spread.as_secs()) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use teloxide::types::ChatId;
+
+    use crate::{
+        delivery::Priority,
+        jobs::{spread_delay, JobKind, JobQueue},
+        presets::Preset,
+    };
+
+    #[test]
+    fn test_job_queue() {
+        let path = std::env::temp_dir().join("notification_bot_test_job_queue.db");
+        let _ = std::fs::remove_file(&path);
+
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        {
+            let mut queue = JobQueue::new(&path);
+            queue
+                .push(ChatId(1), now + Duration::hours(2), JobKind::WakeUp)
+                .unwrap();
+            queue
+                .push(ChatId(2), now - Duration::minutes(1), JobKind::WakeUp)
+                .unwrap();
+            queue.push(ChatId(1), now, JobKind::WakeUp).unwrap();
+        }
+
+        let mut queue = JobQueue::open(&path).unwrap();
+        assert_eq!(queue.for_chat(&ChatId(1)).len(), 2);
+
+        let due = queue.take_due(now).unwrap();
+        assert_eq!(
+            due.iter().map(|job| job.chat_id).collect::<Vec<_>>(),
+            vec![ChatId(2), ChatId(1)]
+        );
+        assert_eq!(queue.all().len(), 1);
+        assert!(queue.take_due(now).unwrap().is_empty());
+
+        queue.restore(&due[..1]).unwrap();
+        assert_eq!(queue.take_due(now).unwrap(), due[..1]);
+
+        assert_eq!(queue.cancel(&ChatId(1), JobKind::WakeUp).unwrap(), 1);
+        assert!(queue.all().is_empty());
+
+        let job = queue.push(ChatId(3), now, JobKind::WakeUp).unwrap();
+        assert_eq!(job.id, 4);
+
+        let reminder = JobKind::Reminder {
+            text: "call mom".to_string(),
+            priority: Priority::High,
+        };
+        let job = queue.push(ChatId(3), now, reminder.clone()).unwrap();
+        let mut queue = JobQueue::open(&path).unwrap();
+        assert_eq!(queue.for_chat(&ChatId(3))[1].kind, reminder);
+        assert!(queue.remove(job.id).unwrap());
+        assert!(!queue.remove(job.id).unwrap());
+
+        let job = queue.push(ChatId(3), now, reminder.clone()).unwrap();
+        assert!(job.kind.is_reminder());
+        assert!(!JobKind::WakeUp.is_reminder());
+        let wake_up = queue.push(ChatId(3), now, JobKind::WakeUp).unwrap();
+        assert_eq!(queue.remove_for(&ChatId(3), wake_up.id).unwrap(), None);
+        assert_eq!(queue.for_chat(&ChatId(3)).len(), 3);
+        assert_eq!(queue.remove_for(&ChatId(4), job.id).unwrap(), None);
+        assert_eq!(
+            queue.remove_for(&ChatId(3), job.id).unwrap(),
+            Some(job.clone())
+        );
+        assert_eq!(queue.remove_for(&ChatId(3), job.id).unwrap(), None);
+
+        // Presets are stopped one at a time along with reminders
+        let preset = JobKind::Preset {
+            preset: Preset::Water,
+            turn: 2,
+        };
+        let job = queue.push(ChatId(3), now, preset).unwrap();
+        let mut queue = JobQueue::open(&path).unwrap();
+        assert_eq!(queue.remove_for(&ChatId(3), job.id).unwrap(), Some(job));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_spread() {
+        let spread = std::time::Duration::from_secs(600);
+        assert_eq!(
+            spread_delay(&ChatId(1), std::time::Duration::ZERO),
+            Duration::zero()
+        );
+
+        let delays: Vec<Duration> = (0..100)
+            .map(|chat| spread_delay(&ChatId(chat), spread))
+            .collect();
+        assert!(delays
+            .iter()
+            .all(|delay| *delay >= Duration::zero() && *delay < Duration::seconds(600)));
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+        assert_eq!(spread_delay(&ChatId(7), spread), delays[7]);
+
+        let path = std::env::temp_dir().join("notification_bot_test_job_spread.db");
+        let _ = std::fs::remove_file(&path);
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 0, 0, 0).unwrap();
+        let mut queue = JobQueue::new(&path).with_spread(spread);
+        let job = queue.push(ChatId(7), now, JobKind::WakeUp).unwrap();
+        assert_eq!(job.due, now + delays[7]);
+        let job = queue.push(ChatId(7), now, JobKind::Snooze).unwrap();
+        assert_eq!(job.due, now);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{path::Path, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, Row};
+use teloxide::types::ChatId;
+
+/// How long sends are kept in the journal, older ones go on start.
+pub const RETENTION: Duration = Duration::days(90);
+
+/// One send of a notification through one channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub chat_id: ChatId,
+    pub at: DateTime<Utc>,
+    pub channel: String,
+    /// Why the send failed, unset when it was delivered
+    pub error: Option<String>,
+}
+
+/// Every notification sent, kept in SQLite for "/history" and the admins'
+/// export.
+pub struct Journal {
+    conn: Mutex<Connection>,
+}
+
+impl Journal {
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Journal> {
+        Journal::with_connection(Connection::open(path)?)
+    }
+
+    /// A journal lost on exit, for the in-memory store.
+    pub fn open_in_memory() -> rusqlite::Result<Journal> {
+        Journal::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> rusqlite::Result<Journal> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sends (
+                chat_id INTEGER NOT NULL,
+                at INTEGER NOT NULL,
+                channel TEXT NOT NULL,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS sends_by_chat ON sends (chat_id, at);",
+        )?;
+        let pruned = conn.execute(
+            "DELETE FROM sends WHERE at < ?1",
+            params![(Utc::now() - RETENTION).timestamp()],
+        )?;
+        if pruned > 0 {
+            log::info!(
+                "Removed {} sends older than {} days",
+                pruned,
+                RETENTION.num_days()
+            );
+        }
+
+        Ok(Journal {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Records a send, a journal that can't be written only loses history.
+    pub fn record(&self, chat_id: ChatId, channel: &str, result: &Result<(), String>) {
+        self.record_at(chat_id, channel, result, Utc::now())
+    }
+
+    fn record_at(
+        &self,
+        chat_id: ChatId,
+        channel: &str,
+        result: &Result<(), String>,
+        at: DateTime<Utc>,
+    ) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(err) = conn.execute(
+            "INSERT INTO sends (chat_id, at, channel, error) VALUES (?1, ?2, ?3, ?4)",
+            params![chat_id.0, at.timestamp(), channel, result.as_ref().err()],
+        ) {
+            log::error!("Unable to record a send to {}: {}", chat_id, err);
+        }
+    }
+
+    /// The chat's latest `limit` sends, the latest first.
+    pub fn recent(&self, chat_id: ChatId, limit: usize) -> rusqlite::Result<Vec<Entry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT chat_id, at, channel, error FROM sends WHERE chat_id = ?1
+            ORDER BY at DESC, rowid DESC LIMIT ?2",
+        )?;
+        let entries = statement
+            .query_map(params![chat_id.0, limit as i64], entry)?
+            .collect();
+        entries
+    }
+
+    /// Every send in the journal as CSV, the earliest first.
+    pub fn export(&self) -> rusqlite::Result<String> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare("SELECT chat_id, at, channel, error FROM sends ORDER BY at, rowid")?;
+        let mut csv = "chat_id,at,channel,delivered,error\n".to_string();
+        for entry in statement.query_map([], entry)? {
+            let entry = entry?;
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                entry.chat_id,
+                entry.at.to_rfc3339(),
+                csv_field(&entry.channel),
+                entry.error.is_none(),
+                csv_field(entry.error.as_deref().unwrap_or_default())
+            ));
+        }
+        Ok(csv)
+    }
+}
+
+fn entry(row: &Row) -> rusqlite::Result<Entry> {
+    Ok(Entry {
+        chat_id: ChatId(row.get(0)?),
+        at: DateTime::from_timestamp(row.get(1)?, 0).unwrap_or_default(),
+        channel: row.get(2)?,
+        error: row.get(3)?,
+    })
+}
+
+/// Quotes a field with separators, quotes or line breaks.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use teloxide::types::ChatId;
+
+    use crate::journal::{Entry, Journal};
+
+    #[test]
+    fn test_journal() {
+        let journal = Journal::open_in_memory().unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+        journal.record_at(ChatId(1), "telegram", &Ok(()), at);
+        journal.record_at(
+            ChatId(1),
+            "slack",
+            &Err("status 500, \"oops\"".to_string()),
+            at + Duration::hours(1),
+        );
+        journal.record_at(ChatId(2), "telegram", &Ok(()), at + Duration::hours(2));
+
+        assert_eq!(
+            journal.recent(ChatId(1), 10).unwrap(),
+            vec![
+                Entry {
+                    chat_id: ChatId(1),
+                    at: at + Duration::hours(1),
+                    channel: "slack".to_string(),
+                    error: Some("status 500, \"oops\"".to_string()),
+                },
+                Entry {
+                    chat_id: ChatId(1),
+                    at,
+                    channel: "telegram".to_string(),
+                    error: None,
+                },
+            ]
+        );
+        assert_eq!(journal.recent(ChatId(1), 1).unwrap().len(), 1);
+        assert_eq!(
+            journal.export().unwrap(),
+            "chat_id,at,channel,delivered,error\n\
+            1,2024-05-06T10:00:00+00:00,telegram,true,\n\
+            1,2024-05-06T11:00:00+00:00,slack,false,\"status 500, \"\"oops\"\"\"\n\
+            2,2024-05-06T12:00:00+00:00,telegram,true,\n"
+        );
+    }
+}
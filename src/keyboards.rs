@@ -0,0 +1,299 @@
+use teloxide::types::{
+    ButtonRequest, InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup,
+};
+
+use crate::{i18n::Locale, tr};
+
+pub const TIMEZONE_CHOICES: [&str; 16] = [
+    "-08:00", "-05:00", "-03:00", "+00:00", "+01:00", "+02:00", "+03:00", "+04:00", "+05:00",
+    "+05:30", "+06:00", "+07:00", "+08:00", "+09:00", "+10:00", "+12:00",
+];
+
+/// Reply keyboard with `choices` laid out in rows of `columns` buttons,
+/// letting mobile users tap a common answer instead of typing it.
+pub fn choices<S: AsRef<str>>(choices: &[S], columns: usize) -> KeyboardMarkup {
+    KeyboardMarkup::new(
+        choices
+            .chunks(columns)
+            .map(|row| {
+                row.iter()
+                    .map(|choice| KeyboardButton::new(choice.as_ref()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>(),
+    )
+    .resize_keyboard(true)
+    .one_time_keyboard(true)
+}
+
+/// Common offsets, plus a button sharing the location where Telegram
+/// allows it, in private chats only.
+pub fn timezones(locale: Locale, with_location: bool) -> KeyboardMarkup {
+    let keyboard = choices(&TIMEZONE_CHOICES, 4);
+    if !with_location {
+        return keyboard;
+    }
+
+    keyboard.append_row(vec![
+        KeyboardButton::new(tr!(locale, "button-location")).request(ButtonRequest::Location)
+    ])
+}
+
+/// Buttons under notifications, each does what its command does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationButton {
+    /// Acknowledges the notification in ack mode
+    Ack,
+    Done,
+    /// Snoozes for an hour
+    Snooze,
+    Stop,
+}
+
+impl NotificationButton {
+    const PREFIX: &'static str = "notify:";
+    const ALL: [NotificationButton; 4] = [
+        NotificationButton::Ack,
+        NotificationButton::Done,
+        NotificationButton::Snooze,
+        NotificationButton::Stop,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            NotificationButton::Ack => "ack",
+            NotificationButton::Done => "done",
+            NotificationButton::Snooze => "snooze",
+            NotificationButton::Stop => "stop",
+        }
+    }
+
+    /// Callback data of the button.
+    pub fn encode(&self) -> String {
+        format!("{}{}", Self::PREFIX, self.name())
+    }
+
+    pub fn decode(data: &str) -> Option<NotificationButton> {
+        let name = data.strip_prefix(Self::PREFIX)?;
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+}
+
+/// Inline keyboard sent along with notifications, "Got it" is there in ack
+/// mode only.
+pub fn notification(locale: Locale, ack: bool) -> InlineKeyboardMarkup {
+    let row = NotificationButton::ALL
+        .into_iter()
+        .filter(|button| ack || *button != NotificationButton::Ack)
+        .map(|button| {
+            InlineKeyboardButton::callback(
+                tr!(locale, &format!("button-{}", button.name())),
+                button.encode(),
+            )
+        });
+    InlineKeyboardMarkup::new([row])
+}
+
+/// Buttons asking to confirm "/stop" and to undo it afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopButton {
+    Confirm,
+    Cancel,
+    /// Restores the settings within the undo window
+    Undo,
+    /// Stops only the chat's reminder job with the id
+    Reminder(u64),
+}
+
+impl StopButton {
+    const PREFIX: &'static str = "stop:";
+    const ALL: [StopButton; 3] = [StopButton::Confirm, StopButton::Cancel, StopButton::Undo];
+
+    fn name(&self) -> &'static str {
+        match self {
+            StopButton::Confirm => "confirm",
+            StopButton::Cancel => "cancel",
+            StopButton::Undo => "undo",
+            StopButton::Reminder(_) => "reminder",
+        }
+    }
+
+    /// Callback data of the button.
+    pub fn encode(&self) -> String {
+        match self {
+            StopButton::Reminder(id) => format!("{}{}:{}", Self::PREFIX, self.name(), id),
+            _ => format!("{}{}", Self::PREFIX, self.name()),
+        }
+    }
+
+    pub fn decode(data: &str) -> Option<StopButton> {
+        let name = data.strip_prefix(Self::PREFIX)?;
+        if let Some(id) = name.strip_prefix("reminder:") {
+            return id.parse().ok().map(StopButton::Reminder);
+        }
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+
+    fn button(&self, locale: Locale) -> InlineKeyboardButton {
+        InlineKeyboardButton::callback(
+            tr!(locale, &format!("button-stop-{}", self.name())),
+            self.encode(),
+        )
+    }
+}
+
+/// Yes and No under the "/stop" question.
+pub fn stop_confirmation(locale: Locale) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[
+        StopButton::Confirm.button(locale),
+        StopButton::Cancel.button(locale),
+    ]])
+}
+
+/// "/stop" of a chat with reminders: everything, one of the `reminders`
+/// given by their ids and labels, or nothing.
+pub fn stop_choice(locale: Locale, reminders: &[(u64, String)]) -> InlineKeyboardMarkup {
+    let everything = InlineKeyboardButton::callback(
+        tr!(locale, "button-stop-everything"),
+        StopButton::Confirm.encode(),
+    );
+    let reminders = reminders.iter().map(|(id, label)| {
+        vec![InlineKeyboardButton::callback(
+            label.clone(),
+            StopButton::Reminder(*id).encode(),
+        )]
+    });
+    InlineKeyboardMarkup::new(
+        std::iter::once(vec![everything])
+            .chain(reminders)
+            .chain(std::iter::once(vec![StopButton::Cancel.button(locale)])),
+    )
+}
+
+/// Undo under the reply to a confirmed "/stop".
+pub fn stop_undo(locale: Locale) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[StopButton::Undo.button(locale)]])
+}
+
+/// Buttons under "/pomodoro" messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PomodoroButton {
+    Pause,
+    Resume,
+    /// Ends the phase right away
+    Skip,
+    Stop,
+}
+
+impl PomodoroButton {
+    const PREFIX: &'static str = "pomodoro:";
+    const ALL: [PomodoroButton; 4] = [
+        PomodoroButton::Pause,
+        PomodoroButton::Resume,
+        PomodoroButton::Skip,
+        PomodoroButton::Stop,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            PomodoroButton::Pause => "pause",
+            PomodoroButton::Resume => "resume",
+            PomodoroButton::Skip => "skip",
+            PomodoroButton::Stop => "stop",
+        }
+    }
+
+    /// Callback data of the button.
+    pub fn encode(&self) -> String {
+        format!("{}{}", Self::PREFIX, self.name())
+    }
+
+    pub fn decode(data: &str) -> Option<PomodoroButton> {
+        let name = data.strip_prefix(Self::PREFIX)?;
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+}
+
+/// Pause or resume, skip and stop under "/pomodoro" messages.
+pub fn pomodoro(locale: Locale, paused: bool) -> InlineKeyboardMarkup {
+    let toggle = match paused {
+        true => PomodoroButton::Resume,
+        false => PomodoroButton::Pause,
+    };
+    let row = [toggle, PomodoroButton::Skip, PomodoroButton::Stop].map(|button| {
+        InlineKeyboardButton::callback(
+            tr!(locale, &format!("button-pomodoro-{}", button.name())),
+            button.encode(),
+        )
+    });
+    InlineKeyboardMarkup::new([row])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        i18n::Locale,
+        keyboards::{
+            choices, notification, pomodoro, NotificationButton, PomodoroButton, StopButton,
+            TIMEZONE_CHOICES,
+        },
+        parsers::parse_timezone,
+    };
+
+    #[test]
+    fn test_timezone_choices_are_valid() {
+        for choice in TIMEZONE_CHOICES {
+            assert!(parse_timezone(choice).is_some(), "{}", choice);
+        }
+    }
+
+    #[test]
+    fn test_choices_layout() {
+        let keyboard = choices(&["a", "b", "c", "d", "e"], 2);
+        let rows: Vec<usize> = keyboard.keyboard.iter().map(|row| row.len()).collect();
+
+        assert_eq!(rows, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_notification_buttons() {
+        for button in NotificationButton::ALL {
+            assert_eq!(NotificationButton::decode(&button.encode()), Some(button));
+        }
+        assert_eq!(NotificationButton::decode("notify:later"), None);
+        assert_eq!(NotificationButton::decode("suggest:window:9-18"), None);
+
+        assert_eq!(notification(Locale::En, false).inline_keyboard[0].len(), 3);
+        assert_eq!(notification(Locale::En, true).inline_keyboard[0].len(), 4);
+    }
+
+    #[test]
+    fn test_stop_buttons() {
+        for button in StopButton::ALL {
+            assert_eq!(StopButton::decode(&button.encode()), Some(button));
+        }
+        assert_eq!(StopButton::decode("stop:maybe"), None);
+        assert_eq!(
+            StopButton::decode(&StopButton::Reminder(42).encode()),
+            Some(StopButton::Reminder(42))
+        );
+        assert_eq!(StopButton::decode("stop:reminder:x"), None);
+        assert_eq!(StopButton::decode("notify:stop"), None);
+    }
+
+    #[test]
+    fn test_pomodoro_buttons() {
+        for button in PomodoroButton::ALL {
+            assert_eq!(PomodoroButton::decode(&button.encode()), Some(button));
+        }
+        assert_eq!(PomodoroButton::decode("stop:confirm"), None);
+
+        let first = |paused| {
+            pomodoro(Locale::En, paused).inline_keyboard[0][0]
+                .text
+                .clone()
+        };
+        assert_eq!(first(false), "Pause");
+        assert_eq!(first(true), "Resume");
+    }
+}
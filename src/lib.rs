@@ -0,0 +1,53 @@
+mod ack_hooks;
+#[cfg(feature = "http")]
+mod alerts;
+pub mod aliases;
+mod blackouts;
+pub mod bot;
+mod channels;
+mod chat_info;
+mod chat_locks;
+pub mod cli;
+mod clock;
+mod commands;
+pub mod config;
+mod deadlines;
+mod delivery;
+mod dialogue_storage;
+mod dialogues;
+#[cfg(feature = "email")]
+mod email;
+pub mod formatting;
+mod handlers;
+mod health;
+#[cfg(feature = "http")]
+mod http;
+pub mod i18n;
+pub mod insights;
+mod jobs;
+mod journal;
+mod keyboards;
+mod maintenance;
+mod message_text;
+pub mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod notify_controller;
+mod offsets_rep;
+pub mod parsers;
+mod pomodoro;
+mod presets;
+mod previews;
+mod processed;
+mod profiles;
+mod release_notes;
+pub mod scheduling;
+mod settings_menu;
+mod stats;
+mod store;
+mod tasks;
+mod telemetry;
+pub mod templates;
+mod transfers;
+#[cfg(feature = "http")]
+mod webhook;
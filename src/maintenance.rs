@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Read-only mode admins turn on around database migrations and the like.
+///
+/// Commands and buttons changing a chat are refused while it's on, scheduled
+/// notifications and delayed jobs keep running.
+#[derive(Default)]
+pub struct Maintenance {
+    on: AtomicBool,
+}
+
+impl Maintenance {
+    pub fn new(on: bool) -> Maintenance {
+        Maintenance {
+            on: AtomicBool::new(on),
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, on: bool) {
+        self.on.store(on, Ordering::Relaxed)
+    }
+}
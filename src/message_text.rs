@@ -0,0 +1,335 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    types::{MessageEntity, ParseMode},
+    utils::{html, markdown},
+};
+
+/// Telegram's MarkdownV2 syntax for custom emoji: `![👍](tg://emoji?id=5368324170671202286)`
+static CUSTOM_EMOJI_RE: &str = r"!\[([^\]]+)\]\(tg://emoji\?id=([0-9]+)\)";
+
+/// How configured text is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Markup {
+    /// Sent as is, only custom emoji markup is turned into entities
+    #[default]
+    Plain,
+    /// Telegram's MarkdownV2
+    MarkdownV2,
+    /// Telegram's subset of HTML
+    Html,
+}
+
+impl Markup {
+    pub const ALL: [Markup; 3] = [Markup::Plain, Markup::MarkdownV2, Markup::Html];
+
+    pub fn from_name(name: &str) -> Option<Markup> {
+        Markup::ALL
+            .into_iter()
+            .find(|markup| markup.name() == name.trim().to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Markup::Plain => "plain",
+            Markup::MarkdownV2 => "markdownv2",
+            Markup::Html => "html",
+        }
+    }
+
+    /// `text` shown as is once parsed in this markup.
+    fn escape(&self, text: &str) -> String {
+        match self {
+            Markup::Plain => text.to_string(),
+            Markup::MarkdownV2 => markdown::escape(text),
+            Markup::Html => html::escape(text),
+        }
+    }
+}
+
+/// Message text together with its entities, or text in a markup Telegram
+/// parses itself.
+///
+/// Entity offsets are counted in UTF-16 code units, so text must be put
+/// together through this type rather than by concatenating strings. Plain
+/// text joined with markup is escaped, its entities are lost.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MessageText {
+    text: String,
+    entities: Vec<MessageEntity>,
+    markup: Markup,
+}
+
+impl MessageText {
+    pub fn plain<S: Into<String>>(text: S) -> MessageText {
+        MessageText {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Configured text written in `markup`, see [`MessageText::parse`] for
+    /// plain text.
+    pub fn with_markup(template: &str, markup: Markup) -> MessageText {
+        match markup {
+            Markup::Plain => MessageText::parse(template),
+            markup => MessageText {
+                text: template.to_string(),
+                entities: vec![],
+                markup,
+            },
+        }
+    }
+
+    /// Parses configured text, turning custom emoji markup into entities.
+    /// The emoji inside the brackets is what clients without premium see.
+    pub fn parse(template: &str) -> MessageText {
+        let regex = Regex::new(CUSTOM_EMOJI_RE).unwrap();
+
+        let mut result = MessageText::default();
+        let mut last_end = 0;
+        for captures in regex.captures_iter(template) {
+            let markup = captures.get(0).unwrap();
+            result.push_str(&template[last_end..markup.start()]);
+
+            let fallback = &captures[1];
+            result.entities.push(MessageEntity::custom_emoji(
+                captures[2].to_string(),
+                utf16_len(&result.text),
+                utf16_len(fallback),
+            ));
+            result.text.push_str(fallback);
+
+            last_end = markup.end();
+        }
+        result.push_str(&template[last_end..]);
+
+        result
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// Appends `other`, shifting its entities past the current text.
+    pub fn append(&mut self, other: &MessageText) {
+        match (self.markup, other.markup) {
+            (ours, theirs) if ours == theirs => {}
+            (ours, Markup::Plain) => {
+                self.text.push_str(&ours.escape(&other.text));
+                return;
+            }
+            (Markup::Plain, theirs) => {
+                self.text = theirs.escape(&self.text);
+                self.entities.clear();
+                self.markup = theirs;
+                self.text.push_str(&other.text);
+                return;
+            }
+            // Markup can't be turned into another one, it goes as plain text
+            (_, _) => {
+                self.append(&MessageText::plain(other.text.clone()));
+                return;
+            }
+        }
+
+        let shift = utf16_len(&self.text);
+        self.entities
+            .extend(other.entities.iter().cloned().map(|mut entity| {
+                entity.offset += shift;
+                entity
+            }));
+        self.text.push_str(&other.text);
+    }
+
+    /// Replaces `{name}` placeholders with the values of `vars`, escaped for
+    /// the markup, unknown ones stay as they are. Entities move along with
+    /// the text around them.
+    pub fn render(&self, vars: &[(&str, String)]) -> MessageText {
+        let mut result = MessageText {
+            markup: self.markup,
+            ..Default::default()
+        };
+        // Where in the source, in UTF-16, a replacement ended and by how much
+        // it changed the length
+        let mut shifts: Vec<(usize, isize)> = vec![];
+        let mut source_len = 0;
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find('{') {
+            let (before, tail) = rest.split_at(start);
+            result.text.push_str(before);
+            source_len += utf16_len(before);
+
+            let var = tail.find('}').and_then(|end| {
+                let name = &tail[1..end];
+                let (_, value) = vars.iter().find(|(var, _)| *var == name)?;
+                Some((&tail[..=end], self.markup.escape(value)))
+            });
+            match var {
+                Some((placeholder, value)) => {
+                    result.text.push_str(&value);
+                    source_len += utf16_len(placeholder);
+                    shifts.push((
+                        source_len,
+                        utf16_len(&value) as isize - utf16_len(placeholder) as isize,
+                    ));
+                    rest = &tail[placeholder.len()..];
+                }
+                None => {
+                    result.text.push('{');
+                    source_len += 1;
+                    rest = &tail[1..];
+                }
+            }
+        }
+        result.text.push_str(rest);
+
+        result.entities = self
+            .entities
+            .iter()
+            .cloned()
+            .map(|mut entity| {
+                let shift: isize = shifts
+                    .iter()
+                    .filter(|(end, _)| *end <= entity.offset)
+                    .map(|(_, shift)| shift)
+                    .sum();
+                entity.offset = entity.offset.saturating_add_signed(shift);
+                entity
+            })
+            .collect();
+        result
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn entities(&self) -> &[MessageEntity] {
+        &self.entities
+    }
+
+    /// How Telegram is to parse the text, unset when it comes with entities.
+    pub fn parse_mode(&self) -> Option<ParseMode> {
+        match self.markup {
+            Markup::Plain => None,
+            Markup::MarkdownV2 => Some(ParseMode::MarkdownV2),
+            Markup::Html => Some(ParseMode::Html),
+        }
+    }
+
+    /// The text as written, markup and all, for when Telegram can't parse it.
+    pub fn as_plain(&self) -> MessageText {
+        MessageText {
+            text: self.text.clone(),
+            entities: match self.markup {
+                Markup::Plain => self.entities.clone(),
+                _ => vec![],
+            },
+            markup: Markup::Plain,
+        }
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::types::MessageEntity;
+
+    use teloxide::types::ParseMode;
+
+    use crate::message_text::{Markup, MessageText};
+
+    #[test]
+    fn test_plain_text() {
+        let text = MessageText::parse("Drink some water [now](later)!");
+
+        assert_eq!(text, MessageText::plain("Drink some water [now](later)!"));
+    }
+
+    #[test]
+    fn test_custom_emoji() {
+        let text = MessageText::parse(
+            "Ура ![👍](tg://emoji?id=5368324170671202286) and ![🔥](tg://emoji?id=42)",
+        );
+
+        assert_eq!(text.text(), "Ура 👍 and 🔥");
+        assert_eq!(
+            text.entities(),
+            &[
+                MessageEntity::custom_emoji("5368324170671202286".to_string(), 4, 2),
+                MessageEntity::custom_emoji("42".to_string(), 11, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let text = MessageText::parse("{weekday} {unknown} {{time} ![👍](tg://emoji?id=1) {count}");
+        let vars = [
+            ("weekday", "Понедельник".to_string()),
+            ("time", "09:00".to_string()),
+            ("count", "3".to_string()),
+        ];
+
+        let rendered = text.render(&vars);
+        assert_eq!(rendered.text(), "Понедельник {unknown} {09:00 👍 3");
+        assert_eq!(
+            rendered.entities(),
+            &[MessageEntity::custom_emoji("1".to_string(), 29, 2)]
+        );
+        assert_eq!(
+            MessageText::plain("{").render(&vars),
+            MessageText::plain("{")
+        );
+    }
+
+    #[test]
+    fn test_markup() {
+        let text = MessageText::with_markup(
+            "*{weekday}* at {date} ![👍](tg://emoji?id=1)",
+            Markup::MarkdownV2,
+        );
+        let mut rendered = text.render(&[("date", "2024-05-06".to_string())]);
+        rendered.append(&MessageText::plain("\n\nSend /done."));
+        assert_eq!(
+            rendered.text(),
+            "*{weekday}* at 2024\\-05\\-06 ![👍](tg://emoji?id=1)\n\nSend /done\\."
+        );
+        assert!(rendered.entities().is_empty());
+        assert_eq!(rendered.parse_mode(), Some(ParseMode::MarkdownV2));
+        assert_eq!(rendered.as_plain().parse_mode(), None);
+
+        let mut html = MessageText::parse("1 < 2 ![👍](tg://emoji?id=1)");
+        html.append(
+            &MessageText::with_markup("<b>{count}</b>", Markup::Html)
+                .render(&[("count", "<3".to_string())]),
+        );
+        assert_eq!(html.text(), "1 &lt; 2 👍<b>&lt;3</b>");
+        assert_eq!(html.parse_mode(), Some(ParseMode::Html));
+
+        assert_eq!(
+            MessageText::with_markup("a.b", Markup::Plain),
+            MessageText::plain("a.b")
+        );
+        assert_eq!(Markup::from_name(" HTML"), Some(Markup::Html));
+        assert_eq!(Markup::from_name("markdown"), None);
+    }
+
+    #[test]
+    fn test_append_shifts_entities() {
+        let mut text = MessageText::plain("🙂 ");
+        text.append(&MessageText::parse("![👍](tg://emoji?id=1)"));
+        text.push_str("\n\nfooter");
+
+        assert_eq!(text.text(), "🙂 👍\n\nfooter");
+        assert_eq!(
+            text.entities(),
+            &[MessageEntity::custom_emoji("1".to_string(), 3, 2)]
+        );
+    }
+}
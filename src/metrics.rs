@@ -0,0 +1,210 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::Mutex as StdMutex,
+    time::{Duration, Instant},
+};
+
+use async_mutex::{Mutex, MutexGuard};
+
+/// Upper bounds of the histogram buckets, in seconds.
+const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// A histogram family, every label value gets its own histogram.
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub label: &'static str,
+}
+
+/// Time spent waiting for a shared lock, by lock.
+pub const LOCK_WAIT: Metric = Metric {
+    name: "notification_bot_lock_wait_seconds",
+    help: "Time spent waiting for a shared lock.",
+    label: "lock",
+};
+
+/// Time spent handling an update or running a job, by handler.
+pub const HANDLER_DURATION: Metric = Metric {
+    name: "notification_bot_handler_duration_seconds",
+    help: "Time spent handling an update or running a delayed job.",
+    label: "handler",
+};
+
+/// A single value that goes up and down.
+pub struct Gauge {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+/// How far the local clock is off from Telegram's.
+pub const CLOCK_SKEW: Gauge = Gauge {
+    name: "notification_bot_clock_skew_seconds",
+    help: "Seconds the local clock is ahead of Telegram's, estimated from incoming messages.",
+};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Appends the histogram in the Prometheus text format.
+    fn write(&self, out: &mut String, name: &str, label: &str, value: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}=\"{}\",le=\"{}\"}} {}",
+                name, label, value, bound, bucket
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}=\"{}\",le=\"+Inf\"}} {}",
+            name, label, value, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}=\"{}\"}} {}", name, label, value, self.sum);
+        let _ = writeln!(
+            out,
+            "{}_count{{{}=\"{}\"}} {}",
+            name, label, value, self.count
+        );
+    }
+}
+
+/// Histograms of a process, keyed by metric name and label value, and gauges.
+#[derive(Default)]
+pub struct Registry {
+    histograms: StdMutex<BTreeMap<(&'static str, String), Histogram>>,
+    gauges: StdMutex<BTreeMap<&'static str, f64>>,
+}
+
+impl Registry {
+    pub const fn new() -> Registry {
+        Registry {
+            histograms: StdMutex::new(BTreeMap::new()),
+            gauges: StdMutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set(&self, gauge: &Gauge, value: f64) {
+        self.gauges.lock().unwrap().insert(gauge.name, value);
+    }
+
+    pub fn observe(&self, metric: &Metric, value: &str, elapsed: Duration) {
+        let mut histograms = self.histograms.lock().unwrap();
+        histograms
+            .entry((metric.name, value.to_string()))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    pub fn get(&self, metric: &Metric, value: &str) -> Option<Histogram> {
+        let histograms = self.histograms.lock().unwrap();
+        histograms.get(&(metric.name, value.to_string())).cloned()
+    }
+
+    /// All histograms in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let histograms = self.histograms.lock().unwrap();
+        let mut out = String::new();
+        for metric in [&LOCK_WAIT, &HANDLER_DURATION] {
+            let _ = writeln!(out, "# HELP {} {}", metric.name, metric.help);
+            let _ = writeln!(out, "# TYPE {} histogram", metric.name);
+            for ((_, value), histogram) in histograms
+                .iter()
+                .filter(|((name, _), _)| *name == metric.name)
+            {
+                histogram.write(&mut out, metric.name, metric.label, value);
+            }
+        }
+
+        let gauges = self.gauges.lock().unwrap();
+        for gauge in [&CLOCK_SKEW] {
+            if let Some(value) = gauges.get(gauge.name) {
+                let _ = writeln!(out, "# HELP {} {}", gauge.name, gauge.help);
+                let _ = writeln!(out, "# TYPE {} gauge", gauge.name);
+                let _ = writeln!(out, "{} {}", gauge.name, value);
+            }
+        }
+        out
+    }
+}
+
+/// Histograms of the running bot.
+pub static REGISTRY: Registry = Registry::new();
+
+/// Locks `mutex` recording how long it took under `name`.
+pub async fn lock<'a, T>(mutex: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    let start = Instant::now();
+    let guard = mutex.lock().await;
+    REGISTRY.observe(&LOCK_WAIT, name, start.elapsed());
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_mutex::Mutex;
+
+    use crate::metrics::{lock, Registry, CLOCK_SKEW, HANDLER_DURATION, LOCK_WAIT, REGISTRY};
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::new();
+        registry.observe(&HANDLER_DURATION, "done", Duration::from_millis(3));
+        registry.observe(&HANDLER_DURATION, "done", Duration::from_secs(2));
+        registry.observe(&LOCK_WAIT, "jobs", Duration::ZERO);
+
+        assert_eq!(registry.get(&HANDLER_DURATION, "done").unwrap().count(), 2);
+        assert_eq!(registry.get(&HANDLER_DURATION, "jobs"), None);
+
+        let text = registry.render();
+        assert!(text.contains("# TYPE notification_bot_handler_duration_seconds histogram"));
+        assert!(text.contains(
+            "notification_bot_handler_duration_seconds_bucket{handler=\"done\",le=\"0.001\"} 0"
+        ));
+        assert!(text.contains(
+            "notification_bot_handler_duration_seconds_bucket{handler=\"done\",le=\"0.005\"} 1"
+        ));
+        assert!(text.contains(
+            "notification_bot_handler_duration_seconds_bucket{handler=\"done\",le=\"+Inf\"} 2"
+        ));
+        assert!(
+            text.contains("notification_bot_handler_duration_seconds_count{handler=\"done\"} 2")
+        );
+        assert!(text.contains("notification_bot_lock_wait_seconds_count{lock=\"jobs\"} 1"));
+        assert!(!text.contains("clock_skew"));
+
+        registry.set(&CLOCK_SKEW, -2.0);
+        assert!(registry
+            .render()
+            .contains("# TYPE notification_bot_clock_skew_seconds gauge\nnotification_bot_clock_skew_seconds -2\n"));
+    }
+
+    #[tokio::test]
+    async fn test_lock() {
+        let mutex = Mutex::new(1);
+        *lock(&mutex, "test_lock").await += 1;
+        assert_eq!(*mutex.lock().await, 2);
+        assert_eq!(REGISTRY.get(&LOCK_WAIT, "test_lock").unwrap().count(), 1);
+    }
+}
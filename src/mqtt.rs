@@ -0,0 +1,192 @@
+use std::{sync::Arc, time::Duration};
+
+use async_mutex::Mutex;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, Publish, QoS, SubscribeFilter};
+use teloxide::types::ChatId;
+use tokio::time::sleep;
+
+use crate::{
+    bot::Bot, config::Source, delivery::Priority, metrics, notify_controller::NotificationSender,
+    store::UserStore, templates,
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Broker connection and the topics routed to chats.
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Topic filters (wildcards allowed) and the chats they notify
+    pub routes: Vec<(String, ChatId)>,
+}
+
+impl MqttConfig {
+    /// Reads `MQTT_HOST`, `MQTT_PORT`, `MQTT_CLIENT_ID` and `MQTT_ROUTES`,
+    /// MQTT is off without a host or routes.
+    pub fn from_source(source: &Source) -> Option<MqttConfig> {
+        let host = source.var("MQTT_HOST").ok()?;
+        let routes = parse_routes(&source.var("MQTT_ROUTES").unwrap_or_default());
+        if routes.is_empty() {
+            log::warn!("MQTT_HOST is set without MQTT_ROUTES, MQTT is off");
+            return None;
+        }
+
+        Some(MqttConfig {
+            host,
+            port: match source.var("MQTT_PORT") {
+                Ok(port) => port.parse().unwrap_or_else(|_| {
+                    log::warn!("Invalid MQTT_PORT {}, using 1883", port);
+                    1883
+                }),
+                Err(_) => 1883,
+            },
+            client_id: source
+                .var("MQTT_CLIENT_ID")
+                .unwrap_or_else(|_| "notification_bot".to_string()),
+            routes,
+        })
+    }
+
+    fn chats(&self, topic: &str) -> Vec<ChatId> {
+        self.routes
+            .iter()
+            .filter(|(filter, _)| rumqttc::matches(topic, filter))
+            .map(|(_, chat_id)| *chat_id)
+            .collect()
+    }
+}
+
+/// Parses "home/door=123;alerts/#=-100456" into topic filters and chats.
+fn parse_routes(routes: &str) -> Vec<(String, ChatId)> {
+    routes
+        .split(';')
+        .map(str::trim)
+        .filter(|route| !route.is_empty())
+        .filter_map(|route| {
+            let parsed = route.rsplit_once('=').and_then(|(filter, chat_id)| {
+                let filter = filter.trim();
+                let chat_id = chat_id.trim().parse::<i64>().ok()?;
+                rumqttc::valid_filter(filter).then(|| (filter.to_string(), ChatId(chat_id)))
+            });
+            if parsed.is_none() {
+                log::warn!("Ignoring invalid MQTT route {}", route);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Relays messages of the routed topics until the process exits.
+pub async fn run(
+    config: MqttConfig,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+) {
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    let (client, mut eventloop) = AsyncClient::new(options, config.routes.len().max(10));
+
+    loop {
+        match eventloop.poll().await {
+            // Subscriptions don't outlive a clean session, renew them on every connect
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                log::info!("Connected to MQTT broker {}:{}", config.host, config.port);
+                let filters = config
+                    .routes
+                    .iter()
+                    .map(|(filter, _)| SubscribeFilter::new(filter.clone(), QoS::AtLeastOnce));
+                if let Err(err) = client.try_subscribe_many(filters) {
+                    log::error!("Unable to subscribe to MQTT topics: {}", err);
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                relay(&config, publish, &*store, &notify_controller_mutex).await;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                log::error!("MQTT connection error: {}", err);
+                sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Sends the message payload to the routed chats, or their usual notification
+/// when the payload is empty.
+async fn relay(
+    config: &MqttConfig,
+    publish: Publish,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+) {
+    let payload = String::from_utf8_lossy(&publish.payload).trim().to_string();
+
+    for chat_id in config.chats(&publish.topic) {
+        let Some(settings) = store.get(&chat_id).await else {
+            log::warn!(
+                "MQTT topic {} is routed to unknown chat {}",
+                publish.topic,
+                chat_id
+            );
+            continue;
+        };
+
+        log::info!("MQTT message on {} relayed to {}", publish.topic, chat_id);
+        let sent = match payload.is_empty() {
+            true => {
+                let notification = metrics::lock(notify_controller_mutex, "notify_controller")
+                    .await
+                    .notify(&chat_id, &settings);
+                notification.await
+            }
+            false => {
+                let notification = metrics::lock(notify_controller_mutex, "notify_controller")
+                    .await
+                    .relay(
+                        &chat_id,
+                        templates::truncate(&payload),
+                        Priority::Normal,
+                        false,
+                    );
+                notification.await
+            }
+        };
+        if !sent {
+            log::warn!(
+                "MQTT message on {} not delivered to {}",
+                publish.topic,
+                chat_id
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::types::ChatId;
+
+    use crate::mqtt::{parse_routes, MqttConfig};
+
+    #[test]
+    fn test_routes() {
+        let routes = parse_routes("home/door=123; alerts/#=-100456;broken;bad/#/x=1");
+        assert_eq!(
+            routes,
+            vec![
+                ("home/door".to_string(), ChatId(123)),
+                ("alerts/#".to_string(), ChatId(-100456)),
+            ]
+        );
+
+        let config = MqttConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "test".to_string(),
+            routes,
+        };
+        assert_eq!(config.chats("alerts/disk/full"), vec![ChatId(-100456)]);
+        assert_eq!(config.chats("home/door"), vec![ChatId(123)]);
+        assert!(config.chats("home/window").is_empty());
+    }
+}
@@ -1,16 +1,203 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{borrow::Cow, collections::HashMap, future::Future, sync::Arc, time::Duration};
 
-use chrono::{DateTime, Datelike, FixedOffset, Local, TimeZone, Timelike, Weekday};
-use teloxide::{requests::Requester, types::ChatId, Bot};
-use tokio::{spawn, task::JoinHandle, time::sleep as async_sleep};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
+use cron::Schedule;
+use teloxide::{
+    errors::{ApiError, AsResponseParameters},
+    payloads::{
+        EditMessageTextSetters, SendDocumentSetters, SendMessageSetters, SendPhotoSetters,
+        SendStickerSetters,
+    },
+    requests::Requester,
+    types::{ChatId, InlineKeyboardMarkup, InputFile, MessageId},
+    RequestError,
+};
+use tokio::{
+    spawn,
+    sync::{mpsc::UnboundedSender, Notify},
+    task::{JoinHandle, JoinSet},
+    time::{sleep as async_sleep, Instant},
+};
 
-pub const HOUR_FROM: u32 = 9;
-pub const HOUR_TO: u32 = 18;
+use crate::{
+    blackouts::{self, Blackout},
+    channels::{self, Channels},
+    delivery::{self, Attachment, Budget, BudgetLevel, Document, Failure, Pipeline, Priority},
+    formatting,
+    i18n::Locale,
+    journal::Journal,
+    keyboards,
+    message_text::{Markup, MessageText},
+    offsets_rep::{Footer, UserSettings},
+    scheduling::{self, get_sleep_time, its_working_time, next_cron},
+    templates::{self, Media, MediaKind, PartOfDay, Rotation, CAPTION_LIMIT},
+    tr,
+};
 
-pub struct NotificationSender {
-    notify_tasks_map: HashMap<ChatId, JoinHandle<()>>,
-    bot: Arc<Bot>,
-    notification: Notification,
+/// How often notifications are sent unless the user chose otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+pub const MIN_INTERVAL: Duration = Duration::from_secs(5 * 60);
+pub const MAX_INTERVAL: Duration = Duration::from_secs(12 * 3600);
+/// How often a notification is repeated in ack mode until it's acknowledged.
+pub const ACK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// How often the supervisor looks for sends that panicked.
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a scheduler loop that ended waits before it's started again.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Scheduled notifications go at this priority, a new message or an edit
+/// alike.
+const NOTIFICATION_PRIORITY: Priority = Priority::Normal;
+/// How many times an edit Telegram asks to hold off is tried again.
+const MAX_EDIT_RETRIES: u32 = 3;
+
+/// Schedules notifications of chats and sends them through `B`, usually a
+/// rate limited bot.
+///
+/// A single scheduler loop serves every chat: it keeps when each chat is due
+/// next in a queue, sleeps until the earliest one and sends the notifications
+/// of all chats due by then at once. A chat found gone for good, e.g. it
+/// blocked the bot, isn't scheduled anymore and is reported to `evicted` so
+/// it gets removed. A supervisor restarts the loop if it panics and
+/// schedules again chats whose send panicked.
+pub struct NotificationSender<B> {
+    bot: Arc<B>,
+    notification: Arc<Notification>,
+    pipeline: Arc<Pipeline>,
+    evicted: UnboundedSender<ChatId>,
+    counts: Arc<SendCounts>,
+    sent: Arc<SentMessages>,
+    blackouts: Arc<Vec<Blackout>>,
+    channels: Arc<Channels>,
+    skipped: Option<UnboundedSender<ChatId>>,
+    delivered: Option<UnboundedSender<(ChatId, NaiveDate, DateTime<Utc>)>>,
+    journal: Option<Arc<Journal>>,
+    queue: Arc<std::sync::Mutex<Queue>>,
+    /// Wakes the scheduler up when a chat may be due before it planned to wake
+    changed: Arc<Notify>,
+    /// Spawned with the first chat, once the builder methods are done, it
+    /// runs the scheduler loop
+    supervisor: Option<JoinHandle<()>>,
+}
+
+/// How many notifications each chat got on its local day, kept across
+/// restarts of its schedule for the daily summary.
+#[derive(Default)]
+struct SendCounts(std::sync::Mutex<HashMap<ChatId, (NaiveDate, u32)>>);
+
+impl SendCounts {
+    fn record(&self, chat_id: ChatId, day: NaiveDate) {
+        let mut counts = self.0.lock().unwrap();
+        let count = counts.entry(chat_id).or_insert((day, 0));
+        if count.0 != day {
+            *count = (day, 0);
+        }
+        count.1 += 1;
+    }
+
+    fn on(&self, chat_id: &ChatId, day: NaiveDate) -> u32 {
+        match self.0.lock().unwrap().get(chat_id) {
+            Some((counted, count)) if *counted == day => *count,
+            _ => 0,
+        }
+    }
+}
+
+/// Notification messages each chat got on its local day, removed once the
+/// chat acknowledges them.
+#[derive(Default)]
+struct SentMessages(std::sync::Mutex<HashMap<ChatId, (NaiveDate, Vec<MessageId>)>>);
+
+impl SentMessages {
+    fn record(&self, chat_id: ChatId, day: NaiveDate, ids: &[MessageId]) {
+        let mut sent = self.0.lock().unwrap();
+        let messages = sent.entry(chat_id).or_insert((day, vec![]));
+        if messages.0 != day {
+            *messages = (day, vec![]);
+        }
+        messages.1.extend_from_slice(ids);
+    }
+
+    /// The last message sent on `day`.
+    fn last(&self, chat_id: &ChatId, day: NaiveDate) -> Option<MessageId> {
+        match self.0.lock().unwrap().get(chat_id) {
+            Some((sent, ids)) if *sent == day => ids.last().copied(),
+            _ => None,
+        }
+    }
+
+    /// Forgets the messages sent on `day` and returns them.
+    fn take(&self, chat_id: &ChatId, day: NaiveDate) -> Vec<MessageId> {
+        let mut sent = self.0.lock().unwrap();
+        match sent.get(chat_id) {
+            Some((sent_on, _)) if *sent_on == day => sent.remove(chat_id).unwrap().1,
+            _ => vec![],
+        }
+    }
+}
+
+/// Chats whose notifications are on, with the wake ups they are due at.
+type Queue = scheduling::Queue<ChatId, Scheduled>;
+
+struct Scheduled {
+    settings: Arc<UserSettings>,
+    wake: Wake,
+    /// Date of the cron schedule the next notification is for, unset for
+    /// one sent outside the schedule
+    cron_date: Option<DateTime<Utc>>,
+    /// Failed sends in a row
+    failures: u32,
+    /// Notifications held back to save the daily budget, the next one sent
+    /// mentions them
+    held: u32,
+}
+
+/// What a chat does when it's due.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Wake {
+    Notify,
+    /// Repeats the notification in ack mode until "Got it" is pressed, the
+    /// next one is due at `until`
+    Repeat {
+        until: Instant,
+    },
+}
+
+/// A chat taken from the queue for a send.
+#[derive(Clone)]
+struct Firing {
+    chat_id: ChatId,
+    ticket: u64,
+    settings: Arc<UserSettings>,
+    wake: Wake,
+    cron_date: Option<DateTime<Utc>>,
+    held: u32,
+}
+
+/// What a due chat's send did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Sent {
+    Delivered,
+    /// Nothing was due today, e.g. the chat is away or it's a holiday
+    Skipped,
+    /// Left out to save the daily budget
+    Held,
+    /// Left out on the chat's "/skip"
+    Suppressed,
+}
+
+/// When a chat is due after a send.
+#[derive(Debug, PartialEq)]
+enum Plan {
+    Due {
+        at: Instant,
+        wake: Wake,
+        cron_date: Option<DateTime<Utc>>,
+        failures: u32,
+    },
+    /// The cron schedule has no dates left
+    Finished,
+    /// The chat is gone for good
+    Evict,
 }
 
 pub enum StartEnum {
@@ -18,58 +205,616 @@ pub enum StartEnum {
     AlreadyExist,
 }
 
-pub struct Notification(String);
+/// The configured messages, one of them is picked for every send, see
+/// [`Rotation`]. Chats with messages of their own get one of those instead.
+pub struct Notification {
+    templates: Vec<Template>,
+    rotation: Rotation,
+    /// How the configured messages are written, chats choose their own
+    markup: Markup,
+}
+
+/// A message with optional variants for parts of the day written as
+/// `[morning]`, `[afternoon]` and `[evening]` sections. Placeholders like
+/// `{time}` are filled in for every chat, see `compose`.
+///
+/// Each variant may come with a photo, a sticker or a document, see
+/// [`templates::media`].
+struct Template {
+    common: MessageText,
+    parts: HashMap<PartOfDay, MessageText>,
+    /// Media of the variants, `None` for the common text
+    media: HashMap<Option<PartOfDay>, Media>,
+}
+
+impl Template {
+    fn build(message: &str, markup: Markup) -> Template {
+        let sections = templates::sections(message);
+        let mut media = HashMap::new();
+        let parts: HashMap<PartOfDay, MessageText> = sections
+            .parts
+            .iter()
+            .map(|(part, text)| {
+                let (text, part_media) = templates::media(text);
+                if let Some(part_media) = part_media {
+                    media.insert(Some(*part), part_media);
+                }
+                (*part, MessageText::with_markup(&text, markup))
+            })
+            .collect();
+        // A message made of sections only falls back to its first one
+        let common = match sections.common.is_empty() {
+            true => match sections.parts.first() {
+                Some((part, _)) => {
+                    if let Some(part_media) = media.get(&Some(*part)).cloned() {
+                        media.insert(None, part_media);
+                    }
+                    parts[part].clone()
+                }
+                None => MessageText::default(),
+            },
+            false => {
+                let (text, common_media) = templates::media(&sections.common);
+                if let Some(common_media) = common_media {
+                    media.insert(None, common_media);
+                }
+                MessageText::with_markup(&text, markup)
+            }
+        };
+
+        Template {
+            common,
+            parts,
+            media,
+        }
+    }
+
+    fn message(&self, part: PartOfDay) -> &MessageText {
+        self.parts.get(&part).unwrap_or(&self.common)
+    }
+
+    /// Media sent along with the message of the `part` of the day.
+    fn media(&self, part: PartOfDay) -> Option<&Media> {
+        match self.parts.contains_key(&part) {
+            true => self.media.get(&Some(part)),
+            false => self.media.get(&None),
+        }
+    }
+}
+
 impl Notification {
-    pub fn build(message: String) -> Notification {
-        Notification(message)
+    /// Notifications picking one of `messages` written in `markup` by
+    /// `rotation`, an empty pool sends empty text.
+    pub fn build(messages: Vec<String>, rotation: Rotation, markup: Markup) -> Notification {
+        let templates: Vec<Template> = messages
+            .iter()
+            .map(|message| Template::build(message, markup))
+            .collect();
+        for (index, template) in templates.iter().enumerate() {
+            for (part, media) in &template.media {
+                match part {
+                    Some(part) => log::info!(
+                        "Notifications of message {} in the {} come with a {}",
+                        index + 1,
+                        part.name(),
+                        media.kind.name()
+                    ),
+                    None => log::info!(
+                        "Notifications of message {} come with a {}",
+                        index + 1,
+                        media.kind.name()
+                    ),
+                }
+            }
+        }
+        if templates.len() > 1 {
+            log::info!("Rotating {} messages, {:?}", templates.len(), rotation);
+        }
+
+        Notification {
+            templates,
+            rotation,
+            markup,
+        }
     }
 
-    pub fn sender(self, bot: Bot) -> NotificationSender {
-        NotificationSender::new(bot, self)
+    pub fn sender<B>(self, bot: B, evicted: UnboundedSender<ChatId>) -> NotificationSender<B>
+    where
+        B: Requester<Err = RequestError> + Send + Sync + 'static,
+        B::SendMessage: Send,
+        B::SendDocument: Send,
+        B::SendPhoto: Send,
+        B::SendSticker: Send,
+        B::EditMessageText: Send,
+    {
+        NotificationSender::new(bot, self, evicted)
     }
 
-    pub fn message(&self) -> &String {
-        return &self.0;
+    /// Text of the notification sent to the chat at `moment`, the `count`th
+    /// of its day, with the media sent along.
+    ///
+    /// The message may refer to `{time}`, `{date}` and `{weekday}` of the
+    /// chat and to the `{count}`.
+    fn compose(
+        &self,
+        settings: &UserSettings,
+        moment: DateTime<Utc>,
+        count: u32,
+    ) -> (MessageText, Option<Media>) {
+        let local = settings
+            .offset_at(moment)
+            .from_utc_datetime(&moment.naive_utc());
+        let vars = [
+            ("time", formatting::time(&local.time())),
+            ("date", local.format("%Y-%m-%d").to_string()),
+            (
+                "weekday",
+                formatting::weekday(local.weekday(), settings.locale).to_string(),
+            ),
+            ("count", count.to_string()),
+        ];
+        // Sends of a day take turns, each day starts a message further
+        let turn = local.num_days_from_ce().max(0) as usize + count.saturating_sub(1) as usize;
+        let own;
+        let template = match settings.messages.is_empty() {
+            true => {
+                let index = self.rotation.pick(self.templates.len(), turn);
+                self.templates.get(index)
+            }
+            false => {
+                let index = self.rotation.pick(settings.messages.len(), turn);
+                let markup = settings.markup.unwrap_or(self.markup);
+                own = Template::build(&settings.messages[index], markup);
+                Some(&own)
+            }
+        };
+        let Some(template) = template else {
+            return (
+                compose(&MessageText::default(), settings.footer, settings.locale),
+                None,
+            );
+        };
+
+        let part = PartOfDay::at(local.hour());
+        let text = compose(
+            &template.message(part).render(&vars),
+            settings.footer,
+            settings.locale,
+        );
+        (text, template.media(part).cloned())
     }
 }
 
-impl NotificationSender {
-    pub fn new(bot: Bot, notification: Notification) -> NotificationSender {
+impl<B> NotificationSender<B>
+where
+    B: Requester<Err = RequestError> + Send + Sync + 'static,
+    B::SendMessage: Send,
+    B::SendDocument: Send,
+    B::SendPhoto: Send,
+    B::SendSticker: Send,
+    B::EditMessageText: Send,
+{
+    pub fn new(
+        bot: B,
+        notification: Notification,
+        evicted: UnboundedSender<ChatId>,
+    ) -> NotificationSender<B> {
         NotificationSender {
-            notify_tasks_map: HashMap::new(),
             bot: Arc::new(bot),
-            notification: notification,
+            notification: Arc::new(notification),
+            pipeline: Arc::new(Pipeline::new()),
+            evicted,
+            counts: Arc::new(SendCounts::default()),
+            sent: Arc::new(SentMessages::default()),
+            blackouts: Arc::new(vec![]),
+            channels: Arc::new(Channels::new()),
+            skipped: None,
+            delivered: None,
+            journal: None,
+            queue: Arc::new(std::sync::Mutex::new(Queue::default())),
+            changed: Arc::new(Notify::new()),
+            supervisor: None,
         }
     }
 
-    pub fn start(&mut self, user_id: &ChatId, offset: FixedOffset) -> StartEnum {
-        if self.notify_tasks_map.contains_key(user_id) {
+    /// Skips notifications of every chat on the `blackouts` days.
+    pub fn with_blackouts(mut self, blackouts: Vec<Blackout>) -> NotificationSender<B> {
+        self.blackouts = Arc::new(blackouts);
+        self
+    }
+
+    /// Cuts sends down as the deployment comes close to its daily `budget`.
+    pub fn with_budget(mut self, budget: Budget) -> NotificationSender<B> {
+        self.pipeline = Arc::new(Pipeline::with_budget(budget));
+        self
+    }
+
+    /// Reports every notification left out on a chat's "/skip" to `skipped`,
+    /// so the count left can be stored.
+    pub fn with_skips(mut self, skipped: UnboundedSender<ChatId>) -> NotificationSender<B> {
+        self.skipped = Some(skipped);
+        self
+    }
+
+    /// Reports every notification delivered to `delivered` with the chat's
+    /// local day, for its statistics.
+    pub fn with_deliveries(
+        mut self,
+        delivered: UnboundedSender<(ChatId, NaiveDate, DateTime<Utc>)>,
+    ) -> NotificationSender<B> {
+        self.delivered = Some(delivered);
+        self
+    }
+
+    /// Records every send of a notification in the `journal`.
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> NotificationSender<B> {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Sends notifications through the `channels` each chat lists, in its
+    /// order, instead of only to the chat.
+    pub fn with_channels(mut self, channels: Arc<Channels>) -> NotificationSender<B> {
+        self.channels = channels;
+        self
+    }
+
+    /// Days every chat's notifications are skipped on.
+    pub fn blackouts(&self) -> &[Blackout] {
+        &self.blackouts
+    }
+
+    /// The blackout the chat's local `day` falls in.
+    pub fn blackout_on(&self, day: NaiveDate) -> Option<&Blackout> {
+        blackouts::find(&self.blackouts, day)
+    }
+
+    /// Schedules the chat's notifications. With `send_immediately` the first
+    /// one goes out right away during working time instead of at the next
+    /// slot.
+    pub fn start(
+        &mut self,
+        user_id: &ChatId,
+        settings: &UserSettings,
+        send_immediately: bool,
+    ) -> StartEnum {
+        // "/start" from another topic moves even a running chat there
+        self.set_topic(user_id, settings.thread_id);
+        if self.is_running(user_id) {
             return StartEnum::AlreadyExist;
         }
+        if self.supervisor.is_none() {
+            self.supervisor = Some(spawn(supervise(Arc::new(self.context()))));
+        }
 
-        let task = spawn(notify_task(
-            user_id.clone(),
-            Arc::clone(&self.bot),
-            offset,
-            self.notification.message().to_owned(),
-        ));
-        self.notify_tasks_map.insert(user_id.clone(), task);
+        let now = Utc::now();
+        let instant = Instant::now();
+        let first = match settings.schedule() {
+            Some(_) if send_immediately => Some((instant, None)),
+            Some(schedule) => next_cron(&schedule, &settings.zone(), now).map(|date| {
+                (
+                    instant + (date - now).to_std().unwrap_or_default(),
+                    Some(date),
+                )
+            }),
+            None => {
+                let date = settings.offset_at(now).from_utc_datetime(&now.naive_utc());
+                let working = its_working_time(
+                    date,
+                    settings.working_hours,
+                    settings.workdays,
+                    &settings.quiet_hours,
+                );
+                match send_immediately && working {
+                    true => Some((instant, None)),
+                    false => Some((
+                        instant
+                            + get_sleep_time(
+                                date,
+                                settings.working_hours,
+                                settings.workdays,
+                                &settings.quiet_hours,
+                                settings.interval(),
+                            ),
+                        None,
+                    )),
+                }
+            }
+        };
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.add(
+            *user_id,
+            Scheduled {
+                settings: Arc::new(settings.clone()),
+                wake: Wake::Notify,
+                cron_date: first.and_then(|(_, date)| date),
+                failures: 0,
+                held: 0,
+            },
+        );
+        match first {
+            Some((at, _)) => queue.schedule(*user_id, at),
+            None => log::info!("Cron schedule of {} has no dates left", user_id),
+        }
+        drop(queue);
+        self.changed.notify_one();
 
-        log::debug!("Added notify task {}", user_id);
+        log::debug!("Scheduled notifications of {}", user_id);
 
         StartEnum::Added
     }
 
+    /// Sends the notification to the chat right away, outside of its schedule.
+    #[cfg_attr(not(any(feature = "http", feature = "mqtt")), allow(dead_code))]
+    pub fn notify(&self, user_id: &ChatId, settings: &UserSettings) -> impl Future<Output = bool> {
+        let bot = Arc::clone(&self.bot);
+        let pipeline = Arc::clone(&self.pipeline);
+        let sent = Arc::clone(&self.sent);
+        let user_id = *user_id;
+        let now = Utc::now();
+        let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+        let (text, media) =
+            self.notification
+                .compose(settings, now, self.counts.on(&user_id, today) + 1);
+        let attachment = media.map(Attachment::Media);
+        let keyboard = keyboards::notification(settings.locale, settings.ack);
+
+        async move {
+            match deliver(
+                &bot,
+                &pipeline,
+                user_id,
+                &text,
+                Some(&keyboard),
+                attachment.as_ref(),
+                NOTIFICATION_PRIORITY,
+                false,
+            )
+            .await
+            {
+                Ok(ids) => {
+                    sent.record(user_id, today, &ids);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// Sends arbitrary text to the chat as a notification, without a sound
+    /// when `silent`.
+    pub fn relay(
+        &self,
+        user_id: &ChatId,
+        text: String,
+        priority: Priority,
+        silent: bool,
+    ) -> impl Future<Output = bool> {
+        let bot = Arc::clone(&self.bot);
+        let pipeline = Arc::clone(&self.pipeline);
+        let user_id = *user_id;
+        let text = MessageText::plain(text);
+
+        async move {
+            deliver(
+                &bot, &pipeline, user_id, &text, None, None, priority, silent,
+            )
+            .await
+            .is_ok()
+        }
+    }
+
+    /// Sends arbitrary text to the chat with `keyboard` under it.
+    pub fn relay_with_keyboard(
+        &self,
+        user_id: &ChatId,
+        text: String,
+        keyboard: InlineKeyboardMarkup,
+    ) -> impl Future<Output = bool> {
+        let bot = Arc::clone(&self.bot);
+        let pipeline = Arc::clone(&self.pipeline);
+        let user_id = *user_id;
+        let text = MessageText::plain(text);
+
+        async move {
+            deliver(
+                &bot,
+                &pipeline,
+                user_id,
+                &text,
+                Some(&keyboard),
+                None,
+                Priority::Normal,
+                false,
+            )
+            .await
+            .is_ok()
+        }
+    }
+
+    /// Sends `document` to the chat with `text` as its caption.
+    pub fn relay_document(
+        &self,
+        user_id: &ChatId,
+        text: String,
+        document: Document,
+        priority: Priority,
+    ) -> impl Future<Output = bool> {
+        let bot = Arc::clone(&self.bot);
+        let pipeline = Arc::clone(&self.pipeline);
+        let user_id = *user_id;
+        let text = MessageText::plain(text);
+        let attachment = Attachment::Document(document);
+
+        async move {
+            deliver(
+                &bot,
+                &pipeline,
+                user_id,
+                &text,
+                None,
+                Some(&attachment),
+                priority,
+                false,
+            )
+            .await
+            .is_ok()
+        }
+    }
+
+    /// Scheduled notifications the chat got on its local `day`, the last one
+    /// only is remembered.
+    pub fn sent_on(&self, user_id: &ChatId, day: NaiveDate) -> u32 {
+        self.counts.on(user_id, day)
+    }
+
+    pub fn is_running(&self, user_id: &ChatId) -> bool {
+        self.queue.lock().unwrap().contains(user_id)
+    }
+
+    /// When the running chat's next notification goes out as its schedule
+    /// stands, e.g. right away after "/start". Unset while a send is in
+    /// flight or when nothing is coming.
+    pub fn next_send(&self, user_id: &ChatId, settings: &UserSettings) -> Option<DateTime<Utc>> {
+        let due = self.queue.lock().unwrap().due_at(user_id)?;
+        let wait = due.saturating_duration_since(Instant::now());
+        let due = Utc::now() + chrono::Duration::from_std(wait).unwrap_or_default();
+
+        // The chat wakes up then, but sends nothing on days off or when skipping
+        match settings.skip == 0 && !is_day_off(settings, &self.blackouts, due) {
+            true => Some(due),
+            false => self.next_after(settings, due - chrono::Duration::seconds(1)),
+        }
+    }
+
+    /// When the chat's first notification after `after` goes out, leaving out
+    /// its holidays, the blackouts and the ones it skips.
+    pub fn next_after(
+        &self,
+        settings: &UserSettings,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let skip = settings.skip as usize;
+        upcoming_notifications(settings, &self.blackouts, after, skip + 1)
+            .get(skip)
+            .copied()
+    }
+
+    /// Stops repeating the last notification of an ack mode chat, returns
+    /// `false` if none waited for it.
+    pub fn ack(&self, user_id: &ChatId) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        let Some(chat) = queue.get_mut(user_id) else {
+            return false;
+        };
+        let Wake::Repeat { until } = chat.wake else {
+            return false;
+        };
+
+        chat.wake = Wake::Notify;
+        queue.schedule(*user_id, until);
+        drop(queue);
+        self.changed.notify_one();
+        true
+    }
+
+    /// Removes the notifications the chat got on its local `day`, once they
+    /// are acknowledged. Ones Telegram doesn't let delete are edited down to
+    /// a short mark instead.
+    pub fn clear_sent(
+        &self,
+        user_id: &ChatId,
+        day: NaiveDate,
+        locale: Locale,
+    ) -> impl Future<Output = ()> {
+        let bot = Arc::clone(&self.bot);
+        let user_id = *user_id;
+        let ids = self.sent.take(&user_id, day);
+        let cleared = tr!(locale, "notification-cleared");
+
+        async move {
+            for id in ids {
+                let Err(err) = bot.delete_message(user_id, id).await else {
+                    continue;
+                };
+                log::debug!("Unable to delete notification of {}: {}", user_id, err);
+                if let Err(err) = bot.edit_message_text(user_id, id, cleared.clone()).await {
+                    log::warn!("Unable to clear notification of {}: {}", user_id, err);
+                }
+            }
+        }
+    }
+
+    /// Forum topic of the group messages to the chat go to.
+    pub fn set_topic(&self, user_id: &ChatId, thread_id: Option<i32>) {
+        self.pipeline.topics.set(user_id, thread_id);
+    }
+
+    /// Restarts a running chat so it picks up changed settings.
+    pub fn restart(&mut self, user_id: &ChatId, settings: &UserSettings) {
+        if self.stop(user_id) {
+            self.start(user_id, settings, false);
+        }
+    }
+
+    /// Unschedules every chat, returns how many were running.
+    pub fn stop_all(&mut self) -> usize {
+        self.queue.lock().unwrap().clear()
+    }
+
     pub fn stop(&mut self, user_id: &ChatId) -> bool {
-        if !self.notify_tasks_map.contains_key(user_id) {
+        // Its wake up left in the queue is skipped, a send in flight is
+        // finished but not followed by another
+        if self.queue.lock().unwrap().remove(user_id).is_none() {
             return false;
         }
 
-        let task = self.notify_tasks_map.remove(user_id).unwrap();
-        task.abort();
-        log::debug!("Stopped {} notify task", user_id);
-        return true;
+        log::debug!("Stopped notifications of {}", user_id);
+        true
     }
+
+    fn context(&self) -> Context<B> {
+        Context {
+            bot: Arc::clone(&self.bot),
+            pipeline: Arc::clone(&self.pipeline),
+            notification: Arc::clone(&self.notification),
+            evicted: self.evicted.clone(),
+            counts: Arc::clone(&self.counts),
+            sent: Arc::clone(&self.sent),
+            blackouts: Arc::clone(&self.blackouts),
+            channels: Arc::clone(&self.channels),
+            skipped: self.skipped.clone(),
+            delivered: self.delivered.clone(),
+            journal: self.journal.clone(),
+            queue: Arc::clone(&self.queue),
+            changed: Arc::clone(&self.changed),
+            sends: std::sync::Mutex::new(vec![]),
+        }
+    }
+}
+
+impl<B> Drop for NotificationSender<B> {
+    fn drop(&mut self) {
+        // The scheduler loop goes along with it
+        if let Some(supervisor) = &self.supervisor {
+            supervisor.abort();
+        }
+    }
+}
+
+/// Builds the text of a notification from the configured message and the
+/// user's footer preference.
+fn compose(message: &MessageText, footer: Footer, locale: Locale) -> MessageText {
+    let mut text = message.clone();
+    match footer {
+        Footer::Text => text.append(&MessageText::plain(format!(
+            "\n\n{}",
+            tr!(locale, "notification-footer")
+        ))),
+        Footer::Hidden => {}
+    }
+    text
 }
 
 fn format_seconds(seconds: u64) -> String {
@@ -89,123 +834,1401 @@ fn format_seconds(seconds: u64) -> String {
         result += &format!("{} seconds ", seconds);
     }
 
-    return result.trim().to_string();
+    result.trim().to_string()
+}
+
+/// When a running chat gets its next notification, as of `now`.
+pub fn next_notification(settings: &UserSettings, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    settings.timing().next_after(now)
+}
+
+/// The next `count` notifications of a running chat, as of `now`, leaving
+/// out its holidays and the `blackouts`. Only the coming year is looked at.
+pub fn upcoming_notifications(
+    settings: &UserSettings,
+    blackouts: &[Blackout],
+    now: DateTime<Utc>,
+    count: usize,
+) -> Vec<DateTime<Utc>> {
+    let horizon = now + chrono::Duration::days(366);
+    std::iter::successors(next_notification(settings, now), |after| {
+        next_notification(settings, *after)
+    })
+    .take_while(|next| *next < horizon)
+    .filter(|next| !is_day_off(settings, blackouts, *next))
+    .take(count)
+    .collect()
+}
+
+/// Whether nothing is sent on the chat's local day of `moment`.
+fn is_day_off(settings: &UserSettings, blackouts: &[Blackout], moment: DateTime<Utc>) -> bool {
+    let day = moment
+        .with_timezone(&settings.offset_at(moment))
+        .date_naive();
+    settings.holidays.contains(&day) || blackouts::find(blackouts, day).is_some()
 }
 
-fn its_working_time(date: DateTime<FixedOffset>) -> bool {
-    match (date.weekday(), date.hour()) {
-        (Weekday::Sat | Weekday::Sun, _) => false,
-        (_, hour) => hour >= HOUR_FROM && hour < HOUR_TO,
+/// Whether the schedule ever fires more often than `MIN_INTERVAL` allows,
+/// judged by its upcoming dates.
+pub fn too_frequent(schedule: &Schedule) -> bool {
+    let min_interval = chrono::Duration::seconds(MIN_INTERVAL.as_secs() as i64);
+    let dates: Vec<DateTime<Utc>> = schedule.upcoming(Utc).take(500).collect();
+    dates
+        .windows(2)
+        .any(|pair| pair[1] - pair[0] < min_interval)
+}
+
+/// Sends `text` with the `keyboard` under it, returns the ids of the
+/// messages sent or why they didn't go. With an
+/// `attachment` the text goes as its caption, or right after it when it's a
+/// sticker or the text is too long for a caption.
+///
+/// Groups in slow mode reject messages sent too soon after the previous one,
+/// the delay they ask for is kept in the pipeline and later sends wait it out.
+/// Low priority texts are dropped while the pipeline is congested or the
+/// daily budget is tight, only high priority ones go once it's spent. A
+/// dropped text comes back as [`Failure::Dropped`]. A text delivered to the
+/// chat moments ago, e.g. by an overlapping reminder, isn't sent again and
+/// no messages come back.
+#[allow(clippy::too_many_arguments)]
+async fn deliver<B>(
+    bot: &B,
+    pipeline: &Pipeline,
+    user_id: ChatId,
+    text: &MessageText,
+    keyboard: Option<&InlineKeyboardMarkup>,
+    attachment: Option<&Attachment>,
+    priority: Priority,
+    silent: bool,
+) -> Result<Vec<MessageId>, Failure>
+where
+    B: Requester<Err = RequestError>,
+{
+    if is_duplicate(pipeline, user_id, text) {
+        return Ok(vec![]);
     }
+    admit(pipeline, user_id, priority)?;
+    let thread_id = pipeline.topics.get(&user_id);
+    let _in_flight = pipeline.start_send();
+    wait_slow_mode(pipeline, user_id).await;
+
+    let sent = match attachment {
+        Some(Attachment::Document(document)) => {
+            let file =
+                InputFile::memory(document.contents.clone()).file_name(document.name.clone());
+            let caption = Some(text);
+            send_file(
+                bot,
+                user_id,
+                MediaKind::Document,
+                file,
+                caption,
+                keyboard,
+                thread_id,
+                silent,
+            )
+            .await
+            .map(|id| vec![id])
+        }
+        Some(Attachment::Media(media)) => {
+            let file = match media.is_url() {
+                true => match media.source.parse() {
+                    Ok(url) => InputFile::url(url),
+                    Err(_) => InputFile::file_id(media.source.clone()),
+                },
+                false => InputFile::file_id(media.source.clone()),
+            };
+            let fits = text.text().encode_utf16().count() <= CAPTION_LIMIT;
+            match media.kind != MediaKind::Sticker && fits {
+                true => send_file(
+                    bot,
+                    user_id,
+                    media.kind,
+                    file,
+                    Some(text),
+                    keyboard,
+                    thread_id,
+                    silent,
+                )
+                .await
+                .map(|id| vec![id]),
+                false => match send_file(
+                    bot, user_id, media.kind, file, None, None, thread_id, silent,
+                )
+                .await
+                {
+                    Ok(file_id) => send_text(bot, user_id, text, keyboard, thread_id, silent)
+                        .await
+                        .map(|id| vec![file_id, id]),
+                    Err(err) => Err(err),
+                },
+            }
+        }
+        None => send_text(bot, user_id, text, keyboard, thread_id, silent)
+            .await
+            .map(|id| vec![id]),
+    };
+
+    let ids = settle(pipeline, user_id, sent)?;
+    pipeline.deduplicator.record(&user_id, text.text());
+    Ok(ids)
 }
 
-fn get_sleep_time(date: DateTime<FixedOffset>) -> Duration {
-    let days = match date.weekday() {
-        Weekday::Fri => {
-            if date.hour() >= HOUR_TO {
-                3
-            } else {
-                0
+/// Edits the chat's message `id` into `text` the way [`deliver`] sends one:
+/// once per slot, within the daily budget and after the chat's slow mode.
+/// An edit Telegram asks to hold off is tried again after the wait.
+async fn deliver_edit<B>(
+    bot: &B,
+    pipeline: &Pipeline,
+    user_id: ChatId,
+    id: MessageId,
+    text: &MessageText,
+    keyboard: &InlineKeyboardMarkup,
+    priority: Priority,
+) -> Result<(), EditFailure>
+where
+    B: Requester<Err = RequestError>,
+{
+    if is_duplicate(pipeline, user_id, text) {
+        return Ok(());
+    }
+    admit(pipeline, user_id, priority).map_err(EditFailure::Failed)?;
+    let _in_flight = pipeline.start_send();
+
+    let mut retries = 0;
+    loop {
+        wait_slow_mode(pipeline, user_id).await;
+        match edit_text(bot, user_id, id, text, keyboard).await {
+            Err(RequestError::RetryAfter(retry_after)) if retries < MAX_EDIT_RETRIES => {
+                log::info!(
+                    "Edit of the notification for {} waits {} as Telegram asked",
+                    user_id,
+                    format_seconds(retry_after.as_secs())
+                );
+                retries += 1;
+                async_sleep(retry_after).await;
+            }
+            Err(err) if delivery::is_uneditable(&err) => {
+                log::info!(
+                    "Notification message {} for {} can't be edited: {}",
+                    id,
+                    user_id,
+                    err
+                );
+                return Err(EditFailure::Unavailable);
+            }
+            edited => {
+                settle(pipeline, user_id, edited).map_err(EditFailure::Failed)?;
+                pipeline.deduplicator.record(&user_id, text.text());
+                return Ok(());
             }
         }
-        Weekday::Sat => 2,
-        Weekday::Sun => 1,
-        _ => 0,
+    }
+}
+
+/// Why the day's notification message wasn't edited.
+#[derive(Debug, PartialEq)]
+enum EditFailure {
+    /// There's no message to edit, it's gone or too old, a new one goes
+    /// instead
+    Unavailable,
+    Failed(Failure),
+}
+
+/// Whether the same text reached the chat within the dedup window.
+fn is_duplicate(pipeline: &Pipeline, user_id: ChatId, text: &MessageText) -> bool {
+    let duplicate = pipeline.deduplicator.is_duplicate(&user_id, text.text());
+    if duplicate {
+        log::info!(
+            "Message for {} merged with an identical one sent in this slot",
+            user_id
+        );
+    }
+    duplicate
+}
+
+/// Drops a message the congested pipeline or the daily budget has no room
+/// for.
+fn admit(pipeline: &Pipeline, user_id: ChatId, priority: Priority) -> Result<(), Failure> {
+    if priority == Priority::Low && pipeline.is_congested() {
+        log::warn!(
+            "Low priority message for {} dropped, the send queue is congested",
+            user_id
+        );
+        return Err(Failure::Dropped);
+    }
+
+    let over_budget = match pipeline.budget.level() {
+        BudgetLevel::Normal => false,
+        BudgetLevel::Tight => priority == Priority::Low,
+        BudgetLevel::Exhausted => priority != Priority::High,
     };
+    if over_budget {
+        log::warn!(
+            "Message for {} dropped, the daily send budget is running out",
+            user_id
+        );
+        return Err(Failure::Dropped);
+    }
+    Ok(())
+}
+
+async fn wait_slow_mode(pipeline: &Pipeline, user_id: ChatId) {
+    if let Some(wait) = pipeline.slow_mode.wait(&user_id) {
+        log::info!(
+            "Notification for {} waits {} for the chat's slow mode",
+            user_id,
+            format_seconds(wait.as_secs())
+        );
+        async_sleep(wait).await;
+    }
+}
 
-    let hours: u32;
-    if days == 0 {
-        if date.hour() < HOUR_FROM {
-            hours = HOUR_FROM - date.hour();
-        } else if date.hour() >= HOUR_TO {
-            hours = 24 - date.hour() + HOUR_FROM;
-        } else {
-            hours = 1;
+/// Counts a request that went through against the budget and the chat's
+/// slow mode, keeps the delay a rejected one asks for.
+fn settle<T>(
+    pipeline: &Pipeline,
+    user_id: ChatId,
+    sent: Result<T, RequestError>,
+) -> Result<T, Failure> {
+    match sent {
+        Ok(sent) => {
+            log::debug!("Notification message for {} sent!", user_id);
+            pipeline.slow_mode.sent(&user_id);
+            pipeline.budget.record();
+            Ok(sent)
+        }
+        Err(err) => {
+            // Private chats have no slow mode, there it's the global flood limit
+            if let Some(retry_after) = err.retry_after().filter(|_| !user_id.is_user()) {
+                pipeline.slow_mode.rejected(&user_id, retry_after);
+            }
+            log::error!("Notification message for {} didn't sent: {}", user_id, err);
+            Err(Failure::of(&err))
         }
-    } else if date.hour() < HOUR_FROM {
-        hours = 24 * days + HOUR_FROM;
-    } else {
-        hours = 24 * days - (date.hour() - HOUR_FROM);
     }
+}
 
-    let mut minutes: u32 = hours * 60;
-    if date.minute() > 0 {
-        minutes -= date.minute();
+/// Sends `text` as a message, as plain text when Telegram can't parse its
+/// markup. Returns the id of the message.
+async fn send_text<B>(
+    bot: &B,
+    user_id: ChatId,
+    text: &MessageText,
+    keyboard: Option<&InlineKeyboardMarkup>,
+    thread_id: Option<i32>,
+    silent: bool,
+) -> Result<MessageId, RequestError>
+where
+    B: Requester<Err = RequestError>,
+{
+    let mut text = Cow::Borrowed(text);
+    loop {
+        let mut request = bot.send_message(user_id, text.text());
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        if !text.entities().is_empty() {
+            request = request.entities(text.entities().to_vec());
+        }
+        if let Some(parse_mode) = text.parse_mode() {
+            request = request.parse_mode(parse_mode);
+        }
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard.clone());
+        }
+        if silent {
+            request = request.disable_notification(true);
+        }
+        match request.await {
+            Err(err) if text.parse_mode().is_some() && delivery::is_markup_error(&err) => {
+                log::warn!("Unable to parse the markup of a message to {}", user_id);
+                text = Cow::Owned(text.as_plain());
+            }
+            sent => return sent.map(|message| message.id),
+        }
     }
+}
 
-    let mut seconds = minutes * 60;
-    if date.second() > 0 {
-        seconds -= date.second();
+/// Edits the chat's message `id` into `text`, as plain text when Telegram
+/// can't parse its markup. Text that is already there counts as edited.
+async fn edit_text<B>(
+    bot: &B,
+    user_id: ChatId,
+    id: MessageId,
+    text: &MessageText,
+    keyboard: &InlineKeyboardMarkup,
+) -> Result<(), RequestError>
+where
+    B: Requester<Err = RequestError>,
+{
+    let mut text = Cow::Borrowed(text);
+    loop {
+        let mut request = bot
+            .edit_message_text(user_id, id, text.text())
+            .reply_markup(keyboard.clone());
+        if !text.entities().is_empty() {
+            request = request.entities(text.entities().to_vec());
+        }
+        if let Some(parse_mode) = text.parse_mode() {
+            request = request.parse_mode(parse_mode);
+        }
+        match request.await {
+            Err(err) if text.parse_mode().is_some() && delivery::is_markup_error(&err) => {
+                log::warn!("Unable to parse the markup of a message to {}", user_id);
+                text = Cow::Owned(text.as_plain());
+            }
+            Err(RequestError::Api(ApiError::MessageNotModified)) => return Ok(()),
+            edited => return edited.map(|_| ()),
+        }
+    }
+}
+
+/// Sends the `file` shown as `kind`, with the `caption` unless it's a
+/// sticker. A caption Telegram can't parse the markup of goes as plain text.
+/// Returns the id of the message.
+#[allow(clippy::too_many_arguments)]
+async fn send_file<B>(
+    bot: &B,
+    user_id: ChatId,
+    kind: MediaKind,
+    file: InputFile,
+    caption: Option<&MessageText>,
+    keyboard: Option<&InlineKeyboardMarkup>,
+    thread_id: Option<i32>,
+    silent: bool,
+) -> Result<MessageId, RequestError>
+where
+    B: Requester<Err = RequestError>,
+{
+    let mut caption = caption.map(Cow::Borrowed);
+    loop {
+        let sent = match kind {
+            MediaKind::Photo => {
+                let mut request = bot.send_photo(user_id, file.clone());
+                if let Some(text) = &caption {
+                    request = request.caption(text.text());
+                    if !text.entities().is_empty() {
+                        request = request.caption_entities(text.entities().to_vec());
+                    }
+                    if let Some(parse_mode) = text.parse_mode() {
+                        request = request.parse_mode(parse_mode);
+                    }
+                }
+                if let Some(thread_id) = thread_id {
+                    request = request.message_thread_id(thread_id);
+                }
+                if let Some(keyboard) = keyboard {
+                    request = request.reply_markup(keyboard.clone());
+                }
+                if silent {
+                    request = request.disable_notification(true);
+                }
+                request.await.map(|message| message.id)
+            }
+            MediaKind::Document => {
+                let mut request = bot.send_document(user_id, file.clone());
+                if let Some(text) = &caption {
+                    request = request.caption(text.text());
+                    if !text.entities().is_empty() {
+                        request = request.caption_entities(text.entities().to_vec());
+                    }
+                    if let Some(parse_mode) = text.parse_mode() {
+                        request = request.parse_mode(parse_mode);
+                    }
+                }
+                if let Some(thread_id) = thread_id {
+                    request = request.message_thread_id(thread_id);
+                }
+                if let Some(keyboard) = keyboard {
+                    request = request.reply_markup(keyboard.clone());
+                }
+                if silent {
+                    request = request.disable_notification(true);
+                }
+                request.await.map(|message| message.id)
+            }
+            MediaKind::Sticker => {
+                let mut request = bot.send_sticker(user_id, file.clone());
+                if let Some(thread_id) = thread_id {
+                    request = request.message_thread_id(thread_id);
+                }
+                if let Some(keyboard) = keyboard {
+                    request = request.reply_markup(keyboard.clone());
+                }
+                if silent {
+                    request = request.disable_notification(true);
+                }
+                request.await.map(|message| message.id)
+            }
+        };
+        match (sent, &caption) {
+            (Err(err), Some(text))
+                if text.parse_mode().is_some() && delivery::is_markup_error(&err) =>
+            {
+                log::warn!("Unable to parse the markup of a caption to {}", user_id);
+                caption = Some(Cow::Owned(text.as_plain()));
+            }
+            (sent, _) => return sent,
+        }
     }
+}
 
-    Duration::from_secs(u64::from(seconds))
+/// What the scheduler loop and the sends it starts share.
+struct Context<B> {
+    bot: Arc<B>,
+    pipeline: Arc<Pipeline>,
+    notification: Arc<Notification>,
+    evicted: UnboundedSender<ChatId>,
+    counts: Arc<SendCounts>,
+    sent: Arc<SentMessages>,
+    blackouts: Arc<Vec<Blackout>>,
+    channels: Arc<Channels>,
+    skipped: Option<UnboundedSender<ChatId>>,
+    delivered: Option<UnboundedSender<(ChatId, NaiveDate, DateTime<Utc>)>>,
+    journal: Option<Arc<Journal>>,
+    queue: Arc<std::sync::Mutex<Queue>>,
+    changed: Arc<Notify>,
+    /// Sends started by the scheduler loop, until the supervisor sees them
+    /// finished
+    sends: std::sync::Mutex<Vec<(Firing, JoinHandle<()>)>>,
 }
 
-async fn notify_task(user_id: ChatId, bot: Arc<Bot>, fixed_offset: FixedOffset, message: String) {
-    let get_user_date = || fixed_offset.from_utc_datetime(&Local::now().naive_utc());
-    let send_notification = || async {
-        match bot
-            .send_message(
-                user_id,
-                format!(
-                    "{}\n\n{}",
-                    message, "Send the \"/done\" command to turn off notifications until tomorrow"
-                ),
-            )
-            .await
-        {
-            Ok(_) => {
-                log::debug!("Notification message for {} sent!", user_id);
-                return true;
+impl<B> Context<B> {
+    /// Keeps how a send through the channel went, for "/channels" and the
+    /// journal.
+    fn record(&self, chat_id: ChatId, channel: &str, result: Result<(), String>) {
+        if let Some(journal) = &self.journal {
+            journal.record(chat_id, channel, &result);
+        }
+        self.channels.record(chat_id, channel, result);
+    }
+}
+
+/// Runs the scheduler loop, starting it again when it ends, e.g. on a panic,
+/// and schedules again chats whose send panicked, as if it failed.
+async fn supervise<B>(context: Arc<Context<B>>)
+where
+    B: Requester<Err = RequestError> + Send + Sync + 'static,
+    B::SendMessage: Send,
+    B::SendDocument: Send,
+    B::SendPhoto: Send,
+    B::SendSticker: Send,
+    B::EditMessageText: Send,
+{
+    // Aborts the loop when the supervisor is aborted
+    let mut scheduler = JoinSet::new();
+    scheduler.spawn(run_scheduler(Arc::clone(&context)));
+    loop {
+        tokio::select! {
+            Some(result) = scheduler.join_next() => {
+                let panicked = result.is_err_and(|err| err.is_panic());
+                log::error!("Scheduler loop ended (panicked: {}), restarting it", panicked);
+                async_sleep(RESTART_DELAY).await;
+                scheduler.spawn(run_scheduler(Arc::clone(&context)));
             }
-            Err(err) => {
-                log::error!("Notification message for {} didn't sent: {}", user_id, err);
-                return false;
+            _ = async_sleep(SUPERVISE_INTERVAL) => {}
+        }
+
+        let finished: Vec<(Firing, JoinHandle<()>)> = {
+            let mut sends = context.sends.lock().unwrap();
+            let (finished, running) = sends.drain(..).partition(|(_, send)| send.is_finished());
+            *sends = running;
+            finished
+        };
+        for (firing, send) in finished {
+            if let Err(err) = send.await {
+                if err.is_panic() {
+                    log::error!("Send to {} panicked, scheduling it again", firing.chat_id);
+                    reschedule(&context, &firing, Err(Failure::Transient));
+                }
             }
         }
-    };
-    let sleep = |duration: Duration| {
-        log::debug!(
-            "Sleep time {}. user_id={}, offset={}",
-            format_seconds(duration.as_secs()),
-            user_id,
-            fixed_offset.to_string(),
-        );
-        async_sleep(duration)
-    };
+    }
+}
 
-    log::debug!("Started notification task for {}!", user_id);
+/// Sleeps until the earliest chat is due, then sends the notifications of
+/// every chat due by then concurrently, each send schedules its chat again.
+async fn run_scheduler<B>(context: Arc<Context<B>>)
+where
+    B: Requester<Err = RequestError> + Send + Sync + 'static,
+    B::SendMessage: Send,
+    B::SendDocument: Send,
+    B::SendPhoto: Send,
+    B::SendSticker: Send,
+    B::EditMessageText: Send,
+{
     loop {
+        let (due, next) = {
+            let mut queue = context.queue.lock().unwrap();
+            let due: Vec<Firing> = queue
+                .take_due(Instant::now())
+                .into_iter()
+                .filter_map(|(chat_id, ticket)| {
+                    let chat = queue.get(&chat_id)?;
+                    Some(Firing {
+                        chat_id,
+                        ticket,
+                        settings: Arc::clone(&chat.settings),
+                        wake: chat.wake,
+                        cron_date: chat.cron_date,
+                        held: chat.held,
+                    })
+                })
+                .collect();
+            (due, queue.next_due())
+        };
+        if !due.is_empty() {
+            log::debug!("Sending {} due notifications", due.len());
+        }
         {
-            let date = get_user_date();
-            if !its_working_time(date) {
-                sleep(get_sleep_time(date)).await;
+            let mut sends = context.sends.lock().unwrap();
+            for firing in due {
+                let send = spawn(fire(Arc::clone(&context), firing.clone()));
+                sends.push((firing, send));
             }
         }
 
-        sleep(match send_notification().await {
-            true => get_sleep_time(get_user_date()),
-            false => Duration::from_secs(60),
-        })
-        .await;
+        scheduling::wait_for(next, &context.changed).await;
+    }
+}
+
+/// Sends a due chat's notification and schedules the chat again, unless it
+/// was stopped or restarted meanwhile.
+async fn fire<B>(context: Arc<Context<B>>, firing: Firing)
+where
+    B: Requester<Err = RequestError>,
+{
+    let Firing {
+        chat_id,
+        ticket,
+        ref settings,
+        wake,
+        held,
+        ..
+    } = firing;
 
-        if !its_working_time(get_user_date()) {
-            log::debug!(
-                "Sending today's last message for {} {}",
-                user_id,
-                fixed_offset.to_string()
+    // Stopped or restarted since it was taken from the queue, e.g. while the
+    // scheduler waited for the lock
+    if !context.queue.lock().unwrap().is_current(&chat_id, ticket) {
+        return;
+    }
+
+    // Repetitions stop when working time is over
+    let now = Utc::now();
+    let repeat_after_hours = matches!(wake, Wake::Repeat { .. })
+        && settings.schedule().is_none()
+        && !its_working_time(
+            settings.offset_at(now).from_utc_datetime(&now.naive_utc()),
+            settings.working_hours,
+            settings.workdays,
+            &settings.quiet_hours,
+        );
+    let sent = match repeat_after_hours {
+        true => Ok(Sent::Skipped),
+        false => send_notification(&context, chat_id, settings, held).await,
+    };
+    reschedule(&context, &firing, sent);
+}
+
+/// Schedules the chat again after its send ended with `sent`, unless it was
+/// stopped or restarted meanwhile.
+fn reschedule<B>(context: &Context<B>, firing: &Firing, sent: Result<Sent, Failure>) {
+    let Firing {
+        chat_id,
+        ticket,
+        ref settings,
+        wake,
+        cron_date,
+        held,
+    } = *firing;
+
+    let mut queue = context.queue.lock().unwrap();
+    let Some(chat) = queue.get_current_mut(&chat_id, ticket) else {
+        return;
+    };
+    match sent {
+        Ok(Sent::Delivered) => chat.held = chat.held.saturating_sub(held),
+        Ok(Sent::Held) => chat.held += 1,
+        Ok(Sent::Suppressed) => {
+            let mut settings = (*chat.settings).clone();
+            settings.skip = settings.skip.saturating_sub(1);
+            chat.settings = Arc::new(settings);
+            if let Some(skipped) = &context.skipped {
+                let _ = skipped.send(chat_id);
+            }
+        }
+        _ => {}
+    }
+
+    match plan(
+        settings,
+        wake,
+        cron_date,
+        sent,
+        chat.failures,
+        Utc::now(),
+        Instant::now(),
+    ) {
+        Plan::Due {
+            at,
+            wake,
+            cron_date,
+            failures,
+        } => {
+            chat.wake = wake;
+            chat.cron_date = cron_date;
+            chat.failures = failures;
+            queue.schedule(chat_id, at);
+            drop(queue);
+            context.changed.notify_one();
+        }
+        Plan::Finished => log::info!("Cron schedule of {} has no dates left", chat_id),
+        Plan::Evict => {
+            log::warn!("Chat {} is gone, stopping its notifications", chat_id);
+            // Nobody listens only while shutting down
+            let _ = context.evicted.send(chat_id);
+        }
+    }
+}
+
+/// When the chat is due after a send that ended with `sent`, as of `now`
+/// and `instant`.
+///
+/// In ack mode a delivered notification is repeated every [`ACK_INTERVAL`]
+/// until it's acknowledged or the next one is closer than that. Failed sends
+/// are retried with a growing delay, except with a cron schedule.
+fn plan(
+    settings: &UserSettings,
+    wake: Wake,
+    cron_date: Option<DateTime<Utc>>,
+    sent: Result<Sent, Failure>,
+    failures: u32,
+    now: DateTime<Utc>,
+    instant: Instant,
+) -> Plan {
+    let delivered = sent == Ok(Sent::Delivered);
+    // Repeats when the next notification is far enough, otherwise waits for it
+    let next = |until: Instant, cron_date| {
+        let wake = match settings.ack
+            && delivered
+            && until.saturating_duration_since(instant) > ACK_INTERVAL
+        {
+            true => Wake::Repeat { until },
+            false => Wake::Notify,
+        };
+        Plan::Due {
+            at: match wake {
+                Wake::Repeat { .. } => instant + ACK_INTERVAL,
+                Wake::Notify => until,
+            },
+            wake,
+            cron_date,
+            failures: 0,
+        }
+    };
+
+    if sent == Err(Failure::Gone) {
+        return Plan::Evict;
+    }
+    if let Wake::Repeat { until } = wake {
+        return next(until, cron_date);
+    }
+
+    if let Some(schedule) = settings.schedule() {
+        // Dates missed while the process was suspended are skipped
+        let after = cron_date.map_or(now, |date| date.max(now));
+        let Some(date) = next_cron(&schedule, &settings.zone(), after) else {
+            return Plan::Finished;
+        };
+        let until = instant + (date - now).to_std().unwrap_or_default();
+        // The notification sent right after "/start" isn't repeated
+        return match cron_date {
+            Some(_) => next(until, Some(date)),
+            None => Plan::Due {
+                at: until,
+                wake: Wake::Notify,
+                cron_date: Some(date),
+                failures: 0,
+            },
+        };
+    }
+
+    match sent {
+        Err(Failure::RetryAfter(retry_after)) => Plan::Due {
+            at: instant + retry_after,
+            wake: Wake::Notify,
+            cron_date: None,
+            failures,
+        },
+        // A dropped notification waits for the next slot like a sent one
+        Ok(_) | Err(Failure::Dropped) => {
+            let date = settings.offset_at(now).from_utc_datetime(&now.naive_utc());
+            let pause = get_sleep_time(
+                date,
+                settings.working_hours,
+                settings.workdays,
+                &settings.quiet_hours,
+                settings.interval(),
             );
-            send_notification().await;
+            next(instant + pause, None)
         }
+        Err(_) => Plan::Due {
+            at: instant + delivery::backoff(failures + 1),
+            wake: Wake::Notify,
+            cron_date: None,
+            failures: failures + 1,
+        },
     }
 }
 
+/// Sends the chat's notification unless nothing is due today or it's held
+/// back to save the daily budget.
+async fn send_notification<B>(
+    context: &Context<B>,
+    user_id: ChatId,
+    settings: &UserSettings,
+    held: u32,
+) -> Result<Sent, Failure>
+where
+    B: Requester<Err = RequestError>,
+{
+    if settings.away {
+        log::debug!("Notification for {} skipped, the user is away", user_id);
+        return Ok(Sent::Skipped);
+    }
+    let now = Utc::now();
+    let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+    if settings.holidays.contains(&today) {
+        log::debug!("Notification for {} skipped, it's a holiday", user_id);
+        return Ok(Sent::Skipped);
+    }
+    if blackouts::find(&context.blackouts, today).is_some() {
+        log::debug!("Notification for {} skipped, it's a blackout", user_id);
+        return Ok(Sent::Skipped);
+    }
+    if settings.skip > 0 {
+        log::debug!(
+            "Notification for {} skipped, {} left to skip",
+            user_id,
+            settings.skip - 1
+        );
+        return Ok(Sent::Suppressed);
+    }
+    // Every other one goes while the budget is tight, none once it's spent
+    match context.pipeline.budget.level() {
+        BudgetLevel::Normal => {}
+        BudgetLevel::Tight if held > 0 => {}
+        _ => {
+            log::info!("Notification for {} held back, saving the budget", user_id);
+            return Ok(Sent::Held);
+        }
+    }
+
+    let count = context.counts.on(&user_id, today) + 1;
+    let (mut text, media) = context.notification.compose(settings, now, count);
+    let attachment = media.map(Attachment::Media);
+    if held > 0 {
+        text.append(&MessageText::plain(format!(
+            "\n\n{}",
+            tr!(settings.locale, "budget-digest", count = held)
+        )));
+    }
+    // The same notification may come twice in a slot, e.g. around a restart
+    // or along with a reminder of the same text
+    if is_duplicate(&context.pipeline, user_id, &text) {
+        return Ok(Sent::Skipped);
+    }
+    let keyboard = keyboards::notification(settings.locale, settings.ack);
+    // No sound at the ends of working hours the chat chose to be soft
+    let local = settings.offset_at(now).from_utc_datetime(&now.naive_utc());
+    let silent = settings.schedule().is_none()
+        && settings
+            .working_hours
+            .is_edge(local.hour(), settings.soft_hours);
+    // Single message mode edits the day's last text notification
+    let previous = match settings.single_message && attachment.is_none() {
+        true => context.sent.last(&user_id, today),
+        false => None,
+    };
+
+    // The first channel that works delivers, the chat being gone still
+    // gets it evicted
+    let mut failure = None;
+    for name in context.channels.order(settings) {
+        let result = match name.as_str() {
+            channels::TELEGRAM => {
+                let edited = match previous {
+                    Some(id) => {
+                        deliver_edit(
+                            &*context.bot,
+                            &context.pipeline,
+                            user_id,
+                            id,
+                            &text,
+                            &keyboard,
+                            NOTIFICATION_PRIORITY,
+                        )
+                        .await
+                    }
+                    None => Err(EditFailure::Unavailable),
+                };
+                match edited {
+                    Ok(()) => Ok(()),
+                    // A new message goes only when there's none to edit
+                    Err(EditFailure::Unavailable) => deliver(
+                        &*context.bot,
+                        &context.pipeline,
+                        user_id,
+                        &text,
+                        Some(&keyboard),
+                        attachment.as_ref(),
+                        NOTIFICATION_PRIORITY,
+                        silent,
+                    )
+                    .await
+                    .map(|ids| context.sent.record(user_id, today, &ids)),
+                    Err(EditFailure::Failed(err)) => Err(err),
+                }
+                .map_err(|err| (err, channels::describe(err)))
+            }
+            _ => context
+                .channels
+                .send(&name, settings, text.text())
+                .await
+                .map(|()| context.pipeline.deduplicator.record(&user_id, text.text()))
+                .map_err(|err| {
+                    log::warn!("Unable to notify {} through {}: {}", user_id, name, err);
+                    (Failure::Transient, err)
+                }),
+        };
+        match result {
+            Ok(()) => {
+                context.record(user_id, &name, Ok(()));
+                context.counts.record(user_id, today);
+                if let Some(delivered) = &context.delivered {
+                    let _ = delivered.send((user_id, today, now));
+                }
+                return Ok(Sent::Delivered);
+            }
+            Err((err, reason)) => {
+                context.record(user_id, &name, Err(reason));
+                if err == Failure::Gone {
+                    return Err(err);
+                }
+                failure.get_or_insert(err);
+            }
+        }
+    }
+    Err(failure.unwrap_or(Failure::Transient))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::notify_controller::{
-        format_seconds, get_sleep_time, its_working_time, HOUR_FROM, HOUR_TO,
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc, Weekday};
+    use teloxide::{
+        types::{ChatId, MessageId},
+        Bot,
     };
-    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+    use tokio::{sync::mpsc::unbounded_channel, time::Instant};
+
+    use crate::{
+        blackouts::Blackout,
+        delivery::{self, Failure, Priority},
+        i18n::Locale,
+        message_text::{Markup, MessageText},
+        notify_controller::{
+            compose, format_seconds, next_notification, plan, send_notification, too_frequent,
+            upcoming_notifications, Notification, Plan, SendCounts, Sent, SentMessages, Wake,
+            ACK_INTERVAL, DEFAULT_INTERVAL,
+        },
+        offsets_rep::{Footer, UserSettings, Workdays, WorkingHours},
+        scheduling::{get_sleep_time, its_working_time, HOUR_FROM, HOUR_TO},
+        templates::Rotation,
+    };
+
+    type Calls = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// A bot talking to a local stand-in for the Bot API, which answers every
+    /// call with `answer(method, body)` and records the calls.
+    fn fake_bot(answer: fn(&str, &str) -> String) -> (Bot, Calls) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Calls::default();
+        let recorded = Arc::clone(&calls);
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let method = line.split(' ').nth(1).unwrap_or_default();
+                let method = method.rsplit('/').next().unwrap().to_lowercase();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let body = String::from_utf8(body).unwrap();
+
+                let response = answer(&method, &body);
+                recorded.lock().unwrap().push((method, body));
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                );
+            }
+        });
+        (Bot::new("1:token").set_api_url(url.parse().unwrap()), calls)
+    }
+
+    /// A sent message as the Bot API returns it.
+    fn message_json(id: i32) -> String {
+        format!(
+            r#"{{"ok":true,"result":{{"message_id":{},"date":0,"chat":{{"id":1,"type":"private","first_name":"A"}},"text":"text"}}}}"#,
+            id
+        )
+    }
+
+    #[test]
+    fn test_send_counts() {
+        let counts = SendCounts::default();
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+
+        counts.record(ChatId(1), monday);
+        counts.record(ChatId(1), monday);
+        counts.record(ChatId(2), monday);
+        assert_eq!(counts.on(&ChatId(1), monday), 2);
+        assert_eq!(counts.on(&ChatId(2), monday), 1);
+        assert_eq!(counts.on(&ChatId(3), monday), 0);
+
+        counts.record(ChatId(1), tuesday);
+        assert_eq!(counts.on(&ChatId(1), tuesday), 1);
+        assert_eq!(counts.on(&ChatId(1), monday), 0);
+    }
+
+    #[test]
+    fn test_sent_messages() {
+        let sent = SentMessages::default();
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+
+        sent.record(ChatId(1), monday, &[MessageId(1)]);
+        sent.record(ChatId(1), monday, &[MessageId(2), MessageId(3)]);
+        sent.record(ChatId(2), monday, &[MessageId(4)]);
+        assert_eq!(
+            sent.take(&ChatId(1), monday),
+            vec![MessageId(1), MessageId(2), MessageId(3)]
+        );
+        assert_eq!(sent.take(&ChatId(1), monday), vec![]);
+
+        sent.record(ChatId(2), tuesday, &[MessageId(5)]);
+        assert_eq!(sent.last(&ChatId(2), monday), None);
+        assert_eq!(sent.last(&ChatId(2), tuesday), Some(MessageId(5)));
+        assert_eq!(sent.take(&ChatId(2), monday), vec![]);
+        sent.record(ChatId(2), tuesday, &[MessageId(6)]);
+        assert_eq!(
+            sent.take(&ChatId(2), tuesday),
+            vec![MessageId(5), MessageId(6)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_sent() {
+        // The second message is too old to delete, it's edited instead
+        let (bot, calls) = fake_bot(|method, body| {
+            match method {
+            "deletemessage" if body.contains(r#""message_id":2"#) => {
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: message can't be deleted"}"#
+                    .to_string()
+            }
+            "deletemessage" => r#"{"ok":true,"result":true}"#.to_string(),
+            _ => message_json(2),
+        }
+        });
+        let (evicted, _) = unbounded_channel();
+        let sender =
+            Notification::build(vec![], Rotation::default(), Markup::Plain).sender(bot, evicted);
+        let day = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        sender
+            .sent
+            .record(ChatId(1), day, &[MessageId(1), MessageId(2)]);
+
+        sender.clear_sent(&ChatId(1), day, Locale::En).await;
+        let calls = calls.lock().unwrap().clone();
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(
+            methods,
+            ["deletemessage", "deletemessage", "editmessagetext"]
+        );
+        assert!(calls[2].1.contains(r#""message_id":2"#), "{}", calls[2].1);
+        assert!(sender.sent.last(&ChatId(1), day).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_single_message_edit_fails() {
+        // The day's notification was deleted, a new one goes instead
+        let (bot, calls) = fake_bot(|method, _| {
+            match method {
+            "editmessagetext" => {
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: message to edit not found"}"#
+                    .to_string()
+            }
+            _ => message_json(6),
+        }
+        });
+        let (evicted, _) = unbounded_channel();
+        let notification = Notification::build(
+            vec!["Stand up".to_string()],
+            Rotation::default(),
+            Markup::Plain,
+        );
+        let sender = notification.sender(bot, evicted);
+        let settings = UserSettings {
+            single_message: true,
+            ..UserSettings::default()
+        };
+        let now = Utc::now();
+        let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+        sender.sent.record(ChatId(1), today, &[MessageId(5)]);
+
+        let sent = send_notification(&sender.context(), ChatId(1), &settings, 0).await;
+        assert_eq!(sent, Ok(Sent::Delivered));
+        let calls = calls.lock().unwrap().clone();
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(methods, ["editmessagetext", "sendmessage"]);
+        assert!(calls[1].1.contains("Stand up"), "{}", calls[1].1);
+        assert_eq!(sender.sent.last(&ChatId(1), today), Some(MessageId(6)));
+    }
+
+    #[tokio::test]
+    async fn test_single_message_edit_waits() {
+        // Telegram asks to wait, the edit goes again instead of a new message
+        // and an unchanged text counts as edited
+        static EDITS: AtomicUsize = AtomicUsize::new(0);
+        let (bot, calls) = fake_bot(|method, _| {
+            match (method, EDITS.fetch_add(1, Ordering::SeqCst)) {
+            ("editmessagetext", 0) => {
+                r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 1","parameters":{"retry_after":1}}"#
+                    .to_string()
+            }
+            ("editmessagetext", _) => {
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: message is not modified: specified new message content and reply markup are exactly the same as a current content and reply markup of the message"}"#
+                    .to_string()
+            }
+            _ => message_json(6),
+        }
+        });
+        let (evicted, _) = unbounded_channel();
+        let notification = Notification::build(
+            vec!["Stand up".to_string()],
+            Rotation::default(),
+            Markup::Plain,
+        );
+        let sender = notification.sender(bot, evicted);
+        let settings = UserSettings {
+            single_message: true,
+            ..UserSettings::default()
+        };
+        let now = Utc::now();
+        let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+        sender.sent.record(ChatId(1), today, &[MessageId(5)]);
+
+        let sent = send_notification(&sender.context(), ChatId(1), &settings, 0).await;
+        assert_eq!(sent, Ok(Sent::Delivered));
+        let calls = calls.lock().unwrap().clone();
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(methods, ["editmessagetext", "editmessagetext"]);
+        assert_eq!(sender.sent.last(&ChatId(1), today), Some(MessageId(5)));
+    }
+
+    #[tokio::test]
+    async fn test_single_message_edit_errs() {
+        // Any other failure is retried later, not turned into a new message
+        let (bot, calls) = fake_bot(|method, _| match method {
+            "editmessagetext" => {
+                r#"{"ok":false,"error_code":500,"description":"Internal Server Error"}"#.to_string()
+            }
+            _ => message_json(6),
+        });
+        let (evicted, _) = unbounded_channel();
+        let notification = Notification::build(
+            vec!["Stand up".to_string()],
+            Rotation::default(),
+            Markup::Plain,
+        );
+        let sender = notification.sender(bot, evicted);
+        let settings = UserSettings {
+            single_message: true,
+            ..UserSettings::default()
+        };
+        let now = Utc::now();
+        let today = now.with_timezone(&settings.offset_at(now)).date_naive();
+        sender.sent.record(ChatId(1), today, &[MessageId(5)]);
+
+        let sent = send_notification(&sender.context(), ChatId(1), &settings, 0).await;
+        assert_eq!(sent, Err(Failure::Transient));
+        let calls = calls.lock().unwrap().clone();
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(methods, ["editmessagetext"]);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_reminders() {
+        // Two reminders and the scheduled notification of one slot say the
+        // same, the chat gets one message
+        let (bot, calls) = fake_bot(|_, _| message_json(7));
+        let (evicted, _) = unbounded_channel();
+        let notification = Notification::build(
+            vec!["Stand up".to_string()],
+            Rotation::default(),
+            Markup::Plain,
+        );
+        let sender = notification.sender(bot, evicted);
+
+        for _ in 0..2 {
+            assert!(
+                sender
+                    .relay(&ChatId(1), "Stand up".to_string(), Priority::Normal, false)
+                    .await
+            );
+        }
+        let settings = UserSettings {
+            footer: Footer::Hidden,
+            ..UserSettings::default()
+        };
+        let sent = send_notification(&sender.context(), ChatId(1), &settings, 0).await;
+        assert_eq!(sent, Ok(Sent::Skipped));
+        assert!(
+            sender
+                .relay(&ChatId(2), "Stand up".to_string(), Priority::Normal, false)
+                .await
+        );
+
+        let calls = calls.lock().unwrap().clone();
+        let chats: Vec<bool> = calls
+            .iter()
+            .map(|(method, body)| method == "sendmessage" && body.contains(r#""chat_id":1"#))
+            .collect();
+        assert_eq!(chats, [true, false]);
+    }
+
+    #[test]
+    fn test_plan() {
+        let settings = UserSettings {
+            offset: 0,
+            ..Default::default()
+        };
+        let ack = UserSettings {
+            offset: 0,
+            ack: true,
+            ..Default::default()
+        };
+        // Monday 10:30, the next slot is at 11:00
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 10, 30, 0).unwrap();
+        let instant = Instant::now();
+        let slot = instant + Duration::from_secs(30 * 60);
+        let due = |at, wake, failures| Plan::Due {
+            at,
+            wake,
+            cron_date: None,
+            failures,
+        };
+
+        let delivered = Ok(Sent::Delivered);
+        assert_eq!(
+            plan(&settings, Wake::Notify, None, delivered, 2, now, instant),
+            due(slot, Wake::Notify, 0)
+        );
+        assert_eq!(
+            plan(
+                &settings,
+                Wake::Notify,
+                None,
+                Ok(Sent::Held),
+                0,
+                now,
+                instant
+            ),
+            due(slot, Wake::Notify, 0)
+        );
+        assert_eq!(
+            plan(
+                &settings,
+                Wake::Notify,
+                None,
+                Err(Failure::Gone),
+                0,
+                now,
+                instant
+            ),
+            Plan::Evict
+        );
+        let retry_after = Err(Failure::RetryAfter(Duration::from_secs(30)));
+        assert_eq!(
+            plan(&settings, Wake::Notify, None, retry_after, 1, now, instant),
+            due(instant + Duration::from_secs(30), Wake::Notify, 1)
+        );
+        assert_eq!(
+            plan(
+                &settings,
+                Wake::Notify,
+                None,
+                Err(Failure::Transient),
+                1,
+                now,
+                instant
+            ),
+            due(instant + delivery::backoff(2), Wake::Notify, 2)
+        );
+        assert_eq!(
+            plan(
+                &settings,
+                Wake::Notify,
+                None,
+                Err(Failure::Dropped),
+                1,
+                now,
+                instant
+            ),
+            due(slot, Wake::Notify, 0)
+        );
+
+        // Ack mode repeats delivered notifications until the next one is close
+        let repeat = Wake::Repeat { until: slot };
+        assert_eq!(
+            plan(&ack, Wake::Notify, None, delivered, 0, now, instant),
+            due(instant + ACK_INTERVAL, repeat, 0)
+        );
+        assert_eq!(
+            plan(&ack, Wake::Notify, None, Ok(Sent::Skipped), 0, now, instant),
+            due(slot, Wake::Notify, 0)
+        );
+        assert_eq!(
+            plan(
+                &ack,
+                Wake::Notify,
+                None,
+                Ok(Sent::Suppressed),
+                0,
+                now,
+                instant
+            ),
+            due(slot, Wake::Notify, 0)
+        );
+        assert_eq!(
+            plan(&ack, repeat, None, delivered, 0, now, instant),
+            due(instant + ACK_INTERVAL, repeat, 0)
+        );
+        let close = instant + ACK_INTERVAL;
+        assert_eq!(
+            plan(
+                &ack,
+                Wake::Repeat { until: close },
+                None,
+                delivered,
+                0,
+                now,
+                instant
+            ),
+            due(close, Wake::Notify, 0)
+        );
+
+        // Cron schedules go from date to date, failures aren't retried
+        let cron = UserSettings {
+            offset: 0,
+            cron: Some("0 0 */2 * * MON-FRI".to_string()),
+            ack: true,
+            ..Default::default()
+        };
+        let date = |hour| Utc.with_ymd_and_hms(2024, 5, 6, hour, 0, 0).unwrap();
+        let noon = instant + Duration::from_secs(90 * 60);
+        assert_eq!(
+            plan(
+                &cron,
+                Wake::Notify,
+                Some(date(10)),
+                Err(Failure::Transient),
+                0,
+                now,
+                instant
+            ),
+            Plan::Due {
+                at: noon,
+                wake: Wake::Notify,
+                cron_date: Some(date(12)),
+                failures: 0,
+            }
+        );
+        assert_eq!(
+            plan(
+                &cron,
+                Wake::Notify,
+                Some(date(10)),
+                delivered,
+                0,
+                now,
+                instant
+            ),
+            Plan::Due {
+                at: instant + ACK_INTERVAL,
+                wake: Wake::Repeat { until: noon },
+                cron_date: Some(date(12)),
+                failures: 0,
+            }
+        );
+        // The one sent right after "/start" isn't repeated
+        assert_eq!(
+            plan(&cron, Wake::Notify, None, delivered, 0, now, instant),
+            Plan::Due {
+                at: noon,
+                wake: Wake::Notify,
+                cron_date: Some(date(12)),
+                failures: 0,
+            }
+        );
+        let finished = UserSettings {
+            cron: Some("0 0 0 1 1 * 2020".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            plan(&finished, Wake::Notify, None, delivered, 0, now, instant),
+            Plan::Finished
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let message = MessageText::parse("Stand up ![🧍](tg://emoji?id=1)");
+
+        assert_eq!(
+            compose(&message, Footer::Text, Locale::En).text(),
+            "Stand up 🧍\n\nSend the \"/done\" command to turn off notifications until tomorrow"
+        );
+        assert_eq!(compose(&message, Footer::Hidden, Locale::En), message);
+    }
 
     #[test]
     fn test_format_seconds() {
@@ -222,11 +2245,11 @@ mod tests {
     }
 
     fn get_date(day: u32, hour: u32, min: u32, secs: u32) -> DateTime<FixedOffset> {
-        return FixedOffset::east_opt(0).unwrap().from_utc_datetime(
-            &Utc.with_ymd_and_hms(2023, 05, day, hour, min, secs)
+        FixedOffset::east_opt(0).unwrap().from_utc_datetime(
+            &Utc.with_ymd_and_hms(2023, 5, day, hour, min, secs)
                 .unwrap()
                 .naive_utc(),
-        );
+        )
     }
 
     #[test]
@@ -234,32 +2257,323 @@ mod tests {
         for minute in 0..=59 {
             for second in 0..=59 {
                 for hour in HOUR_FROM..HOUR_TO {
-                    assert!(its_working_time(get_date(1, hour, minute, second)));
+                    assert!(its_working_time(
+                        get_date(1, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[]
+                    ));
                 }
 
                 for hour in 0..HOUR_FROM {
-                    assert!(!its_working_time(get_date(1, hour, minute, second)));
+                    assert!(!its_working_time(
+                        get_date(1, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[]
+                    ));
                 }
 
                 for hour in HOUR_TO..24 {
-                    assert!(!its_working_time(get_date(1, hour, minute, second)));
+                    assert!(!its_working_time(
+                        get_date(1, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[]
+                    ));
                 }
 
                 for hour in 0..24 {
-                    assert!(!its_working_time(get_date(6, hour, minute, second)));
-                    assert!(!its_working_time(get_date(7, hour, minute, second)));
+                    assert!(!its_working_time(
+                        get_date(6, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[]
+                    ));
+                    assert!(!its_working_time(
+                        get_date(7, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[]
+                    ));
                 }
             }
         }
     }
 
+    #[test]
+    fn test_custom_working_hours() {
+        let hours = WorkingHours { from: 10, to: 16 };
+
+        assert!(!its_working_time(
+            get_date(1, 9, 59, 59),
+            hours,
+            Workdays::default(),
+            &[]
+        ));
+        assert!(its_working_time(
+            get_date(1, 10, 0, 0),
+            hours,
+            Workdays::default(),
+            &[]
+        ));
+        assert!(!its_working_time(
+            get_date(1, 16, 0, 0),
+            hours,
+            Workdays::default(),
+            &[]
+        ));
+
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 9, 30, 0),
+                hours,
+                Workdays::default(),
+                &[],
+                DEFAULT_INTERVAL
+            )
+            .as_secs(),
+            1800
+        );
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 16, 0, 0),
+                hours,
+                Workdays::default(),
+                &[],
+                DEFAULT_INTERVAL
+            )
+            .as_secs(),
+            18 * 3600
+        );
+    }
+
+    #[test]
+    fn test_custom_workdays() {
+        let hours = WorkingHours::default();
+        let workdays = Workdays::from_days([Weekday::Tue, Weekday::Sat]);
+
+        // 2023-05-01 is a Monday
+        assert!(!its_working_time(
+            get_date(1, 10, 0, 0),
+            hours,
+            workdays,
+            &[]
+        ));
+        assert!(its_working_time(
+            get_date(2, 10, 0, 0),
+            hours,
+            workdays,
+            &[]
+        ));
+        assert!(its_working_time(
+            get_date(6, 10, 0, 0),
+            hours,
+            workdays,
+            &[]
+        ));
+
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 10, 0, 0),
+                hours,
+                workdays,
+                &[],
+                DEFAULT_INTERVAL
+            )
+            .as_secs(),
+            23 * 3600
+        );
+        assert_eq!(
+            get_sleep_time(
+                get_date(2, 18, 0, 0),
+                hours,
+                workdays,
+                &[],
+                DEFAULT_INTERVAL
+            )
+            .as_secs(),
+            (4 * 24 + 9 - 18) * 3600
+        );
+        assert_eq!(
+            get_sleep_time(
+                get_date(6, 20, 0, 0),
+                hours,
+                workdays,
+                &[],
+                DEFAULT_INTERVAL
+            )
+            .as_secs(),
+            (3 * 24 + 9 - 20) * 3600
+        );
+
+        let workdays = Workdays::from_days([Weekday::Mon]);
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 18, 0, 0),
+                hours,
+                workdays,
+                &[],
+                DEFAULT_INTERVAL
+            )
+            .as_secs(),
+            (7 * 24 + 9 - 18) * 3600
+        );
+    }
+
+    #[test]
+    fn test_next_notification() {
+        let settings = UserSettings {
+            offset: 3 * 3600,
+            ..Default::default()
+        };
+
+        // Monday 10:20 local time
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 7, 20, 0).unwrap();
+        assert_eq!(
+            next_notification(&settings, now),
+            Some(Utc.with_ymd_and_hms(2023, 5, 1, 8, 0, 0).unwrap())
+        );
+
+        // Friday 19:00 local time
+        let now = Utc.with_ymd_and_hms(2023, 5, 5, 16, 0, 0).unwrap();
+        assert_eq!(
+            next_notification(&settings, now),
+            Some(Utc.with_ymd_and_hms(2023, 5, 8, 6, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_upcoming_notifications() {
+        let settings = UserSettings {
+            offset: 3 * 3600,
+            ..Default::default()
+        };
+
+        // Monday 17:20 local time, the end of the window is the last slot
+        let now = Utc.with_ymd_and_hms(2023, 5, 1, 14, 20, 0).unwrap();
+        assert_eq!(
+            upcoming_notifications(&settings, &[], now, 3),
+            vec![
+                Utc.with_ymd_and_hms(2023, 5, 1, 15, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 5, 2, 6, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 5, 2, 7, 0, 0).unwrap(),
+            ]
+        );
+
+        // Days off are skipped, a holiday on Tuesday, a blackout on Wednesday
+        let settings = UserSettings {
+            holidays: [NaiveDate::from_ymd_opt(2023, 5, 2).unwrap()].into(),
+            ..settings
+        };
+        let blackout = Blackout {
+            from: NaiveDate::from_ymd_opt(2023, 5, 3).unwrap(),
+            to: NaiveDate::from_ymd_opt(2023, 5, 3).unwrap(),
+            reason: String::new(),
+        };
+        assert_eq!(
+            upcoming_notifications(&settings, &[blackout], now, 2),
+            vec![
+                Utc.with_ymd_and_hms(2023, 5, 1, 15, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 5, 4, 6, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let settings = UserSettings {
+            offset: 3 * 3600,
+            cron: Some("0 0 */2 * * MON-FRI".to_string()),
+            ..Default::default()
+        };
+
+        // Friday 19:00 local time, then 20:00 and 22:00
+        let now = Utc.with_ymd_and_hms(2023, 5, 5, 16, 0, 0).unwrap();
+        assert_eq!(
+            next_notification(&settings, now),
+            Some(Utc.with_ymd_and_hms(2023, 5, 5, 17, 0, 0).unwrap())
+        );
+        // Friday 23:30 local time, then Monday midnight
+        let now = Utc.with_ymd_and_hms(2023, 5, 5, 20, 30, 0).unwrap();
+        assert_eq!(
+            next_notification(&settings, now),
+            Some(Utc.with_ymd_and_hms(2023, 5, 7, 21, 0, 0).unwrap())
+        );
+
+        assert!(!too_frequent(&settings.schedule().unwrap()));
+        assert!(too_frequent(&"0 * 9 * * *".parse().unwrap()));
+        assert!(too_frequent(&"0 0,1 9 * * *".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_custom_interval() {
+        let hours = WorkingHours { from: 9, to: 18 };
+        let interval = Duration::from_secs(30 * 60);
+
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 9, 0, 0),
+                hours,
+                Workdays::default(),
+                &[],
+                interval
+            )
+            .as_secs(),
+            1800
+        );
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 9, 40, 10),
+                hours,
+                Workdays::default(),
+                &[],
+                interval
+            )
+            .as_secs(),
+            20 * 60 - 10
+        );
+
+        let interval = Duration::from_secs(2 * 3600);
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 10, 0, 0),
+                hours,
+                Workdays::default(),
+                &[],
+                interval
+            )
+            .as_secs(),
+            3600
+        );
+        // The end of the window comes before the next slot
+        assert_eq!(
+            get_sleep_time(
+                get_date(1, 17, 0, 0),
+                hours,
+                Workdays::default(),
+                &[],
+                interval
+            )
+            .as_secs(),
+            3600
+        );
+    }
+
     #[test]
     fn test_sleep_time_in_working_hours() {
         for hour in HOUR_FROM..=(HOUR_TO - 1) {
             for minute in 0..=59 {
                 for second in 0..=59 {
                     assert_eq!(
-                        get_sleep_time(get_date(1, hour, minute, second)).as_secs(),
+                        get_sleep_time(
+                            get_date(1, hour, minute, second),
+                            WorkingHours::default(),
+                            Workdays::default(),
+                            &[],
+                            DEFAULT_INTERVAL
+                        )
+                        .as_secs(),
                         u64::from(3600 - minute * 60 - second),
                         "hour={}, minute={}, second={}",
                         hour,
@@ -277,8 +2591,14 @@ mod tests {
             for minute in 0..=59 {
                 for second in 0..=59 {
                     assert_eq!(
-                        get_sleep_time(get_date(1, HOUR_FROM - hour_offset, minute, second))
-                            .as_secs(),
+                        get_sleep_time(
+                            get_date(1, HOUR_FROM - hour_offset, minute, second),
+                            WorkingHours::default(),
+                            Workdays::default(),
+                            &[],
+                            DEFAULT_INTERVAL
+                        )
+                        .as_secs(),
                         u64::from(3600 * hour_offset - minute * 60 - second),
                     );
                 }
@@ -291,7 +2611,14 @@ mod tests {
         for hour in HOUR_TO..=23 {
             for minute in 0..=59 {
                 for second in 0..=59 {
-                    let sleep_time = get_sleep_time(get_date(1, hour, minute, second)).as_secs();
+                    let sleep_time = get_sleep_time(
+                        get_date(1, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[],
+                        DEFAULT_INTERVAL,
+                    )
+                    .as_secs();
                     assert_eq!(
                         sleep_time,
                         u64::from((24 - hour + HOUR_FROM) * 3600 - minute * 60 - second),
@@ -312,10 +2639,17 @@ mod tests {
             for hour in 0..=(HOUR_FROM - 1) {
                 for minute in 0..=59 {
                     for second in 0..=59 {
-                        let sleep_time =
-                            get_sleep_time(get_date(day, hour, minute, second)).as_secs();
-                        let expected =
-                            u64::from(((24 * (8 - day) + HOUR_FROM) * 60 - minute) * 60 - second);
+                        let sleep_time = get_sleep_time(
+                            get_date(day, hour, minute, second),
+                            WorkingHours::default(),
+                            Workdays::default(),
+                            &[],
+                            DEFAULT_INTERVAL,
+                        )
+                        .as_secs();
+                        let expected = u64::from(
+                            ((24 * (8 - day) + HOUR_FROM - hour) * 60 - minute) * 60 - second,
+                        );
                         assert_eq!(
                             sleep_time,
                             expected,
@@ -339,8 +2673,14 @@ mod tests {
             for hour in HOUR_FROM..=23 {
                 for minute in 0..=59 {
                     for second in 0..=59 {
-                        let sleep_time =
-                            get_sleep_time(get_date(day, hour, minute, second)).as_secs();
+                        let sleep_time = get_sleep_time(
+                            get_date(day, hour, minute, second),
+                            WorkingHours::default(),
+                            Workdays::default(),
+                            &[],
+                            DEFAULT_INTERVAL,
+                        )
+                        .as_secs();
                         let expected = u64::from(
                             ((24 * (8 - day) - (hour - HOUR_FROM)) * 60 - minute) * 60 - second,
                         );
@@ -366,7 +2706,14 @@ mod tests {
         for hour in HOUR_TO..=23 {
             for minute in 0..=59 {
                 for second in 0..=59 {
-                    let sleep_time = get_sleep_time(get_date(5, hour, minute, second)).as_secs();
+                    let sleep_time = get_sleep_time(
+                        get_date(5, hour, minute, second),
+                        WorkingHours::default(),
+                        Workdays::default(),
+                        &[],
+                        DEFAULT_INTERVAL,
+                    )
+                    .as_secs();
                     let expected =
                         u64::from(((24 * 2 + HOUR_FROM + (24 - hour)) * 60 - minute) * 60 - second);
                     assert_eq!(
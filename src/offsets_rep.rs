@@ -1,80 +1,1007 @@
-use std::{ffi::OsStr, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use chrono::FixedOffset;
-use pickledb::error::Result;
-use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use pickledb::{PickleDb, SerializationMethod};
+use rusqlite::{params, Connection, Params};
+use serde::{Deserialize, Serialize};
 use teloxide::types::ChatId;
 
+pub use crate::scheduling::{QuietHours, Workdays, WorkingHours};
+use crate::{
+    ack_hooks::AckHook,
+    formatting,
+    i18n::Locale,
+    insights::HourHistogram,
+    message_text::Markup,
+    notify_controller::DEFAULT_INTERVAL,
+    profiles::Profile,
+    scheduling::{Timing, Zone},
+    stats::DayStats,
+    store::{Result, UserStore},
+};
+
+/// Version of the database schema, `migrate` upgrades older databases.
+pub const SCHEMA_VERSION: i32 = 5;
+
+/// Settings of subscribed chats stored in SQLite.
+///
+/// Several bots share the database, each sees the chats of its `bot_id`
+/// only. Profiles are shared by all of them.
 pub struct OffsetsRepository {
-    db: PickleDb,
+    conn: Arc<Mutex<Connection>>,
+    /// 0 for the only bot, or the first one of several
+    bot_id: i64,
 }
 
 const _DEFAULT_SECS: i32 = 5 * 3600;
 
-impl OffsetsRepository {
-    pub fn new<P: AsRef<Path>>(path: P) -> OffsetsRepository {
-        let db = PickleDb::new(
-            path,
-            PickleDbDumpPolicy::AutoDump,
-            SerializationMethod::Json,
-        );
+/// What follows the message text in every notification.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Footer {
+    /// Hint about the "/done" command
+    #[default]
+    Text,
+    Hidden,
+}
+
+/// Dead man's switch set with "/checkin": unless the chat writes to the bot
+/// within the period, the contact chat gets an alert.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckIn {
+    pub period_secs: u64,
+    /// Chat alerted when the user goes quiet
+    pub contact: ChatId,
+    /// How the alert calls the user
+    pub name: String,
+    pub last_contact: DateTime<Utc>,
+}
+
+impl CheckIn {
+    pub fn period(&self) -> Duration {
+        Duration::from_secs(self.period_secs)
+    }
+
+    /// When the contact is alerted unless the chat writes before.
+    pub fn due(&self) -> DateTime<Utc> {
+        self.last_contact + chrono::Duration::seconds(self.period_secs as i64)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserSettings {
+    /// Offset from UTC in seconds, the current one for named timezones
+    pub offset: i32,
+    /// IANA timezone name, its offset follows daylight saving time
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default)]
+    pub footer: Footer,
+    /// Send the first notification right after "/start", `None` follows the deployment default
+    #[serde(default)]
+    pub first_send: Option<bool>,
+    /// Local hours at which "/done" was pressed
+    #[serde(default)]
+    pub done_hours: HourHistogram,
+    #[serde(default)]
+    pub working_hours: WorkingHours,
+    #[serde(default)]
+    pub workdays: Workdays,
+    /// Seconds between notifications
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// User-defined command shortcuts, alias name to command name
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Scheduled notifications are muted until the user is back
+    #[serde(default)]
+    pub away: bool,
+    /// Secret for triggering the notification over HTTP
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Renders JSON posted to "/hook/{token}", `None` relays it as is
+    #[serde(default)]
+    pub hook_template: Option<String>,
+    /// Cron expression followed instead of working hours and the interval
+    #[serde(default)]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub check_in: Option<CheckIn>,
+    /// Notifications repeat every few minutes until acknowledged
+    #[serde(default)]
+    pub ack: bool,
+    /// Each notification edits the previous one of the day instead of
+    /// coming as a new message
+    #[serde(default)]
+    pub single_message: bool,
+    /// Hours at both ends of working hours notifications come without a
+    /// sound in, none when 0
+    #[serde(default)]
+    pub soft_hours: u32,
+    /// A summary of the day comes at the end of working hours
+    #[serde(default)]
+    pub summary: bool,
+    /// Team profile the chat joined, its changes apply to the chat
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Local dates without notifications, set by the profile
+    #[serde(default)]
+    pub holidays: BTreeSet<NaiveDate>,
+    /// A group or a supergroup, only its admins start and stop notifications
+    #[serde(default)]
+    pub is_group: bool,
+    /// Forum topic of the group notifications go to
+    #[serde(default)]
+    pub thread_id: Option<i32>,
+    /// Release notes come after updates of the bot
+    #[serde(default = "default_release_notes")]
+    pub release_notes: bool,
+    /// Version of the bot whose release notes the chat got last
+    #[serde(default)]
+    pub seen_version: Option<String>,
+    /// Channels notifications go through in order, only the chat when empty
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Where each channel other than the chat delivers, e.g. a webhook URL
+    #[serde(default)]
+    pub channel_addresses: BTreeMap<String, String>,
+    /// Gets every "/done" of the chat, e.g. to tick off a habit tracker
+    #[serde(default)]
+    pub ack_hook: Option<AckHook>,
+    /// Breaks in working hours without notifications, e.g. lunch
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
+    /// Scheduled notifications left out before they come again
+    #[serde(default)]
+    pub skip: u32,
+    /// The chat's own pool of messages, sent instead of the configured ones
+    #[serde(default)]
+    pub messages: Vec<String>,
+    /// How the chat's own messages are written, as the deployment's when unset
+    #[serde(default)]
+    pub markup: Option<Markup>,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            offset: _DEFAULT_SECS,
+            timezone: None,
+            locale: Locale::default(),
+            footer: Footer::default(),
+            first_send: None,
+            done_hours: HourHistogram::default(),
+            working_hours: WorkingHours::default(),
+            workdays: Workdays::default(),
+            interval_secs: default_interval_secs(),
+            aliases: BTreeMap::new(),
+            away: false,
+            api_token: None,
+            hook_template: None,
+            cron: None,
+            check_in: None,
+            ack: false,
+            single_message: false,
+            soft_hours: 0,
+            summary: false,
+            profile: None,
+            holidays: BTreeSet::new(),
+            is_group: false,
+            thread_id: None,
+            release_notes: default_release_notes(),
+            seen_version: None,
+            channels: vec![],
+            channel_addresses: BTreeMap::new(),
+            ack_hook: None,
+            quiet_hours: vec![],
+            skip: 0,
+            messages: vec![],
+            markup: None,
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    DEFAULT_INTERVAL.as_secs()
+}
+
+fn default_release_notes() -> bool {
+    true
+}
+
+impl UserSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    /// The cron schedule, `None` for the usual working hours and interval.
+    pub fn schedule(&self) -> Option<Schedule> {
+        self.cron
+            .as_deref()
+            .and_then(|expression| Schedule::from_str(expression).ok())
+    }
+
+    pub fn tz(&self) -> Option<Tz> {
+        self.timezone.as_deref().and_then(|name| name.parse().ok())
+    }
+
+    pub fn zone(&self) -> Zone {
+        match self.tz() {
+            Some(tz) => Zone::Named(tz),
+            None => Zone::Fixed(self.stored_offset()),
+        }
+    }
+
+    /// When the chat's notifications are due.
+    pub fn timing(&self) -> Timing {
+        Timing {
+            working_hours: self.working_hours,
+            workdays: self.workdays,
+            quiet_hours: self.quiet_hours.clone(),
+            interval: self.interval(),
+            zone: self.zone(),
+            cron: self.schedule(),
+        }
+    }
+
+    /// The chat's offset from UTC at `moment`.
+    pub fn offset_at(&self, moment: DateTime<Utc>) -> FixedOffset {
+        self.zone().offset_at(moment)
+    }
+
+    /// The chat's offset from UTC right now.
+    pub fn fixed_offset(&self) -> FixedOffset {
+        self.offset_at(Utc::now())
+    }
+
+    /// The timezone as shown to users, e.g. "Europe/Moscow (UTC+03:00)".
+    pub fn timezone_label(&self) -> String {
+        let offset = formatting::offset(&self.fixed_offset());
+        match &self.timezone {
+            Some(name) => format!("{} ({})", name, offset),
+            None => offset,
+        }
+    }
 
-        OffsetsRepository { db }
+    /// The offset the chat set, named timezones override it.
+    pub fn stored_offset(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.offset).unwrap_or_else(|| {
+            panic!(
+                "Unexpected behavior: user timezone is invalid {}",
+                self.offset
+            )
+        })
     }
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<OffsetsRepository> {
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredUser {
+    Settings(Box<UserSettings>),
+    // Records written before per-user settings existed hold just the offset
+    Offset(i32),
+}
+
+impl From<StoredUser> for UserSettings {
+    fn from(stored: StoredUser) -> Self {
+        match stored {
+            StoredUser::Settings(settings) => *settings,
+            StoredUser::Offset(offset) => UserSettings {
+                offset,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl OffsetsRepository {
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<OffsetsRepository> {
+        let conn = Connection::open(path)?;
+        migrate(&conn)?;
+
         Ok(OffsetsRepository {
-            db: PickleDb::load(
-                path,
-                PickleDbDumpPolicy::AutoDump,
-                SerializationMethod::Json,
-            )?,
+            conn: Arc::new(Mutex::new(conn)),
+            bot_id: 0,
         })
     }
 
-    pub fn open_or_create<S: AsRef<OsStr> + ?Sized>(s: &S) -> Result<OffsetsRepository> {
-        let path = Path::new(s);
+    /// The chats of another bot in the same database.
+    pub fn for_bot(&self, bot_id: i64) -> OffsetsRepository {
+        OffsetsRepository {
+            conn: Arc::clone(&self.conn),
+            bot_id,
+        }
+    }
+
+    /// Opens the database at `path`, a new one is filled from the PickleDB
+    /// file at `legacy_path` written by earlier versions, if there is one.
+    pub fn open_or_import<P: AsRef<Path>, L: AsRef<Path>>(
+        path: P,
+        legacy_path: L,
+    ) -> rusqlite::Result<OffsetsRepository> {
+        let (path, legacy_path) = (path.as_ref(), legacy_path.as_ref());
+        let import = !path.exists() && legacy_path.exists();
 
-        if path.exists() {
-            return OffsetsRepository::open(path);
+        let rep = OffsetsRepository::open(path)?;
+        if import {
+            // A partial import would never be retried, start from scratch next time
+            let count = rep.import(legacy_path).inspect_err(|_| {
+                let _ = std::fs::remove_file(path);
+            })?;
+            log::info!(
+                "Imported {} users from {} into {}",
+                count,
+                legacy_path.display(),
+                path.display()
+            );
         }
-        Ok(OffsetsRepository::new(path))
+
+        Ok(rep)
     }
 
-    pub fn get(&self, user_id: &ChatId) -> Option<FixedOffset> {
-        if let Some(secs) = self.db.get::<i32>(&user_id.0.to_string()) {
-            return Some(FixedOffset::east_opt(secs).expect(&format!(
-                "Unexpected behavior: user timezone is invalid {}",
-                secs
-            )));
+    /// Copies all users of a PickleDB file, returns how many were copied.
+    fn import(&self, legacy_path: &Path) -> rusqlite::Result<usize> {
+        let legacy =
+            PickleDb::load_read_only(legacy_path, SerializationMethod::Json).map_err(|err| {
+                log::error!("Unable to read {}: {}", legacy_path.display(), err);
+                rusqlite::Error::InvalidPath(legacy_path.to_path_buf())
+            })?;
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut count = 0;
+        for key in legacy.get_all() {
+            let (Ok(chat_id), Some(stored)) = (key.parse::<i64>(), legacy.get::<StoredUser>(&key))
+            else {
+                log::warn!("Skipping unreadable user record {}", key);
+                continue;
+            };
+
+            write(
+                &tx,
+                self.bot_id,
+                &ChatId(chat_id),
+                &UserSettings::from(stored),
+            )?;
+            count += 1;
         }
-        None
+        tx.commit()?;
+
+        Ok(count)
+    }
+
+    fn query<P: Params>(&self, sql: &str, params: P) -> Vec<(ChatId, UserSettings)> {
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.prepare_cached(sql).and_then(|mut statement| {
+            statement
+                .query_map(params, |row| {
+                    Ok((ChatId(row.get::<_, i64>(0)?), row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        });
+
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(chat_id, settings)| Some((chat_id, parse(&chat_id, &settings)?)))
+                .collect(),
+            Err(err) => {
+                log::error!("Unable to read users: {}", err);
+                vec![]
+            }
+        }
+    }
+
+    fn query_profiles<P: Params>(&self, sql: &str, params: P) -> Vec<(String, Profile)> {
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.prepare_cached(sql).and_then(|mut statement| {
+            statement
+                .query_map(params, |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        });
+
+        match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(name, profile)| {
+                    serde_json::from_str(&profile)
+                        .map_err(|err| log::error!("Invalid profile {}: {}", name, err))
+                        .ok()
+                        .map(|profile| (name, profile))
+                })
+                .collect(),
+            Err(err) => {
+                log::error!("Unable to read profiles: {}", err);
+                vec![]
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl UserStore for OffsetsRepository {
+    async fn get(&self, chat_id: &ChatId) -> Option<UserSettings> {
+        self.query(
+            "SELECT chat_id, settings FROM users WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )
+        .into_iter()
+        .next()
+        .map(|(_, settings)| settings)
+    }
+
+    async fn set(&self, chat_id: &ChatId, settings: &UserSettings) -> Result<()> {
+        write(&self.conn.lock().unwrap(), self.bot_id, chat_id, settings)?;
+        Ok(())
+    }
+
+    async fn rem(&self, chat_id: &ChatId) -> Result<bool> {
+        let removed = self.conn.lock().unwrap().execute(
+            "DELETE FROM users WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+
+        Ok(removed > 0)
     }
 
-    pub fn set(&mut self, user_id: &ChatId, offset: &FixedOffset) -> Result<()> {
-        self.db
-            .set(&user_id.0.to_string(), &offset.local_minus_utc())
+    async fn trash(&self, chat_id: &ChatId) -> Result<bool> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO removed_users (bot_id, chat_id, settings)
+            SELECT bot_id, chat_id, settings FROM users WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+        let removed = tx.execute(
+            "DELETE FROM users WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+        tx.commit()?;
+
+        Ok(removed > 0)
     }
 
-    pub fn add(&mut self, user_id: &ChatId) -> Result<()> {
-        self.db.set(&user_id.0.to_string(), &_DEFAULT_SECS)
+    async fn restore(&self, chat_id: &ChatId) -> Result<bool> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let restored = tx.execute(
+            "INSERT OR REPLACE INTO users (bot_id, chat_id, settings)
+            SELECT bot_id, chat_id, settings FROM removed_users
+            WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM removed_users WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+        tx.commit()?;
+
+        Ok(restored > 0)
     }
 
-    pub fn rem(&mut self, user_id: &ChatId) -> Result<bool> {
-        self.db.rem(&user_id.0.to_string())
+    async fn purge(&self, chat_id: &ChatId) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM removed_users WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM daily_stats WHERE bot_id = ?1 AND chat_id = ?2",
+            params![self.bot_id, chat_id.0],
+        )?;
+        tx.commit()?;
+        Ok(())
     }
 
-    pub fn exists(&self, user_id: &ChatId) -> bool {
-        self.db.exists(&user_id.0.to_string())
+    async fn record_sent(&self, chat_id: &ChatId, day: NaiveDate, at: DateTime<Utc>) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO daily_stats (bot_id, chat_id, day, sent, last_sent)
+            VALUES (?1, ?2, ?3, 1, ?4)
+            ON CONFLICT (bot_id, chat_id, day) DO UPDATE SET sent = sent + 1, last_sent = ?4",
+            params![self.bot_id, chat_id.0, day.to_string(), at.timestamp()],
+        )?;
+        Ok(())
     }
 
-    pub fn get_all(&self) -> Vec<(ChatId, FixedOffset)> {
-        self.db
-            .get_all()
-            .iter()
-            .map(|chat_id_str| {
-                let chat_id = ChatId(chat_id_str.parse::<i64>().unwrap());
-                (chat_id, self.get(&chat_id).unwrap())
-            })
-            .collect()
+    async fn record_done(&self, chat_id: &ChatId, day: NaiveDate, at: DateTime<Utc>) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO daily_stats (bot_id, chat_id, day, done) VALUES (?1, ?2, ?3, 1)
+            ON CONFLICT (bot_id, chat_id, day) DO UPDATE SET
+                done_after = CASE WHEN done THEN done_after ELSE ?4 - last_sent END,
+                done = 1",
+            params![self.bot_id, chat_id.0, day.to_string(), at.timestamp()],
+        )?;
+        Ok(())
+    }
+
+    async fn daily_stats(&self, chat_id: &ChatId, since: NaiveDate) -> Vec<DayStats> {
+        let conn = self.conn.lock().unwrap();
+        let rows = conn
+            .prepare_cached(
+                "SELECT day, sent, done, done_after FROM daily_stats
+                WHERE bot_id = ?1 AND chat_id = ?2 AND day >= ?3 ORDER BY day",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![self.bot_id, chat_id.0, since.to_string()], |row| {
+                        let day: String = row.get(0)?;
+                        Ok(DayStats {
+                            day: day.parse().map_err(|err| {
+                                rusqlite::Error::FromSqlConversionFailure(
+                                    0,
+                                    rusqlite::types::Type::Text,
+                                    Box::new(err),
+                                )
+                            })?,
+                            sent: row.get(1)?,
+                            done: row.get(2)?,
+                            done_after: row
+                                .get::<_, Option<i64>>(3)?
+                                .and_then(|secs| u64::try_from(secs).ok())
+                                .map(Duration::from_secs),
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            });
+
+        rows.unwrap_or_else(|err| {
+            log::error!("Unable to read statistics of {}: {}", chat_id, err);
+            vec![]
+        })
+    }
+
+    async fn get_all(&self) -> Vec<(ChatId, UserSettings)> {
+        self.query(
+            "SELECT chat_id, settings FROM users WHERE bot_id = ?1 ORDER BY chat_id",
+            params![self.bot_id],
+        )
+    }
+
+    async fn check(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get::<_, i64>(0))?;
+        conn.query_row("SELECT COUNT(*) FROM profiles", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        // Waits for a write in progress, the lock serializes them
+        self.conn.lock().unwrap().cache_flush()?;
+        Ok(())
+    }
+
+    async fn find_by_token(&self, token: &str) -> Option<(ChatId, UserSettings)> {
+        self.query(
+            "SELECT chat_id, settings FROM users
+            WHERE bot_id = ?1 AND json_extract(settings, '$.api_token') = ?2",
+            params![self.bot_id, token],
+        )
+        .into_iter()
+        .next()
+    }
+
+    async fn get_profile(&self, name: &str) -> Option<Profile> {
+        self.query_profiles(
+            "SELECT name, profile FROM profiles WHERE name = ?1",
+            params![name],
+        )
+        .into_iter()
+        .next()
+        .map(|(_, profile)| profile)
+    }
+
+    async fn set_profile(&self, name: &str, profile: &Profile) -> Result<()> {
+        let profile = serde_json::to_string(profile)?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO profiles (name, profile) VALUES (?1, ?2)",
+            params![name, profile],
+        )?;
+        Ok(())
+    }
+
+    async fn rem_profile(&self, name: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM profiles WHERE name = ?1", params![name])?;
+
+        Ok(removed > 0)
+    }
+
+    async fn get_profiles(&self) -> Vec<(String, Profile)> {
+        self.query_profiles("SELECT name, profile FROM profiles ORDER BY name", [])
+    }
+}
+
+/// Brings the schema up to `SCHEMA_VERSION`.
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    if version < 1 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS users (
+                chat_id INTEGER PRIMARY KEY,
+                settings TEXT NOT NULL
+            );",
+        )?;
+    }
+    if version < 2 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS profiles (
+                name TEXT PRIMARY KEY,
+                profile TEXT NOT NULL
+            );",
+        )?;
+    }
+    if version < 3 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS removed_users (
+                chat_id INTEGER PRIMARY KEY,
+                settings TEXT NOT NULL
+            );",
+        )?;
+    }
+    if version < 4 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS daily_stats (
+                chat_id INTEGER NOT NULL,
+                day TEXT NOT NULL,
+                sent INTEGER NOT NULL DEFAULT 0,
+                last_sent INTEGER,
+                done INTEGER NOT NULL DEFAULT 0,
+                done_after INTEGER,
+                PRIMARY KEY (chat_id, day)
+            );",
+        )?;
+    }
+    if version < 5 {
+        // Chats stored so far belong to the first bot
+        conn.execute_batch(
+            "BEGIN;
+            ALTER TABLE users RENAME TO users_v4;
+            CREATE TABLE users (
+                bot_id INTEGER NOT NULL DEFAULT 0,
+                chat_id INTEGER NOT NULL,
+                settings TEXT NOT NULL,
+                PRIMARY KEY (bot_id, chat_id)
+            );
+            INSERT INTO users (chat_id, settings) SELECT chat_id, settings FROM users_v4;
+            DROP TABLE users_v4;
+            ALTER TABLE removed_users RENAME TO removed_users_v4;
+            CREATE TABLE removed_users (
+                bot_id INTEGER NOT NULL DEFAULT 0,
+                chat_id INTEGER NOT NULL,
+                settings TEXT NOT NULL,
+                PRIMARY KEY (bot_id, chat_id)
+            );
+            INSERT INTO removed_users (chat_id, settings)
+                SELECT chat_id, settings FROM removed_users_v4;
+            DROP TABLE removed_users_v4;
+            ALTER TABLE daily_stats RENAME TO daily_stats_v4;
+            CREATE TABLE daily_stats (
+                bot_id INTEGER NOT NULL DEFAULT 0,
+                chat_id INTEGER NOT NULL,
+                day TEXT NOT NULL,
+                sent INTEGER NOT NULL DEFAULT 0,
+                last_sent INTEGER,
+                done INTEGER NOT NULL DEFAULT 0,
+                done_after INTEGER,
+                PRIMARY KEY (bot_id, chat_id, day)
+            );
+            INSERT INTO daily_stats (chat_id, day, sent, last_sent, done, done_after)
+                SELECT chat_id, day, sent, last_sent, done, done_after FROM daily_stats_v4;
+            DROP TABLE daily_stats_v4;
+            COMMIT;",
+        )?;
+    }
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+    Ok(())
+}
+
+fn parse(chat_id: &ChatId, settings: &str) -> Option<UserSettings> {
+    serde_json::from_str::<UserSettings>(settings)
+        .map_err(|err| log::error!("Invalid settings of {}: {}", chat_id, err))
+        .ok()
+}
+
+fn write(
+    conn: &Connection,
+    bot_id: i64,
+    chat_id: &ChatId,
+    settings: &UserSettings,
+) -> rusqlite::Result<()> {
+    let settings = serde_json::to_string(settings)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO users (bot_id, chat_id, settings) VALUES (?1, ?2, ?3)",
+        params![bot_id, chat_id.0, settings],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+    use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
+    use teloxide::types::ChatId;
+
+    use crate::{
+        i18n::Locale,
+        offsets_rep::{CheckIn, OffsetsRepository, UserSettings, WorkingHours},
+        profiles::Profile,
+        stats::DayStats,
+        store::UserStore,
+    };
+
+    #[tokio::test]
+    async fn test_import_legacy_records() {
+        let dir = std::env::temp_dir();
+        let path = dir.join("notification_bot_test_import.sqlite3");
+        let legacy_path = dir.join("notification_bot_test_import.db");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&legacy_path);
+
+        {
+            let mut legacy = PickleDb::new(
+                &legacy_path,
+                PickleDbDumpPolicy::AutoDump,
+                SerializationMethod::Json,
+            );
+            // Records written before per-user settings existed hold just the offset
+            legacy.set("42", &(3 * 3600)).unwrap();
+            legacy
+                .set(
+                    "-100",
+                    &UserSettings {
+                        locale: Locale::Ru,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            legacy.set("broken", &1).unwrap();
+        }
+
+        let rep = OffsetsRepository::open_or_import(&path, &legacy_path).unwrap();
+        assert_eq!(rep.get_all().await.len(), 2);
+        assert_eq!(rep.get(&ChatId(-100)).await.unwrap().locale, Locale::Ru);
+
+        let settings = rep.get(&ChatId(42)).await.unwrap();
+        assert_eq!(
+            settings.fixed_offset(),
+            FixedOffset::east_opt(3 * 3600).unwrap()
+        );
+        assert_eq!(settings.locale, Locale::En);
+
+        // The legacy file is only read once
+        rep.rem(&ChatId(-100)).await.unwrap();
+        drop(rep);
+        let rep = OffsetsRepository::open_or_import(&path, &legacy_path).unwrap();
+        assert!(!rep.exists(&ChatId(-100)).await);
+        assert!(rep.exists(&ChatId(42)).await);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&legacy_path);
+    }
+
+    #[tokio::test]
+    async fn test_repository() {
+        let path = std::env::temp_dir().join("notification_bot_test_repository.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        let rep: &dyn UserStore = &OffsetsRepository::open(&path).unwrap();
+
+        rep.add(&ChatId(42), Locale::En).await.unwrap();
+        assert!(rep.exists(&ChatId(42)).await);
+        assert!(rep
+            .update(&ChatId(42), |settings| settings.locale = Locale::Ru)
+            .await
+            .unwrap());
+        let settings = rep.get(&ChatId(42)).await.unwrap();
+        assert_eq!(settings.locale, Locale::Ru);
+
+        assert!(!rep.update(&ChatId(7), |_| {}).await.unwrap());
+        assert!(rep.get(&ChatId(7)).await.is_none());
+
+        assert!(rep.find_by_token("secret").await.is_none());
+        rep.update(&ChatId(42), |settings| {
+            settings.api_token = Some("secret".to_string())
+        })
+        .await
+        .unwrap();
+        assert_eq!(rep.find_by_token("secret").await.unwrap().0, ChatId(42));
+        assert!(rep.find_by_token("").await.is_none());
+
+        assert!(rep.trash(&ChatId(42)).await.unwrap());
+        assert!(!rep.trash(&ChatId(42)).await.unwrap());
+        assert!(rep.get(&ChatId(42)).await.is_none());
+        assert!(rep.restore(&ChatId(42)).await.unwrap());
+        assert_eq!(rep.get(&ChatId(42)).await.unwrap().locale, Locale::Ru);
+        rep.trash(&ChatId(42)).await.unwrap();
+        rep.purge(&ChatId(42)).await.unwrap();
+        assert!(!rep.restore(&ChatId(42)).await.unwrap());
+        assert!(rep.get_all().await.is_empty());
+
+        rep.add(&ChatId(42), Locale::En).await.unwrap();
+        assert!(rep.rem(&ChatId(42)).await.unwrap());
+        assert!(!rep.rem(&ChatId(42)).await.unwrap());
+        assert!(!rep.restore(&ChatId(42)).await.unwrap());
+
+        let profile = Profile {
+            timezone: "Europe/Berlin".to_string(),
+            working_hours: WorkingHours { from: 8, to: 17 },
+            holidays: Default::default(),
+        };
+        rep.set_profile("Berlin office", &profile).await.unwrap();
+        assert_eq!(
+            rep.get_profile("Berlin office").await,
+            Some(profile.clone())
+        );
+        assert_eq!(
+            rep.get_profiles().await,
+            vec![("Berlin office".to_string(), profile)]
+        );
+        assert!(rep.rem_profile("Berlin office").await.unwrap());
+        assert!(!rep.rem_profile("Berlin office").await.unwrap());
+        assert!(rep.get_profile("Berlin office").await.is_none());
+
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 5, 6, 9, 0, 0).unwrap();
+        rep.record_done(&ChatId(1), monday.pred_opt().unwrap(), at)
+            .await
+            .unwrap();
+        rep.record_sent(&ChatId(1), monday, at).await.unwrap();
+        rep.record_sent(&ChatId(1), monday, at + Duration::hours(1))
+            .await
+            .unwrap();
+        rep.record_done(&ChatId(1), monday, at + Duration::minutes(70))
+            .await
+            .unwrap();
+        rep.record_done(&ChatId(1), monday, at + Duration::hours(3))
+            .await
+            .unwrap();
+        assert_eq!(
+            rep.daily_stats(&ChatId(1), monday).await,
+            vec![DayStats {
+                day: monday,
+                sent: 2,
+                done: true,
+                done_after: Some(std::time::Duration::from_secs(600)),
+            }]
+        );
+        assert_eq!(rep.daily_stats(&ChatId(1), NaiveDate::MIN).await.len(), 2);
+        rep.purge(&ChatId(1)).await.unwrap();
+        assert!(rep.daily_stats(&ChatId(1), NaiveDate::MIN).await.is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_bots() {
+        let path = std::env::temp_dir().join("notification_bot_test_bots.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        {
+            // A database from before bots had their own chats
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE users (chat_id INTEGER PRIMARY KEY, settings TEXT NOT NULL);
+                CREATE TABLE profiles (name TEXT PRIMARY KEY, profile TEXT NOT NULL);
+                CREATE TABLE removed_users (chat_id INTEGER PRIMARY KEY, settings TEXT NOT NULL);
+                CREATE TABLE daily_stats (
+                    chat_id INTEGER NOT NULL,
+                    day TEXT NOT NULL,
+                    sent INTEGER NOT NULL DEFAULT 0,
+                    last_sent INTEGER,
+                    done INTEGER NOT NULL DEFAULT 0,
+                    done_after INTEGER,
+                    PRIMARY KEY (chat_id, day)
+                );
+                INSERT INTO users VALUES (42, '{\"offset\": 0}');
+                INSERT INTO daily_stats (chat_id, day, sent) VALUES (42, '2024-05-06', 3);
+                PRAGMA user_version = 4;",
+            )
+            .unwrap();
+        }
+
+        let first = OffsetsRepository::open(&path).unwrap();
+        let second = first.for_bot(123);
+        assert!(first.exists(&ChatId(42)).await);
+        assert_eq!(
+            first.daily_stats(&ChatId(42), NaiveDate::MIN).await.len(),
+            1
+        );
+        assert!(!second.exists(&ChatId(42)).await);
+
+        // The same chat has its own settings with every bot
+        second.add(&ChatId(42), Locale::Ru).await.unwrap();
+        second.trash(&ChatId(42)).await.unwrap();
+        assert!(first.exists(&ChatId(42)).await);
+        assert!(second.restore(&ChatId(42)).await.unwrap());
+        assert_eq!(first.get(&ChatId(42)).await.unwrap().locale, Locale::En);
+        assert_eq!(second.get(&ChatId(42)).await.unwrap().locale, Locale::Ru);
+        assert!(second
+            .daily_stats(&ChatId(42), NaiveDate::MIN)
+            .await
+            .is_empty());
+
+        let profile = Profile {
+            timezone: "Europe/Berlin".to_string(),
+            working_hours: WorkingHours { from: 8, to: 17 },
+            holidays: Default::default(),
+        };
+        first.set_profile("Berlin office", &profile).await.unwrap();
+        assert_eq!(second.get_profile("Berlin office").await, Some(profile));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_check_in() {
+        let check_in = CheckIn {
+            period_secs: 24 * 3600,
+            contact: ChatId(-100),
+            name: "Ann".to_string(),
+            last_contact: Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap(),
+        };
+        assert_eq!(
+            check_in.due(),
+            Utc.with_ymd_and_hms(2024, 3, 2, 10, 0, 0).unwrap()
+        );
+
+        let settings = UserSettings {
+            check_in: Some(check_in),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let restored: UserSettings = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.check_in, settings.check_in);
+        let legacy: UserSettings = serde_json::from_str(r#"{"offset": 0}"#).unwrap();
+        assert_eq!(legacy.check_in, None);
+    }
+
+    #[test]
+    fn test_named_timezone() {
+        let settings = UserSettings {
+            offset: 3600,
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Default::default()
+        };
+        let winter = Utc.with_ymd_and_hms(2023, 1, 15, 12, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2023, 7, 15, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            settings.offset_at(winter),
+            FixedOffset::east_opt(3600).unwrap()
+        );
+        assert_eq!(
+            settings.offset_at(summer),
+            FixedOffset::east_opt(7200).unwrap()
+        );
+
+        let settings = UserSettings {
+            timezone: None,
+            ..settings
+        };
+        assert_eq!(
+            settings.offset_at(summer),
+            FixedOffset::east_opt(3600).unwrap()
+        );
     }
 }
@@ -0,0 +1,371 @@
+use std::{sync::OnceLock, time::Duration};
+
+use chrono::{FixedOffset, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use regex::Regex;
+use tzf_rs::DefaultFinder;
+
+pub static TIMEZONE_RE: &str = r"^([+-])([0-2][0-9]):([0-5][0-9])$";
+static TIME_RE: &str = r"^([0-9]{1,2}):([0-5][0-9])$";
+
+/// Parses a timezone offset like "+05:00" or "-03:30".
+///
+/// Returns `None` for anything that is not a valid offset, including
+/// values that match the pattern but are out of range ("+24:00").
+pub fn parse_timezone(text: &str) -> Option<FixedOffset> {
+    let captures = Regex::new(TIMEZONE_RE).unwrap().captures(text.trim())?;
+
+    let secs = {
+        let hours = captures[2].parse::<i32>().ok()?;
+        let minutes = captures[3].parse::<i32>().ok()?;
+
+        hours * 3600 + minutes * 60
+    };
+
+    match &captures[1] {
+        "+" => FixedOffset::east_opt(secs),
+        _ => FixedOffset::west_opt(secs),
+    }
+}
+
+/// Parses an IANA timezone name like "Europe/Moscow", in any case.
+pub fn parse_timezone_name(text: &str) -> Option<Tz> {
+    Tz::from_str_insensitive(text.trim()).ok()
+}
+
+/// Looks up the timezone at the coordinates in the embedded boundary data.
+pub fn timezone_at(latitude: f64, longitude: f64) -> Option<Tz> {
+    static FINDER: OnceLock<DefaultFinder> = OnceLock::new();
+    let name = FINDER
+        .get_or_init(DefaultFinder::new)
+        .get_tz_name(longitude, latitude);
+    name.parse().ok()
+}
+
+/// Parses a human duration like "45m", "2h", "1h30m" or "1d 2h".
+///
+/// Supported units: `d`, `h`, `m`/`min`, `s`/`sec`. Zero durations are rejected.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let mut chars = text.trim().chars().peekable();
+    chars.peek()?;
+
+    let mut total: u64 = 0;
+    while chars.peek().is_some() {
+        let mut digits = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+            digits.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut unit = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_alphabetic()) {
+            unit.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if digits.is_empty() {
+            return None;
+        }
+        let multiplier = match unit.to_lowercase().as_str() {
+            "d" => 24 * 3600,
+            "h" => 3600,
+            "m" | "min" => 60,
+            "s" | "sec" => 1,
+            _ => return None,
+        };
+        total = total.checked_add(digits.parse::<u64>().ok()?.checked_mul(multiplier)?)?;
+    }
+
+    match total {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Parses a time of day like "9:00" or "18:30".
+pub fn parse_time(text: &str) -> Option<NaiveTime> {
+    let captures = Regex::new(TIME_RE).unwrap().captures(text.trim())?;
+
+    NaiveTime::from_hms_opt(
+        captures[1].parse::<u32>().ok()?,
+        captures[2].parse::<u32>().ok()?,
+        0,
+    )
+}
+
+/// Parses a schedule phrase describing a daily window like "09:00-18:00".
+///
+/// The end of the window must be later than its start.
+pub fn parse_time_window(text: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (from, to) = text.split_once(['-', '–'])?;
+    let (from, to) = (parse_time(from)?, parse_time(to)?);
+
+    match from < to {
+        true => Some((from, to)),
+        false => None,
+    }
+}
+
+fn parse_weekday(text: &str) -> Option<Weekday> {
+    match text.to_lowercase().as_str() {
+        "пн" => Some(Weekday::Mon),
+        "вт" => Some(Weekday::Tue),
+        "ср" => Some(Weekday::Wed),
+        "чт" => Some(Weekday::Thu),
+        "пт" => Some(Weekday::Fri),
+        "сб" => Some(Weekday::Sat),
+        "вс" => Some(Weekday::Sun),
+        text => text.parse().ok(),
+    }
+}
+
+/// Parses a list of weekdays like "mon-fri", "mon, wed, fri" or "пн-ср сб".
+///
+/// Ranges may wrap around the week ("fri-mon"). Returns the days in week
+/// order without duplicates, empty lists are rejected.
+pub fn parse_weekdays(text: &str) -> Option<Vec<Weekday>> {
+    let mut days = [false; 7];
+    for part in text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+    {
+        let (from, to) = match part.split_once(['-', '–']) {
+            Some((from, to)) => (parse_weekday(from)?, parse_weekday(to)?),
+            None => {
+                let day = parse_weekday(part)?;
+                (day, day)
+            }
+        };
+
+        let mut day = from;
+        days[day.num_days_from_monday() as usize] = true;
+        while day != to {
+            day = day.succ();
+            days[day.num_days_from_monday() as usize] = true;
+        }
+    }
+
+    let days: Vec<Weekday> = (0..7u8)
+        .filter(|index| days[*index as usize])
+        .filter_map(|index| Weekday::try_from(index).ok())
+        .collect();
+    match days.is_empty() {
+        true => None,
+        false => Some(days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{FixedOffset, NaiveTime, Weekday};
+    use regex::Regex;
+
+    use crate::parsers::{
+        parse_duration, parse_time_window, parse_timezone, parse_timezone_name, parse_weekdays,
+        timezone_at, TIMEZONE_RE,
+    };
+
+    #[test]
+    fn test_parse_weekdays() {
+        use Weekday::*;
+
+        assert_eq!(
+            parse_weekdays("mon-fri"),
+            Some(vec![Mon, Tue, Wed, Thu, Fri])
+        );
+        assert_eq!(parse_weekdays("Sun, wed,Monday"), Some(vec![Mon, Wed, Sun]));
+        assert_eq!(parse_weekdays("пн-ср сб"), Some(vec![Mon, Tue, Wed, Sat]));
+        assert_eq!(parse_weekdays("fri-mon"), Some(vec![Mon, Fri, Sat, Sun]));
+        assert_eq!(parse_weekdays("tue tue-tue"), Some(vec![Tue]));
+        assert_eq!(parse_weekdays(""), None);
+        assert_eq!(parse_weekdays("mon-"), None);
+        assert_eq!(parse_weekdays("mon, holiday"), None);
+    }
+
+    #[test]
+    fn test_valid_timezone_regex() {
+        let regex = Regex::new(TIMEZONE_RE).unwrap();
+
+        // UTC-23:59 to UTC+23:59
+        for sign in ["-", "+"] {
+            for hour in 0..2 {
+                for minute in 0..59 {
+                    let str_timezone = format!("{}{:0>2}:{:0>2}", sign, hour, minute);
+                    assert!(
+                        regex.is_match(&str_timezone),
+                        "Unable to match {}",
+                        str_timezone
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_timezone_regex() {
+        let regex = Regex::new(TIMEZONE_RE).unwrap();
+
+        assert!(!regex.is_match("-03:00:00"));
+        assert!(!regex.is_match("+03:00:00"));
+
+        assert!(!regex.is_match("-003:00"));
+        assert!(!regex.is_match("+003:00"));
+
+        assert!(!regex.is_match("-3:00"));
+        assert!(!regex.is_match("+3:00"));
+
+        assert!(!regex.is_match("-03:1"));
+        assert!(!regex.is_match("+03:2"));
+
+        assert!(!regex.is_match("-33:00"));
+        assert!(!regex.is_match("+33:00"));
+
+        assert!(!regex.is_match("-03:60"));
+        assert!(!regex.is_match("+03:60"));
+
+        assert!(!regex.is_match("23:59"));
+        assert!(!regex.is_match("plus23:59"));
+        assert!(!regex.is_match(" +23:59 "));
+    }
+
+    #[test]
+    fn test_timezone_regex_groups() {
+        let regex = Regex::new(TIMEZONE_RE).unwrap();
+
+        // UTC-23:59 to UTC+23:59
+        for sign in ["-", "+"] {
+            for hour in 0..2 {
+                for minute in 0..59 {
+                    let str_timezone = format!("{}{:0>2}:{:0>2}", sign, hour, minute);
+                    let captures = regex.captures(&str_timezone);
+
+                    assert!(captures.is_some(), "Unable to match {}", str_timezone);
+                    let captures = captures
+                        .unwrap_or_else(|| panic!("Can't get captures for {}", str_timezone));
+
+                    let matched_sign = captures
+                        .get(1)
+                        .unwrap_or_else(|| panic!("Can't get sign for {}", str_timezone))
+                        .as_str();
+                    assert_eq!(
+                        matched_sign, sign,
+                        "Got invalid sign {}: {}",
+                        str_timezone, matched_sign
+                    );
+
+                    let matched_hour = captures
+                        .get(2)
+                        .unwrap_or_else(|| panic!("Can't get hours for {}", str_timezone))
+                        .as_str();
+                    assert_eq!(
+                        matched_hour,
+                        format!("{:0>2}", hour),
+                        "Got invalid hour {}: {}",
+                        str_timezone,
+                        matched_hour
+                    );
+
+                    let matched_minute = captures
+                        .get(3)
+                        .unwrap_or_else(|| panic!("Can't get minutes for {}", str_timezone))
+                        .as_str();
+                    assert_eq!(
+                        matched_minute,
+                        format!("{:0>2}", minute),
+                        "Got invalid minutes {}: {}",
+                        str_timezone,
+                        matched_minute
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("+05:00"), FixedOffset::east_opt(5 * 3600));
+        assert_eq!(
+            parse_timezone(" -03:30 "),
+            FixedOffset::west_opt(3 * 3600 + 1800)
+        );
+        assert_eq!(parse_timezone("+00:00"), FixedOffset::east_opt(0));
+
+        assert_eq!(parse_timezone("+24:00"), None);
+        assert_eq!(parse_timezone("-29:59"), None);
+        assert_eq!(parse_timezone("05:00"), None);
+        assert_eq!(parse_timezone(""), None);
+    }
+
+    #[test]
+    fn test_parse_timezone_name() {
+        assert_eq!(
+            parse_timezone_name("Europe/Moscow"),
+            Some(chrono_tz::Europe::Moscow)
+        );
+        assert_eq!(
+            parse_timezone_name(" america/new_york "),
+            Some(chrono_tz::America::New_York)
+        );
+        assert_eq!(parse_timezone_name("Mars/Olympus"), None);
+        assert_eq!(parse_timezone_name("+03:00"), None);
+    }
+
+    #[test]
+    fn test_timezone_at() {
+        assert_eq!(
+            timezone_at(55.7558, 37.6173),
+            Some(chrono_tz::Europe::Moscow)
+        );
+        assert_eq!(
+            timezone_at(40.7128, -74.006),
+            Some(chrono_tz::America::New_York)
+        );
+        assert_eq!(
+            timezone_at(-33.8688, 151.2093),
+            Some(chrono_tz::Australia::Sydney)
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45m"), Some(Duration::from_secs(45 * 60)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 3600)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(
+            parse_duration(" 1d 2H "),
+            Some(Duration::from_secs(26 * 3600))
+        );
+        assert_eq!(parse_duration("10 min"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("5 weeks"), None);
+        assert_eq!(parse_duration("99999999999999999999h"), None);
+        assert_eq!(parse_duration("9999999999999999h"), None);
+    }
+
+    #[test]
+    fn test_parse_time_window() {
+        let time = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+
+        assert_eq!(
+            parse_time_window("09:00-18:00"),
+            Some((time(9, 0), time(18, 0)))
+        );
+        assert_eq!(
+            parse_time_window("8:30 – 20:00"),
+            Some((time(8, 30), time(20, 0)))
+        );
+
+        assert_eq!(parse_time_window("18:00-09:00"), None);
+        assert_eq!(parse_time_window("09:00-09:00"), None);
+        assert_eq!(parse_time_window("09:00-24:00"), None);
+        assert_eq!(parse_time_window("09:00"), None);
+        assert_eq!(parse_time_window("9-18"), None);
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::{formatting, i18n::Locale};
+
+/// Minutes of work and of a break when "/pomodoro" comes without them.
+pub const DEFAULT_WORK: u32 = 25;
+pub const DEFAULT_BREAK: u32 = 5;
+
+/// Longest work or break "/pomodoro" accepts, in minutes.
+pub const MAX_MINUTES: u32 = 240;
+
+/// How long a cycle stays paused before it's dropped.
+pub const MAX_PAUSE: Duration = Duration::hours(2);
+
+/// Step of a "/pomodoro" cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+impl Phase {
+    /// The phase after this one along with its round, rounds count work
+    /// phases.
+    pub fn next(&self, round: u32) -> (Phase, u32) {
+        match self {
+            Phase::Work => (Phase::Break, round),
+            Phase::Break => (Phase::Work, round + 1),
+        }
+    }
+
+    /// How long the phase lasts in a cycle of `work` and `rest` minutes.
+    pub fn length(&self, work: u32, rest: u32) -> Duration {
+        match self {
+            Phase::Work => Duration::minutes(work.into()),
+            Phase::Break => Duration::minutes(rest.into()),
+        }
+    }
+}
+
+/// A "/pomodoro" cycle of `work` and `rest` minutes, in its `round` of work.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pomodoro {
+    pub work: u32,
+    pub rest: u32,
+    pub phase: Phase,
+    pub round: u32,
+    /// Seconds left of the phase while it's paused
+    #[serde(default)]
+    pub paused: Option<i64>,
+}
+
+impl Pomodoro {
+    pub fn new(work: u32, rest: u32) -> Pomodoro {
+        Pomodoro {
+            work,
+            rest,
+            phase: Phase::Work,
+            round: 1,
+            paused: None,
+        }
+    }
+
+    /// The cycle once its phase is over.
+    pub fn advance(&self) -> Pomodoro {
+        let (phase, round) = self.phase.next(self.round);
+        Pomodoro {
+            phase,
+            round,
+            paused: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn length(&self) -> Duration {
+        self.phase.length(self.work, self.rest)
+    }
+}
+
+/// Minutes of a phase as the chat reads them.
+pub fn format_minutes(minutes: u32, locale: Locale) -> String {
+    formatting::duration(
+        std::time::Duration::from_secs(u64::from(minutes) * 60),
+        locale,
+    )
+}
+
+/// Parses "/pomodoro" arguments: minutes of work and of a break, both
+/// optional.
+pub fn parse(args: &str) -> Option<(u32, u32)> {
+    let minutes = |value: &str| {
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|minutes| (1..=MAX_MINUTES).contains(minutes))
+    };
+
+    match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [] => Some((DEFAULT_WORK, DEFAULT_BREAK)),
+        [work] => Some((minutes(work)?, DEFAULT_BREAK)),
+        [work, rest] => Some((minutes(work)?, minutes(rest)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pomodoro::{parse, Phase, Pomodoro};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(""), Some((25, 5)));
+        assert_eq!(parse(" 50 "), Some((50, 5)));
+        assert_eq!(parse("50 10"), Some((50, 10)));
+        assert_eq!(parse("0"), None);
+        assert_eq!(parse("25 500"), None);
+        assert_eq!(parse("25m"), None);
+        assert_eq!(parse("25 5 1"), None);
+    }
+
+    #[test]
+    fn test_next() {
+        assert_eq!(Phase::Work.next(1), (Phase::Break, 1));
+        assert_eq!(Phase::Break.next(1), (Phase::Work, 2));
+        assert_eq!(Phase::Break.length(25, 5), chrono::Duration::minutes(5));
+
+        let paused = Pomodoro {
+            paused: Some(60),
+            ..Pomodoro::new(50, 10)
+        };
+        let rest = paused.advance();
+        assert_eq!(
+            (rest.phase, rest.round, rest.paused),
+            (Phase::Break, 1, None)
+        );
+        assert_eq!(rest.length(), chrono::Duration::minutes(10));
+        assert_eq!(rest.advance().round, 2);
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{i18n::Locale, offsets_rep::UserSettings, scheduling::Timing, tr};
+
+/// How many texts each preset rotates through.
+const MESSAGES: usize = 3;
+
+/// Curated reminders "/preset" adds along with the chat's notifications, each
+/// coming every its own interval with texts to rotate through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    Water,
+    Stretch,
+    /// The 20-20-20 rule: every 20 minutes look 20 feet away for 20 seconds
+    Eyes,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Water, Preset::Stretch, Preset::Eyes];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Water => "water",
+            Preset::Stretch => "stretch",
+            Preset::Eyes => "eyes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        let name = name.trim().to_lowercase();
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn interval(&self) -> Duration {
+        match self {
+            Preset::Water => Duration::from_secs(45 * 60),
+            Preset::Stretch => Duration::from_secs(2 * 3600),
+            Preset::Eyes => Duration::from_secs(20 * 60),
+        }
+    }
+
+    /// The text of the preset's `turn` in the chat's language at the time
+    /// it's sent.
+    pub fn message(&self, locale: Locale, turn: u32) -> String {
+        let number = turn as usize % MESSAGES + 1;
+        tr!(locale, &format!("preset-{}-{}", self.name(), number))
+    }
+
+    /// The first time the preset comes after `after`: every its interval
+    /// within the chat's working hours, whatever the chat's own interval and
+    /// cron schedule are.
+    pub fn next_after(
+        &self,
+        settings: &UserSettings,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        Timing {
+            interval: self.interval(),
+            cron: None,
+            ..settings.timing()
+        }
+        .next_after(after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        i18n::Locale,
+        notify_controller::{MAX_INTERVAL, MIN_INTERVAL},
+        offsets_rep::UserSettings,
+        presets::Preset,
+    };
+
+    #[test]
+    fn test_presets() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_name(preset.name()), Some(preset));
+            assert!((MIN_INTERVAL..=MAX_INTERVAL).contains(&preset.interval()));
+            for locale in [Locale::En, Locale::Ru] {
+                for turn in 0..3 {
+                    let message = preset.message(locale, turn);
+                    assert!(!message.starts_with("preset-"), "{}", message);
+                }
+            }
+        }
+        assert_eq!(Preset::from_name(" Water "), Some(Preset::Water));
+        assert_eq!(Preset::from_name("coffee"), None);
+        assert_eq!(
+            Preset::Eyes.message(Locale::En, 3),
+            Preset::Eyes.message(Locale::En, 0)
+        );
+        assert_ne!(
+            Preset::Eyes.message(Locale::En, 0),
+            Preset::Eyes.message(Locale::Ru, 0)
+        );
+    }
+
+    #[test]
+    fn test_next_after() {
+        // The chat's own cron schedule and interval are left to its notifications
+        let settings = UserSettings {
+            cron: Some("0 0 9 * * *".to_string()),
+            interval_secs: 3 * 3600,
+            ..UserSettings::default()
+        };
+        // Monday, 10:00 UTC
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+
+        assert_eq!(
+            Preset::Eyes.next_after(&settings, now),
+            Some(Utc.with_ymd_and_hms(2024, 5, 6, 10, 20, 0).unwrap())
+        );
+        // Not before the next working day, 09:00 at +05:00
+        assert_eq!(
+            Preset::Water.next_after(
+                &settings,
+                Utc.with_ymd_and_hms(2024, 5, 6, 20, 0, 0).unwrap()
+            ),
+            Some(Utc.with_ymd_and_hms(2024, 5, 7, 4, 0, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::{
+    i18n::Locale,
+    offsets_rep::{UserSettings, WorkingHours},
+    tr,
+};
+
+/// A timezone or working hours change, previewed as the notifications it
+/// would lead to before the user confirms it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingsChange {
+    /// Seconds east of UTC
+    Offset(i32),
+    /// A named timezone, the offset follows daylight saving time
+    Timezone(Tz),
+    WorkingHours(WorkingHours),
+}
+
+impl SettingsChange {
+    pub fn apply(&self, settings: &mut UserSettings) {
+        match self {
+            SettingsChange::Offset(offset) => {
+                settings.offset = *offset;
+                settings.timezone = None;
+            }
+            SettingsChange::Timezone(tz) => {
+                settings.offset = tz
+                    .offset_from_utc_datetime(&Utc::now().naive_utc())
+                    .fix()
+                    .local_minus_utc();
+                settings.timezone = Some(tz.name().to_string());
+            }
+            SettingsChange::WorkingHours(hours) => settings.working_hours = *hours,
+        }
+    }
+
+    /// Settings as they would be after the change, `settings` stay intact.
+    pub fn preview(&self, settings: &UserSettings) -> UserSettings {
+        let mut preview = settings.clone();
+        self.apply(&mut preview);
+        preview
+    }
+
+    /// The change as callback data.
+    pub fn encode(&self) -> String {
+        match self {
+            SettingsChange::Offset(offset) => format!("offset:{}", offset),
+            SettingsChange::Timezone(tz) => format!("tz:{}", tz.name()),
+            SettingsChange::WorkingHours(hours) => format!("hours:{}-{}", hours.from, hours.to),
+        }
+    }
+
+    pub fn decode(data: &str) -> Option<SettingsChange> {
+        let (kind, value) = data.split_once(':')?;
+        match kind {
+            "offset" => value
+                .parse::<i32>()
+                .ok()
+                .filter(|offset| offset.abs() < 24 * 3600)
+                .map(SettingsChange::Offset),
+            "tz" => value.parse().ok().map(SettingsChange::Timezone),
+            "hours" => {
+                let (from, to) = value.split_once('-')?;
+                let (from, to) = (from.parse::<u32>().ok()?, to.parse::<u32>().ok()?);
+                if from >= to || to > 24 {
+                    return None;
+                }
+
+                Some(SettingsChange::WorkingHours(WorkingHours { from, to }))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Buttons under a preview.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreviewButton {
+    Confirm(SettingsChange),
+    Cancel,
+}
+
+impl PreviewButton {
+    const PREFIX: &'static str = "preview:";
+
+    /// Callback data of the button, the change itself travels in it.
+    pub fn encode(&self) -> String {
+        match self {
+            PreviewButton::Confirm(change) => {
+                format!("{}confirm:{}", Self::PREFIX, change.encode())
+            }
+            PreviewButton::Cancel => format!("{}cancel", Self::PREFIX),
+        }
+    }
+
+    pub fn decode(data: &str) -> Option<PreviewButton> {
+        match data.strip_prefix(Self::PREFIX)? {
+            "cancel" => Some(PreviewButton::Cancel),
+            data => {
+                SettingsChange::decode(data.strip_prefix("confirm:")?).map(PreviewButton::Confirm)
+            }
+        }
+    }
+}
+
+pub fn keyboard(locale: Locale, change: &SettingsChange) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback(
+            tr!(locale, "button-confirm"),
+            PreviewButton::Confirm(change.clone()).encode(),
+        ),
+        InlineKeyboardButton::callback(
+            tr!(locale, "button-cancel"),
+            PreviewButton::Cancel.encode(),
+        ),
+    ]])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        offsets_rep::{UserSettings, WorkingHours},
+        previews::{PreviewButton, SettingsChange},
+    };
+
+    #[test]
+    fn test_preview_buttons() {
+        let buttons = [
+            PreviewButton::Cancel,
+            PreviewButton::Confirm(SettingsChange::Offset(-3 * 3600 - 1800)),
+            PreviewButton::Confirm(SettingsChange::Timezone(
+                chrono_tz::America::Argentina::ComodRivadavia,
+            )),
+            PreviewButton::Confirm(SettingsChange::WorkingHours(WorkingHours {
+                from: 8,
+                to: 24,
+            })),
+        ];
+        for button in buttons {
+            // Telegram limits callback data to 64 bytes
+            assert!(button.encode().len() <= 64, "{:?}", button);
+            assert_eq!(PreviewButton::decode(&button.encode()), Some(button));
+        }
+
+        assert_eq!(PreviewButton::decode("preview:confirm:hours:18-9"), None);
+        assert_eq!(PreviewButton::decode("preview:confirm:offset:90000"), None);
+        assert_eq!(
+            PreviewButton::decode("preview:confirm:tz:Mars/Olympus"),
+            None
+        );
+        assert_eq!(PreviewButton::decode("notify:done"), None);
+    }
+
+    #[test]
+    fn test_preview_keeps_settings() {
+        let settings = UserSettings {
+            offset: 3 * 3600,
+            timezone: Some("Europe/Moscow".to_string()),
+            ..Default::default()
+        };
+
+        let preview = SettingsChange::Offset(5 * 3600).preview(&settings);
+        assert_eq!(preview.offset, 5 * 3600);
+        assert_eq!(preview.timezone, None);
+        assert_eq!(settings.offset, 3 * 3600);
+        assert_eq!(settings.timezone.as_deref(), Some("Europe/Moscow"));
+    }
+}
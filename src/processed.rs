@@ -0,0 +1,78 @@
+use std::{collections::VecDeque, ffi::OsStr, path::Path, sync::Mutex};
+
+use pickledb::{error::Result, PickleDb, PickleDbDumpPolicy, SerializationMethod};
+
+/// How many of the latest update ids are remembered. Telegram redelivers
+/// updates that weren't confirmed before a crash, those are the latest.
+const CAPACITY: usize = 1000;
+const KEY: &str = "recent";
+
+/// Ids of updates already handled, kept on disk so that updates Telegram
+/// delivers again after a restart, e.g. a "/done", aren't applied twice.
+pub struct ProcessedUpdates {
+    db: Mutex<(PickleDb, VecDeque<i32>)>,
+}
+
+impl ProcessedUpdates {
+    pub fn open_or_create<S: AsRef<OsStr> + ?Sized>(s: &S) -> Result<ProcessedUpdates> {
+        let path = Path::new(s);
+        let policy = PickleDbDumpPolicy::AutoDump;
+        let db = match path.exists() {
+            true => PickleDb::load(path, policy, SerializationMethod::Json)?,
+            false => PickleDb::new(path, policy, SerializationMethod::Json),
+        };
+        let recent = db.get(KEY).unwrap_or_default();
+
+        Ok(ProcessedUpdates {
+            db: Mutex::new((db, recent)),
+        })
+    }
+
+    /// Records the update, `false` if it was handled before.
+    pub fn first_time(&self, id: i32) -> bool {
+        let mut db = self.db.lock().unwrap();
+        let (db, recent) = &mut *db;
+        if recent.contains(&id) {
+            return false;
+        }
+
+        recent.push_back(id);
+        if recent.len() > CAPACITY {
+            recent.pop_front();
+        }
+        // Losing the record only risks handling a redelivery again
+        if let Err(err) = db.set(KEY, recent) {
+            log::error!("Unable to record update {}: {}", id, err);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processed::{ProcessedUpdates, CAPACITY};
+
+    #[test]
+    fn test_processed_updates() {
+        let path = std::env::temp_dir().join("notification_bot_test_processed.db");
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let processed = ProcessedUpdates::open_or_create(&path).unwrap();
+            assert!(processed.first_time(1));
+            assert!(processed.first_time(2));
+            assert!(!processed.first_time(1));
+        }
+
+        // Survives restarts, only the latest ids are kept
+        let processed = ProcessedUpdates::open_or_create(&path).unwrap();
+        assert!(!processed.first_time(2));
+        for id in 3..CAPACITY as i32 + 2 {
+            assert!(processed.first_time(id));
+        }
+        assert!(processed.first_time(1));
+        assert!(!processed.first_time(3));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    offsets_rep::{UserSettings, WorkingHours},
+    previews::SettingsChange,
+};
+
+/// Settings shared by a team, defined by admins with "/profile" and joined
+/// with "/joinprofile". Changing a profile changes the settings of every
+/// chat that joined it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// IANA timezone name
+    pub timezone: String,
+    pub working_hours: WorkingHours,
+    /// Local dates without notifications
+    #[serde(default)]
+    pub holidays: BTreeSet<NaiveDate>,
+}
+
+impl Profile {
+    pub fn tz(&self) -> Option<Tz> {
+        self.timezone.parse().ok()
+    }
+
+    /// Copies the profile into the settings of a chat joining it as `name`.
+    pub fn apply(&self, name: &str, settings: &mut UserSettings) {
+        if let Some(tz) = self.tz() {
+            SettingsChange::Timezone(tz).apply(settings);
+        }
+        settings.working_hours = self.working_hours;
+        settings.holidays = self.holidays.clone();
+        settings.profile = Some(name.to_string());
+    }
+}
+
+/// Detaches the chat from its profile, the settings it brought stay except
+/// for the holidays.
+pub fn leave(settings: &mut UserSettings) {
+    settings.profile = None;
+    settings.holidays.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        offsets_rep::{UserSettings, WorkingHours},
+        profiles::{leave, Profile},
+    };
+
+    #[test]
+    fn test_apply_profile() {
+        let christmas = NaiveDate::from_ymd_opt(2024, 12, 25).unwrap();
+        let profile = Profile {
+            timezone: "Europe/Berlin".to_string(),
+            working_hours: WorkingHours { from: 8, to: 17 },
+            holidays: [christmas].into(),
+        };
+        let mut settings = UserSettings::default();
+
+        profile.apply("Berlin office", &mut settings);
+        assert_eq!(settings.timezone.as_deref(), Some("Europe/Berlin"));
+        assert!([3600, 2 * 3600].contains(&settings.offset));
+        assert_eq!(settings.working_hours, WorkingHours { from: 8, to: 17 });
+        assert!(settings.holidays.contains(&christmas));
+        assert_eq!(settings.profile.as_deref(), Some("Berlin office"));
+
+        leave(&mut settings);
+        assert_eq!(settings.profile, None);
+        assert!(settings.holidays.is_empty());
+        assert_eq!(settings.timezone.as_deref(), Some("Europe/Berlin"));
+    }
+}
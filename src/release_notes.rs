@@ -0,0 +1,76 @@
+use crate::{i18n::Locale, tr};
+
+/// The version running now.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Versions with notes for users, the oldest first. Each has a
+/// "whats-new-<major>-<minor>-<patch>" message in every catalog.
+const RELEASES: &[&str] = &["0.1.0"];
+
+/// Versions whose notes a chat that last saw `seen` hasn't got, the oldest
+/// first. Chats from before notes were tracked get the latest ones only.
+pub fn unseen(seen: Option<&str>) -> Vec<&'static str> {
+    let current = parse(VERSION);
+    let released: Vec<&'static str> = RELEASES
+        .iter()
+        .copied()
+        .filter(|release| parse(release) <= current)
+        .collect();
+    match seen.map(parse) {
+        Some(seen) => released
+            .into_iter()
+            .filter(|release| parse(release) > seen)
+            .collect(),
+        None => released.last().copied().into_iter().collect(),
+    }
+}
+
+/// The notes of `versions` under a heading with the running version.
+pub fn message(versions: &[&str], locale: Locale) -> String {
+    let mut parts = vec![tr!(locale, "whats-new", version = VERSION)];
+    parts.extend(
+        versions
+            .iter()
+            .map(|version| tr!(locale, &format!("whats-new-{}", version.replace('.', "-")))),
+    );
+    parts.push(tr!(locale, "whats-new-footer"));
+    parts.join("\n\n")
+}
+
+/// Numeric parts of "1.2.3", so that "0.10.0" comes after "0.9.0".
+fn parse(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        i18n::Locale,
+        release_notes::{message, parse, unseen, RELEASES, VERSION},
+        tr,
+    };
+
+    #[test]
+    fn test_unseen() {
+        assert!(parse("0.10.0") > parse("0.9.1"));
+        assert_eq!(unseen(Some(VERSION)), Vec::<&str>::new());
+        assert_eq!(unseen(Some("0.0.1")), RELEASES.to_vec());
+        assert_eq!(unseen(None), vec![*RELEASES.last().unwrap()]);
+    }
+
+    #[test]
+    fn test_every_release_has_notes() {
+        for release in RELEASES {
+            let id = format!("whats-new-{}", release.replace('.', "-"));
+            assert_ne!(tr!(Locale::En, &id), id);
+        }
+        assert!(message(RELEASES, Locale::Ru).starts_with(&tr!(
+            Locale::Ru,
+            "whats-new",
+            version = VERSION
+        )));
+    }
+}
@@ -0,0 +1,716 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDateTime, NaiveTime, Offset, TimeZone, Timelike, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::Notify,
+    time::{sleep_until, Instant},
+};
+
+pub const HOUR_FROM: u32 = 9;
+pub const HOUR_TO: u32 = 18;
+/// Clocks skip at most a day, e.g. Samoa crossing the date line
+const MAX_GAP_MINUTES: i64 = 24 * 60;
+
+/// Local hours between which notifications are sent, `to` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingHours {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        WorkingHours {
+            from: HOUR_FROM,
+            to: HOUR_TO,
+        }
+    }
+}
+
+impl WorkingHours {
+    /// Whether the local `hour` is in the first or the last `edge` hours,
+    /// the notification closing them at `to` included.
+    pub fn is_edge(&self, hour: u32, edge: u32) -> bool {
+        edge > 0 && (hour < self.from + edge || hour + edge >= self.to)
+    }
+}
+
+/// A break in working hours without notifications, e.g. lunch, `to` is
+/// exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        (self.from..self.to).contains(&time)
+    }
+}
+
+/// Weekdays notifications are sent on, bit 0 is Monday.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Workdays(u8);
+
+impl Default for Workdays {
+    fn default() -> Self {
+        Workdays::from_days([
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ])
+    }
+}
+
+impl Workdays {
+    pub fn from_days<I: IntoIterator<Item = Weekday>>(days: I) -> Workdays {
+        Workdays(
+            days.into_iter()
+                .fold(0, |mask, day| mask | 1 << day.num_days_from_monday()),
+        )
+    }
+
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.0 & 1 << day.num_days_from_monday() != 0
+    }
+
+    /// The chosen days in week order.
+    pub fn days(&self) -> impl Iterator<Item = Weekday> + '_ {
+        (0..7u8)
+            .filter_map(|index| Weekday::try_from(index).ok())
+            .filter(|day| self.contains(*day))
+    }
+}
+
+/// Where local time of a schedule is told.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Fixed(FixedOffset),
+    /// Follows daylight saving time
+    Named(Tz),
+}
+
+impl Zone {
+    /// The offset from UTC at `moment`.
+    pub fn offset_at(&self, moment: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Zone::Fixed(offset) => *offset,
+            Zone::Named(tz) => tz.offset_from_utc_datetime(&moment.naive_utc()).fix(),
+        }
+    }
+
+    /// Local time at `moment`.
+    pub fn local(&self, moment: DateTime<Utc>) -> DateTime<FixedOffset> {
+        self.offset_at(moment)
+            .from_utc_datetime(&moment.naive_utc())
+    }
+
+    /// The moment the clock shows `local`: the earlier one when the clock
+    /// goes back and shows it twice, the end of the gap when it skips it.
+    pub fn from_local(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Zone::Fixed(offset) => (local - *offset).and_utc(),
+            Zone::Named(tz) => (0..=MAX_GAP_MINUTES)
+                .find_map(|minutes| {
+                    tz.from_local_datetime(&(local + chrono::Duration::minutes(minutes)))
+                        .earliest()
+                })
+                .map(|date| date.with_timezone(&Utc))
+                .unwrap_or_else(|| (local - tz.offset_from_utc_datetime(&local).fix()).and_utc()),
+        }
+    }
+}
+
+/// When a schedule fires: by `cron` when set, otherwise every `interval`
+/// during `working_hours` on `workdays` outside of `quiet_hours`, with one
+/// more at the end of the window.
+#[derive(Clone, Debug)]
+pub struct Timing {
+    pub working_hours: WorkingHours,
+    pub workdays: Workdays,
+    pub quiet_hours: Vec<QuietHours>,
+    pub interval: Duration,
+    pub zone: Zone,
+    pub cron: Option<Schedule>,
+}
+
+impl Timing {
+    /// The first date the schedule fires at after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some(schedule) = &self.cron {
+            return next_cron(schedule, &self.zone, after);
+        }
+
+        let local = self.zone.local(after);
+        let sleep_time = get_sleep_time(
+            local,
+            self.working_hours,
+            self.workdays,
+            &self.quiet_hours,
+            self.interval,
+        );
+        let sleep_time = chrono::Duration::seconds(sleep_time.as_secs() as i64);
+        // Slots are on the wall clock, which may move while waiting for one
+        let next = self.zone.from_local(local.naive_local() + sleep_time);
+        Some(match next > after {
+            true => next,
+            false => after + sleep_time,
+        })
+    }
+}
+
+pub fn its_working_time(
+    date: DateTime<FixedOffset>,
+    hours: WorkingHours,
+    workdays: Workdays,
+    quiet: &[QuietHours],
+) -> bool {
+    in_working_hours(date, hours, workdays)
+        && !quiet.iter().any(|window| window.contains(date.time()))
+}
+
+fn in_working_hours(date: DateTime<FixedOffset>, hours: WorkingHours, workdays: Workdays) -> bool {
+    workdays.contains(date.weekday()) && (hours.from..hours.to).contains(&date.hour())
+}
+
+/// How long after the local `date` the next notification is due.
+///
+/// A slot falling into quiet hours moves to their end, or to the next
+/// working day when they last past the working hours.
+pub fn get_sleep_time(
+    date: DateTime<FixedOffset>,
+    hours: WorkingHours,
+    workdays: Workdays,
+    quiet: &[QuietHours],
+    interval: Duration,
+) -> Duration {
+    let after = |date: DateTime<FixedOffset>| {
+        let sleep = next_slot(date, hours, workdays, interval);
+        date + chrono::Duration::seconds(sleep.as_secs() as i64)
+    };
+
+    let mut next = after(date);
+    // Windows may follow one another, each is passed at most once a day
+    for _ in 0..=2 * quiet.len() {
+        let Some(window) = quiet.iter().find(|window| window.contains(next.time())) else {
+            break;
+        };
+        let time = next.time();
+        next = match window.to.num_seconds_from_midnight() <= hours.to * 3600 {
+            true => next + (window.to - time),
+            false => {
+                let closing =
+                    i64::from(hours.to * 3600) - i64::from(time.num_seconds_from_midnight());
+                after(next + chrono::Duration::seconds(closing))
+            }
+        };
+    }
+    (next - date).to_std().unwrap_or_default()
+}
+
+/// The next slot of the working hours, quiet hours aside.
+fn next_slot(
+    date: DateTime<FixedOffset>,
+    hours: WorkingHours,
+    workdays: Workdays,
+    interval: Duration,
+) -> Duration {
+    let (hour_from, hour_to) = (hours.from, hours.to);
+
+    // During working time sleep until the next slot, slots are counted from
+    // the start of the window and the end of the window is the last one
+    if in_working_hours(date, hours, workdays) {
+        let elapsed = (date.hour() - hour_from) * 3600 + date.minute() * 60 + date.second();
+        let interval = interval.as_secs().clamp(1, u64::from(u32::MAX)) as u32;
+        let next = (elapsed / interval)
+            .saturating_add(1)
+            .saturating_mul(interval);
+        let end = (hour_to - hour_from) * 3600;
+
+        return Duration::from_secs(u64::from(next.min(end) - elapsed));
+    }
+
+    // Otherwise sleep until the window opens on the next working day, a week
+    // without working days is checked again in a week
+    let first_day = match date.hour() < hour_from {
+        true => 0,
+        false => 1,
+    };
+    let days = (first_day..first_day + 7)
+        .find(|days| {
+            let day = date + chrono::Duration::days(i64::from(*days));
+            workdays.contains(day.weekday())
+        })
+        .unwrap_or(7);
+
+    let now = date.hour() * 3600 + date.minute() * 60 + date.second();
+    Duration::from_secs(u64::from(days * 24 * 3600 + hour_from * 3600 - now))
+}
+
+/// The first date of the schedule after `after`, in the `zone` so named
+/// timezones follow daylight saving time.
+pub fn next_cron(schedule: &Schedule, zone: &Zone, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match zone {
+        Zone::Named(tz) => schedule
+            .after(&after.with_timezone(tz))
+            .next()
+            .map(|next| next.with_timezone(&Utc)),
+        Zone::Fixed(offset) => schedule
+            .after(&after.with_timezone(offset))
+            .next()
+            .map(|next| next.with_timezone(&Utc)),
+    }
+}
+
+/// Keys with a value each, due at an instant. It runs the bot's notifications
+/// and the schedules of [`Scheduler`].
+///
+/// A key taken when due gets a ticket for the work it does, rescheduling or
+/// removing the key meanwhile makes the ticket stale.
+pub struct Queue<K, V> {
+    /// Every key with the ticket that counts, the wake ups left behind by
+    /// rescheduling are skipped
+    entries: HashMap<K, (V, u64)>,
+    due: BinaryHeap<Reverse<(Instant, u64, K)>>,
+    tickets: u64,
+}
+
+impl<K: Clone + Eq + Hash + Ord, V> Default for Queue<K, V> {
+    fn default() -> Self {
+        Queue {
+            entries: HashMap::new(),
+            due: BinaryHeap::new(),
+            tickets: 0,
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash + Ord, V> Queue<K, V> {
+    pub fn new() -> Queue<K, V> {
+        Queue::default()
+    }
+
+    /// Adds the key unscheduled, replacing the one there and its wake up.
+    pub fn add(&mut self, key: K, value: V) {
+        self.entries.insert(key, (value, 0));
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(value, _)| value)
+    }
+
+    /// Removes every key, returns how many there were.
+    pub fn clear(&mut self) -> usize {
+        let keys = self.entries.len();
+        self.entries.clear();
+        self.due.clear();
+        keys
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries.get_mut(key).map(|(value, _)| value)
+    }
+
+    /// Every key with its value, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    /// The key's value while `ticket` is still its own.
+    pub fn get_current_mut(&mut self, key: &K, ticket: u64) -> Option<&mut V> {
+        self.entries
+            .get_mut(key)
+            .filter(|(_, current)| *current == ticket)
+            .map(|(value, _)| value)
+    }
+
+    /// Makes the key due at `at`, replacing its pending wake up.
+    pub fn schedule(&mut self, key: K, at: Instant) {
+        self.tickets += 1;
+        let Some((_, ticket)) = self.entries.get_mut(&key) else {
+            return;
+        };
+        *ticket = self.tickets;
+        self.due.push(Reverse((at, self.tickets, key)));
+    }
+
+    /// Whether the work with `ticket` is still the key's, a removed or
+    /// rescheduled key doesn't get it.
+    pub fn is_current(&self, key: &K, ticket: u64) -> bool {
+        self.entries
+            .get(key)
+            .is_some_and(|(_, current)| *current == ticket)
+    }
+
+    /// When the key is due, unset while it's taken or unscheduled.
+    pub fn due_at(&self, key: &K) -> Option<Instant> {
+        let (_, ticket) = self.entries.get(key)?;
+        self.due
+            .iter()
+            .find(|Reverse((_, due_ticket, due_key))| due_key == key && due_ticket == ticket)
+            .map(|Reverse((at, _, _))| *at)
+    }
+
+    /// When the loop should wake up next, maybe for a stale wake up.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.due.peek().map(|Reverse((at, _, _))| *at)
+    }
+
+    /// Takes the keys due by `now`, each gets a ticket for its work.
+    pub fn take_due(&mut self, now: Instant) -> Vec<(K, u64)> {
+        let mut due = vec![];
+        while let Some(Reverse((at, ticket, key))) = self.due.peek().cloned() {
+            if at > now {
+                break;
+            }
+            self.due.pop();
+            if let Some((_, current)) = self.entries.get_mut(&key) {
+                if *current == ticket {
+                    self.tickets += 1;
+                    *current = self.tickets;
+                    due.push((key, self.tickets));
+                }
+            }
+        }
+        due
+    }
+}
+
+/// Sleeps until `next`, or until `changed` is notified when the queue it's
+/// for changes. A change made since the queue was read leaves a permit
+/// behind, so it isn't missed.
+pub async fn wait_for(next: Option<Instant>, changed: &Notify) {
+    match next {
+        Some(next) => {
+            tokio::select! {
+                _ = changed.notified() => {}
+                _ = sleep_until(next) => {}
+            }
+        }
+        None => changed.notified().await,
+    }
+}
+
+type Callback<K> = Arc<dyn Fn(&K, DateTime<Utc>) + Send + Sync>;
+
+/// Runs schedules keyed by `K` on a [`Queue`] and calls back when they fire.
+///
+/// Schedules can be added and removed while [`Scheduler::run`] waits, it
+/// wakes up for changes.
+pub struct Scheduler<K> {
+    queue: Mutex<Queue<K, Timed>>,
+    on_fire: Mutex<Option<Callback<K>>>,
+    changed: Notify,
+}
+
+/// A schedule with the date it fires at next, unset once it has no dates
+/// left.
+struct Timed {
+    timing: Timing,
+    next: Option<DateTime<Utc>>,
+}
+
+impl<K: Clone + Eq + Hash + Ord> Default for Scheduler<K> {
+    fn default() -> Self {
+        Scheduler {
+            queue: Mutex::new(Queue::new()),
+            on_fire: Mutex::new(None),
+            changed: Notify::new(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash + Ord> Scheduler<K> {
+    pub fn new() -> Scheduler<K> {
+        Scheduler::default()
+    }
+
+    /// Adds the schedule, replacing the one under the same `key`.
+    pub fn add_schedule(&self, key: K, timing: Timing) {
+        let now = Utc::now();
+        let next = timing.next_after(now);
+        let mut queue = self.queue.lock().unwrap();
+        queue.add(key.clone(), Timed { timing, next });
+        if let Some(date) = next {
+            queue.schedule(key, instant_of(date, now, Instant::now()));
+        }
+        drop(queue);
+        self.changed.notify_one();
+    }
+
+    /// Removes the schedule, returns `false` if there was none.
+    pub fn remove(&self, key: &K) -> bool {
+        let removed = self.queue.lock().unwrap().remove(key).is_some();
+        self.changed.notify_one();
+        removed
+    }
+
+    /// Calls `callback` with the key and the date of every schedule that
+    /// fires, replacing the previous callback.
+    pub fn on_fire<F>(&self, callback: F)
+    where
+        F: Fn(&K, DateTime<Utc>) + Send + Sync + 'static,
+    {
+        *self.on_fire.lock().unwrap() = Some(Arc::new(callback));
+    }
+
+    /// The next `count` fires of all schedules, the earliest first.
+    pub fn next_fires(&self, count: usize) -> Vec<(DateTime<Utc>, K)> {
+        let queue = self.queue.lock().unwrap();
+        let mut fires: Vec<(DateTime<Utc>, K)> = queue
+            .iter()
+            .flat_map(|(key, timed)| {
+                std::iter::successors(timed.next, |after| timed.timing.next_after(*after))
+                    .take(count)
+                    .map(move |date| (date, key.clone()))
+            })
+            .collect();
+        fires.sort();
+        fires.truncate(count);
+        fires
+    }
+
+    /// Fires schedules as their dates come, until the future is dropped.
+    /// Dates missed while the process was suspended are skipped.
+    pub async fn run(&self) {
+        loop {
+            let (now, instant) = (Utc::now(), Instant::now());
+            let (fired, next) = {
+                let mut queue = self.queue.lock().unwrap();
+                let mut fired = vec![];
+                for (key, ticket) in queue.take_due(instant) {
+                    let Some(timed) = queue.get_current_mut(&key, ticket) else {
+                        continue;
+                    };
+                    let Some(date) = timed.next else {
+                        continue;
+                    };
+                    timed.next = timed.timing.next_after(date.max(now));
+                    if let Some(next) = timed.next {
+                        queue.schedule(key.clone(), instant_of(next, now, instant));
+                    }
+                    fired.push((key, date));
+                }
+                (fired, queue.next_due())
+            };
+
+            // Called without the lock, so callbacks may change schedules
+            let on_fire = self.on_fire.lock().unwrap().clone();
+            if let Some(on_fire) = on_fire {
+                for (key, date) in &fired {
+                    on_fire(key, *date);
+                }
+            }
+
+            wait_for(next, &self.changed).await;
+        }
+    }
+}
+
+/// The instant of `date`, `now` being `instant`.
+fn instant_of(date: DateTime<Utc>, now: DateTime<Utc>, instant: Instant) -> Instant {
+    instant + (date - now).to_std().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use chrono::{FixedOffset, NaiveTime, TimeZone, Utc};
+    use tokio::time::Instant;
+
+    use crate::scheduling::{
+        get_sleep_time, its_working_time, Queue, QuietHours, Scheduler, Timing, Workdays,
+        WorkingHours, Zone,
+    };
+
+    fn timing(cron: Option<&str>) -> Timing {
+        Timing {
+            working_hours: WorkingHours::default(),
+            workdays: Workdays::default(),
+            quiet_hours: vec![],
+            interval: Duration::from_secs(3600),
+            zone: Zone::Fixed(FixedOffset::east_opt(3 * 3600).unwrap()),
+            cron: cron.map(|expression| expression.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_timing() {
+        // Friday 17:30 local time, then 18:00 closing the window and Monday 9:00
+        let timing = timing(None);
+        let friday = Utc.with_ymd_and_hms(2023, 5, 5, 14, 30, 0).unwrap();
+        let closing = Utc.with_ymd_and_hms(2023, 5, 5, 15, 0, 0).unwrap();
+        assert_eq!(timing.next_after(friday), Some(closing));
+        assert_eq!(
+            timing.next_after(closing),
+            Some(Utc.with_ymd_and_hms(2023, 5, 8, 6, 0, 0).unwrap())
+        );
+
+        let zone = Zone::Named(chrono_tz::Europe::Berlin);
+        assert_eq!(zone.offset_at(friday).local_minus_utc(), 2 * 3600);
+        assert_eq!(zone.local(friday).format("%H:%M").to_string(), "16:30");
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+        let quiet = [
+            QuietHours {
+                from: time(13, 0),
+                to: time(14, 0),
+            },
+            QuietHours {
+                from: time(14, 0),
+                to: time(14, 30),
+            },
+            QuietHours {
+                from: time(17, 0),
+                to: time(19, 0),
+            },
+        ];
+        let (hours, workdays) = (WorkingHours::default(), Workdays::default());
+        let offset = FixedOffset::east_opt(3 * 3600).unwrap();
+        // Monday
+        let date = |hour, min| offset.with_ymd_and_hms(2023, 5, 1, hour, min, 0).unwrap();
+        let hour = Duration::from_secs(3600);
+
+        assert!(its_working_time(date(12, 59), hours, workdays, &quiet));
+        assert!(!its_working_time(date(13, 0), hours, workdays, &quiet));
+        assert!(its_working_time(date(14, 30), hours, workdays, &quiet));
+        assert!(!its_working_time(date(17, 30), hours, workdays, &quiet));
+
+        // 13:00 and 14:00 are quiet, both breaks are over at 14:30
+        assert_eq!(
+            get_sleep_time(date(12, 0), hours, workdays, &quiet, hour),
+            Duration::from_secs(2 * 3600 + 30 * 60)
+        );
+        assert_eq!(
+            get_sleep_time(date(13, 10), hours, workdays, &quiet, hour),
+            Duration::from_secs(3600 + 20 * 60)
+        );
+        assert_eq!(
+            get_sleep_time(date(15, 0), hours, workdays, &quiet, hour),
+            hour
+        );
+        // 17:00 and the end of the day are quiet, next is Tuesday 9:00
+        assert_eq!(
+            get_sleep_time(date(16, 0), hours, workdays, &quiet, hour),
+            Duration::from_secs(17 * 3600)
+        );
+        assert_eq!(
+            get_sleep_time(date(16, 0), hours, workdays, &[], hour),
+            hour
+        );
+    }
+
+    #[test]
+    fn test_edge_hours() {
+        let hours = WorkingHours::default();
+        assert!(hours.is_edge(9, 1));
+        assert!(!hours.is_edge(10, 1));
+        assert!(!hours.is_edge(16, 1));
+        assert!(hours.is_edge(17, 1));
+        assert!(hours.is_edge(18, 1));
+        assert!(hours.is_edge(10, 2));
+        assert!(hours.is_edge(16, 2));
+        assert!(!hours.is_edge(9, 0));
+    }
+
+    #[test]
+    fn test_queue() {
+        let mut scheduler = Queue::new();
+        scheduler.add("first", 1);
+        scheduler.add("second", 2);
+        let now = Instant::now();
+        let minute = Duration::from_secs(60);
+        scheduler.schedule("first", now + minute);
+        scheduler.schedule("second", now + 2 * minute);
+        assert_eq!(scheduler.next_due(), Some(now + minute));
+        assert_eq!(scheduler.due_at(&"second"), Some(now + 2 * minute));
+        assert!(scheduler.take_due(now).is_empty());
+
+        // Rescheduled and removed keys leave wake ups that are skipped
+        scheduler.schedule("first", now + 3 * minute);
+        assert_eq!(scheduler.due_at(&"first"), Some(now + 3 * minute));
+        assert_eq!(scheduler.iter().count(), 2);
+        let due = scheduler.take_due(now + 2 * minute);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "second");
+        assert_eq!(scheduler.due_at(&"second"), None);
+        assert_eq!(scheduler.remove(&"first"), Some(1));
+        assert!(scheduler.take_due(now + 3 * minute).is_empty());
+        assert_eq!(scheduler.next_due(), None);
+
+        // Work in flight is no longer due, its ticket tells it's current
+        let (_, ticket) = due[0];
+        assert!(scheduler.is_current(&"second", ticket));
+        assert_eq!(scheduler.get_current_mut(&"second", ticket), Some(&mut 2));
+        scheduler.schedule("second", now);
+        assert!(!scheduler.is_current(&"second", ticket));
+        assert_eq!(scheduler.get_current_mut(&"second", ticket), None);
+        assert!(!scheduler.is_current(&"first", 0));
+        assert_eq!(scheduler.clear(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_scheduler() {
+        let scheduler = Arc::new(Scheduler::new());
+        scheduler.add_schedule("hourly", timing(None));
+        scheduler.add_schedule("every-second", timing(Some("* * * * * *")));
+        scheduler.add_schedule("gone", timing(Some("0 0 0 1 1 * 2020")));
+
+        let fires = scheduler.next_fires(3);
+        assert_eq!(fires.len(), 3);
+        assert!(fires.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert!(fires.iter().all(|(_, key)| *key == "every-second"));
+        assert!(fires[0].0 > Utc::now());
+        assert!(scheduler.remove(&"every-second"));
+        assert!(!scheduler.remove(&"every-second"));
+        assert!(scheduler
+            .next_fires(5)
+            .iter()
+            .all(|(_, key)| *key == "hourly"));
+
+        let (fired, mut fires) = tokio::sync::mpsc::unbounded_channel();
+        scheduler.on_fire(move |key, date| {
+            let _ = fired.send((*key, date));
+        });
+        scheduler.add_schedule("every-second", timing(Some("* * * * * *")));
+        let running = Arc::clone(&scheduler);
+        let run = tokio::spawn(async move { running.run().await });
+        for _ in 0..2 {
+            let (key, date) = tokio::time::timeout(Duration::from_secs(3), fires.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(key, "every-second");
+            assert!(date <= Utc::now());
+        }
+        run.abort();
+    }
+}
@@ -0,0 +1,345 @@
+use chrono::Weekday;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::{
+    formatting,
+    keyboards::TIMEZONE_CHOICES,
+    offsets_rep::{Footer, UserSettings, Workdays, WorkingHours},
+    parsers,
+    previews::SettingsChange,
+    tr,
+};
+
+const WORKING_HOURS_CHOICES: [(u32, u32); 6] =
+    [(7, 16), (8, 17), (9, 18), (10, 19), (11, 20), (12, 21)];
+/// Minutes between notifications
+const INTERVAL_CHOICES: [u64; 6] = [15, 30, 60, 90, 120, 180];
+
+/// Parts of the "/settings" menu, each with its own choices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    Timezone,
+    WorkingHours,
+    Interval,
+    Workdays,
+    /// What follows the message text
+    Message,
+}
+
+impl Section {
+    pub const ALL: [Section; 5] = [
+        Section::Timezone,
+        Section::WorkingHours,
+        Section::Interval,
+        Section::Workdays,
+        Section::Message,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Section::Timezone => "timezone",
+            Section::WorkingHours => "hours",
+            Section::Interval => "interval",
+            Section::Workdays => "workdays",
+            Section::Message => "message",
+        }
+    }
+}
+
+/// Buttons of the "/settings" menu, every press edits the menu in place.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuButton {
+    Open(Section),
+    /// Back to the list of sections
+    Back,
+    Close,
+    /// A timezone or working hours
+    Change(SettingsChange),
+    /// Minutes between notifications
+    Interval(u64),
+    ToggleWorkday(Weekday),
+    ToggleFooter,
+}
+
+impl MenuButton {
+    const PREFIX: &'static str = "menu:";
+
+    /// Callback data of the button.
+    pub fn encode(&self) -> String {
+        let data = match self {
+            MenuButton::Open(section) => format!("open:{}", section.name()),
+            MenuButton::Back => "back".to_string(),
+            MenuButton::Close => "close".to_string(),
+            MenuButton::Change(change) => format!("set:{}", change.encode()),
+            MenuButton::Interval(minutes) => format!("interval:{}", minutes),
+            MenuButton::ToggleWorkday(day) => format!("day:{}", day.num_days_from_monday()),
+            MenuButton::ToggleFooter => "footer".to_string(),
+        };
+        format!("{}{}", Self::PREFIX, data)
+    }
+
+    pub fn decode(data: &str) -> Option<MenuButton> {
+        let data = data.strip_prefix(Self::PREFIX)?;
+        let (kind, value) = data.split_once(':').unwrap_or((data, ""));
+        match (kind, value) {
+            ("open", name) => Section::ALL
+                .into_iter()
+                .find(|section| section.name() == name)
+                .map(MenuButton::Open),
+            ("back", "") => Some(MenuButton::Back),
+            ("close", "") => Some(MenuButton::Close),
+            ("set", change) => SettingsChange::decode(change).map(MenuButton::Change),
+            ("interval", minutes) => minutes
+                .parse()
+                .ok()
+                .filter(|minutes| INTERVAL_CHOICES.contains(minutes))
+                .map(MenuButton::Interval),
+            ("day", day) => day
+                .parse::<u8>()
+                .ok()
+                .and_then(|day| Weekday::try_from(day).ok())
+                .map(MenuButton::ToggleWorkday),
+            ("footer", "") => Some(MenuButton::ToggleFooter),
+            _ => None,
+        }
+    }
+
+    /// The section the menu shows after the press, `None` for the list of
+    /// sections.
+    pub fn section(&self) -> Option<Section> {
+        match self {
+            MenuButton::Open(section) => Some(*section),
+            MenuButton::Back | MenuButton::Close => None,
+            MenuButton::Change(SettingsChange::WorkingHours(_)) => Some(Section::WorkingHours),
+            MenuButton::Change(_) => Some(Section::Timezone),
+            MenuButton::Interval(_) => Some(Section::Interval),
+            MenuButton::ToggleWorkday(_) => Some(Section::Workdays),
+            MenuButton::ToggleFooter => Some(Section::Message),
+        }
+    }
+
+    /// Applies the choice, `false` if it leaves the settings as they were,
+    /// e.g. turning the last workday off.
+    pub fn apply(&self, settings: &mut UserSettings) -> bool {
+        match self {
+            MenuButton::Open(_) | MenuButton::Back | MenuButton::Close => false,
+            MenuButton::Change(change) => {
+                let before = (
+                    settings.offset,
+                    settings.timezone.clone(),
+                    settings.working_hours,
+                );
+                change.apply(settings);
+                before
+                    != (
+                        settings.offset,
+                        settings.timezone.clone(),
+                        settings.working_hours,
+                    )
+            }
+            MenuButton::Interval(minutes) => {
+                let before = settings.interval_secs;
+                settings.interval_secs = minutes * 60;
+                before != settings.interval_secs
+            }
+            MenuButton::ToggleWorkday(day) => {
+                let workdays = settings.workdays;
+                let days: Vec<Weekday> = match workdays.contains(*day) {
+                    true => workdays.days().filter(|other| other != day).collect(),
+                    false => workdays.days().chain([*day]).collect(),
+                };
+                if days.is_empty() {
+                    return false;
+                }
+                settings.workdays = Workdays::from_days(days);
+                true
+            }
+            MenuButton::ToggleFooter => {
+                settings.footer = match settings.footer {
+                    Footer::Text => Footer::Hidden,
+                    Footer::Hidden => Footer::Text,
+                };
+                true
+            }
+        }
+    }
+}
+
+/// Buttons of the `section`, or of the list of sections.
+pub fn keyboard(section: Option<Section>, settings: &UserSettings) -> InlineKeyboardMarkup {
+    let locale = settings.locale;
+    let Some(section) = section else {
+        let rows = Section::ALL.into_iter().map(|section| {
+            vec![button(
+                tr!(locale, &format!("settings-button-{}", section.name())),
+                MenuButton::Open(section),
+            )]
+        });
+        return InlineKeyboardMarkup::new(rows).append_row(vec![button(
+            tr!(locale, "settings-button-close"),
+            MenuButton::Close,
+        )]);
+    };
+
+    let choices: Vec<(String, bool, MenuButton)> = match section {
+        Section::Timezone => {
+            let offset = settings.fixed_offset().local_minus_utc();
+            TIMEZONE_CHOICES
+                .into_iter()
+                .filter_map(|choice| parsers::parse_timezone(choice).map(|tz| (choice, tz)))
+                .map(|(choice, tz)| {
+                    let seconds = tz.local_minus_utc();
+                    let change = SettingsChange::Offset(seconds);
+                    (
+                        choice.to_string(),
+                        seconds == offset,
+                        MenuButton::Change(change),
+                    )
+                })
+                .collect()
+        }
+        Section::WorkingHours => WORKING_HOURS_CHOICES
+            .into_iter()
+            .map(|(from, to)| {
+                let hours = WorkingHours { from, to };
+                (
+                    format!("{:02}–{:02}", from, to),
+                    hours == settings.working_hours,
+                    MenuButton::Change(SettingsChange::WorkingHours(hours)),
+                )
+            })
+            .collect(),
+        Section::Interval => INTERVAL_CHOICES
+            .into_iter()
+            .map(|minutes| {
+                (
+                    formatting::duration(std::time::Duration::from_secs(minutes * 60), locale),
+                    minutes * 60 == settings.interval_secs,
+                    MenuButton::Interval(minutes),
+                )
+            })
+            .collect(),
+        Section::Workdays => (0..7u8)
+            .filter_map(|day| Weekday::try_from(day).ok())
+            .map(|day| {
+                (
+                    formatting::weekday(day, locale).to_string(),
+                    settings.workdays.contains(day),
+                    MenuButton::ToggleWorkday(day),
+                )
+            })
+            .collect(),
+        Section::Message => vec![(
+            tr!(locale, "settings-button-footer"),
+            settings.footer == Footer::Text,
+            MenuButton::ToggleFooter,
+        )],
+    };
+
+    let columns = match section {
+        Section::Timezone | Section::Workdays => 4,
+        _ => 3,
+    };
+    let rows = choices.chunks(columns).map(|row| {
+        row.iter()
+            .map(|(label, chosen, menu_button)| {
+                let label = match chosen {
+                    true => format!("✓ {}", label),
+                    false => label.clone(),
+                };
+                button(label, menu_button.clone())
+            })
+            .collect::<Vec<_>>()
+    });
+    InlineKeyboardMarkup::new(rows).append_row(vec![button(
+        tr!(locale, "settings-button-back"),
+        MenuButton::Back,
+    )])
+}
+
+fn button(label: String, menu_button: MenuButton) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(label, menu_button.encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use crate::{
+        i18n::Locale,
+        offsets_rep::{Footer, UserSettings, Workdays, WorkingHours},
+        previews::SettingsChange,
+        settings_menu::{keyboard, MenuButton, Section},
+    };
+
+    #[test]
+    fn test_menu_buttons() {
+        let buttons = [
+            MenuButton::Open(Section::Timezone),
+            MenuButton::Open(Section::Message),
+            MenuButton::Back,
+            MenuButton::Close,
+            MenuButton::Change(SettingsChange::Offset(5 * 3600 + 1800)),
+            MenuButton::Change(SettingsChange::WorkingHours(WorkingHours {
+                from: 9,
+                to: 18,
+            })),
+            MenuButton::Interval(90),
+            MenuButton::ToggleWorkday(Weekday::Sun),
+            MenuButton::ToggleFooter,
+        ];
+        for button in buttons {
+            // Telegram limits callback data to 64 bytes
+            assert!(button.encode().len() <= 64, "{:?}", button);
+            assert_eq!(MenuButton::decode(&button.encode()), Some(button));
+        }
+        assert_eq!(MenuButton::decode("menu:interval:1"), None);
+        assert_eq!(MenuButton::decode("menu:day:7"), None);
+        assert_eq!(MenuButton::decode("menu:open:nothing"), None);
+        assert_eq!(MenuButton::decode("preview:cancel"), None);
+
+        let settings = UserSettings {
+            locale: Locale::Ru,
+            ..Default::default()
+        };
+        for row in keyboard(None, &settings).inline_keyboard {
+            for button in row {
+                assert!(!button.text.starts_with("settings-"), "{}", button.text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_menu_apply() {
+        let mut settings = UserSettings {
+            workdays: Workdays::from_days([Weekday::Mon]),
+            ..Default::default()
+        };
+
+        assert!(MenuButton::ToggleWorkday(Weekday::Tue).apply(&mut settings));
+        assert!(MenuButton::ToggleWorkday(Weekday::Mon).apply(&mut settings));
+        assert_eq!(settings.workdays, Workdays::from_days([Weekday::Tue]));
+        assert!(!MenuButton::ToggleWorkday(Weekday::Tue).apply(&mut settings));
+
+        assert!(MenuButton::Interval(30).apply(&mut settings));
+        assert!(!MenuButton::Interval(30).apply(&mut settings));
+        assert_eq!(settings.interval_secs, 30 * 60);
+
+        assert!(MenuButton::ToggleFooter.apply(&mut settings));
+        assert_eq!(settings.footer, Footer::Hidden);
+
+        let hours = SettingsChange::WorkingHours(WorkingHours { from: 8, to: 17 });
+        assert!(MenuButton::Change(hours.clone()).apply(&mut settings));
+        assert!(!MenuButton::Change(hours).apply(&mut settings));
+        assert!(!MenuButton::Back.apply(&mut settings));
+
+        // Sections and their choices, each with a way back
+        let sections = keyboard(None, &settings).inline_keyboard;
+        assert_eq!(sections.len(), Section::ALL.len() + 1);
+        let workdays = keyboard(Some(Section::Workdays), &settings).inline_keyboard;
+        assert_eq!(
+            workdays.iter().map(|row| row.len()).collect::<Vec<_>>(),
+            vec![4, 3, 1]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use chrono::{Datelike, Days, NaiveDate};
+
+/// How far back "/mystats" looks, streaks longer than that are cut.
+pub const LOOKBACK: Days = Days::new(366);
+
+/// What happened on a local day of a chat.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DayStats {
+    pub day: NaiveDate,
+    /// Scheduled notifications delivered
+    pub sent: u32,
+    /// Whether "/done" was sent
+    pub done: bool,
+    /// From the last notification before "/done" to "/done", unset when
+    /// "/done" came before any notification
+    pub done_after: Option<Duration>,
+}
+
+/// The chat's numbers as "/mystats" shows them.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub week_sent: u32,
+    pub month_sent: u32,
+    pub average_done_after: Option<Duration>,
+    /// Days in a row acknowledged with "/done", days without notifications
+    /// don't break it and today only counts once acknowledged
+    pub streak: u32,
+}
+
+/// Sums up `days`, the earliest first, as of the local `today`.
+pub fn summarize(days: &[DayStats], today: NaiveDate) -> Summary {
+    let week_start = today.week(chrono::Weekday::Mon).first_day();
+    let month_start = today.with_day(1).unwrap_or(today);
+    let sent_since = |start: NaiveDate| {
+        days.iter()
+            .filter(|stats| stats.day >= start && stats.day <= today)
+            .map(|stats| stats.sent)
+            .sum()
+    };
+
+    let delays: Vec<Duration> = days
+        .iter()
+        .filter(|stats| stats.day >= month_start)
+        .filter_map(|stats| stats.done_after)
+        .collect();
+    let average_done_after = match delays.len() {
+        0 => None,
+        count => Some(delays.iter().sum::<Duration>() / count as u32),
+    };
+
+    let mut streak = 0;
+    for stats in days.iter().rev().filter(|stats| stats.day <= today) {
+        if stats.done {
+            streak += 1;
+        } else if stats.sent > 0 && stats.day != today {
+            break;
+        }
+    }
+
+    Summary {
+        week_sent: sent_since(week_start),
+        month_sent: sent_since(month_start),
+        average_done_after,
+        streak,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::NaiveDate;
+
+    use crate::stats::{summarize, DayStats, Summary};
+
+    #[test]
+    fn test_summarize() {
+        let day = |day: u32, sent: u32, done_after: Option<u64>| DayStats {
+            day: NaiveDate::from_ymd_opt(2024, 5, day).unwrap(),
+            sent,
+            done: done_after.is_some(),
+            done_after: done_after.filter(|secs| *secs > 0).map(Duration::from_secs),
+        };
+        // Wednesday, May 8th
+        let today = NaiveDate::from_ymd_opt(2024, 5, 8).unwrap();
+
+        let days = [
+            day(1, 5, None),
+            day(2, 4, Some(600)),
+            // A weekend without notifications
+            day(6, 3, Some(0)),
+            day(7, 6, Some(1200)),
+            day(8, 2, None),
+        ];
+        assert_eq!(
+            summarize(&days, today),
+            Summary {
+                week_sent: 11,
+                month_sent: 20,
+                average_done_after: Some(Duration::from_secs(900)),
+                streak: 3,
+            }
+        );
+
+        assert_eq!(
+            summarize(&days[..1], today),
+            Summary {
+                week_sent: 0,
+                month_sent: 5,
+                average_done_after: None,
+                streak: 0,
+            }
+        );
+        assert_eq!(summarize(&[], today).streak, 0);
+    }
+}
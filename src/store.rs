@@ -0,0 +1,335 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use teloxide::types::ChatId;
+
+use crate::{i18n::Locale, offsets_rep::UserSettings, profiles::Profile, stats::DayStats};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Where settings of subscribed chats are kept.
+///
+/// Read errors are logged by the backend and reported as missing users.
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn get(&self, chat_id: &ChatId) -> Option<UserSettings>;
+
+    /// Stores `settings` of the chat, adding the chat if it's new.
+    async fn set(&self, chat_id: &ChatId, settings: &UserSettings) -> Result<()>;
+
+    /// Adds the chat with default settings in `locale`.
+    async fn add(&self, chat_id: &ChatId, locale: Locale) -> Result<()> {
+        let settings = UserSettings {
+            locale,
+            ..Default::default()
+        };
+        self.set(chat_id, &settings).await
+    }
+
+    /// Removes the chat, returns `false` if it wasn't there.
+    async fn rem(&self, chat_id: &ChatId) -> Result<bool>;
+
+    /// Removes the chat keeping its settings aside for `restore`, returns
+    /// `false` if it wasn't there.
+    async fn trash(&self, chat_id: &ChatId) -> Result<bool>;
+
+    /// Brings back the chat's settings set aside by `trash`, replacing the
+    /// ones it has now, returns `false` if there are none.
+    async fn restore(&self, chat_id: &ChatId) -> Result<bool>;
+
+    /// Forgets the chat's settings set aside by `trash` and its statistics.
+    async fn purge(&self, chat_id: &ChatId) -> Result<()>;
+
+    /// Counts a notification delivered at `at` on the chat's local `day`.
+    async fn record_sent(&self, chat_id: &ChatId, day: NaiveDate, at: DateTime<Utc>) -> Result<()>;
+
+    /// Marks the chat's local `day` as acknowledged with "/done" at `at`,
+    /// only the first "/done" of a day counts.
+    async fn record_done(&self, chat_id: &ChatId, day: NaiveDate, at: DateTime<Utc>) -> Result<()>;
+
+    /// The chat's days with notifications or "/done" since `since`, the
+    /// earliest first.
+    async fn daily_stats(&self, chat_id: &ChatId, since: NaiveDate) -> Vec<DayStats>;
+
+    async fn exists(&self, chat_id: &ChatId) -> bool {
+        self.get(chat_id).await.is_some()
+    }
+
+    /// All chats, ordered by id.
+    async fn get_all(&self) -> Vec<(ChatId, UserSettings)>;
+
+    /// Fails when the stored settings can't be read, for health checks.
+    async fn check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Makes sure finished writes reached the disk, called on shutdown.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Team profile called `name`.
+    async fn get_profile(&self, name: &str) -> Option<Profile>;
+
+    /// Stores the profile, replacing the one with the same name.
+    async fn set_profile(&self, name: &str, profile: &Profile) -> Result<()>;
+
+    /// Removes the profile, returns `false` if it wasn't there. Chats that
+    /// joined it keep their settings.
+    async fn rem_profile(&self, name: &str) -> Result<bool>;
+
+    /// All profiles, ordered by name.
+    async fn get_profiles(&self) -> Vec<(String, Profile)>;
+
+    /// The chat whose HTTP API token is `token`.
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    async fn find_by_token(&self, token: &str) -> Option<(ChatId, UserSettings)> {
+        self.get_all()
+            .await
+            .into_iter()
+            .find(|(_, settings)| settings.api_token.as_deref() == Some(token))
+    }
+}
+
+impl dyn UserStore + '_ {
+    /// Applies `f` to the stored settings, returns `false` for unknown chats.
+    ///
+    /// Reading and writing back aren't atomic, callers hold the chat's lock.
+    pub async fn update<F: FnOnce(&mut UserSettings)>(
+        &self,
+        chat_id: &ChatId,
+        f: F,
+    ) -> Result<bool> {
+        match self.get(chat_id).await {
+            Some(mut settings) => {
+                f(&mut settings);
+                self.set(chat_id, &settings).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Backends "USER_STORE" and "DIALOGUE_STORE" choose from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// SQLite database next to the bot, the default
+    #[default]
+    Sqlite,
+    /// Nothing survives a restart, for tests and trying the bot out
+    Memory,
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name.trim().to_lowercase().as_str() {
+            "sqlite" => Some(Backend::Sqlite),
+            "memory" => Some(Backend::Memory),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Sqlite => "sqlite",
+            Backend::Memory => "memory",
+        }
+    }
+}
+
+/// Keeps settings in memory only.
+#[derive(Default)]
+pub struct MemoryStore {
+    users: Mutex<BTreeMap<ChatId, UserSettings>>,
+    removed: Mutex<BTreeMap<ChatId, UserSettings>>,
+    stats: Mutex<BTreeMap<(ChatId, NaiveDate), StoredDay>>,
+    profiles: Mutex<BTreeMap<String, Profile>>,
+}
+
+/// A day of statistics with the time of its last notification.
+struct StoredDay {
+    stats: DayStats,
+    last_sent: Option<DateTime<Utc>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn with_day<F: FnOnce(&mut StoredDay)>(&self, chat_id: &ChatId, day: NaiveDate, f: F) {
+        let mut stats = self.stats.lock().unwrap();
+        f(stats.entry((*chat_id, day)).or_insert_with(|| StoredDay {
+            stats: DayStats {
+                day,
+                ..Default::default()
+            },
+            last_sent: None,
+        }));
+    }
+}
+
+#[async_trait]
+impl UserStore for MemoryStore {
+    async fn get(&self, chat_id: &ChatId) -> Option<UserSettings> {
+        self.users.lock().unwrap().get(chat_id).cloned()
+    }
+
+    async fn set(&self, chat_id: &ChatId, settings: &UserSettings) -> Result<()> {
+        self.users
+            .lock()
+            .unwrap()
+            .insert(*chat_id, settings.clone());
+        Ok(())
+    }
+
+    async fn rem(&self, chat_id: &ChatId) -> Result<bool> {
+        Ok(self.users.lock().unwrap().remove(chat_id).is_some())
+    }
+
+    async fn trash(&self, chat_id: &ChatId) -> Result<bool> {
+        let Some(settings) = self.users.lock().unwrap().remove(chat_id) else {
+            return Ok(false);
+        };
+        self.removed.lock().unwrap().insert(*chat_id, settings);
+        Ok(true)
+    }
+
+    async fn restore(&self, chat_id: &ChatId) -> Result<bool> {
+        let Some(settings) = self.removed.lock().unwrap().remove(chat_id) else {
+            return Ok(false);
+        };
+        self.users.lock().unwrap().insert(*chat_id, settings);
+        Ok(true)
+    }
+
+    async fn purge(&self, chat_id: &ChatId) -> Result<()> {
+        self.removed.lock().unwrap().remove(chat_id);
+        self.stats
+            .lock()
+            .unwrap()
+            .retain(|(stats_chat_id, _), _| stats_chat_id != chat_id);
+        Ok(())
+    }
+
+    async fn record_sent(&self, chat_id: &ChatId, day: NaiveDate, at: DateTime<Utc>) -> Result<()> {
+        self.with_day(chat_id, day, |stored| {
+            stored.stats.sent += 1;
+            stored.last_sent = Some(at);
+        });
+        Ok(())
+    }
+
+    async fn record_done(&self, chat_id: &ChatId, day: NaiveDate, at: DateTime<Utc>) -> Result<()> {
+        self.with_day(chat_id, day, |stored| {
+            if !stored.stats.done {
+                stored.stats.done = true;
+                stored.stats.done_after =
+                    stored.last_sent.and_then(|sent| (at - sent).to_std().ok());
+            }
+        });
+        Ok(())
+    }
+
+    async fn daily_stats(&self, chat_id: &ChatId, since: NaiveDate) -> Vec<DayStats> {
+        self.stats
+            .lock()
+            .unwrap()
+            .range((*chat_id, since)..=(*chat_id, NaiveDate::MAX))
+            .map(|(_, stored)| stored.stats.clone())
+            .collect()
+    }
+
+    async fn get_all(&self) -> Vec<(ChatId, UserSettings)> {
+        self.users
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(chat_id, settings)| (*chat_id, settings.clone()))
+            .collect()
+    }
+
+    async fn get_profile(&self, name: &str) -> Option<Profile> {
+        self.profiles.lock().unwrap().get(name).cloned()
+    }
+
+    async fn set_profile(&self, name: &str, profile: &Profile) -> Result<()> {
+        self.profiles
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), profile.clone());
+        Ok(())
+    }
+
+    async fn rem_profile(&self, name: &str) -> Result<bool> {
+        Ok(self.profiles.lock().unwrap().remove(name).is_some())
+    }
+
+    async fn get_profiles(&self) -> Vec<(String, Profile)> {
+        self.profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, profile)| (name.clone(), profile.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::types::ChatId;
+
+    use crate::{
+        i18n::Locale,
+        store::{Backend, MemoryStore, UserStore},
+    };
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store: &dyn UserStore = &MemoryStore::new();
+
+        store.add(&ChatId(42), Locale::Ru).await.unwrap();
+        store.add(&ChatId(-7), Locale::En).await.unwrap();
+        assert!(store.exists(&ChatId(42)).await);
+        assert_eq!(
+            store
+                .get_all()
+                .await
+                .iter()
+                .map(|(chat_id, _)| *chat_id)
+                .collect::<Vec<_>>(),
+            vec![ChatId(-7), ChatId(42)]
+        );
+
+        assert!(store
+            .update(&ChatId(42), |settings| settings.api_token =
+                Some("secret".to_string()))
+            .await
+            .unwrap());
+        assert!(!store.update(&ChatId(1), |_| {}).await.unwrap());
+        let (chat_id, settings) = store.find_by_token("secret").await.unwrap();
+        assert_eq!((chat_id, settings.locale), (ChatId(42), Locale::Ru));
+
+        assert!(store.trash(&ChatId(42)).await.unwrap());
+        assert!(store.get(&ChatId(42)).await.is_none());
+        assert!(store.restore(&ChatId(42)).await.unwrap());
+        assert!(!store.restore(&ChatId(42)).await.unwrap());
+        assert_eq!(store.get(&ChatId(42)).await.unwrap().locale, Locale::Ru);
+        store.trash(&ChatId(42)).await.unwrap();
+        store.purge(&ChatId(42)).await.unwrap();
+        assert!(!store.restore(&ChatId(42)).await.unwrap());
+
+        assert!(store.rem(&ChatId(-7)).await.unwrap());
+        assert!(!store.rem(&ChatId(-7)).await.unwrap());
+        assert!(store.get(&ChatId(-7)).await.is_none());
+    }
+
+    #[test]
+    fn test_backend() {
+        assert_eq!(Backend::from_name(" SQLite"), Some(Backend::Sqlite));
+        assert_eq!(Backend::from_name("memory"), Some(Backend::Memory));
+        assert_eq!(Backend::from_name("redis"), None);
+    }
+}
@@ -0,0 +1,656 @@
+use std::{sync::Arc, time::Instant};
+
+use async_mutex::Mutex;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc};
+use teloxide::{prelude::*, types::MessageId};
+use tokio::{
+    sync::{mpsc, watch},
+    time::sleep,
+};
+
+use crate::{
+    bot::Bot,
+    chat_locks::ChatLocks,
+    deadlines,
+    delivery::{BudgetLevel, Document, Priority},
+    formatting,
+    handlers::{
+        reminders::{
+            next_local_time, next_monthly, next_recurring, next_timer_update, timer_remaining,
+        },
+        settings::summary_time,
+        subscription::stop,
+    },
+    i18n::Locale,
+    jobs::{self, Job, JobKind, JobQueue},
+    keyboards, metrics,
+    notify_controller::{NotificationSender, StartEnum},
+    pomodoro::{self, Phase},
+    release_notes,
+    store::UserStore,
+    tr,
+};
+
+/// Removes chats the scheduler found gone for good, e.g. the bot
+/// was blocked, as if they sent "/stop".
+pub async fn evict_chats(
+    mut evictions: mpsc::UnboundedReceiver<ChatId>,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    chat_locks: Arc<ChatLocks>,
+) {
+    while let Some(chat_id) = evictions.recv().await {
+        let _guard = chat_locks.lock(chat_id).await;
+        let Some(settings) = store.get(&chat_id).await else {
+            continue;
+        };
+        log::info!(target: "audit", "Removing {}, the chat is unreachable", chat_id);
+        stop(
+            &chat_id,
+            settings.locale,
+            &*store,
+            &notify_controller_mutex,
+            &jobs_mutex,
+        )
+        .await;
+    }
+}
+
+/// Stores how many notifications chats have left to skip as they're skipped.
+pub async fn record_skips(
+    mut skips: mpsc::UnboundedReceiver<ChatId>,
+    store: Arc<dyn UserStore>,
+    chat_locks: Arc<ChatLocks>,
+) {
+    while let Some(chat_id) = skips.recv().await {
+        let _guard = chat_locks.lock(chat_id).await;
+        if let Err(err) = store
+            .update(&chat_id, |settings| {
+                settings.skip = settings.skip.saturating_sub(1)
+            })
+            .await
+        {
+            log::error!("Unable to store the skip of {}: {}", chat_id, err);
+        }
+    }
+}
+
+/// Counts delivered notifications in the chats' statistics.
+pub async fn record_deliveries(
+    mut deliveries: mpsc::UnboundedReceiver<(ChatId, NaiveDate, DateTime<Utc>)>,
+    store: Arc<dyn UserStore>,
+) {
+    while let Some((chat_id, day, at)) = deliveries.recv().await {
+        if let Err(err) = store.record_sent(&chat_id, day, at).await {
+            log::error!("Unable to count the notification of {}: {}", chat_id, err);
+        }
+    }
+}
+
+/// Tells admins when the deployment's sends come close to the daily budget
+/// and when it's spent.
+pub async fn alert_budget(
+    mut levels: mpsc::UnboundedReceiver<(BudgetLevel, u32)>,
+    bot: Bot,
+    admins: Vec<UserId>,
+    locale: Locale,
+    limit: u32,
+) {
+    while let Some((level, spent)) = levels.recv().await {
+        let id = match level {
+            BudgetLevel::Normal => continue,
+            BudgetLevel::Tight => "budget-tight-alert",
+            BudgetLevel::Exhausted => "budget-exhausted-alert",
+        };
+        let text = tr!(locale, id, spent = spent, limit = limit);
+        for admin in &admins {
+            if let Err(err) = bot.send_message(*admin, text.clone()).await {
+                log::error!("Unable to alert admin {} about the budget: {}", admin, err);
+            }
+        }
+    }
+}
+
+/// Sends the release notes of the running version once to every chat that
+/// didn't get them. Chats that are away get them after a later start.
+pub async fn announce_release(
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    chat_locks: Arc<ChatLocks>,
+) {
+    let mut announced = 0;
+    for (chat_id, settings) in store.get_all().await {
+        if settings.away || settings.seen_version.as_deref() == Some(release_notes::VERSION) {
+            continue;
+        }
+
+        let versions = release_notes::unseen(settings.seen_version.as_deref());
+        if settings.release_notes && !versions.is_empty() {
+            let relayed = metrics::lock(&notify_controller_mutex, "notify_controller")
+                .await
+                .relay(
+                    &chat_id,
+                    release_notes::message(&versions, settings.locale),
+                    Priority::Normal,
+                    true,
+                );
+            // Tried again on the next start
+            if !relayed.await {
+                continue;
+            }
+            announced += 1;
+        }
+
+        let _guard = chat_locks.lock(chat_id).await;
+        if let Err(err) = store
+            .update(&chat_id, |settings| {
+                settings.seen_version = Some(release_notes::VERSION.to_string())
+            })
+            .await
+        {
+            log::error!("Unable to record release notes of {}: {}", chat_id, err);
+        }
+    }
+    if announced > 0 {
+        log::info!(
+            "Release notes of {} sent to {} chats",
+            release_notes::VERSION,
+            announced
+        );
+    }
+}
+
+/// Runs delayed jobs as they become due, until `stopped` turns true.
+pub async fn run_jobs(
+    bot: Bot,
+    jobs_mutex: Arc<Mutex<JobQueue>>,
+    store: Arc<dyn UserStore>,
+    notify_controller_mutex: Arc<Mutex<NotificationSender<Bot>>>,
+    chat_locks: Arc<ChatLocks>,
+    mut stopped: watch::Receiver<bool>,
+) {
+    loop {
+        // The queue lock is released before running jobs, handlers take it last
+        let due = metrics::lock(&jobs_mutex, "jobs")
+            .await
+            .take_due(Utc::now());
+        match due {
+            Ok(due) => {
+                for (i, job) in due.iter().cloned().enumerate() {
+                    if *stopped.borrow() {
+                        let restored = metrics::lock(&jobs_mutex, "jobs").await.restore(&due[i..]);
+                        match restored {
+                            Ok(()) => log::info!("Put back {} due jobs", due.len() - i),
+                            Err(err) => log::error!("Unable to put back due jobs: {}", err),
+                        }
+                        return;
+                    }
+
+                    let start = Instant::now();
+                    let name = format!("job_{}", job.kind.name());
+                    let _guard = chat_locks.lock(job.chat_id).await;
+                    run_job(job, &bot, &*store, &notify_controller_mutex, &jobs_mutex).await;
+                    metrics::REGISTRY.observe(&metrics::HANDLER_DURATION, &name, start.elapsed());
+                }
+            }
+            Err(err) => log::error!("Unable to take due jobs: {}", err),
+        }
+
+        tokio::select! {
+            _ = sleep(jobs::TICK) => {}
+            _ = stopped.changed() => {}
+        }
+        if *stopped.borrow() {
+            return;
+        }
+    }
+}
+
+async fn run_job(
+    job: Job,
+    bot: &Bot,
+    store: &dyn UserStore,
+    notify_controller_mutex: &Mutex<NotificationSender<Bot>>,
+    jobs_mutex: &Mutex<JobQueue>,
+) {
+    log::info!(
+        "Running {} job {} for {}",
+        job.kind.name(),
+        job.id,
+        job.chat_id
+    );
+
+    match job.kind {
+        JobKind::Reminder { text, priority } => {
+            // Only high priority reminders get through a snooze or "/done",
+            // the others wait until notifications resume
+            if priority != Priority::High {
+                let mut jobs = metrics::lock(jobs_mutex, "jobs").await;
+                let resume = jobs
+                    .for_chat(&job.chat_id)
+                    .into_iter()
+                    .find(|job| JobKind::RESUMING.contains(&job.kind));
+                if let Some(resume) = resume {
+                    match jobs.push(
+                        job.chat_id,
+                        resume.due,
+                        JobKind::Reminder { text, priority },
+                    ) {
+                        Ok(postponed) => log::info!(
+                            "Reminder {} for {} postponed until {} as {}",
+                            job.id,
+                            job.chat_id,
+                            postponed.due,
+                            postponed.id
+                        ),
+                        Err(err) => log::error!(
+                            "Unable to postpone reminder {} for {}: {}",
+                            job.id,
+                            job.chat_id,
+                            err
+                        ),
+                    }
+                    return;
+                }
+            }
+
+            // Likewise they come without a sound while the chat is away
+            let silent = priority != Priority::High
+                && store
+                    .get(&job.chat_id)
+                    .await
+                    .is_some_and(|settings| settings.away);
+            let reminder = metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .relay(&job.chat_id, text, priority, silent);
+            if !reminder.await {
+                log::error!("Reminder {} for {} wasn't delivered", job.id, job.chat_id);
+            }
+        }
+        JobKind::Timer {
+            text,
+            message_id,
+            ends,
+        } => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+            let locale = settings.locale;
+            let now = Utc::now();
+
+            let (countdown, next) = match ends <= now {
+                true => (tr!(locale, "timer-done", text = text.clone()), None),
+                false => (
+                    tr!(
+                        locale,
+                        "timer-running",
+                        text = text.clone(),
+                        remaining = timer_remaining(now, ends, locale)
+                    ),
+                    Some(next_timer_update(now, ends)),
+                ),
+            };
+            // The countdown is cosmetic, the message may be gone
+            if let Err(err) = bot
+                .edit_message_text(job.chat_id, MessageId(message_id), countdown)
+                .await
+            {
+                log::debug!(
+                    "Unable to update timer {} of {}: {}",
+                    job.id,
+                    job.chat_id,
+                    err
+                );
+            }
+
+            match next {
+                Some(due) => {
+                    let kind = JobKind::Timer {
+                        text,
+                        message_id,
+                        ends,
+                    };
+                    if let Err(err) =
+                        metrics::lock(jobs_mutex, "jobs")
+                            .await
+                            .push(job.chat_id, due, kind)
+                    {
+                        log::error!("Unable to schedule timer of {}: {}", job.chat_id, err);
+                    }
+                }
+                None => {
+                    let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                        .await
+                        .relay(&job.chat_id, text, Priority::Normal, false);
+                    if !sent.await {
+                        log::error!("Timer {} for {} wasn't delivered", job.id, job.chat_id);
+                    }
+                }
+            }
+        }
+        JobKind::Pomodoro(cycle) => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+            let locale = settings.locale;
+
+            if cycle.paused.is_some() {
+                log::info!("Pomodoro {} of {} paused for too long", job.id, job.chat_id);
+                let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                    .await
+                    .relay(
+                        &job.chat_id,
+                        tr!(locale, "pomodoro-expired"),
+                        Priority::Normal,
+                        false,
+                    );
+                sent.await;
+                return;
+            }
+
+            let next = cycle.advance();
+            let text = match next.phase {
+                Phase::Break => tr!(
+                    locale,
+                    "pomodoro-break",
+                    round = next.round,
+                    rest = pomodoro::format_minutes(next.rest, locale)
+                ),
+                Phase::Work => tr!(
+                    locale,
+                    "pomodoro-work",
+                    round = next.round,
+                    work = pomodoro::format_minutes(next.work, locale)
+                ),
+            };
+            // Queued before the message goes out, so that its buttons find it
+            let due = Utc::now() + next.length();
+            if let Err(err) = metrics::lock(jobs_mutex, "jobs").await.push(
+                job.chat_id,
+                due,
+                JobKind::Pomodoro(next),
+            ) {
+                log::error!("Unable to schedule pomodoro of {}: {}", job.chat_id, err);
+                return;
+            }
+            let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .relay_with_keyboard(&job.chat_id, text, keyboards::pomodoro(locale, false));
+            if !sent.await {
+                log::error!("Pomodoro {} for {} wasn't delivered", job.id, job.chat_id);
+            }
+        }
+        JobKind::Report { text, day, time } => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+
+            let now = Utc::now();
+            let month = now.with_timezone(&settings.offset_at(now)).date_naive();
+            let document = Document::timesheet(&settings, month);
+            let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .relay_document(&job.chat_id, text.clone(), document, Priority::Normal);
+            if !sent.await {
+                log::error!("Report {} for {} wasn't delivered", job.id, job.chat_id);
+            }
+
+            let due = next_monthly(day, time, &settings.zone(), now);
+            if let Err(err) = metrics::lock(jobs_mutex, "jobs").await.push(
+                job.chat_id,
+                due,
+                JobKind::Report { text, day, time },
+            ) {
+                log::error!("Unable to schedule report of {}: {}", job.chat_id, err);
+            }
+        }
+        JobKind::Weekly { ref text, .. } | JobKind::Monthly { ref text, .. } => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+
+            let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .relay(&job.chat_id, text.clone(), Priority::Normal, false);
+            if !sent.await {
+                log::error!("Reminder {} for {} wasn't delivered", job.id, job.chat_id);
+            }
+
+            let now = Utc::now();
+            let Some(due) = next_recurring(&job.kind, &settings.zone(), now) else {
+                return;
+            };
+            if let Err(err) =
+                metrics::lock(jobs_mutex, "jobs")
+                    .await
+                    .push(job.chat_id, due, job.kind)
+            {
+                log::error!("Unable to schedule reminder of {}: {}", job.chat_id, err);
+            }
+        }
+        JobKind::Deadline { title, deadline } => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+            let locale = settings.locale;
+            let now = Utc::now();
+            let local = deadline.with_timezone(&settings.offset_at(deadline));
+
+            // Jobs run up to a tick late, counts are rounded
+            let left = deadline - now;
+            let text = if left < chrono::Duration::minutes(1) {
+                tr!(locale, "deadline-due", title = title.clone())
+            } else if left < chrono::Duration::days(1) {
+                tr!(
+                    locale,
+                    "deadline-hours",
+                    title = title.clone(),
+                    count = (left.num_minutes() + 30) / 60,
+                    due = formatting::time(&local.time())
+                )
+            } else {
+                tr!(
+                    locale,
+                    "deadline-days",
+                    title = title.clone(),
+                    count = (left.num_hours() + 12) / 24,
+                    due = local.format("%Y-%m-%d %H:%M").to_string()
+                )
+            };
+            let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .relay(&job.chat_id, text, Priority::Normal, false);
+            if !sent.await {
+                log::error!("Deadline {} for {} wasn't delivered", job.id, job.chat_id);
+            }
+
+            let day_start = NaiveTime::from_hms_opt(settings.working_hours.from, 0, 0);
+            let next = day_start.and_then(|day_start| {
+                // Past its run time, so the reminder just sent isn't repeated
+                deadlines::next_reminder(local, day_start, now.max(job.due))
+            });
+            if let Some(due) = next {
+                if let Err(err) = metrics::lock(jobs_mutex, "jobs").await.push(
+                    job.chat_id,
+                    due,
+                    JobKind::Deadline { title, deadline },
+                ) {
+                    log::error!("Unable to schedule deadline of {}: {}", job.chat_id, err);
+                }
+            }
+        }
+        JobKind::Summary => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+            if !settings.summary {
+                return;
+            }
+
+            let now = Utc::now();
+            let offset = settings.offset_at(now);
+            let local = now.with_timezone(&offset);
+            let time = summary_time(settings.working_hours);
+            // After working hours changed the job only moves to the new time
+            let on_time = local.time() >= time && local.time() - time < chrono::Duration::hours(1);
+            let day_off = metrics::lock(notify_controller_mutex, "notify_controller")
+                .await
+                .blackout_on(local.date_naive())
+                .is_some()
+                || settings.holidays.contains(&local.date_naive());
+            if on_time && settings.workdays.contains(local.weekday()) && !day_off {
+                let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                    .await
+                    .sent_on(&job.chat_id, local.date_naive());
+                // "/done" pauses notifications until a wake up the next day
+                let done = metrics::lock(jobs_mutex, "jobs")
+                    .await
+                    .for_chat(&job.chat_id)
+                    .iter()
+                    .any(|job| job.kind == JobKind::WakeUp);
+                let summary = tr!(
+                    settings.locale,
+                    "summary",
+                    sent = sent,
+                    done = if done { "yes" } else { "no" }
+                );
+                let delivered = metrics::lock(notify_controller_mutex, "notify_controller")
+                    .await
+                    .relay(&job.chat_id, summary, Priority::Normal, false);
+                if !delivered.await {
+                    log::error!("Summary for {} wasn't delivered", job.chat_id);
+                }
+            }
+
+            let due = next_local_time(time, &settings.zone(), now);
+            if let Err(err) =
+                metrics::lock(jobs_mutex, "jobs")
+                    .await
+                    .push(job.chat_id, due, JobKind::Summary)
+            {
+                log::error!("Unable to schedule summary of {}: {}", job.chat_id, err);
+            }
+        }
+        JobKind::CheckIn => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+            let Some(check_in) = settings.check_in else {
+                return;
+            };
+
+            // Unless the chat wrote since, the contact is alerted again every
+            // period while it stays quiet
+            let due = match check_in.due() > Utc::now() {
+                true => check_in.due(),
+                false => {
+                    let alert = tr!(
+                        settings.locale,
+                        "check-in-alert",
+                        name = check_in.name.clone(),
+                        period = formatting::duration(check_in.period(), settings.locale)
+                    );
+                    let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                        .await
+                        .relay(&check_in.contact, alert, Priority::High, false);
+                    match sent.await {
+                        true => log::info!(
+                            "{} missed a check-in, alerted {}",
+                            job.chat_id,
+                            check_in.contact
+                        ),
+                        false => log::error!(
+                            "Check-in alert of {} for {} wasn't delivered",
+                            job.chat_id,
+                            check_in.contact
+                        ),
+                    }
+                    Utc::now() + chrono::Duration::seconds(check_in.period_secs as i64)
+                }
+            };
+            if let Err(err) =
+                metrics::lock(jobs_mutex, "jobs")
+                    .await
+                    .push(job.chat_id, due, JobKind::CheckIn)
+            {
+                log::error!("Unable to schedule check-in of {}: {}", job.chat_id, err);
+            }
+        }
+        JobKind::Preset { preset, turn } => {
+            let Some(settings) = store.get(&job.chat_id).await else {
+                return;
+            };
+
+            // Paused along with notifications by a snooze or "/done"
+            let paused = metrics::lock(jobs_mutex, "jobs")
+                .await
+                .for_chat(&job.chat_id)
+                .iter()
+                .any(|job| JobKind::RESUMING.contains(&job.kind));
+            if !paused {
+                let text = preset.message(settings.locale, turn);
+                let sent = metrics::lock(notify_controller_mutex, "notify_controller")
+                    .await
+                    .relay(&job.chat_id, text, Priority::Low, false);
+                if !sent.await {
+                    log::error!("Preset {} for {} wasn't delivered", job.id, job.chat_id);
+                }
+            }
+
+            let Some(due) = preset.next_after(&settings, Utc::now()) else {
+                return;
+            };
+            if let Err(err) = metrics::lock(jobs_mutex, "jobs").await.push(
+                job.chat_id,
+                due,
+                JobKind::Preset {
+                    preset,
+                    turn: turn.wrapping_add(1),
+                },
+            ) {
+                log::error!("Unable to schedule preset of {}: {}", job.chat_id, err);
+            }
+        }
+        JobKind::Purge => {
+            if let Err(err) = store.purge(&job.chat_id).await {
+                log::error!("Unable to purge {}: {}", job.chat_id, err);
+            }
+        }
+        JobKind::WakeUp | JobKind::Snooze => {
+            match store.get(&job.chat_id).await {
+                Some(settings) => {
+                    let mut controller =
+                        metrics::lock(notify_controller_mutex, "notify_controller").await;
+                    // The chat was paused again while the job was waiting for its lock
+                    let paused = metrics::lock(jobs_mutex, "jobs")
+                        .await
+                        .for_chat(&job.chat_id)
+                        .iter()
+                        .any(|job| JobKind::RESUMING.contains(&job.kind));
+                    if paused {
+                        log::info!("{} is paused again, not resuming it", job.chat_id);
+                        return;
+                    }
+
+                    // A wake up comes when the working time starts, its first
+                    // notification goes right away
+                    let send_immediately =
+                        job.kind == JobKind::WakeUp && settings.schedule().is_none();
+                    match controller.start(&job.chat_id, &settings, send_immediately) {
+                        StartEnum::AlreadyExist => {
+                            log::debug!("Notify task for {} already started", job.chat_id)
+                        }
+                        StartEnum::Added => {}
+                    }
+                }
+                None => {
+                    log::info!(
+                        "Unable to wake up because user {} offset doesn't exist",
+                        job.chat_id
+                    )
+                }
+            }
+        }
+    }
+}
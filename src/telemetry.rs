@@ -0,0 +1,91 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::Url;
+use serde::Serialize;
+use tokio::time::sleep;
+
+use crate::{release_notes, store::UserStore};
+
+/// How often the usage ping is sent, the first one goes right after start.
+pub const PING_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+
+/// All a ping tells about the deployment, nothing about chats or people.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Ping {
+    pub version: &'static str,
+    /// A range such as "11-100" rather than the number of chats
+    pub users: &'static str,
+    pub backend: &'static str,
+}
+
+impl Ping {
+    pub fn new(users: usize, backend: &'static str) -> Ping {
+        Ping {
+            version: release_notes::VERSION,
+            users: bucket(users),
+            backend,
+        }
+    }
+}
+
+/// Posts an anonymous [`Ping`] to `url` every [`PING_INTERVAL`], for
+/// deployments whose operator opted in with "TELEMETRY_URL".
+pub async fn run(url: Url, store: Arc<dyn UserStore>, backend: &'static str) {
+    log::info!(
+        "Sending anonymous usage pings to {}: the version, a range of the user count and the store backend",
+        url
+    );
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("a client without custom TLS settings always builds");
+
+    loop {
+        let ping = Ping::new(store.get_all().await.len(), backend);
+        // The bot works the same when the endpoint is down
+        match client.post(url.clone()).json(&ping).send().await {
+            Ok(response) if response.status().is_success() => {
+                log::debug!("Usage ping sent: {:?}", ping)
+            }
+            Ok(response) => log::warn!("Usage ping rejected: {}", response.status()),
+            Err(err) => log::warn!("Unable to send usage ping: {}", err),
+        }
+        sleep(PING_INTERVAL).await;
+    }
+}
+
+/// A range of the user count coarse enough not to identify a deployment.
+fn bucket(users: usize) -> &'static str {
+    match users {
+        0 => "0",
+        1..=10 => "1-10",
+        11..=100 => "11-100",
+        101..=1000 => "101-1000",
+        1001..=10000 => "1001-10000",
+        _ => "10000+",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::telemetry::{bucket, Ping};
+
+    #[test]
+    fn test_ping() {
+        assert_eq!(bucket(0), "0");
+        assert_eq!(bucket(10), "1-10");
+        assert_eq!(bucket(11), "11-100");
+        assert_eq!(bucket(5000), "1001-10000");
+        assert_eq!(bucket(20000), "10000+");
+
+        let ping = serde_json::to_value(Ping::new(42, "sqlite")).unwrap();
+        assert_eq!(
+            ping,
+            serde_json::json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "users": "11-100",
+                "backend": "sqlite",
+            })
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use rand::Rng;
+use serde_json::Value;
+
+/// Longest text Telegram accepts in a single message.
+pub const MESSAGE_LIMIT: usize = 4096;
+
+/// Fills `{{path}}` placeholders of `template` from `payload`.
+///
+/// Paths are dot separated object keys and array indices, e.g.
+/// `{{alerts.0.labels.alertname}}`. Strings are inserted as is, other values
+/// as JSON, and missing ones as nothing. An unclosed `{{` is kept verbatim.
+pub fn render(template: &str, payload: &Value) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+        let path = rest[start + 2..start + 2 + end].trim();
+        match lookup(payload, path) {
+            Some(Value::String(text)) => result.push_str(text),
+            Some(value) => result.push_str(&value.to_string()),
+            None => {}
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+fn lookup<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(payload);
+    }
+
+    path.split('.').try_fold(payload, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Part of the day choosing a variant of the notification message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PartOfDay {
+    Morning,
+    Afternoon,
+    Evening,
+}
+
+impl PartOfDay {
+    pub const ALL: [PartOfDay; 3] = [PartOfDay::Morning, PartOfDay::Afternoon, PartOfDay::Evening];
+
+    /// Morning is 05:00–11:59, afternoon 12:00–16:59, evening the rest.
+    pub fn at(hour: u32) -> PartOfDay {
+        match hour {
+            5..=11 => PartOfDay::Morning,
+            12..=16 => PartOfDay::Afternoon,
+            _ => PartOfDay::Evening,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PartOfDay::Morning => "morning",
+            PartOfDay::Afternoon => "afternoon",
+            PartOfDay::Evening => "evening",
+        }
+    }
+}
+
+/// A message split into parts of the day.
+#[derive(Debug, Default, PartialEq)]
+pub struct Sections {
+    /// Text before the first section, for parts of the day without one
+    pub common: String,
+    pub parts: Vec<(PartOfDay, String)>,
+}
+
+/// Splits `message` at `[morning]`, `[afternoon]` and `[evening]` lines.
+///
+/// A message without such lines is common text as is. Otherwise blank lines
+/// around each section are dropped.
+pub fn sections(message: &str) -> Sections {
+    let header = |line: &str| {
+        PartOfDay::ALL
+            .into_iter()
+            .find(|part| line.trim() == format!("[{}]", part.name()))
+    };
+    if !message.lines().any(|line| header(line).is_some()) {
+        return Sections {
+            common: message.to_string(),
+            parts: vec![],
+        };
+    }
+
+    let mut sections = Sections::default();
+    let mut current: Option<PartOfDay> = None;
+    let mut text = String::new();
+    let mut flush = |part: Option<PartOfDay>, text: &mut String| {
+        let section = text.trim_matches(['\r', '\n']).to_string();
+        text.clear();
+        match part {
+            Some(part) => sections.parts.push((part, section)),
+            None => sections.common = section,
+        }
+    };
+    for line in message.split_inclusive('\n') {
+        match header(line) {
+            Some(part) => {
+                flush(current, &mut text);
+                current = Some(part);
+            }
+            None => text.push_str(line),
+        }
+    }
+    flush(current, &mut text);
+
+    sections
+}
+
+/// Longest caption Telegram accepts under a photo or a document.
+pub const CAPTION_LIMIT: usize = 1024;
+
+/// How a file attached to a message is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaKind {
+    Photo,
+    /// Sent on its own, stickers have no caption
+    Sticker,
+    Document,
+}
+
+impl MediaKind {
+    pub const ALL: [MediaKind; 3] = [MediaKind::Photo, MediaKind::Sticker, MediaKind::Document];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MediaKind::Photo => "photo",
+            MediaKind::Sticker => "sticker",
+            MediaKind::Document => "document",
+        }
+    }
+}
+
+/// A file attached to a message, by Telegram file id or URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Media {
+    pub kind: MediaKind,
+    pub source: String,
+}
+
+impl Media {
+    /// Whether `source` is a URL Telegram downloads rather than a file id.
+    pub fn is_url(&self) -> bool {
+        self.source.starts_with("https://") || self.source.starts_with("http://")
+    }
+}
+
+/// Takes the `[photo <file id or URL>]`, `[sticker ...]` or `[document ...]`
+/// line out of `message`, returns the rest and the media. Only the first
+/// such line counts, blank lines around the rest are dropped.
+pub fn media(message: &str) -> (String, Option<Media>) {
+    let parse = |line: &str| {
+        let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+        let (kind, source) = inner.split_once(' ')?;
+        let kind = MediaKind::ALL
+            .into_iter()
+            .find(|candidate| candidate.name() == kind)?;
+        let source = source.trim();
+        (!source.is_empty() && !source.contains(char::is_whitespace)).then(|| Media {
+            kind,
+            source: source.to_string(),
+        })
+    };
+
+    let mut found = None;
+    let mut rest = String::new();
+    for line in message.split_inclusive('\n') {
+        match found.is_none().then(|| parse(line)).flatten() {
+            Some(media) => found = Some(media),
+            None => rest.push_str(line),
+        }
+    }
+    match found {
+        Some(media) => (rest.trim_matches(['\r', '\n']).to_string(), Some(media)),
+        None => (message.to_string(), None),
+    }
+}
+
+/// How a message is picked out of a pool of several.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    Random,
+    /// One after another, each day of a chat starts a message further
+    RoundRobin,
+}
+
+impl Rotation {
+    pub fn from_name(name: &str) -> Option<Rotation> {
+        match name.trim().to_lowercase().as_str() {
+            "random" => Some(Rotation::Random),
+            "round-robin" => Some(Rotation::RoundRobin),
+            _ => None,
+        }
+    }
+
+    /// Index of the message sent on the `turn`th send out of a pool of `len`.
+    pub fn pick(&self, len: usize, turn: usize) -> usize {
+        match (self, len) {
+            (_, 0) => 0,
+            (Rotation::Random, len) => rand::thread_rng().gen_range(0..len),
+            (Rotation::RoundRobin, len) => turn % len,
+        }
+    }
+}
+
+/// Splits a pool of messages at `---` lines, empty ones are left out.
+pub fn pool(value: &str) -> Vec<String> {
+    let mut messages = vec![String::new()];
+    for line in value.split_inclusive('\n') {
+        match line.trim() == "---" {
+            true => messages.push(String::new()),
+            false => messages.last_mut().unwrap().push_str(line),
+        }
+    }
+    messages
+        .into_iter()
+        .map(|message| message.trim_matches(['\r', '\n']).to_string())
+        .filter(|message| !message.trim().is_empty())
+        .collect()
+}
+
+/// Cuts `text` to fit into a single Telegram message.
+pub fn truncate(text: &str) -> String {
+    if text.chars().count() <= MESSAGE_LIMIT {
+        return text.to_string();
+    }
+
+    let mut text: String = text.chars().take(MESSAGE_LIMIT - 1).collect();
+    text.push('…');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::templates::{
+        media, pool, render, sections, truncate, Media, MediaKind, PartOfDay, Rotation, Sections,
+        MESSAGE_LIMIT,
+    };
+
+    #[test]
+    fn test_render() {
+        let payload = json!({
+            "status": "firing",
+            "alerts": [{"labels": {"alertname": "DiskFull"}, "value": 97.5}],
+        });
+
+        assert_eq!(
+            render(
+                "{{status}}: {{ alerts.0.labels.alertname }} ({{alerts.0.value}}%)",
+                &payload
+            ),
+            "firing: DiskFull (97.5%)"
+        );
+        assert_eq!(render("[{{missing.key}}]", &payload), "[]");
+        assert_eq!(render("{{alerts.x}}", &payload), "");
+        assert_eq!(render("no placeholders", &payload), "no placeholders");
+        assert_eq!(render("open {{status", &payload), "open {{status");
+        assert_eq!(render("{{}}", &json!(1)), "1");
+    }
+
+    #[test]
+    fn test_sections() {
+        assert_eq!(
+            sections("Drink water\n[not a section]"),
+            Sections {
+                common: "Drink water\n[not a section]".to_string(),
+                parts: vec![],
+            }
+        );
+        assert_eq!(
+            sections("Drink water\n\n[morning]\nGood morning!\n [evening] \nLast one\nfor today\n"),
+            Sections {
+                common: "Drink water".to_string(),
+                parts: vec![
+                    (PartOfDay::Morning, "Good morning!".to_string()),
+                    (PartOfDay::Evening, "Last one\nfor today".to_string()),
+                ],
+            }
+        );
+        assert_eq!(sections("[afternoon]\nLunch").common, "");
+    }
+
+    #[test]
+    fn test_part_of_day() {
+        assert_eq!(PartOfDay::at(4), PartOfDay::Evening);
+        assert_eq!(PartOfDay::at(5), PartOfDay::Morning);
+        assert_eq!(PartOfDay::at(12), PartOfDay::Afternoon);
+        assert_eq!(PartOfDay::at(17), PartOfDay::Evening);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short"), "short");
+
+        let long = "я".repeat(MESSAGE_LIMIT + 1);
+        let truncated = truncate(&long);
+        assert_eq!(truncated.chars().count(), MESSAGE_LIMIT);
+        assert!(truncated.ends_with('…'));
+    }
+
+    #[test]
+    fn test_media() {
+        assert_eq!(
+            media("[photo https://example.com/water.jpg]\n\nDrink water"),
+            (
+                "Drink water".to_string(),
+                Some(Media {
+                    kind: MediaKind::Photo,
+                    source: "https://example.com/water.jpg".to_string(),
+                })
+            )
+        );
+        let (text, sticker) = media("Stretch!\n [sticker CAACAgIAAxkBAAEB] \n[document x]");
+        assert_eq!(text, "Stretch!\n[document x]");
+        assert_eq!(
+            sticker.clone().map(|media| media.kind),
+            Some(MediaKind::Sticker)
+        );
+        assert!(!sticker.unwrap().is_url());
+
+        for message in [
+            "Drink water",
+            "[video https://example.com/a.mp4]",
+            "[photo]",
+            "[photo a b]",
+        ] {
+            assert_eq!(media(message), (message.to_string(), None));
+        }
+    }
+
+    #[test]
+    fn test_pool() {
+        assert_eq!(
+            pool("Drink water\n---\n\n[morning]\nStretch\n---\n---  \n"),
+            vec!["Drink water".to_string(), "[morning]\nStretch".to_string()]
+        );
+        assert_eq!(pool("Drink water"), vec!["Drink water".to_string()]);
+        assert!(pool("---").is_empty());
+
+        assert_eq!(
+            Rotation::from_name(" Round-Robin"),
+            Some(Rotation::RoundRobin)
+        );
+        assert_eq!(Rotation::from_name("shuffle"), None);
+        let turns: Vec<usize> = (0..4)
+            .map(|turn| Rotation::RoundRobin.pick(3, turn))
+            .collect();
+        assert_eq!(turns, vec![0, 1, 2, 0]);
+        assert!(Rotation::Random.pick(3, 0) < 3);
+        assert_eq!(Rotation::Random.pick(0, 5), 0);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use teloxide::types::ChatId;
+
+/// How long a "/transfer" code can be used.
+pub const TRANSFER_TTL: Duration = Duration::from_secs(10 * 60);
+const CODE_LENGTH: usize = 8;
+
+/// One-time codes moving the settings of a chat to another one, e.g. after
+/// switching to a new Telegram account.
+///
+/// Codes are kept in memory only, a restart invalidates them.
+#[derive(Default)]
+pub struct Transfers {
+    codes: Mutex<HashMap<String, (ChatId, Instant)>>,
+}
+
+impl Transfers {
+    pub fn new() -> Transfers {
+        Transfers::default()
+    }
+
+    /// A new code for the chat, replacing the one it got before.
+    pub fn issue(&self, chat_id: ChatId) -> String {
+        let code: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(CODE_LENGTH)
+            .map(|c| char::from(c).to_ascii_uppercase())
+            .collect();
+        self.insert_at(chat_id, code.clone(), Instant::now());
+        code
+    }
+
+    /// The chat that issued `code` unless it expired, the code can't be used
+    /// again either way.
+    pub fn redeem(&self, code: &str) -> Option<ChatId> {
+        self.redeem_at(code, Instant::now())
+    }
+
+    fn insert_at(&self, chat_id: ChatId, code: String, now: Instant) {
+        let mut codes = self.codes.lock().unwrap();
+        codes.retain(|_, (issuer, issued)| {
+            *issuer != chat_id && now.duration_since(*issued) < TRANSFER_TTL
+        });
+        codes.insert(code, (chat_id, now));
+    }
+
+    fn redeem_at(&self, code: &str, now: Instant) -> Option<ChatId> {
+        let (chat_id, issued) = self
+            .codes
+            .lock()
+            .unwrap()
+            .remove(&code.trim().to_ascii_uppercase())?;
+        (now.duration_since(issued) < TRANSFER_TTL).then_some(chat_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use teloxide::types::ChatId;
+
+    use crate::transfers::{Transfers, TRANSFER_TTL};
+
+    #[test]
+    fn test_transfer_codes() {
+        let transfers = Transfers::new();
+        let now = Instant::now();
+
+        let code = transfers.issue(ChatId(42));
+        assert_eq!(code.len(), 8);
+        assert_eq!(
+            transfers.redeem_at(&code.to_lowercase(), now),
+            Some(ChatId(42))
+        );
+        assert_eq!(transfers.redeem_at(&code, now), None);
+
+        // A new code replaces the previous one of the chat
+        transfers.insert_at(ChatId(42), "FIRST".to_string(), now);
+        transfers.insert_at(ChatId(42), "SECOND".to_string(), now);
+        transfers.insert_at(ChatId(7), "OTHER".to_string(), now);
+        assert_eq!(transfers.redeem_at("FIRST", now), None);
+        assert_eq!(transfers.redeem_at("SECOND", now), Some(ChatId(42)));
+
+        let later = now + TRANSFER_TTL + Duration::from_secs(1);
+        assert_eq!(transfers.redeem_at("OTHER", later), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, RwLock},
+};
+
+use teloxide::{
+    payloads::SetWebhookSetters,
+    requests::Requester,
+    stop::{mk_stop_token, StopToken},
+    types::Update,
+    update_listeners::{StatefulListener, UpdateListener},
+};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use url::Url;
+
+/// Header Telegram repeats the secret given to "setWebhook" in.
+pub const SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+
+type UpdateSender = UnboundedSender<Result<Update, Infallible>>;
+type Updates = UnboundedReceiverStream<Result<Update, Infallible>>;
+
+/// Where the HTTP API puts updates Telegram posts to the webhook, for the
+/// dispatcher to read them as if they came from long polling.
+#[derive(Clone)]
+pub struct Inbox {
+    sender: Arc<RwLock<Option<UpdateSender>>>,
+    secret: String,
+}
+
+impl Inbox {
+    pub fn is_authorized(&self, secret: Option<&str>) -> bool {
+        secret == Some(self.secret.as_str())
+    }
+
+    /// Hands the update to the dispatcher, `false` once it's shutting down.
+    /// Telegram retries refused updates, so the next start gets them.
+    pub fn push(&self, update: Update) -> bool {
+        match &*self.sender.read().unwrap() {
+            Some(sender) => sender.send(Ok(update)).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// An inbox for the HTTP API and the update listener reading it.
+///
+/// When the dispatcher stops the inbox refuses new updates, the ones already
+/// in it are still dispatched so that nothing Telegram considers delivered
+/// is lost.
+pub fn channel(secret: String) -> (Inbox, impl UpdateListener<Err = Infallible>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let sender = Arc::new(RwLock::new(Some(sender)));
+    let (stop_token, stop_flag) = mk_stop_token();
+
+    let closing = Arc::clone(&sender);
+    tokio::spawn(async move {
+        stop_flag.await;
+        // The stream ends once the queued updates are read
+        closing.write().unwrap().take();
+    });
+
+    let listener = StatefulListener::new(
+        (UnboundedReceiverStream::new(receiver), stop_token),
+        updates,
+        |state: &mut (Updates, StopToken)| state.1.clone(),
+    );
+    (Inbox { sender, secret }, listener)
+}
+
+fn updates(state: &mut (Updates, StopToken)) -> &mut Updates {
+    &mut state.0
+}
+
+/// Points Telegram at the webhook, it takes over from long polling.
+///
+/// Pending updates are kept: the ones that came while the bot polled or was
+/// down are posted to the webhook. It stays set on shutdown so updates wait
+/// for the next start, a start with long polling removes it and fetches them.
+pub async fn set<B: Requester>(bot: &B, url: Url, secret: &str) -> Result<(), B::Err> {
+    bot.set_webhook(url)
+        .secret_token(secret.to_string())
+        .drop_pending_updates(false)
+        .await?;
+    Ok(())
+}
+
+/// Whether Telegram accepts `secret` for "setWebhook".
+pub fn is_valid_secret(secret: &str) -> bool {
+    (1..=256).contains(&secret.len())
+        && secret
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::{
+        types::Update,
+        update_listeners::{AsUpdateStream, UpdateListener},
+    };
+    use tokio_stream::StreamExt;
+
+    use crate::webhook::{channel, is_valid_secret};
+
+    #[test]
+    fn test_is_valid_secret() {
+        assert!(is_valid_secret("s3cret_-TOKEN"));
+        assert!(!is_valid_secret(""));
+        assert!(!is_valid_secret("with space"));
+        assert!(!is_valid_secret(&"a".repeat(257)));
+    }
+
+    #[tokio::test]
+    async fn test_inbox() {
+        let (inbox, mut listener) = channel("secret".to_string());
+        assert!(inbox.is_authorized(Some("secret")));
+        assert!(!inbox.is_authorized(Some("guess")));
+        assert!(!inbox.is_authorized(None));
+
+        let update = |id| {
+            serde_json::from_value::<Update>(serde_json::json!({
+                "update_id": id,
+                "message": {
+                    "message_id": id,
+                    "date": 0,
+                    "chat": {"id": 1, "type": "private", "first_name": "Ann"},
+                    "text": "/done"
+                }
+            }))
+            .unwrap()
+        };
+        assert!(inbox.push(update(1)));
+
+        // Queued updates are still read after the stop, new ones are refused
+        listener.stop_token().stop();
+        tokio::task::yield_now().await;
+        assert!(!inbox.push(update(2)));
+        let stream = listener.as_stream();
+        tokio::pin!(stream);
+        assert_eq!(stream.next().await.unwrap().unwrap().id, 1);
+        assert!(stream.next().await.is_none());
+    }
+}